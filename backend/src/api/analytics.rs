@@ -1,8 +1,12 @@
 use actix_web::{get, web, HttpResponse, Responder};
 use crate::AppState;
 use crate::analytics::{SystemMetrics, PoolMetrics};
+use crate::errors::ApiError;
+use crate::lp_analytics;
 use chrono::{DateTime, Utc};
 use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
 
 #[derive(Serialize)]
 struct AnalyticsResponse {
@@ -38,7 +42,28 @@ async fn get_pool_analytics(
     }
 }
 
+// Per-wallet LP position analytics: current value, impermanent loss, fee share and claimed rewards
+#[get("/analytics/lp/{wallet}")]
+async fn get_wallet_lp_analytics(
+    wallet: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, ApiError> {
+    let wallet = Pubkey::from_str(&wallet)
+        .map_err(|_| ApiError::BadRequest("Invalid wallet address".to_string()))?;
+    let client = state
+        .solana_client
+        .as_ref()
+        .ok_or_else(|| ApiError::SolanaError("Solana client not available".to_string()))?;
+
+    let analytics = lp_analytics::get_wallet_lp_analytics(client, &wallet)
+        .await
+        .map_err(|e| ApiError::SolanaError(e.to_string()))?;
+
+    Ok(HttpResponse::Ok().json(analytics))
+}
+
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(get_analytics)
-       .service(get_pool_analytics);
+       .service(get_pool_analytics)
+       .service(get_wallet_lp_analytics);
 }
\ No newline at end of file
@@ -0,0 +1,345 @@
+use anchor_lang::{AccountDeserialize, Discriminator};
+use anyhow::{anyhow, Result};
+use dex_core::lp_mining::{MAX_REWARD_STREAMS, PRICE_PRECISION};
+use dex_core::{LiquidityPool, LiquidityPoolState, RewardConfig, StakingInfo, UserPosition};
+use serde::Serialize;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::solana::{find_program_address, get_account, get_optional_account};
+
+/// UserPosition中owner字段的偏移量（8字节鉴别器 + 1字节schema_version）
+const POSITION_OWNER_OFFSET: usize = 8 + 1;
+
+/// 单个奖励流的累计领取数量
+#[derive(Debug, Serialize, Clone)]
+pub struct ClaimedReward {
+    pub reward_mint: String,
+    pub amount: u64,
+}
+
+/// 单个LP持仓的收益分析，价值均以B代币（报价代币）的最小单位计
+#[derive(Debug, Serialize, Clone)]
+pub struct LpPositionAnalytics {
+    pub pool: String,
+    pub shares: u64,
+    pub staked_shares: u64,
+    pub pool_share: f64,
+    pub token_a_amount: u64,
+    pub token_b_amount: u64,
+    pub entry_price: f64,
+    pub current_price: f64,
+    pub entry_value: u64,
+    pub current_value: u64,
+    pub hold_value: u64,
+    pub impermanent_loss: i64,
+    pub impermanent_loss_pct: f64,
+    pub fees_earned_24h_a: u64,
+    pub fees_earned_24h_b: u64,
+    pub pnl: i64,
+    pub pnl_pct: f64,
+    pub rewards_claimed: Vec<ClaimedReward>,
+}
+
+/// 钱包的全部LP持仓
+#[derive(Debug, Serialize, Clone)]
+pub struct WalletLpAnalytics {
+    pub wallet: String,
+    pub positions: Vec<LpPositionAnalytics>,
+    pub total_entry_value: u64,
+    pub total_current_value: u64,
+    pub total_pnl: i64,
+}
+
+/// 根据链上账户计算单个持仓的当前价值、无常损失、手续费分成和已领取奖励
+///
+/// 无常损失相对于入场时按池比例持有两种代币、不提供流动性的价值计算；
+/// 手续费已计入储备，包含在当前价值中，`fees_earned_24h_*`只是按份额估算的最近24小时分成。
+pub fn analyze_position(
+    pool_key: &Pubkey,
+    position: &UserPosition,
+    staking: Option<&StakingInfo>,
+    pool: &LiquidityPool,
+    state: &LiquidityPoolState,
+    reward_config: &RewardConfig,
+) -> LpPositionAnalytics {
+    let staked_shares = staking.map_or(0, |s| s.staked_amount);
+    let owned_shares = position.shares.saturating_add(staked_shares);
+
+    let share_of = |amount: u64| -> u64 {
+        if pool.total_shares == 0 {
+            return 0;
+        }
+        (amount as u128 * owned_shares as u128 / pool.total_shares as u128) as u64
+    };
+    let token_a_amount = share_of(state.a_reserve);
+    let token_b_amount = share_of(state.b_reserve);
+
+    let current_price = state.current_price as f64 / PRICE_PRECISION as f64;
+    let entry_price = position.entry_price as f64 / PRICE_PRECISION as f64;
+    let current_value = (token_a_amount as f64 * current_price) as u64 + token_b_amount;
+
+    // 入场时两侧价值各占一半，继续持有时A侧价值随价格变化
+    let entry_value = position.entry_value;
+    let hold_value = if entry_price > 0.0 {
+        (entry_value as f64 / 2.0 * (1.0 + current_price / entry_price)) as u64
+    } else {
+        entry_value
+    };
+
+    let impermanent_loss = current_value as i64 - hold_value as i64;
+    let pnl = current_value as i64 - entry_value as i64;
+    let pct = |delta: i64, base: u64| {
+        if base > 0 {
+            delta as f64 / base as f64 * 100.0
+        } else {
+            0.0
+        }
+    };
+
+    let stream_count = (reward_config.stream_count as usize).min(MAX_REWARD_STREAMS);
+    let rewards_claimed = (0..stream_count)
+        .map(|i| ClaimedReward {
+            reward_mint: reward_config.streams[i].reward_mint.to_string(),
+            amount: position.rewards_claimed[i]
+                .saturating_add(staking.map_or(0, |s| s.rewards_claimed[i])),
+        })
+        .collect();
+
+    LpPositionAnalytics {
+        pool: pool_key.to_string(),
+        shares: position.shares,
+        staked_shares,
+        pool_share: if pool.total_shares > 0 {
+            owned_shares as f64 / pool.total_shares as f64
+        } else {
+            0.0
+        },
+        token_a_amount,
+        token_b_amount,
+        entry_price,
+        current_price,
+        entry_value,
+        current_value,
+        hold_value,
+        impermanent_loss,
+        impermanent_loss_pct: pct(impermanent_loss, hold_value),
+        fees_earned_24h_a: share_of(state.fees_a_24h),
+        fees_earned_24h_b: share_of(state.fees_b_24h),
+        pnl,
+        pnl_pct: pct(pnl, entry_value),
+        rewards_claimed,
+    }
+}
+
+/// 查询钱包在所有流动性池中的持仓并逐个分析
+pub async fn get_wallet_lp_analytics(
+    client: &RpcClient,
+    wallet: &Pubkey,
+) -> Result<WalletLpAnalytics> {
+    let program_id = dex_core::ID;
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, &UserPosition::DISCRIMINATOR)),
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                POSITION_OWNER_OFFSET,
+                wallet.as_ref(),
+            )),
+        ]),
+        account_config: RpcAccountInfoConfig::default(),
+        with_context: None,
+    };
+
+    let accounts = client
+        .get_program_accounts_with_config(&program_id, config)
+        .map_err(|e| anyhow!("Failed to fetch LP positions: {}", e))?;
+
+    let mut positions = Vec::with_capacity(accounts.len());
+    for (_, account) in accounts {
+        let position = UserPosition::try_deserialize(&mut account.data.as_slice())
+            .map_err(|e| anyhow!("Failed to deserialize LP position: {}", e))?;
+        let pool_key = position.pool;
+
+        let (state_key, _) =
+            find_program_address(&[b"pool_state", pool_key.as_ref()], &program_id);
+        let (config_key, _) =
+            find_program_address(&[b"reward_config", pool_key.as_ref()], &program_id);
+        let (staking_key, _) = find_program_address(
+            &[b"staking_info", pool_key.as_ref(), wallet.as_ref()],
+            &program_id,
+        );
+
+        let pool: LiquidityPool = get_account(client, &pool_key).await?;
+        let state: LiquidityPoolState = get_account(client, &state_key).await?;
+        let reward_config: RewardConfig = get_account(client, &config_key).await?;
+        // 没有质押过的用户不存在StakingInfo账户
+        let staking: Option<StakingInfo> = get_optional_account(client, &staking_key).await?;
+
+        positions.push(analyze_position(
+            &pool_key,
+            &position,
+            staking.as_ref(),
+            &pool,
+            &state,
+            &reward_config,
+        ));
+    }
+
+    Ok(WalletLpAnalytics {
+        wallet: wallet.to_string(),
+        total_entry_value: positions.iter().map(|p| p.entry_value).sum(),
+        total_current_value: positions.iter().map(|p| p.current_value).sum(),
+        total_pnl: positions.iter().map(|p| p.pnl).sum(),
+        positions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dex_core::lp_mining::{DailyData, RewardStream};
+
+    fn pool(total_shares: u64) -> LiquidityPool {
+        LiquidityPool {
+            schema_version: 0,
+            market: Pubkey::new_unique(),
+            token_a: Pubkey::new_unique(),
+            token_b: Pubkey::new_unique(),
+            token_a_vault: Pubkey::new_unique(),
+            token_b_vault: Pubkey::new_unique(),
+            lp_mint: Pubkey::new_unique(),
+            lp_stake_vault: Pubkey::new_unique(),
+            fee_rate: 30,
+            total_value_locked: 0,
+            total_shares,
+            total_staked: 0,
+            total_boosted_stake: 0,
+            created_at: 0,
+            last_update_ts: 0,
+            bump: 0,
+        }
+    }
+
+    fn state(
+        a_reserve: u64,
+        b_reserve: u64,
+        price: u64,
+        fees_a: u64,
+        fees_b: u64,
+    ) -> LiquidityPoolState {
+        LiquidityPoolState {
+            schema_version: 0,
+            pool: Pubkey::new_unique(),
+            a_reserve,
+            b_reserve,
+            current_price: price * PRICE_PRECISION as u64,
+            volume_24h: 0,
+            fees_a_24h: fees_a,
+            fees_b_24h: fees_b,
+            apr: 0,
+            is_active: true,
+            daily_data: [DailyData::default(); 7],
+        }
+    }
+
+    fn reward_config(stream_count: u8) -> RewardConfig {
+        RewardConfig {
+            schema_version: 0,
+            pool: Pubkey::new_unique(),
+            admin: Pubkey::new_unique(),
+            stream_count,
+            streams: [RewardStream::default(); MAX_REWARD_STREAMS],
+            bump: 0,
+        }
+    }
+
+    fn staking(staked_amount: u64, claimed: u64) -> StakingInfo {
+        let mut rewards_claimed = [0; MAX_REWARD_STREAMS];
+        rewards_claimed[0] = claimed;
+        StakingInfo {
+            schema_version: 0,
+            owner: Pubkey::new_unique(),
+            pool: Pubkey::new_unique(),
+            staked_amount,
+            reward_debts: [0; MAX_REWARD_STREAMS],
+            rewards_claimed,
+            last_update_ts: 0,
+            lock_period: 0,
+            unlock_time: 0,
+            boost_factor: 10_000,
+            unclaimed_rewards: [0; MAX_REWARD_STREAMS],
+            bump: 0,
+        }
+    }
+
+    // 价格从1涨到4：入场时持有400 A + 400 B，恒定乘积下现在是200 A + 800 B
+    #[test]
+    fn price_move_shows_impermanent_loss_pnl_and_fee_share() {
+        let mut position = UserPosition {
+            shares: 100,
+            entry_price: PRICE_PRECISION as u64,
+            entry_value: 800,
+            ..Default::default()
+        };
+        position.rewards_claimed[0] = 7;
+        let staking = staking(100, 5);
+
+        let analytics = analyze_position(
+            &Pubkey::new_unique(),
+            &position,
+            Some(&staking),
+            &pool(1_000),
+            &state(1_000, 4_000, 4, 50, 300),
+            &reward_config(1),
+        );
+
+        assert_eq!(analytics.staked_shares, 100);
+        assert_eq!(analytics.pool_share, 0.2);
+        assert_eq!(
+            (analytics.token_a_amount, analytics.token_b_amount),
+            (200, 800)
+        );
+        assert_eq!(analytics.current_value, 1_600);
+        assert_eq!(analytics.hold_value, 2_000);
+        assert_eq!(analytics.impermanent_loss, -400);
+        assert_eq!(analytics.impermanent_loss_pct, -20.0);
+        assert_eq!(analytics.pnl, 800);
+        assert_eq!(analytics.pnl_pct, 100.0);
+        assert_eq!(
+            (analytics.fees_earned_24h_a, analytics.fees_earned_24h_b),
+            (10, 60)
+        );
+        assert_eq!(analytics.rewards_claimed.len(), 1);
+        assert_eq!(analytics.rewards_claimed[0].amount, 12);
+    }
+
+    #[test]
+    fn empty_pool_and_unknown_entry_price_do_not_divide_by_zero() {
+        let position = UserPosition {
+            shares: 100,
+            entry_value: 800,
+            ..Default::default()
+        };
+
+        let analytics = analyze_position(
+            &Pubkey::new_unique(),
+            &position,
+            None,
+            &pool(0),
+            &state(1_000, 4_000, 4, 50, 300),
+            &reward_config(0),
+        );
+
+        assert_eq!(analytics.pool_share, 0.0);
+        assert_eq!(analytics.current_value, 0);
+        assert_eq!(analytics.fees_earned_24h_a, 0);
+        // 没有入场价格时按入场价值计算持有价值
+        assert_eq!(analytics.hold_value, 800);
+        assert_eq!(analytics.pnl, -800);
+        assert!(analytics.rewards_claimed.is_empty());
+    }
+}
@@ -11,6 +11,7 @@ mod buyback;
 mod mev;
 mod errors;
 mod analytics;
+mod lp_analytics;
 mod middleware;
 mod rate_limiter;
 mod config;
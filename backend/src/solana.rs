@@ -1,6 +1,7 @@
 use anchor_client::{Client, Cluster, Program};
 use anchor_lang::AccountDeserialize;
 use anyhow::{Result, anyhow};
+use dex_events::DexEvent;
 use solana_client::{
     rpc_client::RpcClient,
     rpc_config::{RpcTransactionConfig, RpcSendTransactionConfig},
@@ -69,6 +70,26 @@ pub async fn get_account<T: AccountDeserialize>(
     Ok(account_data)
 }
 
+/// 获取可能不存在的账户数据，账户不存在时返回None，RPC和反序列化错误照常返回
+pub async fn get_optional_account<T: AccountDeserialize>(
+    client: &RpcClient,
+    address: &Pubkey,
+) -> Result<Option<T>> {
+    let account = client.get_account_with_commitment(address, client.commitment())
+        .map_err(|e| anyhow!("Failed to fetch account data: {}", e))?
+        .value;
+
+    match account {
+        Some(account) => {
+            let mut data = account.data.as_slice();
+            let account_data = T::try_deserialize(&mut data)
+                .map_err(|e| anyhow!("Failed to deserialize account data: {}", e))?;
+            Ok(Some(account_data))
+        }
+        None => Ok(None),
+    }
+}
+
 /// 查找PDA (Program Derived Address)
 pub fn find_program_address(seeds: &[&[u8]], program_id: &Pubkey) -> (Pubkey, u8) {
     Pubkey::find_program_address(seeds, program_id)
@@ -186,6 +207,31 @@ impl SolanaService {
         Ok(transaction_status)
     }
 
+    /// 获取一笔交易中dex_core发出的事件（失败交易返回空列表）
+    pub async fn get_transaction_events(
+        &self,
+        signature: &str,
+    ) -> Result<Vec<DexEvent>, Box<dyn std::error::Error>> {
+        let config = RpcTransactionConfig {
+            encoding: None,
+            commitment: Some(CommitmentConfig::confirmed()),
+            max_supported_transaction_version: Some(0),
+        };
+
+        let tx = self.rpc_client.get_transaction_with_config(
+            &signature.parse()?,
+            config,
+        )?;
+
+        let meta = match tx.transaction.meta {
+            Some(meta) if meta.err.is_none() => meta,
+            _ => return Ok(Vec::new()),
+        };
+        let logs: Option<Vec<String>> = meta.log_messages.into();
+
+        Ok(dex_events::parse_logs(&dex_events::PROGRAM_ID, &logs.unwrap_or_default())?)
+    }
+
     pub async fn get_token_balance(
         &self,
         token_account: &Pubkey,
@@ -0,0 +1,224 @@
+//! dex_core事件解码库
+//!
+//! 将交易日志中由`emit!`写入的`Program data:`记录解码为类型化的[`DexEvent`]，
+//! 并通过[`SequenceTracker`]按市场检查事件序号是否连续。后端和中继器共用这一实现，
+//! 不再各自解析日志字符串。
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::collections::HashMap;
+use thiserror::Error;
+
+pub use dex_core::events::*;
+pub use dex_core::ID as PROGRAM_ID;
+
+/// `emit!`写入日志时使用的前缀
+const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+
+#[derive(Debug, Error)]
+pub enum DecodeError {
+    #[error("base64解码失败: {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("事件数据长度不足")]
+    TooShort,
+    #[error("事件{name}反序列化失败: {source}")]
+    Deserialize {
+        name: &'static str,
+        source: std::io::Error,
+    },
+}
+
+// 为每种事件生成枚举分支、名称、序号访问和按鉴别器解码的代码
+macro_rules! dex_events {
+    (
+        sequenced { $($sv:ident($st:ty) => $market:ident),* $(,)? }
+        unsequenced { $($uv:ident($ut:ty)),* $(,)? }
+    ) => {
+        /// dex_core发出的事件
+        #[derive(Debug, Clone)]
+        pub enum DexEvent {
+            $($sv($st),)*
+            $($uv($ut),)*
+        }
+
+        impl DexEvent {
+            /// 事件名称
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(DexEvent::$sv(_) => stringify!($sv),)*
+                    $(DexEvent::$uv(_) => stringify!($uv),)*
+                }
+            }
+
+            /// 事件所属市场和序号，不属于单个市场的事件返回None
+            pub fn market_seq(&self) -> Option<(Pubkey, u64)> {
+                match self {
+                    $(DexEvent::$sv(e) => Some((e.$market, e.seq)),)*
+                    $(DexEvent::$uv(_) => None,)*
+                }
+            }
+
+            /// 解码一条事件数据（8字节鉴别器 + borsh），鉴别器未知时返回None
+            pub fn decode(data: &[u8]) -> Result<Option<Self>, DecodeError> {
+                if data.len() < 8 {
+                    return Err(DecodeError::TooShort);
+                }
+                let (discriminator, mut payload) = data.split_at(8);
+                $(
+                    if discriminator == &<$st>::DISCRIMINATOR[..] {
+                        return <$st>::deserialize(&mut payload)
+                            .map(|e| Some(DexEvent::$sv(e)))
+                            .map_err(|source| DecodeError::Deserialize {
+                                name: stringify!($sv),
+                                source,
+                            });
+                    }
+                )*
+                $(
+                    if discriminator == &<$ut>::DISCRIMINATOR[..] {
+                        return <$ut>::deserialize(&mut payload)
+                            .map(|e| Some(DexEvent::$uv(e)))
+                            .map_err(|source| DecodeError::Deserialize {
+                                name: stringify!($uv),
+                                source,
+                            });
+                    }
+                )*
+                Ok(None)
+            }
+        }
+    };
+}
+
+dex_events! {
+    sequenced {
+        MarketCreated(MarketCreatedEvent) => market,
+        OrderPlaced(OrderPlacedEvent) => market,
+        OrderCanceled(OrderCanceledEvent) => market,
+        Trade(TradeEvent) => market,
+        CancelOnDisconnect(CancelOnDisconnectEvent) => market,
+        AccessControlChanged(AccessControlChangedEvent) => market,
+        FundsSettled(FundsSettledEvent) => market,
+        LiquidityChanged(LiquidityChangedEvent) => market,
+        RewardsClaimed(RewardsClaimedEvent) => market,
+        CrossChainOrderCreated(CrossChainOrderCreatedEvent) => source_market,
+        CrossChainOrderDelivered(CrossChainOrderDeliveredEvent) => source_market,
+        CrossChainTxConfirmed(CrossChainTxConfirmedEvent) => target_market,
+        CrossChainOrderRefunded(CrossChainOrderRefundedEvent) => source_market,
+        CrossChainTransferQueued(CrossChainTransferQueuedEvent) => target_market,
+        CrossChainTransferCancelled(CrossChainTransferCancelledEvent) => target_market,
+        AdvancedOrderCreated(AdvancedOrderCreatedEvent) => market,
+        MarketStatusChanged(MarketStatusChangedEvent) => market,
+        RiskWarning(RiskWarningEvent) => market,
+        MarketMetrics(MarketMetricsEvent) => market,
+        StorageOptimization(StorageOptimizationEvent) => market,
+    }
+    unsequenced {
+        AccountActivity(AccountActivityEvent),
+        SystemStatus(SystemStatusEvent),
+        AccountMigrated(AccountMigratedEvent),
+        HtlcLocked(HtlcLockedEvent),
+        HtlcClaimed(HtlcClaimedEvent),
+        HtlcRefunded(HtlcRefundedEvent),
+        BridgePauseChanged(BridgePauseChangedEvent),
+    }
+}
+
+/// 从一笔交易的日志中解码`program_id`发出的事件，按发出顺序返回
+///
+/// 只解码调用栈顶为`program_id`时写入的`Program data:`记录，经CPI调用的其他程序
+/// 写入的数据会被忽略。失败交易的状态会回滚，事件序号也不会被占用，调用方应跳过失败交易。
+pub fn parse_logs<S: AsRef<str>>(
+    program_id: &Pubkey,
+    logs: &[S],
+) -> Result<Vec<DexEvent>, DecodeError> {
+    let program_id = program_id.to_string();
+    let mut invoke_stack: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+
+    for log in logs {
+        let log = log.as_ref();
+
+        if let Some(data) = log.strip_prefix(PROGRAM_DATA_PREFIX) {
+            if invoke_stack.last() == Some(&program_id.as_str()) {
+                if let Some(event) = DexEvent::decode(&STANDARD.decode(data.trim())?)? {
+                    events.push(event);
+                }
+            }
+            continue;
+        }
+
+        // "Program <id> invoke [n]" / "Program <id> success" / "Program <id> failed: ..."
+        if let Some(rest) = log.strip_prefix("Program ") {
+            let mut parts = rest.splitn(2, ' ');
+            if let (Some(id), Some(action)) = (parts.next(), parts.next()) {
+                if action.starts_with("invoke [") {
+                    invoke_stack.push(id);
+                } else if action == "success" || action.starts_with("failed") {
+                    invoke_stack.pop();
+                }
+            }
+        }
+    }
+
+    Ok(events)
+}
+
+/// 序号检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceStatus {
+    /// 该市场第一次出现且没有检查点，无法判断之前是否有缺口
+    First,
+    /// 与上一条事件连续
+    InOrder,
+    /// 漏收了`expected..received`之间的事件（不含`received`）
+    Gap { expected: u64, received: u64 },
+    /// 序号不大于已处理的序号，重复投递或重放
+    Duplicate,
+}
+
+/// 按市场跟踪已处理的最大事件序号
+#[derive(Debug, Default, Clone)]
+pub struct SequenceTracker {
+    last_seen: HashMap<Pubkey, u64>,
+}
+
+impl SequenceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从持久化的检查点恢复某个市场已处理的序号
+    pub fn restore(&mut self, market: Pubkey, last_seq: u64) {
+        self.last_seen.insert(market, last_seq);
+    }
+
+    /// 某个市场已处理的最大序号
+    pub fn last_seq(&self, market: &Pubkey) -> Option<u64> {
+        self.last_seen.get(market).copied()
+    }
+
+    /// 检查一条事件的序号，不属于单个市场的事件返回None
+    pub fn observe(&mut self, event: &DexEvent) -> Option<SequenceStatus> {
+        event
+            .market_seq()
+            .map(|(market, seq)| self.observe_seq(market, seq))
+    }
+
+    /// 检查并记录某个市场的事件序号；出现缺口时同样推进到新序号，由调用方补拉缺失区间
+    pub fn observe_seq(&mut self, market: Pubkey, seq: u64) -> SequenceStatus {
+        let status = match self.last_seen.get(&market) {
+            None if seq == 1 => SequenceStatus::InOrder, // 序号从1开始
+            None => SequenceStatus::First,
+            Some(&last) if seq <= last => return SequenceStatus::Duplicate,
+            Some(&last) if seq == last + 1 => SequenceStatus::InOrder,
+            Some(&last) => SequenceStatus::Gap {
+                expected: last + 1,
+                received: seq,
+            },
+        };
+        self.last_seen.insert(market, seq);
+        status
+    }
+}
@@ -0,0 +1,353 @@
+use crate::ErrorCode;
+use anchor_lang::prelude::*;
+use uint::construct_uint;
+
+construct_uint! {
+    pub struct U256(4);
+}
+
+// 价格以Q64.64定点数表示的sqrt(price)，price = 1.0001^tick 为B/A
+pub const Q64: u128 = 1 << 64;
+
+// 可用的最小/最大tick，对应的sqrt价格在Q64.64下约为2^32和2^96
+pub const MIN_TICK: i32 = -443636;
+pub const MAX_TICK: i32 = 443636;
+
+// sqrt(1.0001)^(-2^i) 的Q64.64表示，用于按位计算tick对应的sqrt价格
+const TICK_FACTORS: [u128; 19] = [
+    0xfffcb933bd6fad37,
+    0xfff97272373d4132,
+    0xfff2e50f5f656932,
+    0xffe5caca7e10e4e6,
+    0xffcb9843d60f6159,
+    0xff973b41fa98c081,
+    0xff2ea16466c96a38,
+    0xfe5dee046a99a2a8,
+    0xfcbe86c7900a88ae,
+    0xf987a7253ac41317,
+    0xf3392b0822b70005,
+    0xe7159475a2c29b74,
+    0xd097f3bdfd2022b8,
+    0xa9f746462d870fdf,
+    0x70d869a156d2a1b8,
+    0x31be135f97d08fd9,
+    0x9aa508b5b7a84e1,
+    0x5d6af8dedb8119,
+    0x2216e584f5fa,
+];
+
+// tick对应的sqrt价格 (Q64.64)
+pub fn sqrt_price_at_tick(tick: i32) -> Result<u128> {
+    let abs_tick = tick.unsigned_abs();
+    require!(abs_tick <= MAX_TICK as u32, ErrorCode::InvalidTick);
+
+    // 先计算sqrt(1.0001)^(-|tick|)，每一步的乘积都小于2^128
+    let mut ratio = Q64;
+    for (i, factor) in TICK_FACTORS.iter().enumerate() {
+        if abs_tick & (1 << i) != 0 {
+            ratio = (ratio * factor) >> 64;
+        }
+    }
+
+    if tick > 0 {
+        ratio = u128::MAX / ratio;
+    }
+    Ok(ratio)
+}
+
+// 满足 sqrt_price_at_tick(tick) <= sqrt_price 的最大tick
+pub fn tick_at_sqrt_price(sqrt_price_x64: u128) -> Result<i32> {
+    require!(
+        sqrt_price_x64 >= sqrt_price_at_tick(MIN_TICK)?
+            && sqrt_price_x64 <= sqrt_price_at_tick(MAX_TICK)?,
+        ErrorCode::InvalidSqrtPrice
+    );
+
+    let (mut low, mut high) = (MIN_TICK, MAX_TICK);
+    while low < high {
+        let mid = low + (high - low + 1) / 2;
+        if sqrt_price_at_tick(mid)? <= sqrt_price_x64 {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    Ok(low)
+}
+
+// 价格在 [sqrt_a, sqrt_b] 区间内移动时流动性对应的A代币数量
+// L * (sqrt_b - sqrt_a) / (sqrt_a * sqrt_b)
+pub fn amount_a_delta(sqrt_a: u128, sqrt_b: u128, liquidity: u128, round_up: bool) -> Result<u128> {
+    let (lower, upper) = if sqrt_a < sqrt_b { (sqrt_a, sqrt_b) } else { (sqrt_b, sqrt_a) };
+    require!(lower > 0, ErrorCode::InvalidSqrtPrice);
+
+    let numerator = (U256::from(liquidity) << 64) * U256::from(upper - lower);
+    let amount = div(div(numerator, U256::from(upper), round_up), U256::from(lower), round_up);
+    to_u128(amount)
+}
+
+// 价格在 [sqrt_a, sqrt_b] 区间内移动时流动性对应的B代币数量
+// L * (sqrt_b - sqrt_a)
+pub fn amount_b_delta(sqrt_a: u128, sqrt_b: u128, liquidity: u128, round_up: bool) -> Result<u128> {
+    let (lower, upper) = if sqrt_a < sqrt_b { (sqrt_a, sqrt_b) } else { (sqrt_b, sqrt_a) };
+
+    let product = U256::from(liquidity) * U256::from(upper - lower);
+    to_u128(div(product, U256::from(Q64), round_up))
+}
+
+// 输入amount后的新sqrt价格，输入A时价格下降，输入B时价格上升
+// 两个方向都向有利于池子的方向取整
+pub fn next_sqrt_price_from_input(
+    sqrt_price_x64: u128,
+    liquidity: u128,
+    amount: u64,
+    a_to_b: bool,
+) -> Result<u128> {
+    require!(liquidity > 0, ErrorCode::InsufficientLiquidity);
+    if amount == 0 {
+        return Ok(sqrt_price_x64);
+    }
+
+    if a_to_b {
+        // L / (L / sqrt_p + amount)，向上取整
+        let numerator = U256::from(liquidity) << 64;
+        let denominator = numerator / U256::from(sqrt_price_x64) + U256::from(amount);
+        to_u128(div(numerator, denominator, true))
+    } else {
+        // sqrt_p + amount / L，向下取整
+        let delta = ((amount as u128) << 64) / liquidity;
+        sqrt_price_x64
+            .checked_add(delta)
+            .ok_or_else(|| error!(ErrorCode::MathOverflow))
+    }
+}
+
+// 一次兑换步骤的结果
+#[derive(Debug, Clone, Copy)]
+pub struct SwapStep {
+    pub sqrt_price_next_x64: u128,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee_amount: u64,
+}
+
+// 在当前流动性下把价格向sqrt_price_target推进，最多消耗amount_remaining（含手续费）
+pub fn compute_swap_step(
+    sqrt_price_current_x64: u128,
+    sqrt_price_target_x64: u128,
+    liquidity: u128,
+    amount_remaining: u64,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> Result<SwapStep> {
+    require!(
+        fee_denominator > 0 && fee_numerator < fee_denominator,
+        ErrorCode::InvalidFee
+    );
+    let a_to_b = sqrt_price_target_x64 < sqrt_price_current_x64;
+    let fee_rest = (fee_denominator - fee_numerator) as u128;
+
+    let amount_remaining_less_fee =
+        (amount_remaining as u128 * fee_rest / fee_denominator as u128) as u64;
+
+    let amount_in_to_target = if a_to_b {
+        amount_a_delta(sqrt_price_target_x64, sqrt_price_current_x64, liquidity, true)?
+    } else {
+        amount_b_delta(sqrt_price_current_x64, sqrt_price_target_x64, liquidity, true)?
+    };
+
+    let reached_target = amount_remaining_less_fee as u128 >= amount_in_to_target;
+    let (sqrt_price_next_x64, amount_in) = if reached_target {
+        (sqrt_price_target_x64, amount_in_to_target as u64)
+    } else {
+        let next = next_sqrt_price_from_input(
+            sqrt_price_current_x64,
+            liquidity,
+            amount_remaining_less_fee,
+            a_to_b,
+        )?;
+        let amount_in = if a_to_b {
+            amount_a_delta(next, sqrt_price_current_x64, liquidity, true)?
+        } else {
+            amount_b_delta(sqrt_price_current_x64, next, liquidity, true)?
+        };
+        (next, to_u64(amount_in)?)
+    };
+
+    let amount_out = if a_to_b {
+        amount_b_delta(sqrt_price_next_x64, sqrt_price_current_x64, liquidity, false)?
+    } else {
+        amount_a_delta(sqrt_price_current_x64, sqrt_price_next_x64, liquidity, false)?
+    };
+
+    // 未到达目标价格时剩余的输入全部作为手续费，否则按费率向上取整
+    // 向上取整的手续费不超过扣除amount_in后的剩余输入，保证 amount_in + fee_amount <= amount_remaining
+    let amount_left = amount_remaining
+        .checked_sub(amount_in)
+        .ok_or_else(|| error!(ErrorCode::MathOverflow))?;
+    let fee_amount = if reached_target {
+        let fee = div(
+            U256::from(amount_in) * U256::from(fee_numerator),
+            U256::from(fee_rest),
+            true,
+        );
+        fee.min(U256::from(amount_left)).as_u64()
+    } else {
+        amount_left
+    };
+
+    Ok(SwapStep {
+        sqrt_price_next_x64,
+        amount_in,
+        amount_out: to_u64(amount_out)?,
+        fee_amount,
+    })
+}
+
+// 单位流动性的手续费增长 (Q64.64)
+pub fn fee_growth_delta(fee_amount: u64, liquidity: u128) -> u128 {
+    if liquidity == 0 {
+        return 0;
+    }
+    ((fee_amount as u128) << 64) / liquidity
+}
+
+// [tick_lower, tick_upper) 区间内的单位流动性手续费增长 = 全局 - 下端以下 - 上端以上
+// tick的区间外增长在current_tick >= tick时表示tick以下的增长，否则表示tick以上的增长
+pub fn fee_growth_inside(
+    fee_growth_global_x64: u128,
+    lower_outside_x64: u128,
+    upper_outside_x64: u128,
+    tick_lower: i32,
+    tick_upper: i32,
+    current_tick: i32,
+) -> u128 {
+    let below = if current_tick >= tick_lower {
+        lower_outside_x64
+    } else {
+        fee_growth_global_x64.wrapping_sub(lower_outside_x64)
+    };
+    let above = if current_tick < tick_upper {
+        upper_outside_x64
+    } else {
+        fee_growth_global_x64.wrapping_sub(upper_outside_x64)
+    };
+    fee_growth_global_x64
+        .wrapping_sub(below)
+        .wrapping_sub(above)
+}
+
+// 按单位流动性的手续费增长计算应得手续费，增长量按u128回绕处理
+pub fn fees_owed(fee_growth_inside_x64: u128, fee_growth_last_x64: u128, liquidity: u128) -> u64 {
+    let growth = fee_growth_inside_x64.wrapping_sub(fee_growth_last_x64);
+    let owed = (U256::from(growth) * U256::from(liquidity)) >> 64;
+    if owed > U256::from(u64::MAX) {
+        u64::MAX
+    } else {
+        owed.as_u64()
+    }
+}
+
+// 带取整方向的除法
+fn div(numerator: U256, denominator: U256, round_up: bool) -> U256 {
+    let quotient = numerator / denominator;
+    if round_up && quotient * denominator != numerator {
+        quotient + 1
+    } else {
+        quotient
+    }
+}
+
+fn to_u128(value: U256) -> Result<u128> {
+    require!(value <= U256::from(u128::MAX), ErrorCode::MathOverflow);
+    Ok(value.as_u128())
+}
+
+pub fn to_u64(value: u128) -> Result<u64> {
+    u64::try_from(value).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sqrt_price_at_boundary_ticks() {
+        assert_eq!(sqrt_price_at_tick(0).unwrap(), Q64);
+        assert_eq!(sqrt_price_at_tick(MIN_TICK).unwrap(), 4_295_048_016);
+        assert_eq!(
+            sqrt_price_at_tick(MAX_TICK).unwrap(),
+            79_226_673_521_066_979_257_578_248_091
+        );
+        assert!(sqrt_price_at_tick(MIN_TICK - 1).is_err());
+        assert!(sqrt_price_at_tick(MAX_TICK + 1).is_err());
+    }
+
+    #[test]
+    fn tick_at_sqrt_price_round_trip() {
+        let edges = [MIN_TICK, MIN_TICK + 1, -1, 0, 1, MAX_TICK - 1, MAX_TICK];
+        for tick in edges.into_iter().chain((MIN_TICK..=MAX_TICK).step_by(997)) {
+            let sqrt_price = sqrt_price_at_tick(tick).unwrap();
+            assert_eq!(tick_at_sqrt_price(sqrt_price).unwrap(), tick);
+        }
+
+        // 两个tick之间的价格取较小的tick
+        let between = sqrt_price_at_tick(10).unwrap() + 1;
+        assert_eq!(tick_at_sqrt_price(between).unwrap(), 10);
+        let between = sqrt_price_at_tick(-10).unwrap() - 1;
+        assert_eq!(tick_at_sqrt_price(between).unwrap(), -11);
+    }
+
+    #[test]
+    fn swap_step_never_spends_more_than_remaining() {
+        let current = sqrt_price_at_tick(0).unwrap();
+        let fees = [(0, 1), (1, 3), (3, 1000), (30, 10_000), (999, 1000)];
+        for target_tick in [-500, -1, 1, 500] {
+            let target = sqrt_price_at_tick(target_tick).unwrap();
+            for liquidity in [1, 1_000, 1_000_000_007, Q64] {
+                for amount_remaining in [1, 2, 3, 7, 100, 12_345, 1_000_000, u32::MAX as u64] {
+                    for (fee_numerator, fee_denominator) in fees {
+                        let step = compute_swap_step(
+                            current,
+                            target,
+                            liquidity,
+                            amount_remaining,
+                            fee_numerator,
+                            fee_denominator,
+                        )
+                        .unwrap();
+                        assert!(
+                            step.amount_in as u128 + step.fee_amount as u128
+                                <= amount_remaining as u128,
+                            "{:?} exceeds {} (target {}, liquidity {}, fee {}/{})",
+                            step,
+                            amount_remaining,
+                            target_tick,
+                            liquidity,
+                            fee_numerator,
+                            fee_denominator
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn swap_step_reaching_target_keeps_fee_within_remaining() {
+        let current = sqrt_price_at_tick(0).unwrap();
+        let target = sqrt_price_at_tick(-1).unwrap();
+        let liquidity = 1_000_000_007;
+        let to_target = amount_a_delta(target, current, liquidity, true).unwrap() as u64;
+
+        // 刚好够到达目标价格的最小输入
+        let amount_remaining = (to_target..)
+            .find(|&amount| amount * 9_970 / 10_000 >= to_target)
+            .unwrap();
+        let step =
+            compute_swap_step(current, target, liquidity, amount_remaining, 30, 10_000).unwrap();
+        assert_eq!(step.sqrt_price_next_x64, target);
+        assert_eq!(step.amount_in, to_target);
+        assert!(step.amount_in + step.fee_amount <= amount_remaining);
+    }
+}
@@ -0,0 +1,871 @@
+use crate::{ErrorCode, PoolFees};
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+
+pub mod math;
+
+use math::{
+    amount_a_delta, amount_b_delta, compute_swap_step, fee_growth_delta, fee_growth_inside,
+    fees_owed, sqrt_price_at_tick, tick_at_sqrt_price, to_u64, MAX_TICK, MIN_TICK,
+};
+
+// 集中流动性池
+//
+// LP在 [tick_lower, tick_upper) 价格区间内提供流动性，价格 = 1.0001^tick (B/A)。
+// 每个tick记录跨越时的净流动性变化和区间外的手续费增长，区间内的手续费增长由
+// 全局增长减去两端区间外的增长得到，适合在锚定价格附近为相关资产对提供深度。
+
+// 每个TickArray账户包含的tick数量
+pub const TICK_ARRAY_SIZE: usize = 32;
+
+// 集中流动性池
+#[account]
+pub struct ConcentratedPool {
+    pub token_a_mint: Pubkey,
+    pub token_b_mint: Pubkey,
+    pub vault_a: Pubkey,
+    pub vault_b: Pubkey,
+    pub fees: PoolFees,
+    pub tick_spacing: u16,
+    pub sqrt_price_x64: u128,
+    pub current_tick: i32,
+    pub liquidity: u128,
+    pub fee_growth_global_a_x64: u128,
+    pub fee_growth_global_b_x64: u128,
+    pub authority: Pubkey,
+    pub authority_bump: u8,
+}
+
+impl ConcentratedPool {
+    // 一个TickArray覆盖的tick范围
+    pub fn ticks_per_array(&self) -> i32 {
+        self.tick_spacing as i32 * TICK_ARRAY_SIZE as i32
+    }
+
+    // 包含tick的TickArray的起始tick
+    pub fn tick_array_start(&self, tick: i32) -> i32 {
+        let span = self.ticks_per_array();
+        tick.div_euclid(span) * span
+    }
+
+    // 检查tick是否在范围内并且是tick_spacing的整数倍
+    pub fn validate_tick(&self, tick: i32) -> Result<()> {
+        require!(
+            (MIN_TICK..=MAX_TICK).contains(&tick) && tick % self.tick_spacing as i32 == 0,
+            ErrorCode::InvalidTick
+        );
+        Ok(())
+    }
+}
+
+// 单个tick的状态
+#[zero_copy]
+#[repr(packed)]
+#[derive(Default)]
+pub struct Tick {
+    pub initialized: u8,
+    pub liquidity_net: i128,
+    pub liquidity_gross: u128,
+    pub fee_growth_outside_a_x64: u128,
+    pub fee_growth_outside_b_x64: u128,
+}
+
+impl Tick {
+    // 价格跨越该tick：区间外手续费增长翻转到另一侧，返回净流动性
+    pub fn cross(&mut self, fee_growth_global_a_x64: u128, fee_growth_global_b_x64: u128) -> i128 {
+        self.fee_growth_outside_a_x64 =
+            fee_growth_global_a_x64.wrapping_sub(self.fee_growth_outside_a_x64);
+        self.fee_growth_outside_b_x64 =
+            fee_growth_global_b_x64.wrapping_sub(self.fee_growth_outside_b_x64);
+        self.liquidity_net
+    }
+}
+
+// 连续TICK_ARRAY_SIZE个可用tick
+#[account(zero_copy)]
+#[repr(packed)]
+pub struct TickArray {
+    pub pool: Pubkey,
+    pub start_tick_index: i32,
+    pub ticks: [Tick; TICK_ARRAY_SIZE],
+}
+
+impl TickArray {
+    // tick在数组中的位置
+    fn offset(&self, tick: i32, tick_spacing: u16) -> Result<usize> {
+        let start = self.start_tick_index;
+        let spacing = tick_spacing as i32;
+        require!(
+            tick >= start
+                && tick < start + spacing * TICK_ARRAY_SIZE as i32
+                && (tick - start) % spacing == 0,
+            ErrorCode::InvalidTickArray
+        );
+        Ok(((tick - start) / spacing) as usize)
+    }
+
+    pub fn tick_mut(&mut self, tick: i32, tick_spacing: u16) -> Result<&mut Tick> {
+        let offset = self.offset(tick, tick_spacing)?;
+        Ok(&mut self.ticks[offset])
+    }
+
+    // 数组内下一个已初始化的tick：a_to_b时找 <= tick 的最大值，否则找 > tick 的最小值
+    fn next_initialized(&self, tick: i32, tick_spacing: u16, a_to_b: bool) -> Option<i32> {
+        let spacing = tick_spacing as i32;
+        let index = |i: usize| self.start_tick_index + i as i32 * spacing;
+
+        if a_to_b {
+            (0..TICK_ARRAY_SIZE)
+                .rev()
+                .find(|&i| index(i) <= tick && self.ticks[i].initialized != 0)
+                .map(index)
+        } else {
+            (0..TICK_ARRAY_SIZE)
+                .find(|&i| index(i) > tick && self.ticks[i].initialized != 0)
+                .map(index)
+        }
+    }
+}
+
+// LP的区间持仓
+#[account]
+pub struct Position {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    pub fee_growth_inside_last_a_x64: u128,
+    pub fee_growth_inside_last_b_x64: u128,
+    pub tokens_owed_a: u64,
+    pub tokens_owed_b: u64,
+    pub bump: u8,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConcentratedPool<'info> {
+    #[account(init, payer = authority, space = 8 + std::mem::size_of::<ConcentratedPool>())]
+    pub pool: Account<'info, ConcentratedPool>,
+    pub token_a_mint: Account<'info, Mint>,
+    pub token_b_mint: Account<'info, Mint>,
+    /// CHECK: 池子金库的PDA权限账户，只用于签名
+    #[account(seeds = [pool.key().as_ref()], bump)]
+    pub pool_authority: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"vault_a", pool.key().as_ref()],
+        bump,
+        token::mint = token_a_mint,
+        token::authority = pool_authority,
+    )]
+    pub vault_a: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"vault_b", pool.key().as_ref()],
+        bump,
+        token::mint = token_b_mint,
+        token::authority = pool_authority,
+    )]
+    pub vault_b: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(start_tick_index: i32)]
+pub struct InitializeTickArray<'info> {
+    pub pool: Account<'info, ConcentratedPool>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<TickArray>(),
+        seeds = [b"tick_array", pool.key().as_ref(), &start_tick_index.to_le_bytes()],
+        bump
+    )]
+    pub tick_array: AccountLoader<'info, TickArray>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(tick_lower: i32, tick_upper: i32)]
+pub struct OpenPosition<'info> {
+    pub pool: Account<'info, ConcentratedPool>,
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + std::mem::size_of::<Position>(),
+        seeds = [
+            b"position",
+            pool.key().as_ref(),
+            owner.key().as_ref(),
+            &tick_lower.to_le_bytes(),
+            &tick_upper.to_le_bytes()
+        ],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// 增加/减少流动性共用的账户，区间两端在同一个TickArray时两个账户可以相同
+#[derive(Accounts)]
+pub struct ModifyLiquidity<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, ConcentratedPool>,
+    #[account(mut, has_one = pool, has_one = owner)]
+    pub position: Account<'info, Position>,
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &pool.tick_array_start(position.tick_lower).to_le_bytes()
+        ],
+        bump
+    )]
+    pub tick_array_lower: AccountLoader<'info, TickArray>,
+    #[account(
+        mut,
+        seeds = [
+            b"tick_array",
+            pool.key().as_ref(),
+            &pool.tick_array_start(position.tick_upper).to_le_bytes()
+        ],
+        bump
+    )]
+    pub tick_array_upper: AccountLoader<'info, TickArray>,
+    #[account(
+        mut,
+        constraint = user_token_a.mint == pool.token_a_mint @ ErrorCode::InvalidPoolTokens
+    )]
+    pub user_token_a: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_b.mint == pool.token_b_mint @ ErrorCode::InvalidPoolTokens
+    )]
+    pub user_token_b: Account<'info, TokenAccount>,
+    #[account(mut, address = pool.vault_a @ ErrorCode::InvalidPoolTokens)]
+    pub vault_a: Account<'info, TokenAccount>,
+    #[account(mut, address = pool.vault_b @ ErrorCode::InvalidPoolTokens)]
+    pub vault_b: Account<'info, TokenAccount>,
+    /// CHECK: 池子金库的PDA权限账户，只用于签名
+    #[account(address = pool.authority)]
+    pub pool_authority: UncheckedAccount<'info>,
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+// 集中流动性兑换，remaining_accounts按兑换方向依次传入价格会经过的TickArray
+#[derive(Accounts)]
+pub struct SwapConcentrated<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, ConcentratedPool>,
+    #[account(mut)]
+    pub user_token_in: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub user_token_out: Account<'info, TokenAccount>,
+    #[account(mut, address = pool.vault_a @ ErrorCode::InvalidPoolTokens)]
+    pub vault_a: Account<'info, TokenAccount>,
+    #[account(mut, address = pool.vault_b @ ErrorCode::InvalidPoolTokens)]
+    pub vault_b: Account<'info, TokenAccount>,
+    /// CHECK: 池子金库的PDA权限账户，只用于签名
+    #[account(address = pool.authority)]
+    pub pool_authority: UncheckedAccount<'info>,
+    pub user: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+pub fn initialize_pool(
+    ctx: Context<InitializeConcentratedPool>,
+    tick_spacing: u16,
+    fees: PoolFees,
+    initial_sqrt_price_x64: u128,
+) -> Result<()> {
+    require!(tick_spacing > 0, ErrorCode::InvalidTick);
+    require!(
+        fees.swap_fee_denominator > 0 && fees.swap_fee_numerator < fees.swap_fee_denominator,
+        ErrorCode::InvalidFee
+    );
+
+    let pool = &mut ctx.accounts.pool;
+    pool.token_a_mint = ctx.accounts.token_a_mint.key();
+    pool.token_b_mint = ctx.accounts.token_b_mint.key();
+    pool.vault_a = ctx.accounts.vault_a.key();
+    pool.vault_b = ctx.accounts.vault_b.key();
+    pool.fees = fees;
+    pool.tick_spacing = tick_spacing;
+    pool.sqrt_price_x64 = initial_sqrt_price_x64;
+    pool.current_tick = tick_at_sqrt_price(initial_sqrt_price_x64)?;
+    pool.liquidity = 0;
+    pool.fee_growth_global_a_x64 = 0;
+    pool.fee_growth_global_b_x64 = 0;
+    pool.authority = ctx.accounts.pool_authority.key();
+    pool.authority_bump = *ctx.bumps.get("pool_authority").unwrap();
+    Ok(())
+}
+
+pub fn initialize_tick_array(
+    ctx: Context<InitializeTickArray>,
+    start_tick_index: i32,
+) -> Result<()> {
+    let pool = &ctx.accounts.pool;
+    require!(
+        start_tick_index == pool.tick_array_start(start_tick_index),
+        ErrorCode::InvalidTickArray
+    );
+
+    let mut tick_array = ctx.accounts.tick_array.load_init()?;
+    tick_array.pool = pool.key();
+    tick_array.start_tick_index = start_tick_index;
+    Ok(())
+}
+
+pub fn open_position(ctx: Context<OpenPosition>, tick_lower: i32, tick_upper: i32) -> Result<()> {
+    let pool = &ctx.accounts.pool;
+    pool.validate_tick(tick_lower)?;
+    pool.validate_tick(tick_upper)?;
+    require!(tick_lower < tick_upper, ErrorCode::InvalidTick);
+
+    let position = &mut ctx.accounts.position;
+    position.pool = pool.key();
+    position.owner = ctx.accounts.owner.key();
+    position.tick_lower = tick_lower;
+    position.tick_upper = tick_upper;
+    position.liquidity = 0;
+    position.fee_growth_inside_last_a_x64 = 0;
+    position.fee_growth_inside_last_b_x64 = 0;
+    position.tokens_owed_a = 0;
+    position.tokens_owed_b = 0;
+    position.bump = *ctx.bumps.get("position").unwrap();
+    Ok(())
+}
+
+// 增加持仓流动性，转入的代币数量不超过上限
+pub fn increase_liquidity(
+    ctx: Context<ModifyLiquidity>,
+    liquidity_delta: u128,
+    max_amount_a: u64,
+    max_amount_b: u64,
+) -> Result<()> {
+    require!(liquidity_delta > 0, ErrorCode::ZeroTradingTokens);
+    let delta = i128::try_from(liquidity_delta).map_err(|_| error!(ErrorCode::MathOverflow))?;
+
+    let (amount_a, amount_b) = modify_position(ctx.accounts, delta)?;
+    require!(
+        amount_a <= max_amount_a && amount_b <= max_amount_b,
+        ErrorCode::SlippageExceeded
+    );
+
+    let accounts = &ctx.accounts;
+    for (from, to, amount) in [
+        (&accounts.user_token_a, &accounts.vault_a, amount_a),
+        (&accounts.user_token_b, &accounts.vault_b, amount_b),
+    ] {
+        if amount > 0 {
+            token::transfer(
+                CpiContext::new(
+                    accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: from.to_account_info(),
+                        to: to.to_account_info(),
+                        authority: accounts.owner.to_account_info(),
+                    },
+                ),
+                amount,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// 减少持仓流动性，取回的代币记入持仓，随手续费一起提取
+pub fn decrease_liquidity(
+    ctx: Context<ModifyLiquidity>,
+    liquidity_delta: u128,
+    min_amount_a: u64,
+    min_amount_b: u64,
+) -> Result<()> {
+    require!(liquidity_delta > 0, ErrorCode::ZeroTradingTokens);
+    require!(
+        liquidity_delta <= ctx.accounts.position.liquidity,
+        ErrorCode::InsufficientLiquidity
+    );
+    let delta = i128::try_from(liquidity_delta).map_err(|_| error!(ErrorCode::MathOverflow))?;
+
+    let (amount_a, amount_b) = modify_position(ctx.accounts, -delta)?;
+    require!(
+        amount_a >= min_amount_a && amount_b >= min_amount_b,
+        ErrorCode::SlippageExceeded
+    );
+
+    let position = &mut ctx.accounts.position;
+    position.tokens_owed_a = position
+        .tokens_owed_a
+        .checked_add(amount_a)
+        .ok_or(ErrorCode::MathOverflow)?;
+    position.tokens_owed_b = position
+        .tokens_owed_b
+        .checked_add(amount_b)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+}
+
+// 提取持仓的手续费和已减少的流动性
+pub fn collect(ctx: Context<ModifyLiquidity>) -> Result<()> {
+    // 先结算到当前为止的手续费
+    if ctx.accounts.position.liquidity > 0 {
+        modify_position(ctx.accounts, 0)?;
+    }
+
+    let accounts = &mut ctx.accounts;
+    let amount_a = std::mem::take(&mut accounts.position.tokens_owed_a);
+    let amount_b = std::mem::take(&mut accounts.position.tokens_owed_b);
+
+    let pool_key = accounts.pool.key();
+    let seeds: &[&[u8]] = &[pool_key.as_ref(), &[accounts.pool.authority_bump]];
+    for (from, to, amount) in [
+        (&accounts.vault_a, &accounts.user_token_a, amount_a),
+        (&accounts.vault_b, &accounts.user_token_b, amount_b),
+    ] {
+        if amount > 0 {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: from.to_account_info(),
+                        to: to.to_account_info(),
+                        authority: accounts.pool_authority.to_account_info(),
+                    },
+                    &[seeds],
+                ),
+                amount,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// 更新区间两端的tick、结算持仓手续费并修改流动性，返回对应的代币数量
+// 增加流动性时数量向上取整，减少时向下取整
+fn modify_position(accounts: &mut ModifyLiquidity, liquidity_delta: i128) -> Result<(u64, u64)> {
+    let pool = &mut accounts.pool;
+    let position = &mut accounts.position;
+    let (tick_lower, tick_upper) = (position.tick_lower, position.tick_upper);
+
+    let lower = update_tick(&accounts.tick_array_lower, pool, tick_lower, liquidity_delta, false)?;
+    let upper = update_tick(&accounts.tick_array_upper, pool, tick_upper, liquidity_delta, true)?;
+
+    let current = pool.current_tick;
+    let inside_a = fee_growth_inside(
+        pool.fee_growth_global_a_x64,
+        lower.fee_growth_outside_a_x64,
+        upper.fee_growth_outside_a_x64,
+        tick_lower,
+        tick_upper,
+        current,
+    );
+    let inside_b = fee_growth_inside(
+        pool.fee_growth_global_b_x64,
+        lower.fee_growth_outside_b_x64,
+        upper.fee_growth_outside_b_x64,
+        tick_lower,
+        tick_upper,
+        current,
+    );
+
+    position.tokens_owed_a = position.tokens_owed_a.saturating_add(fees_owed(
+        inside_a,
+        position.fee_growth_inside_last_a_x64,
+        position.liquidity,
+    ));
+    position.tokens_owed_b = position.tokens_owed_b.saturating_add(fees_owed(
+        inside_b,
+        position.fee_growth_inside_last_b_x64,
+        position.liquidity,
+    ));
+    position.fee_growth_inside_last_a_x64 = inside_a;
+    position.fee_growth_inside_last_b_x64 = inside_b;
+    position.liquidity = position
+        .liquidity
+        .checked_add_signed(liquidity_delta)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    // 流动性归零的tick不再需要跨越
+    if liquidity_delta < 0 {
+        for (loader, tick) in [
+            (&accounts.tick_array_lower, tick_lower),
+            (&accounts.tick_array_upper, tick_upper),
+        ] {
+            let mut tick_array = loader.load_mut()?;
+            let tick = tick_array.tick_mut(tick, pool.tick_spacing)?;
+            if tick.liquidity_gross == 0 {
+                *tick = Tick::default();
+            }
+        }
+    }
+
+    let liquidity = liquidity_delta.unsigned_abs();
+    let round_up = liquidity_delta > 0;
+    let sqrt_lower = sqrt_price_at_tick(tick_lower)?;
+    let sqrt_upper = sqrt_price_at_tick(tick_upper)?;
+
+    let (amount_a, amount_b) = if current < tick_lower {
+        (amount_a_delta(sqrt_lower, sqrt_upper, liquidity, round_up)?, 0)
+    } else if current < tick_upper {
+        pool.liquidity = pool
+            .liquidity
+            .checked_add_signed(liquidity_delta)
+            .ok_or(ErrorCode::MathOverflow)?;
+        (
+            amount_a_delta(pool.sqrt_price_x64, sqrt_upper, liquidity, round_up)?,
+            amount_b_delta(sqrt_lower, pool.sqrt_price_x64, liquidity, round_up)?,
+        )
+    } else {
+        (0, amount_b_delta(sqrt_lower, sqrt_upper, liquidity, round_up)?)
+    };
+
+    Ok((to_u64(amount_a)?, to_u64(amount_b)?))
+}
+
+// 修改tick的流动性，首次初始化时把区间外手续费增长设为全局值（约定此前的增长都发生在tick以下）
+fn update_tick(
+    loader: &AccountLoader<TickArray>,
+    pool: &ConcentratedPool,
+    tick_index: i32,
+    liquidity_delta: i128,
+    upper: bool,
+) -> Result<Tick> {
+    let mut tick_array = loader.load_mut()?;
+    let tick = tick_array.tick_mut(tick_index, pool.tick_spacing)?;
+
+    let liquidity_gross = tick
+        .liquidity_gross
+        .checked_add_signed(liquidity_delta)
+        .ok_or(ErrorCode::MathOverflow)?;
+    if tick.initialized == 0 && liquidity_delta != 0 {
+        if tick_index <= pool.current_tick {
+            tick.fee_growth_outside_a_x64 = pool.fee_growth_global_a_x64;
+            tick.fee_growth_outside_b_x64 = pool.fee_growth_global_b_x64;
+        }
+        tick.initialized = 1;
+    }
+
+    // 价格向上跨越下端时加入流动性，跨越上端时移除
+    let liquidity_net = if upper {
+        tick.liquidity_net.checked_sub(liquidity_delta)
+    } else {
+        tick.liquidity_net.checked_add(liquidity_delta)
+    };
+    tick.liquidity_net = liquidity_net.ok_or(ErrorCode::MathOverflow)?;
+    tick.liquidity_gross = liquidity_gross;
+
+    Ok(*tick)
+}
+
+// 按输入数量兑换，价格不越过sqrt_price_limit_x64
+pub fn swap<'info>(
+    ctx: Context<'_, '_, '_, 'info, SwapConcentrated<'info>>,
+    amount_in: u64,
+    minimum_amount_out: u64,
+    sqrt_price_limit_x64: u128,
+) -> Result<()> {
+    let accounts = ctx.accounts;
+    let pool = &mut accounts.pool;
+    require!(amount_in > 0, ErrorCode::ZeroTradingTokens);
+
+    let mint_in = accounts.user_token_in.mint;
+    let mint_out = accounts.user_token_out.mint;
+    let a_to_b = if mint_in == pool.token_a_mint && mint_out == pool.token_b_mint {
+        true
+    } else if mint_in == pool.token_b_mint && mint_out == pool.token_a_mint {
+        false
+    } else {
+        return err!(ErrorCode::InvalidPoolTokens);
+    };
+
+    let min_sqrt_price = sqrt_price_at_tick(MIN_TICK)?;
+    let max_sqrt_price = sqrt_price_at_tick(MAX_TICK)?;
+    if a_to_b {
+        require!(
+            sqrt_price_limit_x64 < pool.sqrt_price_x64 && sqrt_price_limit_x64 >= min_sqrt_price,
+            ErrorCode::InvalidSqrtPrice
+        );
+    } else {
+        require!(
+            sqrt_price_limit_x64 > pool.sqrt_price_x64 && sqrt_price_limit_x64 <= max_sqrt_price,
+            ErrorCode::InvalidSqrtPrice
+        );
+    }
+
+    let tick_arrays = load_tick_arrays(ctx.remaining_accounts, pool.key(), pool, a_to_b)?;
+    let span = pool.ticks_per_array();
+    let direction = if a_to_b { -1 } else { 1 };
+    let last_start = pool.tick_array_start(pool.current_tick)
+        + direction * span * (tick_arrays.len() as i32 - 1);
+
+    let mut amount_remaining = amount_in;
+    let mut amount_out: u64 = 0;
+    let mut sqrt_price = pool.sqrt_price_x64;
+    let mut current_tick = pool.current_tick;
+    let mut liquidity = pool.liquidity;
+    let mut fee_growth_global = if a_to_b {
+        pool.fee_growth_global_a_x64
+    } else {
+        pool.fee_growth_global_b_x64
+    };
+
+    while amount_remaining > 0 && sqrt_price != sqrt_price_limit_x64 {
+        // 传入的TickArray范围内的下一个已初始化tick；找不到时走到范围边界
+        let mut next_tick = None;
+        for loader in &tick_arrays {
+            let tick_array = loader.load()?;
+            next_tick = tick_array.next_initialized(current_tick, pool.tick_spacing, a_to_b);
+            if next_tick.is_some() {
+                break;
+            }
+        }
+        let (next_tick, initialized) = match next_tick {
+            Some(tick) => (tick, true),
+            None if a_to_b => {
+                require!(current_tick >= last_start, ErrorCode::InsufficientTickArrays);
+                (last_start, false)
+            }
+            None => (last_start + span, false),
+        };
+        let next_tick = next_tick.clamp(MIN_TICK, MAX_TICK);
+
+        let sqrt_price_next_tick = sqrt_price_at_tick(next_tick)?;
+        let sqrt_price_target = if a_to_b {
+            sqrt_price_next_tick.max(sqrt_price_limit_x64)
+        } else {
+            sqrt_price_next_tick.min(sqrt_price_limit_x64)
+        };
+
+        let step = compute_swap_step(
+            sqrt_price,
+            sqrt_price_target,
+            liquidity,
+            amount_remaining,
+            pool.fees.swap_fee_numerator,
+            pool.fees.swap_fee_denominator,
+        )?;
+
+        amount_remaining = amount_remaining
+            .checked_sub(step.amount_in)
+            .and_then(|v| v.checked_sub(step.fee_amount))
+            .ok_or(ErrorCode::MathOverflow)?;
+        amount_out = amount_out
+            .checked_add(step.amount_out)
+            .ok_or(ErrorCode::MathOverflow)?;
+        fee_growth_global =
+            fee_growth_global.wrapping_add(fee_growth_delta(step.fee_amount, liquidity));
+        sqrt_price = step.sqrt_price_next_x64;
+
+        if sqrt_price == sqrt_price_next_tick {
+            if !initialized {
+                // 走到传入范围的边界，两个方向都停在边界，剩余输入不再兑换
+                // 向下时边界tick在范围内且未初始化，视为已跨越；
+                // 向上时边界tick属于下一个TickArray，停在它的价格上但不跨越，下次兑换时再处理
+                current_tick = next_tick - 1;
+                break;
+            }
+
+            let liquidity_net =
+                cross_tick(&tick_arrays, pool, next_tick, a_to_b, fee_growth_global)?;
+            // 向下跨越时流动性变化方向相反
+            let liquidity_net = if a_to_b {
+                -liquidity_net
+            } else {
+                liquidity_net
+            };
+            liquidity = liquidity
+                .checked_add_signed(liquidity_net)
+                .ok_or(ErrorCode::MathOverflow)?;
+            current_tick = if a_to_b { next_tick - 1 } else { next_tick };
+        } else {
+            current_tick = tick_at_sqrt_price(sqrt_price)?;
+        }
+    }
+
+    require!(amount_out > 0, ErrorCode::ZeroTradingTokens);
+    require!(amount_out >= minimum_amount_out, ErrorCode::SlippageExceeded);
+
+    pool.sqrt_price_x64 = sqrt_price;
+    pool.current_tick = current_tick;
+    pool.liquidity = liquidity;
+    if a_to_b {
+        pool.fee_growth_global_a_x64 = fee_growth_global;
+    } else {
+        pool.fee_growth_global_b_x64 = fee_growth_global;
+    }
+
+    let amount_in = amount_in - amount_remaining;
+    let (vault_in, vault_out) = if a_to_b {
+        (&accounts.vault_a, &accounts.vault_b)
+    } else {
+        (&accounts.vault_b, &accounts.vault_a)
+    };
+
+    token::transfer(
+        CpiContext::new(
+            accounts.token_program.to_account_info(),
+            Transfer {
+                from: accounts.user_token_in.to_account_info(),
+                to: vault_in.to_account_info(),
+                authority: accounts.user.to_account_info(),
+            },
+        ),
+        amount_in,
+    )?;
+
+    let pool_key = pool.key();
+    let seeds: &[&[u8]] = &[pool_key.as_ref(), &[pool.authority_bump]];
+    token::transfer(
+        CpiContext::new_with_signer(
+            accounts.token_program.to_account_info(),
+            Transfer {
+                from: vault_out.to_account_info(),
+                to: accounts.user_token_out.to_account_info(),
+                authority: accounts.pool_authority.to_account_info(),
+            },
+            &[seeds],
+        ),
+        amount_out,
+    )?;
+
+    Ok(())
+}
+
+// 读取兑换路径上的TickArray：属于该池子、第一个包含当前tick、按兑换方向连续
+fn load_tick_arrays<'info>(
+    remaining_accounts: &[AccountInfo<'info>],
+    pool_key: Pubkey,
+    pool: &ConcentratedPool,
+    a_to_b: bool,
+) -> Result<Vec<AccountLoader<'info, TickArray>>> {
+    require!(!remaining_accounts.is_empty(), ErrorCode::InsufficientTickArrays);
+
+    let span = pool.ticks_per_array();
+    let mut expected_start = pool.tick_array_start(pool.current_tick);
+    let mut tick_arrays = Vec::with_capacity(remaining_accounts.len());
+
+    for info in remaining_accounts {
+        let loader = AccountLoader::<TickArray>::try_from(info)?;
+        {
+            let tick_array = loader.load()?;
+            require!(
+                tick_array.pool == pool_key && tick_array.start_tick_index == expected_start,
+                ErrorCode::InvalidTickArray
+            );
+        }
+        tick_arrays.push(loader);
+        expected_start += if a_to_b { -span } else { span };
+    }
+
+    Ok(tick_arrays)
+}
+
+// 跨越已初始化的tick：翻转区间外手续费增长，返回该tick的净流动性
+fn cross_tick(
+    tick_arrays: &[AccountLoader<TickArray>],
+    pool: &ConcentratedPool,
+    tick_index: i32,
+    a_to_b: bool,
+    fee_growth_global_in: u128,
+) -> Result<i128> {
+    let start = pool.tick_array_start(tick_index);
+    for loader in tick_arrays {
+        let mut tick_array = loader.load_mut()?;
+        if tick_array.start_tick_index != start {
+            continue;
+        }
+
+        let (global_a, global_b) = if a_to_b {
+            (fee_growth_global_in, pool.fee_growth_global_b_x64)
+        } else {
+            (pool.fee_growth_global_a_x64, fee_growth_global_in)
+        };
+        let tick = tick_array.tick_mut(tick_index, pool.tick_spacing)?;
+        return Ok(tick.cross(global_a, global_b));
+    }
+
+    err!(ErrorCode::InsufficientTickArrays)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK_LOWER: i32 = -100;
+    const TICK_UPPER: i32 = 100;
+
+    fn inside(global: u128, lower: &Tick, upper: &Tick, current_tick: i32) -> u128 {
+        let (lower_outside, upper_outside) = (
+            lower.fee_growth_outside_a_x64,
+            upper.fee_growth_outside_a_x64,
+        );
+        fee_growth_inside(
+            global,
+            lower_outside,
+            upper_outside,
+            TICK_LOWER,
+            TICK_UPPER,
+            current_tick,
+        )
+    }
+
+    // 价格从区间下方向上穿过整个区间再回到区间内，区间内增长只包含价格在区间内时产生的手续费
+    #[test]
+    fn fee_growth_inside_across_crossed_ticks() {
+        // 全局增长接近u128上限，过程中会回绕
+        let mut global = u128::MAX - 500;
+        // 两端在价格低于区间时初始化，区间外增长为0（约定此前的增长都发生在tick以下）
+        let mut lower = Tick::default();
+        let mut upper = Tick::default();
+        let mut current = -200;
+        let start = inside(global, &lower, &upper, current);
+
+        // 区间下方产生的手续费不计入区间内
+        global = global.wrapping_add(500);
+        assert_eq!(inside(global, &lower, &upper, current), start);
+
+        // 向上跨越下端
+        lower.cross(global, 0);
+        current = TICK_LOWER;
+        assert_eq!(inside(global, &lower, &upper, current), start);
+
+        global = global.wrapping_add(300);
+        assert_eq!(
+            inside(global, &lower, &upper, current),
+            start.wrapping_add(300)
+        );
+
+        // 向上跨越上端，区间上方产生的手续费不计入
+        upper.cross(global, 0);
+        current = TICK_UPPER;
+        global = global.wrapping_add(700);
+        assert_eq!(
+            inside(global, &lower, &upper, current),
+            start.wrapping_add(300)
+        );
+
+        // 向下跨回上端，区间内再产生手续费
+        upper.cross(global, 0);
+        current = TICK_UPPER - 1;
+        global = global.wrapping_add(50);
+        let growth = inside(global, &lower, &upper, current).wrapping_sub(start);
+        assert_eq!(growth, 350);
+
+        // 流动性为1（Q64.64）的持仓按区间内增长结算手续费
+        assert_eq!(fees_owed(start.wrapping_add(growth), start, math::Q64), 350);
+    }
+}
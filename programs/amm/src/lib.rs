@@ -1,6 +1,13 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer, Mint};
 
+pub mod concentrated;
+
+pub use concentrated::{
+    ConcentratedPool, InitializeConcentratedPool, InitializeTickArray, ModifyLiquidity,
+    OpenPosition, Position, SwapConcentrated, Tick, TickArray,
+};
+
 declare_id!("AMM11111111111111111111111111111111111111111");
 
 #[program]
@@ -28,16 +35,13 @@ pub mod amm {
     ) -> Result<()> {
         // 计算LP代币数量
         let pool = &ctx.accounts.pool;
-        let total_supply = ctx.accounts.lp_mint.supply;
-        let lp_amount = if total_supply == 0 {
-            (amount_a as u128 * amount_b as u128).sqrt() as u64
-        } else {
-            let ratio = (amount_a as u128 * total_supply as u128) / pool.reserve_a as u128;
-            std::cmp::min(
-                ratio as u64,
-                (amount_b as u128 * total_supply as u128 / pool.reserve_b as u128) as u64,
-            )
-        };
+        let lp_amount = calculate_lp_tokens(
+            amount_a,
+            amount_b,
+            pool.reserve_a,
+            pool.reserve_b,
+            ctx.accounts.lp_mint.supply,
+        )?;
 
         require!(lp_amount >= min_lp_tokens, ErrorCode::SlippageExceeded);
 
@@ -59,8 +63,8 @@ pub mod amm {
 
         // 更新储备金
         let pool = &mut ctx.accounts.pool;
-        pool.reserve_a = pool.reserve_a.checked_add(amount_a).unwrap();
-        pool.reserve_b = pool.reserve_b.checked_add(amount_b).unwrap();
+        pool.reserve_a = pool.reserve_a.checked_add(amount_a).ok_or(ErrorCode::MathOverflow)?;
+        pool.reserve_b = pool.reserve_b.checked_add(amount_b).ok_or(ErrorCode::MathOverflow)?;
 
         Ok(())
     }
@@ -72,11 +76,14 @@ pub mod amm {
         min_amount_b: u64,
     ) -> Result<()> {
         let pool = &ctx.accounts.pool;
-        let total_supply = ctx.accounts.lp_mint.supply;
 
         // 计算返还的代币数量
-        let amount_a = (lp_amount as u128 * pool.reserve_a as u128 / total_supply as u128) as u64;
-        let amount_b = (lp_amount as u128 * pool.reserve_b as u128 / total_supply as u128) as u64;
+        let (amount_a, amount_b) = calculate_withdraw_amounts(
+            lp_amount,
+            pool.reserve_a,
+            pool.reserve_b,
+            ctx.accounts.lp_mint.supply,
+        )?;
 
         require!(
             amount_a >= min_amount_a && amount_b >= min_amount_b,
@@ -101,49 +108,269 @@ pub mod amm {
 
         // 更新储备金
         let pool = &mut ctx.accounts.pool;
-        pool.reserve_a = pool.reserve_a.checked_sub(amount_a).unwrap();
-        pool.reserve_b = pool.reserve_b.checked_sub(amount_b).unwrap();
+        pool.reserve_a = pool.reserve_a.checked_sub(amount_a).ok_or(ErrorCode::MathOverflow)?;
+        pool.reserve_b = pool.reserve_b.checked_sub(amount_b).ok_or(ErrorCode::MathOverflow)?;
 
         Ok(())
     }
 
+    // 按输入数量兑换，方向由输入代币账户的mint决定
     pub fn swap(
         ctx: Context<Swap>,
         amount_in: u64,
         minimum_amount_out: u64,
     ) -> Result<()> {
-        let pool = &ctx.accounts.pool;
-        
+        let a_to_b = ctx.accounts.direction()?;
+        let (reserve_in, reserve_out) = ctx.accounts.pool.reserves(a_to_b);
+        let fees = ctx.accounts.pool.fees;
+
         // 计算输出金额（使用恒定乘积公式）
         let amount_out = calculate_output_amount(
             amount_in,
-            pool.reserve_a,
-            pool.reserve_b,
-            pool.fees.swap_fee_numerator,
-            pool.fees.swap_fee_denominator,
+            reserve_in,
+            reserve_out,
+            fees.swap_fee_numerator,
+            fees.swap_fee_denominator,
         )?;
 
+        require!(amount_out > 0, ErrorCode::ZeroTradingTokens);
         require!(amount_out >= minimum_amount_out, ErrorCode::SlippageExceeded);
 
-        // 执行代币交换
-        token::transfer(
-            ctx.accounts.into_transfer_in_context(),
-            amount_in,
-        )?;
-        token::transfer(
-            ctx.accounts.into_transfer_out_context(),
+        let bump = *ctx.bumps.get("pool_authority").unwrap();
+        ctx.accounts.execute(a_to_b, amount_in, amount_out, bump)
+    }
+
+    // 按输出数量兑换，实际输入不超过max_amount_in
+    pub fn swap_exact_out(
+        ctx: Context<Swap>,
+        amount_out: u64,
+        max_amount_in: u64,
+    ) -> Result<()> {
+        let a_to_b = ctx.accounts.direction()?;
+        let (reserve_in, reserve_out) = ctx.accounts.pool.reserves(a_to_b);
+        let fees = ctx.accounts.pool.fees;
+
+        require!(amount_out > 0, ErrorCode::ZeroTradingTokens);
+
+        // 反推所需的输入金额
+        let amount_in = calculate_input_amount(
             amount_out,
+            reserve_in,
+            reserve_out,
+            fees.swap_fee_numerator,
+            fees.swap_fee_denominator,
         )?;
 
-        // 更新储备金
-        let pool = &mut ctx.accounts.pool;
-        pool.reserve_a = pool.reserve_a.checked_add(amount_in).unwrap();
-        pool.reserve_b = pool.reserve_b.checked_sub(amount_out).unwrap();
+        require!(amount_in <= max_amount_in, ErrorCode::SlippageExceeded);
 
-        Ok(())
+        let bump = *ctx.bumps.get("pool_authority").unwrap();
+        ctx.accounts.execute(a_to_b, amount_in, amount_out, bump)
+    }
+
+    // 创建集中流动性池，初始价格以Q64.64格式的sqrt(price)给出
+    pub fn initialize_concentrated_pool(
+        ctx: Context<InitializeConcentratedPool>,
+        tick_spacing: u16,
+        fees: PoolFees,
+        initial_sqrt_price_x64: u128,
+    ) -> Result<()> {
+        concentrated::initialize_pool(ctx, tick_spacing, fees, initial_sqrt_price_x64)
+    }
+
+    pub fn initialize_tick_array(
+        ctx: Context<InitializeTickArray>,
+        start_tick_index: i32,
+    ) -> Result<()> {
+        concentrated::initialize_tick_array(ctx, start_tick_index)
+    }
+
+    pub fn open_position(
+        ctx: Context<OpenPosition>,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> Result<()> {
+        concentrated::open_position(ctx, tick_lower, tick_upper)
+    }
+
+    pub fn increase_liquidity(
+        ctx: Context<ModifyLiquidity>,
+        liquidity_delta: u128,
+        max_amount_a: u64,
+        max_amount_b: u64,
+    ) -> Result<()> {
+        concentrated::increase_liquidity(ctx, liquidity_delta, max_amount_a, max_amount_b)
+    }
+
+    pub fn decrease_liquidity(
+        ctx: Context<ModifyLiquidity>,
+        liquidity_delta: u128,
+        min_amount_a: u64,
+        min_amount_b: u64,
+    ) -> Result<()> {
+        concentrated::decrease_liquidity(ctx, liquidity_delta, min_amount_a, min_amount_b)
+    }
+
+    pub fn collect(ctx: Context<ModifyLiquidity>) -> Result<()> {
+        concentrated::collect(ctx)
+    }
+
+    // 集中流动性兑换，remaining_accounts传入价格经过的TickArray
+    pub fn swap_concentrated<'info>(
+        ctx: Context<'_, '_, '_, 'info, SwapConcentrated<'info>>,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        sqrt_price_limit_x64: u128,
+    ) -> Result<()> {
+        concentrated::swap(ctx, amount_in, minimum_amount_out, sqrt_price_limit_x64)
     }
 }
 
+// 恒定乘积公式计算输出金额
+// 手续费和输出都向下取整，舍入误差留在池子中
+pub fn calculate_output_amount(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> Result<u64> {
+    require!(
+        fee_denominator > 0 && fee_numerator < fee_denominator,
+        ErrorCode::InvalidFee
+    );
+    require!(reserve_in > 0 && reserve_out > 0, ErrorCode::InsufficientLiquidity);
+
+    let amount_in_after_fee = (amount_in as u128)
+        .checked_mul((fee_denominator - fee_numerator) as u128)
+        .and_then(|v| v.checked_div(fee_denominator as u128))
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let numerator = amount_in_after_fee
+        .checked_mul(reserve_out as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let denominator = (reserve_in as u128)
+        .checked_add(amount_in_after_fee)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    u64::try_from(numerator / denominator).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+// 恒定乘积公式反推得到amount_out所需的输入金额
+// 扣费前后的输入都向上取整，舍入误差由交易者承担
+pub fn calculate_input_amount(
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> Result<u64> {
+    require!(
+        fee_denominator > 0 && fee_numerator < fee_denominator,
+        ErrorCode::InvalidFee
+    );
+    require!(
+        reserve_in > 0 && amount_out < reserve_out,
+        ErrorCode::InsufficientLiquidity
+    );
+
+    let amount_in_after_fee = ceil_div(
+        (reserve_in as u128)
+            .checked_mul(amount_out as u128)
+            .ok_or(ErrorCode::MathOverflow)?,
+        (reserve_out - amount_out) as u128,
+    )?;
+    let amount_in = ceil_div(
+        amount_in_after_fee
+            .checked_mul(fee_denominator as u128)
+            .ok_or(ErrorCode::MathOverflow)?,
+        (fee_denominator - fee_numerator) as u128,
+    )?;
+
+    u64::try_from(amount_in).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+// 计算存入amount_a和amount_b可铸造的LP代币数量
+// 首次注入按sqrt(a*b)计算，之后按两边占比的较小值计算，全部向下取整，舍入误差留在池子中
+pub fn calculate_lp_tokens(
+    amount_a: u64,
+    amount_b: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+    total_supply: u64,
+) -> Result<u64> {
+    let lp_amount = if total_supply == 0 {
+        integer_sqrt(
+            (amount_a as u128)
+                .checked_mul(amount_b as u128)
+                .ok_or(ErrorCode::MathOverflow)?,
+        )
+    } else {
+        require!(
+            reserve_a > 0 && reserve_b > 0,
+            ErrorCode::InsufficientLiquidity
+        );
+        let share_a = (amount_a as u128)
+            .checked_mul(total_supply as u128)
+            .and_then(|v| v.checked_div(reserve_a as u128))
+            .ok_or(ErrorCode::MathOverflow)?;
+        let share_b = (amount_b as u128)
+            .checked_mul(total_supply as u128)
+            .and_then(|v| v.checked_div(reserve_b as u128))
+            .ok_or(ErrorCode::MathOverflow)?;
+        share_a.min(share_b)
+    };
+
+    require!(lp_amount > 0, ErrorCode::ZeroTradingTokens);
+    u64::try_from(lp_amount).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+// 计算销毁lp_amount个LP代币可取回的 (A数量, B数量)
+// 两边都向下取整，舍入误差留在池子中
+pub fn calculate_withdraw_amounts(
+    lp_amount: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+    total_supply: u64,
+) -> Result<(u64, u64)> {
+    require!(
+        total_supply > 0 && lp_amount <= total_supply,
+        ErrorCode::InsufficientLiquidity
+    );
+
+    let share = |reserve: u64| -> Result<u64> {
+        let amount = (lp_amount as u128)
+            .checked_mul(reserve as u128)
+            .and_then(|v| v.checked_div(total_supply as u128))
+            .ok_or(ErrorCode::MathOverflow)?;
+        u64::try_from(amount).map_err(|_| error!(ErrorCode::MathOverflow))
+    };
+
+    Ok((share(reserve_a)?, share(reserve_b)?))
+}
+
+// 向下取整的整数平方根（牛顿迭代）
+fn integer_sqrt(value: u128) -> u128 {
+    if value < 2 {
+        return value;
+    }
+    let mut x = value;
+    let mut y = x / 2 + (x & 1);
+    while y < x {
+        x = y;
+        y = (x + value / x) / 2;
+    }
+    x
+}
+
+// 向上取整的除法
+fn ceil_div(numerator: u128, denominator: u128) -> Result<u128> {
+    require!(denominator > 0, ErrorCode::MathOverflow);
+    numerator
+        .checked_add(denominator - 1)
+        .map(|v| v / denominator)
+        .ok_or_else(|| error!(ErrorCode::MathOverflow))
+}
+
 #[account]
 pub struct Pool {
     pub token_a_mint: Pubkey,
@@ -155,6 +382,17 @@ pub struct Pool {
     pub authority: Pubkey,
 }
 
+impl Pool {
+    // 按兑换方向返回 (输入储备, 输出储备)
+    pub fn reserves(&self, a_to_b: bool) -> (u64, u64) {
+        if a_to_b {
+            (self.reserve_a, self.reserve_b)
+        } else {
+            (self.reserve_b, self.reserve_a)
+        }
+    }
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
 pub struct PoolFees {
     pub swap_fee_numerator: u64,
@@ -167,6 +405,22 @@ pub enum ErrorCode {
     SlippageExceeded,
     #[msg("Invalid pool tokens")]
     InvalidPoolTokens,
+    #[msg("Math overflow")]
+    MathOverflow,
+    #[msg("Insufficient pool liquidity")]
+    InsufficientLiquidity,
+    #[msg("Invalid fee parameters")]
+    InvalidFee,
+    #[msg("Swap amount rounds to zero")]
+    ZeroTradingTokens,
+    #[msg("Invalid tick")]
+    InvalidTick,
+    #[msg("Invalid sqrt price")]
+    InvalidSqrtPrice,
+    #[msg("Invalid tick array")]
+    InvalidTickArray,
+    #[msg("Not enough tick arrays for the swap")]
+    InsufficientTickArrays,
 }
 
 #[derive(Accounts)]
@@ -200,4 +454,133 @@ pub struct AddLiquidity<'info> {
     pub token_program: Program<'info, Token>,
     #[account(signer)]
     pub authority: Signer<'info>,
-}
\ No newline at end of file
+}
+
+#[derive(Accounts)]
+pub struct Swap<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+    #[account(mut)]
+    pub user_token_in: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub user_token_out: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = pool_token_in.mint == user_token_in.mint @ ErrorCode::InvalidPoolTokens,
+        constraint = pool_token_in.owner == pool_authority.key() @ ErrorCode::InvalidPoolTokens
+    )]
+    pub pool_token_in: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = pool_token_out.mint == user_token_out.mint @ ErrorCode::InvalidPoolTokens,
+        constraint = pool_token_out.owner == pool_authority.key() @ ErrorCode::InvalidPoolTokens
+    )]
+    pub pool_token_out: Account<'info, TokenAccount>,
+    /// CHECK: 池子金库的PDA权限账户，只用于签名
+    #[account(seeds = [pool.key().as_ref()], bump)]
+    pub pool_authority: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub user: Signer<'info>,
+}
+
+impl<'info> Swap<'info> {
+    // 根据输入代币账户的mint判断方向，A→B时返回true
+    fn direction(&self) -> Result<bool> {
+        let pool = &self.pool;
+        let mint_in = self.user_token_in.mint;
+        let mint_out = self.user_token_out.mint;
+
+        if mint_in == pool.token_a_mint && mint_out == pool.token_b_mint {
+            Ok(true)
+        } else if mint_in == pool.token_b_mint && mint_out == pool.token_a_mint {
+            Ok(false)
+        } else {
+            err!(ErrorCode::InvalidPoolTokens)
+        }
+    }
+
+    fn into_transfer_in_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            Transfer {
+                from: self.user_token_in.to_account_info(),
+                to: self.pool_token_in.to_account_info(),
+                authority: self.user.to_account_info(),
+            },
+        )
+    }
+
+    fn into_transfer_out_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        CpiContext::new(
+            self.token_program.to_account_info(),
+            Transfer {
+                from: self.pool_token_out.to_account_info(),
+                to: self.user_token_out.to_account_info(),
+                authority: self.pool_authority.to_account_info(),
+            },
+        )
+    }
+
+    // 执行代币交换并更新储备金
+    fn execute(&mut self, a_to_b: bool, amount_in: u64, amount_out: u64, bump: u8) -> Result<()> {
+        token::transfer(self.into_transfer_in_context(), amount_in)?;
+
+        let pool_key = self.pool.key();
+        let seeds: &[&[u8]] = &[pool_key.as_ref(), &[bump]];
+        token::transfer(
+            self.into_transfer_out_context().with_signer(&[seeds]),
+            amount_out,
+        )?;
+
+        let pool = &mut self.pool;
+        let (reserve_in, reserve_out) = pool.reserves(a_to_b);
+        let reserve_in = reserve_in
+            .checked_add(amount_in)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let reserve_out = reserve_out
+            .checked_sub(amount_out)
+            .ok_or(ErrorCode::InsufficientLiquidity)?;
+
+        if a_to_b {
+            pool.reserve_a = reserve_in;
+            pool.reserve_b = reserve_out;
+        } else {
+            pool.reserve_b = reserve_in;
+            pool.reserve_a = reserve_out;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_deposit_mints_floor_sqrt() {
+        assert_eq!(calculate_lp_tokens(4, 9, 0, 0, 0).unwrap(), 6);
+        assert_eq!(calculate_lp_tokens(2, 5, 0, 0, 0).unwrap(), 3);
+        assert_eq!(
+            calculate_lp_tokens(u64::MAX, u64::MAX, 0, 0, 0).unwrap(),
+            u64::MAX
+        );
+        assert!(calculate_lp_tokens(0, 1_000, 0, 0, 0).is_err());
+    }
+
+    #[test]
+    fn deposits_round_in_the_pools_favor() {
+        // 按较少的一边计算，且向下取整
+        assert_eq!(calculate_lp_tokens(10, 30, 30, 60, 100).unwrap(), 33);
+        // 空储备但已有LP供应时不能除以零
+        assert!(calculate_lp_tokens(10, 10, 0, 100, 100).is_err());
+    }
+
+    #[test]
+    fn withdrawals_round_down_and_reject_bad_supply() {
+        assert_eq!(calculate_withdraw_amounts(1, 10, 21, 3).unwrap(), (3, 7));
+        assert_eq!(calculate_withdraw_amounts(3, 10, 21, 3).unwrap(), (10, 21));
+        assert!(calculate_withdraw_amounts(1, 10, 10, 0).is_err());
+        assert!(calculate_withdraw_amounts(4, 10, 10, 3).is_err());
+    }
+}
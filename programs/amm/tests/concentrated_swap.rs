@@ -0,0 +1,290 @@
+// 集中流动性兑换在传入的TickArray范围边界上的行为：两个方向都停在边界并部分成交
+use amm::concentrated::math::{compute_swap_step, sqrt_price_at_tick, MAX_TICK, MIN_TICK};
+use amm::{ConcentratedPool, PoolFees, TickArray};
+use anchor_lang::{AccountSerialize, Discriminator, InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use solana_program::program_option::COption;
+use solana_program::program_pack::Pack;
+use solana_program::rent::Rent;
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::account_info::AccountInfo;
+use solana_sdk::entrypoint::ProgramResult;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+
+const LIQUIDITY: u128 = 1_000_000_000_000;
+const CURRENT_TICK: i32 = 10;
+const FEE_NUMERATOR: u64 = 30;
+const FEE_DENOMINATOR: u64 = 10_000;
+const USER_BALANCE: u64 = 10_000_000_000;
+
+// Anchor生成的entry要求账户切片与AccountInfo同生命周期，这里复制一份账户列表以适配processor!
+fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    amm::entry(program_id, accounts, data)
+}
+
+fn rent_exempt_account(data: Vec<u8>, owner: Pubkey) -> Account {
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn add_token_account(
+    program_test: &mut ProgramTest,
+    address: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) {
+    let mut data = vec![0u8; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        delegate: COption::None,
+        state: spl_token::state::AccountState::Initialized,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    program_test.add_account(*address, rent_exempt_account(data, spl_token::ID));
+}
+
+async fn token_balance(context: &mut ProgramTestContext, address: &Pubkey) -> u64 {
+    let account = context
+        .banks_client
+        .get_account(*address)
+        .await
+        .unwrap()
+        .unwrap();
+    spl_token::state::Account::unpack(&account.data)
+        .unwrap()
+        .amount
+}
+
+struct PoolFixture {
+    context: ProgramTestContext,
+    user: Keypair,
+    pool: Pubkey,
+    pool_authority: Pubkey,
+    vault_a: Pubkey,
+    vault_b: Pubkey,
+    user_token_a: Pubkey,
+    user_token_b: Pubkey,
+    tick_array: Pubkey,
+}
+
+// 价格位于tick 10，流动性来自范围更大的持仓，传入的TickArray [0, 32) 内没有已初始化的tick
+async fn start_pool() -> PoolFixture {
+    let mut program_test = ProgramTest::new("amm", amm::ID, processor!(process_instruction));
+    let user = Keypair::new();
+    let pool = Pubkey::new_unique();
+    let (pool_authority, authority_bump) = Pubkey::find_program_address(&[pool.as_ref()], &amm::ID);
+    let (token_a_mint, token_b_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (vault_a, vault_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (user_token_a, user_token_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    for (address, mint, owner) in [
+        (&vault_a, &token_a_mint, &pool_authority),
+        (&vault_b, &token_b_mint, &pool_authority),
+        (&user_token_a, &token_a_mint, &user.pubkey()),
+        (&user_token_b, &token_b_mint, &user.pubkey()),
+    ] {
+        add_token_account(&mut program_test, address, mint, owner, USER_BALANCE);
+    }
+
+    let pool_account = ConcentratedPool {
+        token_a_mint,
+        token_b_mint,
+        vault_a,
+        vault_b,
+        fees: PoolFees {
+            swap_fee_numerator: FEE_NUMERATOR,
+            swap_fee_denominator: FEE_DENOMINATOR,
+        },
+        tick_spacing: 1,
+        sqrt_price_x64: sqrt_price_at_tick(CURRENT_TICK).unwrap(),
+        current_tick: CURRENT_TICK,
+        liquidity: LIQUIDITY,
+        fee_growth_global_a_x64: 0,
+        fee_growth_global_b_x64: 0,
+        authority: pool_authority,
+        authority_bump,
+    };
+    let mut data = Vec::new();
+    pool_account.try_serialize(&mut data).unwrap();
+    data.resize(8 + std::mem::size_of::<ConcentratedPool>(), 0);
+    program_test.add_account(pool, rent_exempt_account(data, amm::ID));
+
+    let tick_array = Pubkey::new_unique();
+    let mut data = vec![0u8; 8 + std::mem::size_of::<TickArray>()];
+    data[..8].copy_from_slice(&TickArray::DISCRIMINATOR);
+    let array: &mut TickArray = bytemuck::from_bytes_mut(&mut data[8..]);
+    array.pool = pool;
+    array.start_tick_index = 0;
+    program_test.add_account(tick_array, rent_exempt_account(data, amm::ID));
+
+    PoolFixture {
+        context: program_test.start_with_context().await,
+        user,
+        pool,
+        pool_authority,
+        vault_a,
+        vault_b,
+        user_token_a,
+        user_token_b,
+        tick_array,
+    }
+}
+
+// 只传入 [0, 32) 这一个TickArray，兑换到价格限制为止
+async fn swap(fixture: &mut PoolFixture, a_to_b: bool, amount_in: u64) {
+    let (user_token_in, user_token_out, sqrt_price_limit_x64) = if a_to_b {
+        (
+            fixture.user_token_a,
+            fixture.user_token_b,
+            sqrt_price_at_tick(MIN_TICK).unwrap(),
+        )
+    } else {
+        (
+            fixture.user_token_b,
+            fixture.user_token_a,
+            sqrt_price_at_tick(MAX_TICK).unwrap(),
+        )
+    };
+    let mut accounts = amm::accounts::SwapConcentrated {
+        pool: fixture.pool,
+        user_token_in,
+        user_token_out,
+        vault_a: fixture.vault_a,
+        vault_b: fixture.vault_b,
+        pool_authority: fixture.pool_authority,
+        user: fixture.user.pubkey(),
+        token_program: spl_token::ID,
+    }
+    .to_account_metas(None);
+    accounts.push(AccountMeta::new(fixture.tick_array, false));
+    let swap = Instruction {
+        program_id: amm::ID,
+        accounts,
+        data: amm::instruction::SwapConcentrated {
+            amount_in,
+            minimum_amount_out: 1,
+            sqrt_price_limit_x64,
+        }
+        .data(),
+    };
+    let context = &mut fixture.context;
+    let transaction = Transaction::new_signed_with_payer(
+        &[swap],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &fixture.user],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+}
+
+async fn pool_state(fixture: &mut PoolFixture) -> ConcentratedPool {
+    let account = fixture
+        .context
+        .banks_client
+        .get_account(fixture.pool)
+        .await
+        .unwrap()
+        .unwrap();
+    anchor_lang::AccountDeserialize::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+#[tokio::test]
+async fn a_to_b_swap_ends_on_last_tick_array_boundary() {
+    let mut fixture = start_pool().await;
+
+    // 输入足以把价格推过tick 0，兑换应停在传入范围的下边界，而不是因缺少下一个TickArray失败
+    let amount_in = 1_000_000_000;
+    swap(&mut fixture, true, amount_in).await;
+
+    let boundary = sqrt_price_at_tick(0).unwrap();
+    let step = compute_swap_step(
+        sqrt_price_at_tick(CURRENT_TICK).unwrap(),
+        boundary,
+        LIQUIDITY,
+        amount_in,
+        FEE_NUMERATOR,
+        FEE_DENOMINATOR,
+    )
+    .unwrap();
+    assert_eq!(step.sqrt_price_next_x64, boundary);
+
+    // tick 0在传入范围内且未初始化，停在边界时视为已跨越
+    let pool_account = pool_state(&mut fixture).await;
+    assert_eq!(pool_account.sqrt_price_x64, boundary);
+    assert_eq!(pool_account.current_tick, -1);
+    assert_eq!(pool_account.liquidity, LIQUIDITY);
+
+    // 只收取走到边界所需的输入
+    let spent = step.amount_in + step.fee_amount;
+    assert!(spent < amount_in);
+    assert_eq!(
+        token_balance(&mut fixture.context, &fixture.user_token_a).await,
+        USER_BALANCE - spent
+    );
+    assert_eq!(
+        token_balance(&mut fixture.context, &fixture.user_token_b).await,
+        USER_BALANCE + step.amount_out
+    );
+}
+
+#[tokio::test]
+async fn b_to_a_swap_ends_on_last_tick_array_boundary() {
+    let mut fixture = start_pool().await;
+
+    // 输入足以把价格推过tick 32，兑换应停在传入范围的上边界，与A→B方向一致
+    let amount_in = 2_000_000_000;
+    swap(&mut fixture, false, amount_in).await;
+
+    let boundary = sqrt_price_at_tick(32).unwrap();
+    let step = compute_swap_step(
+        sqrt_price_at_tick(CURRENT_TICK).unwrap(),
+        boundary,
+        LIQUIDITY,
+        amount_in,
+        FEE_NUMERATOR,
+        FEE_DENOMINATOR,
+    )
+    .unwrap();
+    assert_eq!(step.sqrt_price_next_x64, boundary);
+
+    // tick 32属于下一个TickArray，停在它的价格上但不跨越
+    let pool_account = pool_state(&mut fixture).await;
+    assert_eq!(pool_account.sqrt_price_x64, boundary);
+    assert_eq!(pool_account.current_tick, 31);
+    assert_eq!(pool_account.liquidity, LIQUIDITY);
+
+    let spent = step.amount_in + step.fee_amount;
+    assert!(spent < amount_in);
+    assert_eq!(
+        token_balance(&mut fixture.context, &fixture.user_token_b).await,
+        USER_BALANCE - spent
+    );
+    assert_eq!(
+        token_balance(&mut fixture.context, &fixture.user_token_a).await,
+        USER_BALANCE + step.amount_out
+    );
+}
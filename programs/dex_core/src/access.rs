@@ -0,0 +1,257 @@
+use crate::core::Market;
+use crate::events::{AccessControlAction, EventHandler};
+use crate::migration::CURRENT_SCHEMA_VERSION;
+use crate::risk::RiskParameters;
+use crate::ErrorCode;
+use anchor_lang::prelude::*;
+
+// 市场准入控制 - 通过白名单或黑名单限制可交易的钱包
+// 在下单、存款和结算三条路径上检查
+pub struct AccessControlEngine;
+
+// 准入模式
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AccessMode {
+    Allowlist = 0, // 白名单：只有成员可以交易
+    Denylist = 1,  // 黑名单：成员不能交易
+}
+
+// 市场准入控制账户
+#[account]
+pub struct MarketAccessControl {
+    pub schema_version: u8, // 账户布局版本
+    pub market: Pubkey,    // 市场公钥
+    pub authority: Pubkey, // 名单管理员
+    pub mode: AccessMode,  // 准入模式
+    pub member_count: u32, // 成员数量
+    pub bump: u8,          // PDA bump值
+}
+
+impl MarketAccessControl {
+    pub const LEN: usize = 1 + 32 + 32 + 1 + 4 + 1;
+}
+
+// 名单成员记录（PDA存在即为成员）
+#[account]
+pub struct AccessMember {
+    pub schema_version: u8,     // 账户布局版本
+    pub access_control: Pubkey, // 所属准入控制账户
+    pub wallet: Pubkey,         // 成员钱包
+    pub added_at: i64,          // 加入时间
+    pub bump: u8,               // PDA bump值
+}
+
+impl AccessMember {
+    pub const LEN: usize = 1 + 32 + 32 + 8 + 1;
+}
+
+impl AccessControlEngine {
+    // 推导成员记录的PDA地址
+    pub fn member_address(access_control: &Pubkey, wallet: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"access_member", access_control.as_ref(), wallet.as_ref()],
+            &crate::ID,
+        )
+        .0
+    }
+
+    // 检查钱包是否允许在该市场交易
+    // 调用方需要传入成员记录的PDA地址，黑名单模式下该账户不存在即表示允许
+    pub fn check_access(
+        market: &Market,
+        access_control: Option<&Account<MarketAccessControl>>,
+        member: Option<&AccountInfo>,
+        wallet: &Pubkey,
+    ) -> Result<()> {
+        // 未启用准入控制的市场不做限制
+        let expected_key = match market.access_control {
+            Some(key) => key,
+            None => return Ok(()),
+        };
+
+        let access_control = access_control.ok_or(ErrorCode::WalletNotPermitted)?;
+        require_keys_eq!(
+            access_control.key(),
+            expected_key,
+            ErrorCode::WalletNotPermitted
+        );
+
+        let member = member.ok_or(ErrorCode::WalletNotPermitted)?;
+        require_keys_eq!(
+            member.key(),
+            Self::member_address(&access_control.key(), wallet),
+            ErrorCode::WalletNotPermitted
+        );
+
+        let is_member = member.owner == &crate::ID && !member.data_is_empty();
+
+        match access_control.mode {
+            AccessMode::Allowlist => require!(is_member, ErrorCode::WalletNotPermitted),
+            AccessMode::Denylist => require!(!is_member, ErrorCode::WalletNotPermitted),
+        }
+
+        Ok(())
+    }
+}
+
+// 初始化准入控制所需的账户
+#[derive(Accounts)]
+pub struct InitializeAccessControl<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    // 只有市场的风控管理员可以启用准入控制
+    #[account(
+        seeds = [b"risk_parameters", market.key().as_ref()],
+        bump = risk_parameters.bump,
+        has_one = authority @ ErrorCode::UnauthorizedOperation
+    )]
+    pub risk_parameters: Account<'info, RiskParameters>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + MarketAccessControl::LEN,
+        seeds = [b"access_control", market.key().as_ref()],
+        bump
+    )]
+    pub access_control: Account<'info, MarketAccessControl>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// 添加名单成员所需的账户
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct AddAccessMember<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"access_control", market.key().as_ref()],
+        bump = access_control.bump,
+        has_one = market,
+        has_one = authority @ ErrorCode::UnauthorizedOperation
+    )]
+    pub access_control: Account<'info, MarketAccessControl>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AccessMember::LEN,
+        seeds = [b"access_member", access_control.key().as_ref(), wallet.as_ref()],
+        bump
+    )]
+    pub access_member: Account<'info, AccessMember>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// 移除名单成员所需的账户
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct RemoveAccessMember<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"access_control", market.key().as_ref()],
+        bump = access_control.bump,
+        has_one = market,
+        has_one = authority @ ErrorCode::UnauthorizedOperation
+    )]
+    pub access_control: Account<'info, MarketAccessControl>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"access_member", access_control.key().as_ref(), wallet.as_ref()],
+        bump = access_member.bump
+    )]
+    pub access_member: Account<'info, AccessMember>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+// 为市场启用准入控制
+pub fn initialize_access_control(
+    ctx: Context<InitializeAccessControl>,
+    mode: AccessMode,
+) -> Result<()> {
+    require!(
+        ctx.accounts.market.access_control.is_none(),
+        ErrorCode::InvalidParameters
+    );
+
+    let access_control = &mut ctx.accounts.access_control;
+    access_control.schema_version = CURRENT_SCHEMA_VERSION;
+    access_control.market = ctx.accounts.market.key();
+    access_control.authority = ctx.accounts.authority.key();
+    access_control.mode = mode;
+    access_control.member_count = 0;
+    access_control.bump = *ctx.bumps.get("access_control").unwrap();
+
+    ctx.accounts.market.access_control = Some(access_control.key());
+
+    EventHandler::emit_access_control_changed(
+        ctx.accounts.market.key(),
+        ctx.accounts.market.next_event_seq(),
+        access_control.key(),
+        AccessControlAction::Initialized,
+        mode,
+        None,
+    );
+
+    Ok(())
+}
+
+// 添加名单成员
+pub fn add_access_member(ctx: Context<AddAccessMember>, wallet: Pubkey) -> Result<()> {
+    let access_control = &mut ctx.accounts.access_control;
+    let member = &mut ctx.accounts.access_member;
+
+    member.schema_version = CURRENT_SCHEMA_VERSION;
+    member.access_control = access_control.key();
+    member.wallet = wallet;
+    member.added_at = Clock::get()?.unix_timestamp;
+    member.bump = *ctx.bumps.get("access_member").unwrap();
+
+    access_control.member_count = access_control.member_count.saturating_add(1);
+
+    EventHandler::emit_access_control_changed(
+        ctx.accounts.market.key(),
+        ctx.accounts.market.next_event_seq(),
+        access_control.key(),
+        AccessControlAction::MemberAdded,
+        access_control.mode,
+        Some(wallet),
+    );
+
+    Ok(())
+}
+
+// 移除名单成员（关闭成员记录并退还租金）
+pub fn remove_access_member(ctx: Context<RemoveAccessMember>, wallet: Pubkey) -> Result<()> {
+    let access_control = &mut ctx.accounts.access_control;
+    access_control.member_count = access_control.member_count.saturating_sub(1);
+
+    EventHandler::emit_access_control_changed(
+        ctx.accounts.market.key(),
+        ctx.accounts.market.next_event_seq(),
+        access_control.key(),
+        AccessControlAction::MemberRemoved,
+        access_control.mode,
+        Some(wallet),
+    );
+
+    Ok(())
+}
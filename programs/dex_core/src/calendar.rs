@@ -0,0 +1,454 @@
+use crate::core::Market;
+use crate::migration::CURRENT_SCHEMA_VERSION;
+use crate::risk::RiskParameters;
+use crate::ErrorCode;
+use anchor_lang::prelude::*;
+
+// 交易日历 - 负责按市场维护交易时段、时区和休市日
+pub struct CalendarEngine;
+
+pub const MINUTES_PER_DAY: i64 = 1440;
+pub const SECONDS_PER_DAY: i64 = 86400;
+pub const MAX_HOLIDAYS: usize = 32;
+
+// 交易阶段
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TradingPhase {
+    Closed = 0,         // 休市
+    PreOpenAuction = 1, // 开盘前集合竞价（只挂单不撮合）
+    Open = 2,           // 连续交易
+    CancelOnly = 3,     // 收盘前只允许撤单
+}
+
+// 单日交易时段（本地时间，按分钟计）
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Debug)]
+pub struct TradingSession {
+    pub enabled: bool,            // 当天是否开市
+    pub open_minute: u16,         // 开盘时间（当日第几分钟，0-1439）
+    pub close_minute: u16,        // 收盘时间（1-1440，小于等于开盘时间表示跨午夜）
+    pub pre_open_minutes: u16,    // 开盘前集合竞价时长（分钟）
+    pub cancel_only_minutes: u16, // 收盘前只撤单时长（分钟）
+}
+
+impl TradingSession {
+    pub const LEN: usize = 1 + 2 + 2 + 2 + 2;
+
+    // 时段长度（分钟），跨午夜时段自动加一天
+    pub fn duration_minutes(&self) -> i64 {
+        let open = self.open_minute as i64;
+        let close = self.close_minute as i64;
+        if close > open {
+            close - open
+        } else {
+            close + MINUTES_PER_DAY - open
+        }
+    }
+}
+
+// 市场交易日历
+#[account]
+pub struct TradingCalendar {
+    pub schema_version: u8,               // 账户布局版本
+    pub market: Pubkey,                   // 市场公钥
+    pub authority: Pubkey,                // 日历管理员
+    pub utc_offset_minutes: i16,          // 本地时区相对UTC的偏移（分钟）
+    pub sessions: [TradingSession; 7],    // 每周交易时段（0=周一 ... 6=周日）
+    pub holidays_count: u8,               // 休市日数量
+    pub holidays: [i32; MAX_HOLIDAYS],    // 休市日（本地日期，距1970-01-01的天数）
+    pub is_active: bool,                  // 是否启用日历
+    pub bump: u8,                         // PDA bump值
+}
+
+impl TradingCalendar {
+    pub const LEN: usize =
+        1 + 32 + 32 + 2 + (7 * TradingSession::LEN) + 1 + (4 * MAX_HOLIDAYS) + 1 + 1;
+
+    // 判断某个本地日期是否为休市日
+    pub fn is_holiday(&self, local_day: i64) -> bool {
+        self.holidays[..self.holidays_count as usize]
+            .iter()
+            .any(|&day| day as i64 == local_day)
+    }
+
+    // 计算给定UTC时间戳所处的交易阶段
+    pub fn phase_at(&self, unix_timestamp: i64) -> TradingPhase {
+        if !self.is_active {
+            return TradingPhase::Open;
+        }
+
+        let local_ts = unix_timestamp + self.utc_offset_minutes as i64 * 60;
+        let local_day = local_ts.div_euclid(SECONDS_PER_DAY);
+        let minute_of_day = local_ts.rem_euclid(SECONDS_PER_DAY) / 60;
+
+        // 依次检查昨天开始的跨午夜时段、今天的时段和明天在午夜前开始的集合竞价
+        for day_offset in [-1i64, 0, 1] {
+            let session_day = local_day + day_offset;
+            let session = &self.sessions[weekday_from_days(session_day) as usize];
+
+            if !session.enabled || self.is_holiday(session_day) {
+                continue;
+            }
+
+            // 相对于session_day零点的分钟数
+            let now = minute_of_day - day_offset * MINUTES_PER_DAY;
+            let open = session.open_minute as i64;
+            let close = open + session.duration_minutes();
+            let pre_open_start = open - session.pre_open_minutes as i64;
+            let cancel_only_start = close - session.cancel_only_minutes as i64;
+
+            if now >= pre_open_start && now < open {
+                return TradingPhase::PreOpenAuction;
+            }
+            if now >= open && now < close {
+                if now >= cancel_only_start {
+                    return TradingPhase::CancelOnly;
+                }
+                return TradingPhase::Open;
+            }
+        }
+
+        TradingPhase::Closed
+    }
+}
+
+// 根据距1970-01-01的天数计算星期（0=周一 ... 6=周日）
+pub fn weekday_from_days(days: i64) -> u8 {
+    // 1970-01-01 是周四
+    (days + 3).rem_euclid(7) as u8
+}
+
+// 将公历日期转换为距1970-01-01的天数
+pub fn days_from_civil(year: i32, month: u8, day: u8) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year } as i64;
+    let m = month as i64;
+    let d = day as i64;
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+impl CalendarEngine {
+    // 根据当前交易阶段检查是否允许下单，返回是否只允许挂单（集合竞价期间）
+    pub fn check_can_place(calendar: &TradingCalendar, unix_timestamp: i64) -> Result<bool> {
+        match calendar.phase_at(unix_timestamp) {
+            TradingPhase::Open => Ok(false),
+            TradingPhase::PreOpenAuction => Ok(true),
+            TradingPhase::CancelOnly => Err(ErrorCode::CancelOnlyPeriod.into()),
+            TradingPhase::Closed => Err(ErrorCode::MarketClosed.into()),
+        }
+    }
+
+    // 校验交易时段配置
+    pub fn validate_session(session: &TradingSession) -> Result<()> {
+        if !session.enabled {
+            return Ok(());
+        }
+
+        require!(
+            session.open_minute < MINUTES_PER_DAY as u16
+                && session.close_minute > 0
+                && session.close_minute <= MINUTES_PER_DAY as u16,
+            ErrorCode::InvalidParameters
+        );
+
+        let duration = session.duration_minutes();
+        require!(
+            (session.cancel_only_minutes as i64) < duration,
+            ErrorCode::InvalidParameters
+        );
+        require!(
+            (session.pre_open_minutes as i64) + duration <= MINUTES_PER_DAY,
+            ErrorCode::InvalidParameters
+        );
+
+        Ok(())
+    }
+}
+
+// 初始化交易日历所需的账户
+#[derive(Accounts)]
+pub struct InitializeTradingCalendar<'info> {
+    pub market: Account<'info, Market>,
+
+    // 只有市场的风控管理员可以创建交易日历
+    #[account(
+        seeds = [b"risk_parameters", market.key().as_ref()],
+        bump = risk_parameters.bump,
+        has_one = authority @ ErrorCode::UnauthorizedOperation
+    )]
+    pub risk_parameters: Account<'info, RiskParameters>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + TradingCalendar::LEN,
+        seeds = [b"trading_calendar", market.key().as_ref()],
+        bump
+    )]
+    pub trading_calendar: Account<'info, TradingCalendar>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// 更新交易日历所需的账户
+#[derive(Accounts)]
+pub struct UpdateTradingCalendar<'info> {
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"trading_calendar", market.key().as_ref()],
+        bump = trading_calendar.bump,
+        has_one = market,
+        has_one = authority @ ErrorCode::UnauthorizedOperation
+    )]
+    pub trading_calendar: Account<'info, TradingCalendar>,
+
+    pub authority: Signer<'info>,
+}
+
+// 初始化交易日历
+pub fn initialize_trading_calendar(
+    ctx: Context<InitializeTradingCalendar>,
+    utc_offset_minutes: i16,
+    sessions: [TradingSession; 7],
+) -> Result<()> {
+    require!(
+        (utc_offset_minutes as i64).abs() <= 14 * 60,
+        ErrorCode::InvalidParameters
+    );
+    for session in sessions.iter() {
+        CalendarEngine::validate_session(session)?;
+    }
+
+    let calendar = &mut ctx.accounts.trading_calendar;
+    calendar.schema_version = CURRENT_SCHEMA_VERSION;
+    calendar.market = ctx.accounts.market.key();
+    calendar.authority = ctx.accounts.authority.key();
+    calendar.utc_offset_minutes = utc_offset_minutes;
+    calendar.sessions = sessions;
+    calendar.holidays_count = 0;
+    calendar.holidays = [0; MAX_HOLIDAYS];
+    calendar.is_active = true;
+    calendar.bump = *ctx.bumps.get("trading_calendar").unwrap();
+
+    Ok(())
+}
+
+// 设置某个工作日的交易时段
+pub fn set_trading_session(
+    ctx: Context<UpdateTradingCalendar>,
+    weekday: u8,
+    session: TradingSession,
+) -> Result<()> {
+    require!(weekday < 7, ErrorCode::InvalidParameters);
+    CalendarEngine::validate_session(&session)?;
+
+    ctx.accounts.trading_calendar.sessions[weekday as usize] = session;
+
+    Ok(())
+}
+
+// 更新时区偏移和启用状态
+pub fn update_trading_calendar(
+    ctx: Context<UpdateTradingCalendar>,
+    utc_offset_minutes: i16,
+    is_active: bool,
+) -> Result<()> {
+    require!(
+        (utc_offset_minutes as i64).abs() <= 14 * 60,
+        ErrorCode::InvalidParameters
+    );
+
+    let calendar = &mut ctx.accounts.trading_calendar;
+    calendar.utc_offset_minutes = utc_offset_minutes;
+    calendar.is_active = is_active;
+
+    Ok(())
+}
+
+// 添加休市日（本地日期）
+pub fn add_trading_holiday(
+    ctx: Context<UpdateTradingCalendar>,
+    year: u16,
+    month: u8,
+    day: u8,
+) -> Result<()> {
+    require!(
+        (1..=12).contains(&month) && (1..=31).contains(&day),
+        ErrorCode::InvalidParameters
+    );
+
+    let calendar = &mut ctx.accounts.trading_calendar;
+    let local_day = days_from_civil(year as i32, month, day);
+
+    // 已存在则忽略
+    if calendar.is_holiday(local_day) {
+        return Ok(());
+    }
+
+    require!(
+        (calendar.holidays_count as usize) < MAX_HOLIDAYS,
+        ErrorCode::StorageFull
+    );
+
+    let count = calendar.holidays_count as usize;
+    calendar.holidays[count] = local_day as i32;
+    calendar.holidays_count += 1;
+
+    Ok(())
+}
+
+// 移除休市日
+pub fn remove_trading_holiday(
+    ctx: Context<UpdateTradingCalendar>,
+    year: u16,
+    month: u8,
+    day: u8,
+) -> Result<()> {
+    let calendar = &mut ctx.accounts.trading_calendar;
+    let local_day = days_from_civil(year as i32, month, day) as i32;
+    let count = calendar.holidays_count as usize;
+
+    let idx = calendar.holidays[..count]
+        .iter()
+        .position(|&d| d == local_day)
+        .ok_or(ErrorCode::InvalidParameters)?;
+
+    // 将最后一个条目移到当前位置
+    calendar.holidays[idx] = calendar.holidays[count - 1];
+    calendar.holidays[count - 1] = 0;
+    calendar.holidays_count -= 1;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2024-01-01是周一
+    fn monday() -> i64 {
+        days_from_civil(2024, 1, 1)
+    }
+
+    fn session(
+        open_minute: u16,
+        close_minute: u16,
+        pre_open: u16,
+        cancel_only: u16,
+    ) -> TradingSession {
+        TradingSession {
+            enabled: true,
+            open_minute,
+            close_minute,
+            pre_open_minutes: pre_open,
+            cancel_only_minutes: cancel_only,
+        }
+    }
+
+    fn calendar(
+        utc_offset_minutes: i16,
+        sessions: [TradingSession; 7],
+        holidays: &[i64],
+    ) -> TradingCalendar {
+        let mut calendar = TradingCalendar {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            market: Pubkey::default(),
+            authority: Pubkey::default(),
+            utc_offset_minutes,
+            sessions,
+            holidays_count: holidays.len() as u8,
+            holidays: [0; MAX_HOLIDAYS],
+            is_active: true,
+            bump: 0,
+        };
+        for (slot, &day) in calendar.holidays.iter_mut().zip(holidays) {
+            *slot = day as i32;
+        }
+        calendar
+    }
+
+    // 本地日期某个时刻的交易阶段
+    fn phase(calendar: &TradingCalendar, local_day: i64, hour: i64, minute: i64) -> TradingPhase {
+        let local_minutes = hour * 60 + minute - calendar.utc_offset_minutes as i64;
+        calendar.phase_at(local_day * SECONDS_PER_DAY + local_minutes * 60)
+    }
+
+    #[test]
+    fn overnight_session_continues_after_midnight() {
+        let mut sessions = [TradingSession::default(); 7];
+        sessions[0] = session(22 * 60, 2 * 60, 0, 30); // 周一 22:00 - 周二 02:00
+        let calendar = calendar(480, sessions, &[]);
+        let tuesday = monday() + 1;
+
+        assert_eq!(phase(&calendar, monday(), 21, 59), TradingPhase::Closed);
+        assert_eq!(phase(&calendar, monday(), 23, 0), TradingPhase::Open);
+        assert_eq!(phase(&calendar, tuesday, 1, 0), TradingPhase::Open);
+        assert_eq!(phase(&calendar, tuesday, 1, 45), TradingPhase::CancelOnly);
+        assert_eq!(phase(&calendar, tuesday, 2, 0), TradingPhase::Closed);
+    }
+
+    #[test]
+    fn pre_open_auction_starts_before_midnight() {
+        let mut sessions = [TradingSession::default(); 7];
+        sessions[1] = session(10, 8 * 60, 30, 0); // 周二 00:10 开盘，前一天 23:40 开始集合竞价
+        let calendar = calendar(-300, sessions, &[]);
+        let tuesday = monday() + 1;
+
+        assert_eq!(phase(&calendar, monday(), 23, 39), TradingPhase::Closed);
+        assert_eq!(
+            phase(&calendar, monday(), 23, 45),
+            TradingPhase::PreOpenAuction
+        );
+        assert_eq!(
+            phase(&calendar, tuesday, 0, 5),
+            TradingPhase::PreOpenAuction
+        );
+        assert_eq!(phase(&calendar, tuesday, 0, 10), TradingPhase::Open);
+    }
+
+    #[test]
+    fn holiday_closes_sessions_starting_that_day() {
+        let mut sessions = [TradingSession::default(); 7];
+        sessions[0] = session(22 * 60, 2 * 60, 0, 0); // 周一 22:00 - 周二 02:00
+        sessions[1] = session(10, 8 * 60, 30, 0); // 周二 00:10 - 08:00
+        let tuesday = monday() + 1;
+
+        // 周一休市：周一开始的跨午夜时段整段关闭，周二的集合竞价照常在周一晚上开始
+        let calendar_monday_off = calendar(0, sessions, &[monday()]);
+        assert_eq!(
+            phase(&calendar_monday_off, monday(), 23, 0),
+            TradingPhase::Closed
+        );
+        assert_eq!(
+            phase(&calendar_monday_off, monday(), 23, 45),
+            TradingPhase::PreOpenAuction
+        );
+        assert_eq!(
+            phase(&calendar_monday_off, tuesday, 0, 5),
+            TradingPhase::PreOpenAuction
+        );
+
+        // 周二休市：周一开始的跨午夜时段照常，周二的时段和集合竞价都不开始
+        let calendar_tuesday_off = calendar(0, sessions, &[tuesday]);
+        assert_eq!(
+            phase(&calendar_tuesday_off, monday(), 23, 45),
+            TradingPhase::Open
+        );
+        assert_eq!(
+            phase(&calendar_tuesday_off, tuesday, 1, 0),
+            TradingPhase::Open
+        );
+        assert_eq!(
+            phase(&calendar_tuesday_off, tuesday, 3, 0),
+            TradingPhase::Closed
+        );
+    }
+}
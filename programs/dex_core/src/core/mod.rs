@@ -1,9 +1,19 @@
-use crate::events::EventHandler;
+use crate::access::{AccessControlEngine, MarketAccessControl};
+use crate::calendar::{CalendarEngine, TradingCalendar};
+use crate::events::{EventHandler, RiskWarningType};
+use crate::limits::UserTradingLimits;
+#[cfg(feature = "trading-limits")]
+use crate::limits::{TradingLimitConfig, TradingLimits};
+use crate::migration::CURRENT_SCHEMA_VERSION;
 use crate::orderbook::{Order, OrderBook, OrderType, Side};
-use crate::risk::RiskEngine;
-use crate::storage::OptimizedStorage;
+use crate::risk::{RiskEngine, RiskParameters};
+use crate::storage::{
+    archive_full_history, OptimizedStorage, PriceLevelCache, StorageIndex, StoragePage,
+    UserTradeHistory,
+};
 use crate::ErrorCode;
 use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer as SystemTransfer};
 use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 
 // 自成交行为枚举
@@ -14,25 +24,34 @@ pub enum SelfTradeBehavior {
     AbortTransaction, // 中止交易
 }
 
+// 市场名称的最大字节数（加上4字节长度前缀共占32字节，与引入InitSpace之前的布局一致）
+pub const MAX_MARKET_NAME_LEN: usize = 28;
+
 // 市场状态
 #[account]
+#[derive(InitSpace)]
 pub struct Market {
-    pub base_mint: Pubkey,              // 基础代币铸币权
-    pub quote_mint: Pubkey,             // 报价代币铸币权
-    pub lot_size: u64,                  // 最小交易量单位
-    pub tick_size: u64,                 // 最小价格变动单位
-    pub base_decimals: u8,              // 基础代币小数位数
-    pub quote_decimals: u8,             // 报价代币小数位数
-    pub maker_fee: i64,                 // 做市商费率，负值表示返佣
-    pub taker_fee: i64,                 // 吃单方费率
-    pub base_vault: Pubkey,             // 基础代币保管库
-    pub quote_vault: Pubkey,            // 报价代币保管库
-    pub lp_token_mint: Option<Pubkey>,  // 流动性代币铸币权
-    pub reward_mint: Option<Pubkey>,    // 奖励代币铸币权
-    pub name: String,                   // 交易对名称
+    pub schema_version: u8,            // 账户布局版本
+    pub base_mint: Pubkey,             // 基础代币铸币权
+    pub quote_mint: Pubkey,            // 报价代币铸币权
+    pub lot_size: u64,                 // 最小交易量单位
+    pub tick_size: u64,                // 最小价格变动单位
+    pub base_decimals: u8,             // 基础代币小数位数
+    pub quote_decimals: u8,            // 报价代币小数位数
+    pub maker_fee: i64,                // 做市商费率，负值表示返佣
+    pub taker_fee: i64,                // 吃单方费率
+    pub base_vault: Pubkey,            // 基础代币保管库
+    pub quote_vault: Pubkey,           // 报价代币保管库
+    pub lp_token_mint: Option<Pubkey>, // 流动性代币铸币权
+    pub reward_mint: Option<Pubkey>,   // 奖励代币铸币权
+    // 交易对名称
+    #[max_len(MAX_MARKET_NAME_LEN)]
+    pub name: String,
     pub active: bool,                   // 市场是否活跃
     pub last_traded_price: Option<u64>, // 最后成交价
     pub total_volume: u64,              // 总成交量
+    pub total_quote_volume: u128,       // 总成交额（报价代币）
+    pub total_trades: u64,              // 总成交笔数
     pub total_fees_collected: u64,      // 总手续费收入
     pub min_base_order_size: u64,       // 最小基础代币订单大小
     pub min_quote_order_size: u64,      // 最小报价代币订单大小
@@ -40,37 +59,25 @@ pub struct Market {
     pub cross_chain_enabled: bool,      // 是否启用跨链交易
     pub stress_test_mode: bool,         // 压力测试模式
     pub market_authority_bump: u8,      // 市场权限PDA的bump
+    pub access_control: Option<Pubkey>, // 准入控制账户（None表示不限制）
+    pub event_sequence: u64,            // 最近一次发出事件的序号
 }
 
 impl Market {
-    pub const LEN: usize = 32
-        + 32
-        + 8
-        + 8
-        + 1
-        + 1
-        + 8
-        + 8
-        + 32
-        + 32
-        + (1 + 32)
-        + (1 + 32)
-        + 32
-        + 1
-        + 9
-        + 8
-        + 8
-        + 8
-        + 8
-        + 8
-        + 1
-        + 1
-        + 1;
+    pub const LEN: usize = Self::INIT_SPACE;
+
+    // 分配下一个事件序号（每个市场单调递增，从1开始）
+    pub fn next_event_seq(&mut self) -> u64 {
+        self.event_sequence += 1;
+        self.event_sequence
+    }
 }
 
 // 用户的开放订单账户
 #[account]
+#[derive(InitSpace)]
 pub struct OpenOrders {
+    pub schema_version: u8,         // 账户布局版本
     pub owner: Pubkey,              // 所有者公钥
     pub market: Pubkey,             // 所属市场
     pub locked_base_tokens: u64,    // 锁定的基础代币数量
@@ -86,12 +93,37 @@ pub struct OpenOrders {
     // 压缩存储的订单信息
     pub orders_bitmap: [u64; 4], // 使用位图快速查找订单
     pub order_count: u16,        // 当前订单数量
+    // 断线撤单心跳（0表示未启用）
+    pub heartbeat_timeout_secs: u32, // 心跳超时时间（秒）
+    pub last_heartbeat_ts: i64,      // 上次心跳时间
+    pub keeper_fee_lamports: u64,    // 预存的撤单奖励（lamports）
+    // 反刷单统计（按RiskParameters中的周期重置）
+    pub activity_epoch: u64,   // 当前统计周期编号
+    pub epoch_placements: u32, // 本周期下单次数
+    pub epoch_cancels: u32,    // 本周期撤单次数
+    pub epoch_fills: u32,      // 本周期成交次数
+    pub last_place_slot: u64,  // 上次下单的slot
+    // 做市挖矿积分（由sample_maker_quotes累计）
+    pub maker_score_epoch: u64,  // 积分所属的挖矿周期
+    pub maker_score: u128,       // 该周期内的挂单积分（数量 × 秒）
+    pub maker_rewards_owed: u64, // 已结算、待领取的做市奖励
+    // 滚动成交额计数和用户限额覆盖值（仅在启用trading-limits特性时生效）
+    pub trading_limits: UserTradingLimits,
 }
 
 impl OpenOrders {
-    pub const LEN: usize = 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + (8 * 4) + 2;
+    pub const LEN: usize = Self::INIT_SPACE;
+
+    // 检查心跳是否已超时
+    pub fn is_heartbeat_expired(&self, current_ts: i64) -> bool {
+        self.heartbeat_timeout_secs > 0
+            && current_ts > self.last_heartbeat_ts + self.heartbeat_timeout_secs as i64
+    }
 }
 
+// 单次断线撤单最多处理的订单数，避免超出计算单元限制
+pub const MAX_CANCELS_PER_CRANK: usize = 16;
+
 // Anchor账户验证结构定义
 #[derive(Accounts)]
 pub struct InitializeMarket<'info> {
@@ -125,6 +157,14 @@ pub struct InitializeMarket<'info> {
         space = 8 + OrderBook::LEN
     )]
     pub order_book: AccountLoader<'info, OrderBook>,
+    #[account(
+        init,
+        payer = authority,
+        space = OptimizedStorage::get_price_level_cache_size(),
+        seeds = [b"price_level_cache", market.key().as_ref()],
+        bump
+    )]
+    pub price_level_cache: Account<'info, PriceLevelCache>,
     #[account(mut)]
     pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
@@ -136,7 +176,7 @@ pub struct InitializeMarket<'info> {
 pub struct PlaceOrder<'info> {
     #[account(mut, has_one = base_vault, has_one = quote_vault)]
     pub market: Account<'info, Market>,
-    #[account(mut)]
+    #[account(mut, has_one = market @ ErrorCode::InvalidMarketId)]
     pub order_book: AccountLoader<'info, OrderBook>,
     #[account(
         init_if_needed,
@@ -148,7 +188,37 @@ pub struct PlaceOrder<'info> {
     pub open_orders: Account<'info, OpenOrders>,
     #[account(mut)]
     pub user_token_account: Account<'info, TokenAccount>,
-    #[account(signer)]
+    #[account(
+        mut,
+        seeds = [b"price_level_cache", market.key().as_ref()],
+        bump
+    )]
+    pub price_level_cache: Account<'info, PriceLevelCache>,
+    /// CHECK: 市场交易日历的PDA，可能尚未创建，未创建或未启用（is_active = false）时视为全天开市
+    #[account(seeds = [b"trading_calendar", market.key().as_ref()], bump)]
+    pub trading_calendar: UncheckedAccount<'info>,
+    /// CHECK: 市场交易限额配置的PDA，可能尚未创建，未创建时只应用用户覆盖值（仅在启用trading-limits特性时读取）
+    #[account(seeds = [b"trading_limits", market.key().as_ref()], bump)]
+    pub trading_limit_config: UncheckedAccount<'info>,
+    pub access_control: Option<Account<'info, MarketAccessControl>>,
+    /// CHECK: 准入名单成员记录的PDA，可能不存在，在AccessControlEngine中校验
+    pub access_member: Option<UncheckedAccount<'info>>,
+    // 用户交易历史，传入时记录本次吃单成交
+    #[account(
+        mut,
+        seeds = [b"trade_history", market.key().as_ref(), authority.key().as_ref()],
+        bump = user_trade_history.bump
+    )]
+    pub user_trade_history: Option<Account<'info, UserTradeHistory>>,
+    // 交易历史已满时接收最旧记录的下一个归档页面（由allocate_trade_history_page预先创建）及归档索引
+    #[account(mut)]
+    pub trade_history_archive_page: Option<Account<'info, StoragePage>>,
+    #[account(mut)]
+    pub storage_index: Option<Account<'info, StorageIndex>>,
+    /// CHECK: 市场风控参数的PDA，同时存放收取的反刷单下单费，未创建时不做反刷单检查
+    #[account(mut, seeds = [b"risk_parameters", market.key().as_ref()], bump)]
+    pub risk_parameters: UncheckedAccount<'info>,
+    #[account(mut)]
     pub authority: Signer<'info>,
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
@@ -159,7 +229,7 @@ pub struct PlaceOrder<'info> {
 pub struct CancelOrder<'info> {
     #[account(mut)]
     pub market: Account<'info, Market>,
-    #[account(mut)]
+    #[account(mut, has_one = market @ ErrorCode::InvalidMarketId)]
     pub order_book: AccountLoader<'info, OrderBook>,
     #[account(
         mut,
@@ -167,6 +237,15 @@ pub struct CancelOrder<'info> {
         bump
     )]
     pub open_orders: Account<'info, OpenOrders>,
+    #[account(
+        mut,
+        seeds = [b"price_level_cache", market.key().as_ref()],
+        bump
+    )]
+    pub price_level_cache: Account<'info, PriceLevelCache>,
+    /// CHECK: 市场风控参数的PDA，未创建时不统计撤单次数
+    #[account(seeds = [b"risk_parameters", market.key().as_ref()], bump)]
+    pub risk_parameters: UncheckedAccount<'info>,
     #[account(signer)]
     pub authority: Signer<'info>,
 }
@@ -201,12 +280,65 @@ pub struct SettleFunds<'info> {
     pub user_base_account: Account<'info, TokenAccount>,
     #[account(mut)]
     pub user_quote_account: Account<'info, TokenAccount>,
+    pub access_control: Option<Account<'info, MarketAccessControl>>,
+    /// CHECK: 准入名单成员记录的PDA，可能不存在，在AccessControlEngine中校验
+    pub access_member: Option<UncheckedAccount<'info>>,
     #[account(signer)]
     pub authority: Signer<'info>,
     pub token_program: Program<'info, Token>,
     pub clock: Sysvar<'info, Clock>,
 }
 
+#[derive(Accounts)]
+pub struct ConfigureHeartbeat<'info> {
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        seeds = [b"open_orders", authority.key().as_ref(), market.key().as_ref()],
+        bump = open_orders.bump
+    )]
+    pub open_orders: Account<'info, OpenOrders>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Heartbeat<'info> {
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        seeds = [b"open_orders", authority.key().as_ref(), market.key().as_ref()],
+        bump = open_orders.bump
+    )]
+    pub open_orders: Account<'info, OpenOrders>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelOnDisconnect<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    #[account(mut, has_one = market @ ErrorCode::InvalidMarketId)]
+    pub order_book: AccountLoader<'info, OrderBook>,
+    #[account(
+        mut,
+        seeds = [b"open_orders", owner.key().as_ref(), market.key().as_ref()],
+        bump = open_orders.bump
+    )]
+    pub open_orders: Account<'info, OpenOrders>,
+    #[account(
+        mut,
+        seeds = [b"price_level_cache", market.key().as_ref()],
+        bump
+    )]
+    pub price_level_cache: Account<'info, PriceLevelCache>,
+    /// CHECK: 仅用于推导OpenOrders的PDA
+    pub owner: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+}
+
 // 核心功能实现
 pub fn initialize_market(
     ctx: Context<InitializeMarket>,
@@ -219,12 +351,17 @@ pub fn initialize_market(
     taker_fee: i64,
 ) -> Result<()> {
     let market = &mut ctx.accounts.market;
+    market.schema_version = CURRENT_SCHEMA_VERSION;
     market.base_mint = ctx.accounts.base_mint.key();
     market.quote_mint = ctx.accounts.quote_mint.key();
     market.lot_size = lot_size;
     market.tick_size = tick_size;
     market.base_decimals = base_decimals;
     market.quote_decimals = quote_decimals;
+    require!(
+        market_name.len() <= MAX_MARKET_NAME_LEN,
+        ErrorCode::InvalidParameters
+    );
     market.name = market_name;
     market.maker_fee = maker_fee;
     market.taker_fee = taker_fee;
@@ -240,14 +377,24 @@ pub fn initialize_market(
     market.cross_chain_enabled = false; // 默认禁用跨链
     market.stress_test_mode = false; // 默认非压测模式
     market.market_authority_bump = *ctx.bumps.get("market_authority").unwrap();
+    market.access_control = None; // 默认不限制交易钱包
+    market.event_sequence = 0;
+
+    // 初始化价格档位缓存
+    OptimizedStorage::initialize_price_level_cache(
+        &mut ctx.accounts.price_level_cache,
+        market.key(),
+    );
 
     // 初始化订单簿
     let order_book = &mut ctx.accounts.order_book.load_init()?;
     order_book.initialize();
+    order_book.market = market.key();
 
     // 记录市场创建事件
     EventHandler::emit_market_created(
         market.key(),
+        market.next_event_seq(),
         ctx.accounts.base_mint.key(),
         ctx.accounts.quote_mint.key(),
         lot_size,
@@ -257,8 +404,11 @@ pub fn initialize_market(
     Ok(())
 }
 
-pub fn place_order(
-    ctx: Context<PlaceOrder>,
+// remaining_accounts: 可选，本次吃单成交涉及的做市商的OpenOrders和交易历史账户（可写），顺序不限
+// 用于统计做市方成交次数和记录做市方交易历史，未传入的做市商跳过，不影响成交
+// 做市方交易历史已满时还需传入其下一个归档页面和归档索引（可写），否则下单失败
+pub fn place_order<'info>(
+    ctx: Context<'_, '_, 'info, 'info, PlaceOrder<'info>>,
     side: Side,
     limit_price: u64,
     max_quantity: u64,
@@ -268,6 +418,47 @@ pub fn place_order(
 ) -> Result<()> {
     // 检查市场是否活跃
     require!(ctx.accounts.market.active, ErrorCode::MarketNotActive);
+    require!(
+        ctx.accounts.market.schema_version == CURRENT_SCHEMA_VERSION,
+        ErrorCode::AccountNeedsMigration
+    );
+
+    // 首次下单时初始化开放订单账户
+    let open_orders = &mut ctx.accounts.open_orders;
+    if open_orders.owner == Pubkey::default() {
+        open_orders.schema_version = CURRENT_SCHEMA_VERSION;
+        open_orders.owner = ctx.accounts.authority.key();
+        open_orders.market = ctx.accounts.market.key();
+        open_orders.bump = *ctx.bumps.get("open_orders").unwrap();
+    }
+    require!(
+        open_orders.schema_version == CURRENT_SCHEMA_VERSION,
+        ErrorCode::AccountNeedsMigration
+    );
+
+    // 检查钱包是否有权在该市场交易
+    AccessControlEngine::check_access(
+        &ctx.accounts.market,
+        ctx.accounts.access_control.as_ref(),
+        ctx.accounts.access_member.as_ref().map(|m| m.as_ref()),
+        &ctx.accounts.authority.key(),
+    )?;
+
+    // 检查交易日历 - 集合竞价期间只接受挂单，不进行撮合
+    let calendar = load_optional_pda::<TradingCalendar>(&ctx.accounts.trading_calendar)?;
+    let auction_only = match calendar {
+        Some(calendar) => CalendarEngine::check_can_place(&calendar, Clock::get()?.unix_timestamp)?,
+        None => false,
+    };
+    let order_type = if auction_only {
+        require!(
+            order_type == OrderType::Limit || order_type == OrderType::PostOnly,
+            ErrorCode::AuctionRestingOrdersOnly
+        );
+        OrderType::PostOnly
+    } else {
+        order_type
+    };
 
     // 检查价格和数量是否有效
     require!(max_quantity > 0, ErrorCode::InvalidOrderQuantity);
@@ -312,18 +503,91 @@ pub fn place_order(
     // 风险检查 - 验证用户资金是否足够
     RiskEngine::check_funds(&ctx.accounts, &order)?;
 
+    // 交易限额检查 - 单笔和滚动24小时名义金额
+    #[cfg(feature = "trading-limits")]
+    TradingLimits::check(
+        load_optional_pda::<TradingLimitConfig>(&ctx.accounts.trading_limit_config)?.as_deref(),
+        &ctx.accounts.open_orders.trading_limits,
+        TradingLimits::order_notional(
+            limit_price,
+            max_quantity,
+            ctx.accounts.market.lot_size,
+            ctx.accounts.market.tick_size,
+        ),
+        Clock::get()?.unix_timestamp,
+    )?;
+
+    // 反刷单检查 - 撤单/成交比过高时收取下单费（转入风控参数账户，由风控管理员提取）或限速
+    let risk_params = load_optional_pda::<RiskParameters>(&ctx.accounts.risk_parameters)?;
+    let spam_fee = match &risk_params {
+        Some(risk_params) => RiskEngine::check_order_spam(
+            risk_params,
+            &mut ctx.accounts.open_orders,
+            Clock::get()?.slot,
+        )?,
+        None => 0,
+    };
+    if spam_fee > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                SystemTransfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: ctx.accounts.risk_parameters.to_account_info(),
+                },
+            ),
+            spam_fee,
+        )?;
+
+        EventHandler::emit_risk_warning(
+            ctx.accounts.market.key(),
+            ctx.accounts.market.next_event_seq(),
+            ctx.accounts.authority.key(),
+            RiskWarningType::AccountAnomaly,
+            1,
+            format!("撤单/成交比过高，收取下单费: {} lamports", spam_fee),
+        );
+    }
+
     // 处理订单 - 先尝试匹配，然后根据订单类型决定是否添加到订单簿
     let order_book = &mut ctx.accounts.order_book.load_mut()?;
-    let (trades, remaining_order) = order_book.process_order(order, self_trade_behavior)?;
+    let (trades, remaining_order, purged) = order_book.process_order(order, self_trade_behavior)?;
+
+    // 成交、清理过期订单或挂单影响前N档时刷新价格档位缓存
+    let cache = &mut ctx.accounts.price_level_cache;
+    let rested_in_cache = remaining_order.as_ref().map_or(false, |o| {
+        OptimizedStorage::price_level_cache_touched(cache, o.side, o.price)
+    });
+    if !trades.is_empty() || purged || rested_in_cache {
+        OptimizedStorage::refresh_price_level_cache(cache, order_book, Clock::get()?.slot)?;
+    }
 
     // 处理交易结果
-    let market = &ctx.accounts.market;
+    let market = &mut ctx.accounts.market;
     let open_orders = &mut ctx.accounts.open_orders;
 
+    // 记录本周期成交次数（吃单方按订单计，做市方按被成交的挂单计）
+    // 吃单方无法预知会与哪些做市商成交，未传入OpenOrders的做市商跳过计数，不影响成交
+    let current_slot = Clock::get()?.slot;
+    if let Some(risk_params) = &risk_params {
+        if !trades.is_empty() {
+            RiskEngine::record_fill(risk_params, open_orders, current_slot);
+        }
+        for trade in trades.iter().filter(|t| t.maker != open_orders.owner) {
+            let maker_open_orders =
+                find_maker_open_orders(ctx.remaining_accounts, &trade.maker, &market.key());
+            if let Some(mut maker_open_orders) = maker_open_orders {
+                RiskEngine::record_fill(risk_params, &mut maker_open_orders, current_slot);
+                maker_open_orders.exit(&crate::ID)?;
+            }
+        }
+    }
+
     for trade in &trades {
         // 记录交易事件
         EventHandler::emit_trade(
             market.key(),
+            market.next_event_seq(),
             side,
             trade.maker_order_id,
             order_id,
@@ -333,6 +597,18 @@ pub fn place_order(
             ctx.accounts.authority.key(),
         );
 
+        // 成交部分记入滚动限额
+        #[cfg(feature = "trading-limits")]
+        open_orders.trading_limits.record(
+            Clock::get()?.unix_timestamp,
+            TradingLimits::order_notional(
+                trade.price,
+                trade.quantity,
+                market.lot_size,
+                market.tick_size,
+            ),
+        );
+
         // 更新用户的交易统计
         open_orders.filled_base_quantity += trade.base_quantity;
         open_orders.filled_quote_quantity += trade.quote_quantity;
@@ -346,6 +622,64 @@ pub fn place_order(
         };
 
         open_orders.fees_paid += taker_fee;
+
+        // 记录到吃单方的交易历史，缓冲区已满时先归档到传入的下一个归档页面
+        if let Some(history) = ctx.accounts.user_trade_history.as_mut() {
+            archive_full_history(
+                history,
+                ctx.accounts.trade_history_archive_page.as_mut(),
+                ctx.accounts.storage_index.as_mut(),
+                market,
+                current_slot,
+            )?;
+            OptimizedStorage::add_to_user_history(
+                history,
+                market.key(),
+                order_id,
+                trade.price,
+                trade.base_quantity,
+                side as u8,
+                trade.timestamp,
+                taker_fee,
+            )?;
+        }
+
+        // 做市方的交易历史通过remaining_accounts传入时一并记录
+        if trade.maker != open_orders.owner {
+            let maker_history =
+                find_maker_trade_history(ctx.remaining_accounts, &trade.maker, &market.key());
+            if let Some(mut maker_history) = maker_history {
+                let mut archive = if maker_history.is_full() {
+                    find_history_archive(ctx.remaining_accounts, &maker_history)
+                } else {
+                    None
+                };
+                let (page, index) = match archive.as_mut() {
+                    Some((page, index)) => (Some(page), Some(index)),
+                    None => (None, None),
+                };
+                archive_full_history(&mut maker_history, page, index, market, current_slot)?;
+                if let Some((page, index)) = &archive {
+                    page.exit(&crate::ID)?;
+                    index.exit(&crate::ID)?;
+                }
+                let maker_side = match side {
+                    Side::Bid => Side::Ask,
+                    Side::Ask => Side::Bid,
+                };
+                OptimizedStorage::add_to_user_history(
+                    &mut maker_history,
+                    market.key(),
+                    trade.maker_order_id,
+                    trade.price,
+                    trade.base_quantity,
+                    maker_side as u8,
+                    trade.timestamp,
+                    trade.maker_fee,
+                )?;
+                maker_history.exit(&crate::ID)?;
+            }
+        }
     }
 
     // 处理剩余订单
@@ -370,9 +704,22 @@ pub fn place_order(
         if order_type == OrderType::Limit || order_type == OrderType::PostOnly {
             add_to_open_orders(open_orders, &remaining)?;
 
+            // 挂单部分按下单时间先记入滚动限额，撤单时从同一个桶退回
+            #[cfg(feature = "trading-limits")]
+            open_orders.trading_limits.record(
+                remaining.timestamp,
+                TradingLimits::order_notional(
+                    remaining.price,
+                    remaining.quantity,
+                    market.lot_size,
+                    market.tick_size,
+                ),
+            );
+
             // 发出挂单事件
             EventHandler::emit_order_placed(
                 market.key(),
+                market.next_event_seq(),
                 remaining.order_id,
                 remaining.owner,
                 remaining.side,
@@ -388,6 +735,11 @@ pub fn place_order(
         let market = &mut ctx.accounts.market;
         market.last_traded_price = Some(last_trade.price);
         market.total_volume += trades.iter().map(|t| t.base_quantity).sum::<u64>();
+        market.total_quote_volume += trades
+            .iter()
+            .map(|t| t.quote_quantity as u128)
+            .sum::<u128>();
+        market.total_trades += trades.len() as u64;
     }
 
     Ok(())
@@ -416,32 +768,32 @@ pub fn cancel_order(ctx: Context<CancelOrder>, order_id: u128, side: Side) -> Re
     let order_book = &mut ctx.accounts.order_book.load_mut()?;
     let removed_order = order_book.cancel_order(order_id, side)?;
 
-    // 更新OpenOrders账户并解锁资金
-    if side == Side::Bid {
-        // 解锁quote tokens
-        let locked_amount = RiskEngine::calculate_required_quote_for_bid(
-            removed_order.price,
-            removed_order.quantity,
-            ctx.accounts.market.lot_size,
-            ctx.accounts.market.tick_size,
-            ctx.accounts.market.taker_fee,
-        );
-        open_orders.locked_quote_tokens = open_orders
-            .locked_quote_tokens
-            .saturating_sub(locked_amount);
-    } else {
-        // 解锁base tokens
-        let locked_amount = removed_order.quantity * ctx.accounts.market.lot_size;
-        open_orders.locked_base_tokens =
-            open_orders.locked_base_tokens.saturating_sub(locked_amount);
+    // 撤销的价格位于缓存档位内时刷新价格档位缓存
+    let cache = &mut ctx.accounts.price_level_cache;
+    if OptimizedStorage::price_level_cache_touched(cache, side, removed_order.price) {
+        OptimizedStorage::refresh_price_level_cache(cache, order_book, Clock::get()?.slot)?;
     }
 
+    // 更新OpenOrders账户并解锁资金
+    unlock_order_funds(
+        open_orders,
+        &ctx.accounts.market,
+        &removed_order,
+        Clock::get()?.unix_timestamp,
+    );
+
     // 从用户的开放订单列表中移除该订单
     remove_from_open_orders(open_orders, order_id)?;
 
+    // 记录本周期撤单次数（用于反刷单统计）
+    if let Some(risk_params) = load_optional_pda::<RiskParameters>(&ctx.accounts.risk_parameters)? {
+        RiskEngine::record_cancel(&risk_params, open_orders, Clock::get()?.slot);
+    }
+
     // 发出取消事件
     EventHandler::emit_order_canceled(
         ctx.accounts.market.key(),
+        ctx.accounts.market.next_event_seq(),
         order_id,
         ctx.accounts.authority.key(),
         side,
@@ -455,6 +807,21 @@ pub fn cancel_order(ctx: Context<CancelOrder>, order_id: u128, side: Side) -> Re
 pub fn settle_funds(ctx: Context<SettleFunds>) -> Result<()> {
     // 检查市场是否活跃
     require!(ctx.accounts.market.active, ErrorCode::MarketNotActive);
+    require!(
+        ctx.accounts.market.schema_version == CURRENT_SCHEMA_VERSION
+            && ctx.accounts.open_orders.schema_version == CURRENT_SCHEMA_VERSION,
+        ErrorCode::AccountNeedsMigration
+    );
+
+    // 检查钱包是否有权在该市场结算
+    // 按需求结算与下单、存款一样受准入控制：被移出白名单或加入黑名单的钱包
+    // 需要由名单管理员重新放行后才能取回已结算的资金
+    AccessControlEngine::check_access(
+        &ctx.accounts.market,
+        ctx.accounts.access_control.as_ref(),
+        ctx.accounts.access_member.as_ref().map(|m| m.as_ref()),
+        &ctx.accounts.authority.key(),
+    )?;
 
     let market = &ctx.accounts.market;
     let open_orders = &mut ctx.accounts.open_orders;
@@ -533,7 +900,8 @@ pub fn settle_funds(ctx: Context<SettleFunds>) -> Result<()> {
 
     // 发出结算事件
     EventHandler::emit_funds_settled(
-        market.key(),
+        ctx.accounts.market.key(),
+        ctx.accounts.market.next_event_seq(),
         ctx.accounts.authority.key(),
         base_to_settle,
         quote_to_settle,
@@ -542,6 +910,274 @@ pub fn settle_funds(ctx: Context<SettleFunds>) -> Result<()> {
     Ok(())
 }
 
+// 配置断线撤单心跳，timeout_secs为0表示关闭并退还预存奖励
+// 仍有挂单时预存奖励只能增加，避免用户抢在keeper之前撤走奖励
+pub fn configure_heartbeat(
+    ctx: Context<ConfigureHeartbeat>,
+    timeout_secs: u32,
+    keeper_fee_lamports: u64,
+) -> Result<()> {
+    let current_ts = Clock::get()?.unix_timestamp;
+    let new_fee = if timeout_secs == 0 {
+        0
+    } else {
+        keeper_fee_lamports
+    };
+    let old_fee = ctx.accounts.open_orders.keeper_fee_lamports;
+    require!(
+        new_fee >= old_fee || ctx.accounts.open_orders.order_count == 0,
+        ErrorCode::KeeperFeeLocked
+    );
+
+    if new_fee > old_fee {
+        // 补充预存的撤单奖励
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                SystemTransfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: ctx.accounts.open_orders.to_account_info(),
+                },
+            ),
+            new_fee - old_fee,
+        )?;
+    } else if new_fee < old_fee {
+        // 退还多余的撤单奖励
+        let refund = old_fee - new_fee;
+        **ctx
+            .accounts
+            .open_orders
+            .to_account_info()
+            .try_borrow_mut_lamports()? -= refund;
+        **ctx
+            .accounts
+            .authority
+            .to_account_info()
+            .try_borrow_mut_lamports()? += refund;
+    }
+
+    let open_orders = &mut ctx.accounts.open_orders;
+    open_orders.heartbeat_timeout_secs = timeout_secs;
+    open_orders.keeper_fee_lamports = new_fee;
+    open_orders.last_heartbeat_ts = current_ts;
+
+    Ok(())
+}
+
+// 发送心跳
+pub fn heartbeat(ctx: Context<Heartbeat>) -> Result<()> {
+    let open_orders = &mut ctx.accounts.open_orders;
+    require!(
+        open_orders.heartbeat_timeout_secs > 0,
+        ErrorCode::HeartbeatNotEnabled
+    );
+
+    let clock = Clock::get()?;
+    open_orders.last_heartbeat_ts = clock.unix_timestamp;
+    open_orders.last_update_slot = clock.slot;
+
+    Ok(())
+}
+
+// 心跳超时后，任何人都可以撤销该用户的全部挂单并获得奖励
+pub fn cancel_on_disconnect(ctx: Context<CancelOnDisconnect>) -> Result<()> {
+    let clock = Clock::get()?;
+    let (current_ts, current_slot) = (clock.unix_timestamp, clock.slot);
+    let market = &mut ctx.accounts.market;
+    let owner = ctx.accounts.owner.key();
+    let open_orders = &mut ctx.accounts.open_orders;
+
+    require!(
+        open_orders.heartbeat_timeout_secs > 0,
+        ErrorCode::HeartbeatNotEnabled
+    );
+    require!(
+        open_orders.is_heartbeat_expired(current_ts),
+        ErrorCode::HeartbeatNotExpired
+    );
+
+    let order_book = &mut ctx.accounts.order_book.load_mut()?;
+    let orders_before = open_orders.order_count.max(1);
+    let mut canceled_count = 0u16;
+
+    for side in [Side::Bid, Side::Ask] {
+        let remaining = MAX_CANCELS_PER_CRANK - canceled_count as usize;
+        for order_id in order_book.collect_owner_orders(&owner, side, remaining) {
+            // 复用订单簿的撤单路径
+            let removed_order = order_book.cancel_order(order_id, side)?;
+            unlock_order_funds(open_orders, market, &removed_order, current_ts);
+            remove_from_open_orders(open_orders, order_id)?;
+
+            EventHandler::emit_order_canceled(
+                market.key(),
+                market.next_event_seq(),
+                order_id,
+                owner,
+                side,
+                removed_order.price,
+                removed_order.quantity,
+            );
+            canceled_count += 1;
+        }
+    }
+
+    // 有撤单时刷新价格档位缓存
+    if canceled_count > 0 {
+        OptimizedStorage::refresh_price_level_cache(
+            &mut ctx.accounts.price_level_cache,
+            order_book,
+            current_slot,
+        )?;
+    }
+
+    // 所有挂单都已撤销时，支付剩余奖励并关闭心跳，剩余订单留给下一次调用
+    // 未完成时按本次撤单占比支付部分奖励，避免用户在两次调用之间撤走奖励
+    let completed = order_book
+        .collect_owner_orders(&owner, Side::Bid, 1)
+        .is_empty()
+        && order_book
+            .collect_owner_orders(&owner, Side::Ask, 1)
+            .is_empty();
+    let keeper_fee = if completed {
+        open_orders.heartbeat_timeout_secs = 0;
+        open_orders.keeper_fee_lamports
+    } else {
+        (open_orders.keeper_fee_lamports as u128 * canceled_count as u128 / orders_before as u128)
+            as u64
+    };
+
+    if keeper_fee > 0 {
+        open_orders.keeper_fee_lamports -= keeper_fee;
+        **open_orders.to_account_info().try_borrow_mut_lamports()? -= keeper_fee;
+        **ctx
+            .accounts
+            .keeper
+            .to_account_info()
+            .try_borrow_mut_lamports()? += keeper_fee;
+    }
+
+    EventHandler::emit_cancel_on_disconnect(
+        market.key(),
+        market.next_event_seq(),
+        owner,
+        ctx.accounts.keeper.key(),
+        canceled_count,
+        completed,
+        keeper_fee,
+    );
+
+    Ok(())
+}
+
+// 辅助函数 - 撤单后解锁订单占用的资金，并退回未成交部分占用的交易限额
+// removed_order.timestamp是订单簿记录的下单时间
+fn unlock_order_funds(
+    open_orders: &mut OpenOrders,
+    market: &Market,
+    removed_order: &Order,
+    current_ts: i64,
+) {
+    #[cfg(feature = "trading-limits")]
+    open_orders.trading_limits.release(
+        removed_order.timestamp,
+        current_ts,
+        TradingLimits::order_notional(
+            removed_order.price,
+            removed_order.quantity,
+            market.lot_size,
+            market.tick_size,
+        ),
+    );
+    #[cfg(not(feature = "trading-limits"))]
+    let _ = current_ts;
+
+    if removed_order.side == Side::Bid {
+        // 解锁quote tokens
+        let locked_amount = RiskEngine::calculate_required_quote_for_bid(
+            removed_order.price,
+            removed_order.quantity,
+            market.lot_size,
+            market.tick_size,
+            market.taker_fee,
+        );
+        open_orders.locked_quote_tokens = open_orders
+            .locked_quote_tokens
+            .saturating_sub(locked_amount);
+    } else {
+        // 解锁base tokens
+        let locked_amount = removed_order.quantity * market.lot_size;
+        open_orders.locked_base_tokens =
+            open_orders.locked_base_tokens.saturating_sub(locked_amount);
+    }
+}
+
+// 辅助函数 - 读取按PDA地址传入、可能尚未创建的市场配置账户，未创建时返回None
+// 地址由账户约束中的seeds校验，因此调用方无法通过省略账户绕过已存在的配置
+pub(crate) fn load_optional_pda<'info, T>(
+    info: &AccountInfo<'info>,
+) -> Result<Option<Account<'info, T>>>
+where
+    T: AccountSerialize + AccountDeserialize + Owner + Clone,
+{
+    if info.owner != &crate::ID || info.data_is_empty() {
+        return Ok(None);
+    }
+    Account::try_from(info).map(Some)
+}
+
+// 辅助函数 - 在remaining_accounts中查找做市商在该市场的可写OpenOrders账户，未传入时返回None
+fn find_maker_open_orders<'info>(
+    remaining_accounts: &'info [AccountInfo<'info>],
+    maker: &Pubkey,
+    market: &Pubkey,
+) -> Option<Account<'info, OpenOrders>> {
+    remaining_accounts
+        .iter()
+        .filter(|info| info.is_writable)
+        .filter_map(|info| Account::<OpenOrders>::try_from(info).ok())
+        .find(|open_orders| open_orders.owner == *maker && open_orders.market == *market)
+}
+
+// 辅助函数 - 在remaining_accounts中查找做市商在该市场的可写交易历史账户，未传入时返回None
+fn find_maker_trade_history<'info>(
+    remaining_accounts: &'info [AccountInfo<'info>],
+    maker: &Pubkey,
+    market: &Pubkey,
+) -> Option<Account<'info, UserTradeHistory>> {
+    remaining_accounts
+        .iter()
+        .filter(|info| info.is_writable)
+        .filter_map(|info| Account::<UserTradeHistory>::try_from(info).ok())
+        .find(|history| history.owner == *maker && history.market == *market)
+}
+
+// 辅助函数 - 在remaining_accounts中查找交易历史的下一个归档页面和归档索引，未传入时返回None
+fn find_history_archive<'info>(
+    remaining_accounts: &'info [AccountInfo<'info>],
+    history: &Account<'info, UserTradeHistory>,
+) -> Option<(Account<'info, StoragePage>, Account<'info, StorageIndex>)> {
+    let history_key = history.key();
+    let page_id = history.archived_page_count;
+    let page_key = Pubkey::find_program_address(
+        &[
+            b"storage_page",
+            history_key.as_ref(),
+            &page_id.to_le_bytes(),
+        ],
+        &crate::ID,
+    )
+    .0;
+
+    let writable = || remaining_accounts.iter().filter(|info| info.is_writable);
+    let page = writable()
+        .find(|info| info.key() == page_key)
+        .and_then(|info| Account::<StoragePage>::try_from(info).ok())?;
+    let index = writable()
+        .filter_map(|info| Account::<StorageIndex>::try_from(info).ok())
+        .find(|index| index.user_trade_history == history_key)?;
+    Some((page, index))
+}
+
 // 辅助函数 - 生成订单ID
 pub fn generate_order_id(user_pubkey: &Pubkey, client_order_id: u64, slot: u64) -> u128 {
     let mut hasher = blake3::Hasher::new();
@@ -0,0 +1,254 @@
+use crate::events::EventHandler;
+use crate::migration::CURRENT_SCHEMA_VERSION;
+use crate::ErrorCode;
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+use solana_program::hash::hashv;
+
+// 哈希时间锁合约 (HTLC) - 不依赖中继器的原子跨链交换
+//
+// 发起方在一条链上用hashlock = sha256(preimage)锁定代币，对方在另一条链上用同一hashlock
+// 锁定、且超时时间更短；发起方领取对方的代币时公开原像，对方再用该原像领取这里的代币。
+// 哈希算法与EVM上常见的HashedTimelock合约一致（sha256(bytes32)）。
+
+// 锁定时超时时间距当前时间的最小间隔（秒）
+pub const MIN_HTLC_TIMELOCK_SECS: i64 = 3600;
+
+// 锁定时超时时间距当前时间的最大间隔（秒）
+pub const MAX_HTLC_TIMELOCK_SECS: i64 = 7 * 86400;
+
+// 哈希时间锁状态
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HtlcStatus {
+    Locked,   // 已锁定
+    Claimed,  // 接收方已凭原像领取
+    Refunded, // 超时后已退还发送方
+}
+
+// 哈希时间锁托管账户
+#[account]
+pub struct HtlcEscrow {
+    pub schema_version: u8,  // 账户布局版本
+    pub sender: Pubkey,      // 锁定方，超时后可退款
+    pub recipient: Pubkey,   // 接收方，提供原像后获得代币
+    pub mint: Pubkey,        // 锁定的代币Mint
+    pub amount: u64,         // 锁定数量
+    pub hashlock: [u8; 32],  // sha256(preimage)
+    pub timelock: i64,       // 超时时间，此后只能退款
+    pub status: HtlcStatus,  // 当前状态
+    pub preimage: [u8; 32],  // 领取后公开的原像
+    pub created_at: i64,     // 创建时间
+    pub bump: u8,            // PDA bump值
+}
+
+impl HtlcEscrow {
+    // 检查原像是否与hashlock匹配
+    pub fn verify_preimage(&self, preimage: &[u8; 32]) -> bool {
+        hashv(&[preimage]).to_bytes() == self.hashlock
+    }
+}
+
+// 锁定代币所需的账户
+#[derive(Accounts)]
+#[instruction(hashlock: [u8; 32])]
+pub struct HtlcLock<'info> {
+    #[account(
+        init,
+        payer = sender,
+        space = 8 + std::mem::size_of::<HtlcEscrow>(),
+        seeds = [b"htlc", sender.key().as_ref(), hashlock.as_ref()],
+        bump
+    )]
+    pub htlc: Account<'info, HtlcEscrow>,
+
+    #[account(
+        init,
+        payer = sender,
+        seeds = [b"htlc_vault", htlc.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = htlc,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = sender_token_account.mint == mint.key() @ ErrorCode::InvalidUserAccount
+    )]
+    pub sender_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+// 凭原像领取所需的账户（任何人都可以提交，代币只会转给接收方）
+#[derive(Accounts)]
+pub struct HtlcClaim<'info> {
+    #[account(
+        mut,
+        seeds = [b"htlc", htlc.sender.as_ref(), htlc.hashlock.as_ref()],
+        bump = htlc.bump
+    )]
+    pub htlc: Account<'info, HtlcEscrow>,
+
+    #[account(mut, seeds = [b"htlc_vault", htlc.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = recipient_token_account.owner == htlc.recipient @ ErrorCode::InvalidUserAccount,
+        constraint = recipient_token_account.mint == htlc.mint @ ErrorCode::InvalidUserAccount
+    )]
+    pub recipient_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+// 超时退款所需的账户（任何人都可以提交，代币只会退回发送方）
+#[derive(Accounts)]
+pub struct HtlcRefund<'info> {
+    #[account(
+        mut,
+        seeds = [b"htlc", htlc.sender.as_ref(), htlc.hashlock.as_ref()],
+        bump = htlc.bump
+    )]
+    pub htlc: Account<'info, HtlcEscrow>,
+
+    #[account(mut, seeds = [b"htlc_vault", htlc.key().as_ref()], bump)]
+    pub vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = sender_token_account.owner == htlc.sender @ ErrorCode::InvalidUserAccount,
+        constraint = sender_token_account.mint == htlc.mint @ ErrorCode::InvalidUserAccount
+    )]
+    pub sender_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+// 用hashlock和timelock锁定代币
+pub fn htlc_lock(
+    ctx: Context<HtlcLock>,
+    hashlock: [u8; 32],
+    recipient: Pubkey,
+    amount: u64,
+    timelock: i64,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    require!(amount > 0, ErrorCode::InvalidParameters);
+    require!(
+        timelock >= now + MIN_HTLC_TIMELOCK_SECS && timelock <= now + MAX_HTLC_TIMELOCK_SECS,
+        ErrorCode::InvalidHtlcTimelock
+    );
+
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.sender_token_account.to_account_info(),
+                to: ctx.accounts.vault.to_account_info(),
+                authority: ctx.accounts.sender.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    let htlc = &mut ctx.accounts.htlc;
+    htlc.schema_version = CURRENT_SCHEMA_VERSION;
+    htlc.sender = ctx.accounts.sender.key();
+    htlc.recipient = recipient;
+    htlc.mint = ctx.accounts.mint.key();
+    htlc.amount = amount;
+    htlc.hashlock = hashlock;
+    htlc.timelock = timelock;
+    htlc.status = HtlcStatus::Locked;
+    htlc.preimage = [0; 32];
+    htlc.created_at = now;
+    htlc.bump = *ctx.bumps.get("htlc").unwrap();
+
+    EventHandler::emit_htlc_locked(
+        htlc.key(),
+        htlc.sender,
+        recipient,
+        htlc.mint,
+        amount,
+        hashlock,
+        timelock,
+    );
+
+    Ok(())
+}
+
+// 超时前凭原像把代币转给接收方，并在账户和事件中公开原像
+pub fn htlc_claim(ctx: Context<HtlcClaim>, preimage: [u8; 32]) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let htlc = &mut ctx.accounts.htlc;
+
+    require!(htlc.status == HtlcStatus::Locked, ErrorCode::HtlcNotLocked);
+    require!(now < htlc.timelock, ErrorCode::HtlcExpired);
+    require!(htlc.verify_preimage(&preimage), ErrorCode::InvalidHtlcPreimage);
+
+    transfer_from_vault(
+        htlc,
+        &ctx.accounts.vault,
+        &ctx.accounts.recipient_token_account,
+        &ctx.accounts.token_program,
+    )?;
+
+    htlc.status = HtlcStatus::Claimed;
+    htlc.preimage = preimage;
+
+    EventHandler::emit_htlc_claimed(htlc.key(), htlc.recipient, htlc.amount, preimage);
+
+    Ok(())
+}
+
+// 超时后把代币退还发送方
+pub fn htlc_refund(ctx: Context<HtlcRefund>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let htlc = &mut ctx.accounts.htlc;
+
+    require!(htlc.status == HtlcStatus::Locked, ErrorCode::HtlcNotLocked);
+    require!(now >= htlc.timelock, ErrorCode::HtlcNotExpired);
+
+    transfer_from_vault(
+        htlc,
+        &ctx.accounts.vault,
+        &ctx.accounts.sender_token_account,
+        &ctx.accounts.token_program,
+    )?;
+
+    htlc.status = HtlcStatus::Refunded;
+
+    EventHandler::emit_htlc_refunded(htlc.key(), htlc.sender, htlc.amount);
+
+    Ok(())
+}
+
+// 由HTLC账户签名从托管金库转出全部锁定数量
+fn transfer_from_vault<'info>(
+    htlc: &Account<'info, HtlcEscrow>,
+    vault: &Account<'info, TokenAccount>,
+    to: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+) -> Result<()> {
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Transfer {
+                from: vault.to_account_info(),
+                to: to.to_account_info(),
+                authority: htlc.to_account_info(),
+            },
+            &[&[b"htlc", htlc.sender.as_ref(), htlc.hashlock.as_ref(), &[htlc.bump]]],
+        ),
+        htlc.amount,
+    )
+}
@@ -0,0 +1,540 @@
+use super::{ChainRegistry, CrossChain, CrossChainBridgeConfig, CrossChainBridgeStats};
+use crate::core::Market;
+use crate::events::{BridgePauseReason, EventHandler};
+use crate::migration::CURRENT_SCHEMA_VERSION;
+use crate::ErrorCode;
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+// 跨链转入（从托管金库转出）的限流
+//
+// 每条外部链、每种代币以及整个跨链桥各有一个滚动窗口，记录最近24小时从托管金库转出的数量。
+// 超过任一上限的转入进入延迟队列，延迟期内守护者可以取消；已转出量加上队列中的数量超过上限的
+// ANOMALY_CAP_MULTIPLIER倍视为异常转出，跨链桥自动暂停，等待管理员排查后恢复。
+// 队列中的转入延迟期满后仍需等窗口内的已转出量降到上限以下才能执行。
+
+// 滚动窗口的分格数量
+pub const OUTFLOW_WINDOW_BUCKETS: usize = 24;
+
+// 每格覆盖的时间（秒），窗口总长度为24小时
+pub const OUTFLOW_BUCKET_SECS: i64 = 3600;
+
+// 窗口内转出量超过上限的该倍数时视为异常并自动暂停跨链桥
+pub const ANOMALY_CAP_MULTIPLIER: u64 = 2;
+
+// 超限转入的默认延迟时间（秒）
+pub const DEFAULT_RELEASE_DELAY_SECS: i64 = 6 * 3600;
+
+// 延迟时间的上限（秒）
+pub const MAX_RELEASE_DELAY_SECS: i64 = 7 * 86400;
+
+// 转出量的滚动窗口
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct OutflowWindow {
+    pub cap: u64,                               // 窗口内允许的最大转出量（0表示不限制）
+    pub bucket_start: i64,                      // 最新一格的开始时间
+    pub buckets: [u64; OUTFLOW_WINDOW_BUCKETS], // 每格的转出量，buckets[0]为最新一格
+    pub pending: u64,                           // 延迟队列中尚未执行或取消的转出量
+}
+
+// 转出检查结果，按严重程度排序
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum OutflowCheck {
+    WithinCap,  // 可以立即转出
+    OverCap,    // 超过上限，进入延迟队列
+    Anomalous,  // 远超上限，进入延迟队列并暂停跨链桥
+}
+
+impl OutflowWindow {
+    // 把窗口滚动到当前时间，丢弃已超出窗口的分格
+    pub fn advance(&mut self, now: i64) {
+        let current = now - now.rem_euclid(OUTFLOW_BUCKET_SECS);
+        if current <= self.bucket_start {
+            return;
+        }
+
+        let elapsed = ((current - self.bucket_start) / OUTFLOW_BUCKET_SECS) as u64;
+        if elapsed >= OUTFLOW_WINDOW_BUCKETS as u64 {
+            self.buckets.fill(0);
+        } else {
+            let elapsed = elapsed as usize;
+            self.buckets.rotate_right(elapsed);
+            self.buckets[..elapsed].fill(0);
+        }
+        self.bucket_start = current;
+    }
+
+    // 窗口内的总转出量
+    pub fn total(&self) -> u64 {
+        self.buckets
+            .iter()
+            .fold(0u64, |total, amount| total.saturating_add(*amount))
+    }
+
+    // 检查再转出amount后是否超限（调用前需先advance）
+    // 延迟队列中的数量一并计入，避免拆成多笔超限转入绕过异常检测
+    pub fn check(&self, amount: u64) -> OutflowCheck {
+        if self.cap == 0 {
+            return OutflowCheck::WithinCap;
+        }
+
+        let total = self
+            .total()
+            .saturating_add(self.pending)
+            .saturating_add(amount);
+        if total > self.cap.saturating_mul(ANOMALY_CAP_MULTIPLIER) {
+            OutflowCheck::Anomalous
+        } else if total > self.cap {
+            OutflowCheck::OverCap
+        } else {
+            OutflowCheck::WithinCap
+        }
+    }
+
+    // 检查能否执行队列中的一笔转出（调用前需先advance）
+    // 不计入队列中的其他转入，否则排队的转入会互相阻塞；单笔超过上限的转入在窗口为空时才能执行
+    pub fn can_release(&self, amount: u64) -> bool {
+        let total = self.total();
+        self.cap == 0 || total == 0 || total.saturating_add(amount) <= self.cap
+    }
+
+    // 记录一笔转出（调用前需先advance）
+    pub fn record(&mut self, amount: u64) {
+        self.buckets[0] = self.buckets[0].saturating_add(amount);
+    }
+}
+
+// 单个代币的转出限额
+#[account]
+pub struct TokenOutflowLimit {
+    pub schema_version: u8,           // 账户布局版本
+    pub mint: Pubkey,                 // 代币Mint
+    pub window: OutflowWindow,        // 该代币的滚动窗口
+    pub bump: u8,                     // PDA bump值
+}
+
+// 转入记录状态
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InboundTransferStatus {
+    Released,   // 已转给接收方
+    Queued,     // 超限，在延迟队列中等待
+    Cancelled,  // 守护者已取消，代币留在托管金库
+}
+
+// 外部链转入的记录，同时防止同一笔源链交易被重复确认
+#[account]
+pub struct InboundTransfer {
+    pub schema_version: u8,           // 账户布局版本
+    pub market: Pubkey,               // 用于分配事件序号的市场
+    pub source_chain_id: u64,         // 源链ID
+    pub tx_hash: [u8; 32],            // 源链交易哈希
+    pub recipient: Pubkey,            // 接收方
+    pub mint: Pubkey,                 // 转入的代币Mint
+    pub amount: u64,                  // 转入数量
+    pub status: InboundTransferStatus, // 当前状态
+    pub confirmed_at: i64,            // 中继器确认时间
+    pub release_after: i64,           // 延迟队列中的最早执行时间
+    pub bump: u8,                     // PDA bump值
+}
+
+impl InboundTransfer {
+    // 事件中的订单ID：转入没有本链订单，取源链交易哈希的前16字节
+    pub fn order_id(&self) -> u128 {
+        let mut id = [0u8; 16];
+        id.copy_from_slice(&self.tx_hash[..16]);
+        u128::from_le_bytes(id)
+    }
+}
+
+// 依次滚动链、代币和全局窗口，返回最严重的检查结果
+pub fn check_outflow(
+    windows: [&mut OutflowWindow; 3],
+    amount: u64,
+    now: i64,
+) -> OutflowCheck {
+    windows
+        .into_iter()
+        .map(|window| {
+            window.advance(now);
+            window.check(amount)
+        })
+        .max()
+        .unwrap_or(OutflowCheck::WithinCap)
+}
+
+// 把一笔转出计入链、代币和全局窗口
+pub fn record_outflow(windows: [&mut OutflowWindow; 3], amount: u64, now: i64) {
+    for window in windows {
+        window.advance(now);
+        window.record(amount);
+    }
+}
+
+// 把进入延迟队列的转出计入链、代币和全局窗口的排队量
+pub fn queue_outflow(windows: [&mut OutflowWindow; 3], amount: u64) {
+    for window in windows {
+        window.pending = window.pending.saturating_add(amount);
+    }
+}
+
+// 队列中的转入执行或取消后从排队量中扣除
+pub fn dequeue_outflow(windows: [&mut OutflowWindow; 3], amount: u64) {
+    for window in windows {
+        window.pending = window.pending.saturating_sub(amount);
+    }
+}
+
+// 依次滚动链、代币和全局窗口，检查能否执行队列中的转出
+pub fn check_release(windows: [&mut OutflowWindow; 3], amount: u64, now: i64) -> bool {
+    windows.into_iter().all(|window| {
+        window.advance(now);
+        window.can_release(amount)
+    })
+}
+
+// 设置守护者、延迟时间和全局上限所需的账户
+#[derive(Accounts)]
+pub struct ConfigureBridgeLimits<'info> {
+    #[account(
+        mut,
+        seeds = [b"cross_chain_bridge"],
+        bump = bridge_config.bump,
+        has_one = admin @ ErrorCode::UnauthorizedOperation
+    )]
+    pub bridge_config: Account<'info, CrossChainBridgeConfig>,
+
+    #[account(mut, seeds = [b"cross_chain_stats"], bump)]
+    pub bridge_stats: Account<'info, CrossChainBridgeStats>,
+
+    pub admin: Signer<'info>,
+}
+
+// 设置代币转出限额所需的账户
+#[derive(Accounts)]
+pub struct SetTokenOutflowLimit<'info> {
+    #[account(
+        seeds = [b"cross_chain_bridge"],
+        bump = bridge_config.bump,
+        has_one = admin @ ErrorCode::UnauthorizedOperation
+    )]
+    pub bridge_config: Account<'info, CrossChainBridgeConfig>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + std::mem::size_of::<TokenOutflowLimit>(),
+        seeds = [b"token_outflow_limit", token_mint.key().as_ref()],
+        bump
+    )]
+    pub token_outflow_limit: Account<'info, TokenOutflowLimit>,
+
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// 暂停/恢复跨链桥所需的账户（管理员可暂停和恢复，守护者只能暂停）
+#[derive(Accounts)]
+pub struct SetBridgePaused<'info> {
+    #[account(mut, seeds = [b"cross_chain_bridge"], bump = bridge_config.bump)]
+    pub bridge_config: Account<'info, CrossChainBridgeConfig>,
+
+    pub authority: Signer<'info>,
+}
+
+// 执行延迟转入所需的账户（任何人都可以调用，代币只会转给接收方）
+#[derive(Accounts)]
+pub struct ExecuteDelayedTransfer<'info> {
+    #[account(mut, address = inbound_transfer.market @ ErrorCode::InvalidMarketId)] // 用于分配事件序号
+    pub market: Account<'info, Market>,
+
+    #[account(seeds = [b"cross_chain_bridge"], bump = bridge_config.bump)]
+    pub bridge_config: Account<'info, CrossChainBridgeConfig>,
+
+    #[account(mut, seeds = [b"cross_chain_stats"], bump)]
+    pub bridge_stats: Account<'info, CrossChainBridgeStats>,
+
+    #[account(mut, seeds = [b"chain_registry"], bump = chain_registry.bump)]
+    pub chain_registry: Account<'info, ChainRegistry>,
+
+    #[account(
+        mut,
+        seeds = [b"token_outflow_limit", inbound_transfer.mint.as_ref()],
+        bump = token_outflow_limit.bump
+    )]
+    pub token_outflow_limit: Account<'info, TokenOutflowLimit>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"inbound_transfer",
+            &inbound_transfer.source_chain_id.to_le_bytes(),
+            inbound_transfer.tx_hash.as_ref()
+        ],
+        bump = inbound_transfer.bump
+    )]
+    pub inbound_transfer: Account<'info, InboundTransfer>,
+
+    #[account(
+        mut,
+        seeds = [b"cross_chain_escrow", inbound_transfer.mint.as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = recipient_token_account.owner == inbound_transfer.recipient @ ErrorCode::InvalidUserAccount,
+        constraint = recipient_token_account.mint == inbound_transfer.mint @ ErrorCode::InvalidUserAccount
+    )]
+    pub recipient_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+// 取消延迟转入所需的账户（仅守护者）
+#[derive(Accounts)]
+pub struct CancelDelayedTransfer<'info> {
+    #[account(mut, address = inbound_transfer.market @ ErrorCode::InvalidMarketId)] // 用于分配事件序号
+    pub market: Account<'info, Market>,
+
+    #[account(
+        seeds = [b"cross_chain_bridge"],
+        bump = bridge_config.bump,
+        has_one = guardian @ ErrorCode::UnauthorizedOperation
+    )]
+    pub bridge_config: Account<'info, CrossChainBridgeConfig>,
+
+    #[account(mut, seeds = [b"cross_chain_stats"], bump)]
+    pub bridge_stats: Account<'info, CrossChainBridgeStats>,
+
+    #[account(mut, seeds = [b"chain_registry"], bump = chain_registry.bump)]
+    pub chain_registry: Account<'info, ChainRegistry>,
+
+    #[account(
+        mut,
+        seeds = [b"token_outflow_limit", inbound_transfer.mint.as_ref()],
+        bump = token_outflow_limit.bump
+    )]
+    pub token_outflow_limit: Account<'info, TokenOutflowLimit>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"inbound_transfer",
+            &inbound_transfer.source_chain_id.to_le_bytes(),
+            inbound_transfer.tx_hash.as_ref()
+        ],
+        bump = inbound_transfer.bump
+    )]
+    pub inbound_transfer: Account<'info, InboundTransfer>,
+
+    pub guardian: Signer<'info>,
+}
+
+// 设置守护者、超限转入的延迟时间和全局转出上限
+pub fn configure_bridge_limits(
+    ctx: Context<ConfigureBridgeLimits>,
+    guardian: Pubkey,
+    release_delay_secs: i64,
+    global_outflow_cap: u64,
+) -> Result<()> {
+    require!(
+        release_delay_secs > 0 && release_delay_secs <= MAX_RELEASE_DELAY_SECS,
+        ErrorCode::InvalidCrossChainParams
+    );
+
+    let config = &mut ctx.accounts.bridge_config;
+    config.guardian = guardian;
+    config.release_delay_secs = release_delay_secs;
+    ctx.accounts.bridge_stats.global_outflow.cap = global_outflow_cap;
+
+    Ok(())
+}
+
+// 设置单个代币的转出上限，托管金库中的代币只有设置过限额后才能转入
+pub fn set_token_outflow_limit(ctx: Context<SetTokenOutflowLimit>, cap: u64) -> Result<()> {
+    let limit = &mut ctx.accounts.token_outflow_limit;
+    if limit.schema_version == 0 {
+        limit.schema_version = CURRENT_SCHEMA_VERSION;
+        limit.mint = ctx.accounts.token_mint.key();
+        limit.window = OutflowWindow::default();
+        limit.bump = *ctx.bumps.get("token_outflow_limit").unwrap();
+    }
+    limit.window.cap = cap;
+
+    Ok(())
+}
+
+// 暂停或恢复跨链桥
+pub fn set_bridge_paused(ctx: Context<SetBridgePaused>, paused: bool) -> Result<()> {
+    let config = &mut ctx.accounts.bridge_config;
+    let authority = ctx.accounts.authority.key();
+
+    let reason = if authority == config.admin {
+        BridgePauseReason::Admin
+    } else if authority == config.guardian && paused {
+        BridgePauseReason::Guardian
+    } else {
+        return err!(ErrorCode::UnauthorizedOperation);
+    };
+
+    config.is_paused = paused;
+    EventHandler::emit_bridge_pause_changed(paused, authority, reason);
+
+    Ok(())
+}
+
+// 延迟期满后执行队列中的转入，各窗口的已转出量仍超限时需等窗口滚动后再执行
+pub fn execute_delayed_transfer(ctx: Context<ExecuteDelayedTransfer>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let accounts = &mut ctx.accounts;
+    let transfer = &mut accounts.inbound_transfer;
+
+    CrossChain::check_bridge_active(&accounts.bridge_config)?;
+    require!(
+        transfer.status == InboundTransferStatus::Queued,
+        ErrorCode::InboundTransferNotQueued
+    );
+    require!(now >= transfer.release_after, ErrorCode::ReleaseDelayNotElapsed);
+
+    let chain = accounts
+        .chain_registry
+        .find_chain_mut(transfer.source_chain_id)
+        .ok_or(ErrorCode::UnsupportedChainId)?;
+    require!(
+        check_release(
+            [
+                &mut chain.outflow,
+                &mut accounts.token_outflow_limit.window,
+                &mut accounts.bridge_stats.global_outflow,
+            ],
+            transfer.amount,
+            now,
+        ),
+        ErrorCode::OutflowCapExceeded
+    );
+    dequeue_outflow(
+        [
+            &mut chain.outflow,
+            &mut accounts.token_outflow_limit.window,
+            &mut accounts.bridge_stats.global_outflow,
+        ],
+        transfer.amount,
+    );
+    record_outflow(
+        [
+            &mut chain.outflow,
+            &mut accounts.token_outflow_limit.window,
+            &mut accounts.bridge_stats.global_outflow,
+        ],
+        transfer.amount,
+        now,
+    );
+
+    CrossChain::release_from_escrow(
+        &accounts.bridge_config,
+        &accounts.escrow_vault,
+        &accounts.recipient_token_account,
+        &accounts.token_program,
+        transfer.amount,
+    )?;
+    transfer.status = InboundTransferStatus::Released;
+    CrossChain::update_stats(&mut accounts.bridge_stats, chain, transfer.amount)?;
+
+    let market = &mut accounts.market;
+    EventHandler::emit_cross_chain_tx_confirmed(
+        market.key(),
+        market.next_event_seq(),
+        transfer.source_chain_id,
+        transfer.tx_hash,
+        transfer.order_id(),
+        transfer.recipient,
+    );
+
+    Ok(())
+}
+
+// 守护者取消队列中的转入，代币留在托管金库
+pub fn cancel_delayed_transfer(ctx: Context<CancelDelayedTransfer>) -> Result<()> {
+    let accounts = &mut ctx.accounts;
+    let transfer = &mut accounts.inbound_transfer;
+    require!(
+        transfer.status == InboundTransferStatus::Queued,
+        ErrorCode::InboundTransferNotQueued
+    );
+
+    let chain = accounts
+        .chain_registry
+        .find_chain_mut(transfer.source_chain_id)
+        .ok_or(ErrorCode::UnsupportedChainId)?;
+    dequeue_outflow(
+        [
+            &mut chain.outflow,
+            &mut accounts.token_outflow_limit.window,
+            &mut accounts.bridge_stats.global_outflow,
+        ],
+        transfer.amount,
+    );
+    transfer.status = InboundTransferStatus::Cancelled;
+
+    let market = &mut accounts.market;
+    EventHandler::emit_cross_chain_transfer_cancelled(
+        market.key(),
+        market.next_event_seq(),
+        transfer.source_chain_id,
+        transfer.tx_hash,
+        transfer.recipient,
+        transfer.amount,
+        accounts.guardian.key(),
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn window(cap: u64) -> OutflowWindow {
+        let mut window = OutflowWindow {
+            cap,
+            ..Default::default()
+        };
+        window.advance(NOW);
+        window
+    }
+
+    #[test]
+    fn queued_outflow_counts_toward_anomaly_check() {
+        let mut window = window(1_000);
+        window.record(600);
+
+        // 每笔都只是超限，排队量累计后远超上限
+        assert_eq!(window.check(500), OutflowCheck::OverCap);
+        window.pending += 500;
+        assert_eq!(window.check(500), OutflowCheck::OverCap);
+        window.pending += 500;
+        assert_eq!(window.check(500), OutflowCheck::Anomalous);
+    }
+
+    #[test]
+    fn release_waits_for_window_to_drain() {
+        let mut window = window(1_000);
+        window.record(800);
+        window.pending = 900;
+
+        // 只看已转出量，不被其他排队的转入阻塞
+        assert!(window.can_release(200));
+        assert!(!window.can_release(300));
+
+        // 24小时后窗口清空，单笔超过上限的转入也可以执行
+        window.advance(NOW + OUTFLOW_WINDOW_BUCKETS as i64 * OUTFLOW_BUCKET_SECS);
+        assert!(window.can_release(1_500));
+    }
+}
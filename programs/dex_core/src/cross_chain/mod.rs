@@ -1,18 +1,44 @@
+use crate::core::Market;
+use crate::events::{BridgePauseReason, CrossChainRefundReason, EventHandler};
+use crate::migration::CURRENT_SCHEMA_VERSION;
+use crate::ErrorCode;
 use anchor_lang::prelude::*;
-use solana_program::keccak;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+use solana_program::sysvar::instructions as instructions_sysvar;
+use solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+use solana_program::{ed25519_program, keccak};
+
+pub mod htlc;
+pub mod limits;
+
+use limits::{
+    check_outflow, queue_outflow, record_outflow, InboundTransfer, InboundTransferStatus,
+    OutflowCheck, OutflowWindow, TokenOutflowLimit, DEFAULT_RELEASE_DELAY_SECS,
+};
+
+// Ed25519程序指令数据布局：[签名数量u8, 填充u8, 每个签名14字节的偏移量表, ...]
+const ED25519_OFFSETS_START: usize = 2;
+const ED25519_OFFSETS_SIZE: usize = 14;
+const ED25519_PUBKEY_SIZE: usize = 32;
+const ED25519_SIGNATURE_SIZE: usize = 64;
+// 偏移量表中的指令索引为该值时表示数据位于Ed25519指令自身
+const ED25519_CURRENT_INSTRUCTION: u16 = u16::MAX;
 
 // 跨链接口和桥接功能
 pub struct CrossChain;
 
-// 支持的外部链ID
-pub enum ChainId {
-    Ethereum = 1,
-    BinanceSmartChain = 56,
-    Polygon = 137,
-    Avalanche = 43114,
-    Arbitrum = 42161,
-    Optimism = 10,
-}
+// 本链（Solana）在跨链订单中使用的链ID，外部链ID不能为0
+pub const SOLANA_CHAIN_ID: u64 = 0;
+
+// 跨链订单在该时间（秒）内未被确认即可退款
+pub const CROSS_CHAIN_ORDER_EXPIRY_SECS: i64 = 24 * 3600;
+
+// 链注册表最多容纳的外部链数量
+pub const MAX_REGISTERED_CHAINS: usize = 16;
+
+const SECONDS_PER_DAY: i64 = 86400;
 
 // 跨链交易状态
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Debug)]
@@ -27,6 +53,7 @@ pub enum CrossChainTxStatus {
 #[account]
 #[derive(Default)]
 pub struct CrossChainOrder {
+    pub schema_version: u8,           // 账户布局版本
     pub owner: Pubkey,                // 订单所有者
     pub source_chain_id: u64,         // 源链ID
     pub target_chain_id: u64,         // 目标链ID
@@ -39,30 +66,171 @@ pub struct CrossChainOrder {
     pub status: u8,                   // 状态 (使用CrossChainTxStatus的值)
     pub created_at: i64,              // 创建时间
     pub confirmed_at: Option<i64>,    // 确认时间
-    pub data: [u8; 64],               // 附加数据
+    pub data: [u8; 64],               // 附加数据，前32字节为目标链接收地址（左侧补零，与ABI编码一致）
+    pub market: Pubkey,               // 发起订单的市场
+    pub delivery_attestations: u8,    // 已证明订单在目标链完成的中继器（按中继器列表下标的位图）
+    pub bump: u8,                     // PDA bump值
 }
 
 // 跨链桥配置
 #[account]
 #[derive(Default)]
 pub struct CrossChainBridgeConfig {
+    pub schema_version: u8,           // 账户布局版本
     pub admin: Pubkey,                // 管理员
     pub relayers: [Pubkey; 5],        // 中继器列表
     pub required_confirmations: u8,   // 所需确认数
-    pub chain_confirmations: [u16; 10], // 各链所需区块确认数
+    pub is_paused: bool,              // 是否暂停
+    pub guardian: Pubkey,             // 守护者，可暂停跨链桥和取消延迟转入
+    pub release_delay_secs: i64,      // 超限转入的延迟时间
+    pub bump: u8,                     // PDA bump值
+}
+
+// 外部链配置（链注册表中的一项）
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct ChainConfig {
+    pub chain_id: u64,                // 外部链ID (EVM chain id)
+    pub enabled: bool,                // 是否允许新的跨链订单
+    pub required_confirmations: u16,  // 所需区块确认数
     pub fee_basis_points: u16,        // 基点费率 (1bp = 0.01%)
     pub min_transfer_amount: u64,     // 最小转账金额
     pub max_transfer_amount: u64,     // 最大转账金额
-    pub is_paused: bool,              // 是否暂停
+    pub daily_cap: u64,               // 每日转出上限（0表示不限制）
+    pub day_start: i64,               // 当日统计的开始时间
+    pub daily_volume: u64,            // 当日已转出金额
+    pub outflow: OutflowWindow,       // 从该链转入时托管金库转出的滚动窗口
+    pub total_volume: u64,            // 与该链之间的累计跨链交易量（转出和转入）
+}
+
+// 可由管理员修改的链参数
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct ChainParams {
+    pub enabled: bool,
+    pub required_confirmations: u16,
+    pub fee_basis_points: u16,
+    pub min_transfer_amount: u64,
+    pub max_transfer_amount: u64,
+    pub daily_cap: u64,
+    pub outflow_cap: u64,
+}
+
+// 链注册表 - 管理员无需升级程序即可接入新的外部链
+#[account]
+pub struct ChainRegistry {
+    pub schema_version: u8,           // 账户布局版本
+    pub admin: Pubkey,                // 管理员（与跨链桥管理员相同）
+    pub chain_count: u8,              // 已注册的链数量
+    pub chains: [ChainConfig; MAX_REGISTERED_CHAINS], // 已注册的链
+    pub bump: u8,                     // PDA bump值
+}
+
+impl ChainParams {
+    // 检查参数是否合理
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.required_confirmations > 0
+                && self.fee_basis_points < 10000
+                && self.min_transfer_amount <= self.max_transfer_amount,
+            ErrorCode::InvalidCrossChainParams
+        );
+        Ok(())
+    }
+
+    fn apply(&self, chain: &mut ChainConfig) {
+        chain.enabled = self.enabled;
+        chain.required_confirmations = self.required_confirmations;
+        chain.fee_basis_points = self.fee_basis_points;
+        chain.min_transfer_amount = self.min_transfer_amount;
+        chain.max_transfer_amount = self.max_transfer_amount;
+        chain.daily_cap = self.daily_cap;
+        chain.outflow.cap = self.outflow_cap;
+    }
+}
+
+impl ChainRegistry {
+    // 已注册的链
+    pub fn chains(&self) -> &[ChainConfig] {
+        &self.chains[..self.chain_count as usize]
+    }
+
+    // 按链ID查找
+    pub fn find_chain(&self, chain_id: u64) -> Option<&ChainConfig> {
+        self.chains().iter().find(|c| c.chain_id == chain_id)
+    }
+
+    pub fn find_chain_mut(&mut self, chain_id: u64) -> Option<&mut ChainConfig> {
+        let count = self.chain_count as usize;
+        self.chains[..count].iter_mut().find(|c| c.chain_id == chain_id)
+    }
+
+    // 注册新链
+    pub fn add_chain(&mut self, chain_id: u64, params: &ChainParams) -> Result<()> {
+        require!(chain_id != 0, ErrorCode::UnsupportedChainId);
+        require!(self.find_chain(chain_id).is_none(), ErrorCode::ChainAlreadyRegistered);
+        require!(
+            (self.chain_count as usize) < MAX_REGISTERED_CHAINS,
+            ErrorCode::ChainRegistryFull
+        );
+        params.validate()?;
+
+        let chain = &mut self.chains[self.chain_count as usize];
+        *chain = ChainConfig {
+            chain_id,
+            ..Default::default()
+        };
+        params.apply(chain);
+        self.chain_count += 1;
+        Ok(())
+    }
+
+    // 修改已注册链的参数
+    pub fn update_chain(&mut self, chain_id: u64, params: &ChainParams) -> Result<()> {
+        params.validate()?;
+        let chain = self
+            .find_chain_mut(chain_id)
+            .ok_or(ErrorCode::UnsupportedChainId)?;
+        params.apply(chain);
+        Ok(())
+    }
+
+    // 检查向目标链转出的金额并计入当日额度，返回跨链手续费
+    pub fn reserve_transfer(&mut self, chain_id: u64, amount: u64, now: i64) -> Result<u64> {
+        let chain = self
+            .find_chain_mut(chain_id)
+            .ok_or(ErrorCode::UnsupportedChainId)?;
+        require!(chain.enabled, ErrorCode::ChainDisabled);
+        require!(
+            amount >= chain.min_transfer_amount && amount <= chain.max_transfer_amount,
+            ErrorCode::TransferAmountOutOfRange
+        );
+
+        let today_start = now - now.rem_euclid(SECONDS_PER_DAY);
+        if chain.day_start != today_start {
+            chain.day_start = today_start;
+            chain.daily_volume = 0;
+        }
+        let daily_volume = chain.daily_volume.saturating_add(amount);
+        require!(
+            chain.daily_cap == 0 || daily_volume <= chain.daily_cap,
+            ErrorCode::ChainDailyCapExceeded
+        );
+        chain.daily_volume = daily_volume;
+
+        Ok(CrossChain::calculate_fee(chain, amount))
+    }
 }
 
 // 跨链桥统计
 #[account]
 pub struct CrossChainBridgeStats {
+    pub schema_version: u8,           // 账户布局版本
     pub total_volume: u64,            // 总交易量
     pub total_tx_count: u64,          // 总交易数
-    pub chain_volumes: [u64; 10],     // 各链交易量
+    pub chain_volumes: [u64; 10],     // 已废弃：按链ID取模会冲突，各链交易量记录在链注册表的total_volume中
     pub daily_stats: [DailyStats; 7], // 最近7天统计
+    pub total_refunded: u64,          // 累计退款金额
+    pub refund_count: u64,            // 累计退款笔数
+    pub global_outflow: OutflowWindow, // 托管金库总转出的滚动窗口
 }
 
 // 每日统计
@@ -81,35 +249,31 @@ impl CrossChain {
         relayers: [Pubkey; 5],
         required_confirmations: u8,
     ) -> Result<()> {
-        require!(required_confirmations > 0 && required_confirmations <= relayers.len() as u8, 
-                 ProgramError::InvalidArgument);
-        
+        require!(
+            required_confirmations > 0 && required_confirmations <= relayers.len() as u8,
+            ErrorCode::InvalidCrossChainParams
+        );
+
+        config.schema_version = CURRENT_SCHEMA_VERSION;
         config.admin = admin;
         config.relayers = relayers;
         config.required_confirmations = required_confirmations;
-        
-        // 设置默认每链确认数
-        config.chain_confirmations[ChainId::Ethereum as usize % 10] = 20;
-        config.chain_confirmations[ChainId::BinanceSmartChain as usize % 10] = 15;
-        config.chain_confirmations[ChainId::Polygon as usize % 10] = 256;
-        config.chain_confirmations[ChainId::Avalanche as usize % 10] = 12;
-        config.chain_confirmations[ChainId::Arbitrum as usize % 10] = 10;
-        config.chain_confirmations[ChainId::Optimism as usize % 10] = 10;
-        
-        // 设置默认费率和限制
-        config.fee_basis_points = 30; // 默认0.3%
-        config.min_transfer_amount = 10_000_000; // 0.01 TOKEN (假设6位小数)
-        config.max_transfer_amount = 1_000_000_000_000; // 1,000,000 TOKEN (假设6位小数)
         config.is_paused = false;
-        
+        config.guardian = admin;
+        config.release_delay_secs = DEFAULT_RELEASE_DELAY_SECS;
+
         Ok(())
     }
-    
+
     // 初始化统计信息
     pub fn initialize_stats(stats: &mut CrossChainBridgeStats) {
+        stats.schema_version = CURRENT_SCHEMA_VERSION;
         stats.total_volume = 0;
         stats.total_tx_count = 0;
         stats.chain_volumes.fill(0);
+        stats.total_refunded = 0;
+        stats.refund_count = 0;
+        stats.global_outflow = OutflowWindow::default();
         
         // 初始化每日统计
         let current_timestamp = Clock::get().unwrap().unix_timestamp;
@@ -139,6 +303,7 @@ impl CrossChain {
     ) -> Result<()> {
         let clock = Clock::get()?;
         
+        order.schema_version = CURRENT_SCHEMA_VERSION;
         order.owner = owner;
         order.source_chain_id = source_chain_id;
         order.target_chain_id = target_chain_id;
@@ -152,6 +317,7 @@ impl CrossChain {
         order.created_at = clock.unix_timestamp;
         order.confirmed_at = None;
         order.data = data;
+        order.delivery_attestations = 0;
         
         Ok(())
     }
@@ -186,10 +352,10 @@ impl CrossChain {
         Ok(())
     }
     
-    // 更新统计信息
+    // 更新统计信息，各链交易量记在链注册表对应的项上
     pub fn update_stats(
         stats: &mut CrossChainBridgeStats,
-        chain_id: u64,
+        chain: &mut ChainConfig,
         amount: u64,
     ) -> Result<()> {
         // 更新总量统计
@@ -197,8 +363,7 @@ impl CrossChain {
         stats.total_tx_count = stats.total_tx_count.saturating_add(1);
         
         // 更新链特定统计
-        let chain_idx = (chain_id % 10) as usize;
-        stats.chain_volumes[chain_idx] = stats.chain_volumes[chain_idx].saturating_add(amount);
+        chain.total_volume = chain.total_volume.saturating_add(amount);
         
         // 更新每日统计
         let current_timestamp = Clock::get()?.unix_timestamp;
@@ -227,47 +392,56 @@ impl CrossChain {
         Ok(())
     }
     
-    // 验证中继器签名
+    // 验证中继器签名，签名数量必须达到所需确认数
     pub fn verify_relayer_signatures(
         config: &CrossChainBridgeConfig,
         order_hash: [u8; 32],
-        signatures: &[[u8; 64]],
         signers: &[Pubkey],
+        instructions_sysvar: &AccountInfo,
     ) -> Result<()> {
-        // 验证签名数量足够
-        require!(
-            signatures.len() >= config.required_confirmations as usize,
-            ProgramError::InvalidArgument
-        );
-        
         require!(
-            signatures.len() == signers.len(),
-            ProgramError::InvalidArgument
+            signers.len() >= config.required_confirmations as usize,
+            ErrorCode::InsufficientRelayerSignatures
         );
-        
-        // 验证签名者是否为有效中继器
-        let mut valid_signatures = 0;
-        
-        for (i, &signer) in signers.iter().enumerate() {
-            // 检查是否为配置的中继器
-            let is_valid_relayer = config.relayers.contains(&signer);
-            
-            if is_valid_relayer {
-                // 验证签名
-                if Self::verify_ed25519_signature(signer, &order_hash, &signatures[i]) {
-                    valid_signatures += 1;
-                    
-                    if valid_signatures >= config.required_confirmations as usize {
-                        return Ok(());
-                    }
-                }
-            }
+        Self::verify_relayer_attestations(config, order_hash, signers, instructions_sysvar)?;
+        Ok(())
+    }
+
+    // 验证中继器证明，不检查数量，返回各签名者在中继器列表中的下标
+    // 签名由同一交易中位于本指令之前的Ed25519程序指令验证，这里通过指令sysvar确认
+    // 每个声明的签名者都是配置的中继器、互不重复，并且对order_hash的签名已被验证
+    pub fn verify_relayer_attestations(
+        config: &CrossChainBridgeConfig,
+        order_hash: [u8; 32],
+        signers: &[Pubkey],
+        instructions_sysvar: &AccountInfo,
+    ) -> Result<Vec<usize>> {
+        let verified = Self::load_ed25519_signatures(instructions_sysvar)?;
+
+        let mut indices = Vec::with_capacity(signers.len());
+        for (i, signer) in signers.iter().enumerate() {
+            let index = config
+                .relayers
+                .iter()
+                .position(|relayer| relayer == signer)
+                .filter(|_| *signer != Pubkey::default())
+                .ok_or(ErrorCode::InvalidRelayerSignature)?;
+            require!(
+                !signers[..i].contains(signer),
+                ErrorCode::DuplicateRelayerSignature
+            );
+            require!(
+                verified
+                    .iter()
+                    .any(|(pubkey, message)| pubkey == signer && message[..] == order_hash[..]),
+                ErrorCode::InvalidRelayerSignature
+            );
+            indices.push(index);
         }
-        
-        // 有效签名不足
-        Err(ProgramError::InvalidArgument.into())
+
+        Ok(indices)
     }
-    
+
     // 生成跨链消息哈希
     pub fn generate_order_hash(order: &CrossChainOrder) -> [u8; 32] {
         // 组合所有关键字段作为消息
@@ -286,54 +460,144 @@ impl CrossChain {
         keccak::hashv(&[&message]).to_bytes()
     }
     
-    // 验证ED25519签名 (简化版，实际实现可能需要更复杂的逻辑)
-    fn verify_ed25519_signature(
-        pubkey: Pubkey,
-        message: &[u8; 32],
-        signature: &[u8; 64],
-    ) -> bool {
-        // 这里应该实现实际的ED25519签名验证
-        // 在实际代码中，你可能需要使用ed25519_dalek或其他库
-        // 这里简化实现，实际代码需要正确验证
-        
-        // 模拟签名验证成功
-        true
+    // 读取本指令之前所有Ed25519程序指令已验证的 (公钥, 消息)
+    fn load_ed25519_signatures(
+        instructions_sysvar: &AccountInfo,
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>> {
+        let current_index = load_current_index_checked(instructions_sysvar)? as usize;
+
+        let mut verified = Vec::new();
+        for index in 0..current_index {
+            let instruction = load_instruction_at_checked(index, instructions_sysvar)?;
+            if instruction.program_id == ed25519_program::ID {
+                verified.extend(Self::parse_ed25519_instruction(&instruction.data)?);
+            }
+        }
+
+        Ok(verified)
     }
-    
-    // 验证订单是否在金额限制内
-    pub fn validate_transfer_amount(
-        config: &CrossChainBridgeConfig,
-        amount: u64,
-    ) -> Result<()> {
-        require!(
-            amount >= config.min_transfer_amount,
-            ProgramError::InvalidArgument
-        );
-        
+
+    // 解析Ed25519程序指令数据，返回其中每个签名的 (公钥, 消息)
+    // 只接受数据全部位于该指令自身的签名，否则公钥或消息可能取自其他指令，与这里读到的不一致
+    pub fn parse_ed25519_instruction(data: &[u8]) -> Result<Vec<(Pubkey, Vec<u8>)>> {
         require!(
-            amount <= config.max_transfer_amount,
-            ProgramError::InvalidArgument
+            data.len() >= ED25519_OFFSETS_START,
+            ErrorCode::InvalidRelayerSignature
         );
-        
-        Ok(())
+        let count = data[0] as usize;
+
+        let mut result = Vec::with_capacity(count);
+        for i in 0..count {
+            let start = ED25519_OFFSETS_START + i * ED25519_OFFSETS_SIZE;
+            let signature_offset = Self::read_u16(data, start)?;
+            let signature_ix = Self::read_u16(data, start + 2)?;
+            let pubkey_offset = Self::read_u16(data, start + 4)?;
+            let pubkey_ix = Self::read_u16(data, start + 6)?;
+            let message_offset = Self::read_u16(data, start + 8)?;
+            let message_size = Self::read_u16(data, start + 10)?;
+            let message_ix = Self::read_u16(data, start + 12)?;
+
+            require!(
+                signature_ix == ED25519_CURRENT_INSTRUCTION
+                    && pubkey_ix == ED25519_CURRENT_INSTRUCTION
+                    && message_ix == ED25519_CURRENT_INSTRUCTION,
+                ErrorCode::InvalidRelayerSignature
+            );
+            Self::read_bytes(data, signature_offset, ED25519_SIGNATURE_SIZE)?;
+
+            let pubkey_bytes = Self::read_bytes(data, pubkey_offset, ED25519_PUBKEY_SIZE)?;
+            let pubkey = Pubkey::try_from(pubkey_bytes)
+                .map_err(|_| error!(ErrorCode::InvalidRelayerSignature))?;
+            let message = Self::read_bytes(data, message_offset, message_size as usize)?.to_vec();
+            result.push((pubkey, message));
+        }
+
+        Ok(result)
     }
-    
+
+    fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+        data.get(offset..offset + 2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+            .ok_or_else(|| error!(ErrorCode::InvalidRelayerSignature))
+    }
+
+    fn read_bytes(data: &[u8], offset: u16, len: usize) -> Result<&[u8]> {
+        data.get(offset as usize..offset as usize + len)
+            .ok_or_else(|| error!(ErrorCode::InvalidRelayerSignature))
+    }
+
     // 计算跨链费用
-    pub fn calculate_fee(
-        config: &CrossChainBridgeConfig,
-        amount: u64,
-    ) -> u64 {
-        // 基于基点费率计算手续费
+    pub fn calculate_fee(chain: &ChainConfig, amount: u64) -> u64 {
+        // 基于目标链的基点费率计算手续费
         // 1 bp = 0.01%
-        ((amount as u128) * (config.fee_basis_points as u128) / 10000) as u64
+        ((amount as u128) * (chain.fee_basis_points as u128) / 10000) as u64
     }
-    
+
     // 检查桥是否暂停
     pub fn check_bridge_active(config: &CrossChainBridgeConfig) -> Result<()> {
-        require!(!config.is_paused, ProgramError::InvalidAccountData);
+        require!(!config.is_paused, ErrorCode::BridgePaused);
         Ok(())
     }
     
+    // 生成失败证明的消息哈希，与订单哈希区分开，防止确认签名被当作失败证明使用
+    pub fn generate_failure_hash(order: &CrossChainOrder) -> [u8; 32] {
+        let order_hash = Self::generate_order_hash(order);
+        keccak::hashv(&[b"cross_chain_order_failed", &order_hash]).to_bytes()
+    }
+
+    // 生成跨链订单已在目标链完成的证明哈希，中继器对其签名证明该笔目标链交易已执行
+    pub fn generate_delivery_hash(order: &CrossChainOrder, target_tx_hash: &[u8; 32]) -> [u8; 32] {
+        let order_hash = Self::generate_order_hash(order);
+        keccak::hashv(&[b"cross_chain_order_delivered", &order_hash, target_tx_hash]).to_bytes()
+    }
+
+    // 生成外部链转入的消息哈希，中继器对其签名证明源链交易已达到所需确认数
+    pub fn generate_inbound_hash(
+        source_chain_id: u64,
+        tx_hash: &[u8; 32],
+        recipient: &Pubkey,
+        mint: &Pubkey,
+        amount: u64,
+    ) -> [u8; 32] {
+        keccak::hashv(&[
+            b"cross_chain_inbound",
+            &source_chain_id.to_le_bytes(),
+            tx_hash,
+            recipient.as_ref(),
+            mint.as_ref(),
+            &amount.to_le_bytes(),
+        ])
+        .to_bytes()
+    }
+
+    // 由跨链桥配置签名从托管金库转出
+    pub fn release_from_escrow<'info>(
+        config: &Account<'info, CrossChainBridgeConfig>,
+        escrow_vault: &Account<'info, TokenAccount>,
+        to: &Account<'info, TokenAccount>,
+        token_program: &Program<'info, Token>,
+        amount: u64,
+    ) -> Result<()> {
+        token::transfer(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                Transfer {
+                    from: escrow_vault.to_account_info(),
+                    to: to.to_account_info(),
+                    authority: config.to_account_info(),
+                },
+                &[&[b"cross_chain_bridge", &[config.bump]]],
+            ),
+            amount,
+        )
+    }
+
+    // 记录退款：已计入的交易量保持不变，单独统计退款
+    pub fn record_refund(stats: &mut CrossChainBridgeStats, amount: u64) {
+        stats.total_refunded = stats.total_refunded.saturating_add(amount);
+        stats.refund_count = stats.refund_count.saturating_add(1);
+    }
+
     // 检查跨链订单是否过期
     pub fn check_order_expiry(
         order: &CrossChainOrder,
@@ -349,4 +613,610 @@ impl CrossChain {
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+// 初始化跨链桥所需的账户（部署后调用一次，调用者成为管理员）
+#[derive(Accounts)]
+pub struct InitializeCrossChainBridge<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + std::mem::size_of::<CrossChainBridgeConfig>(),
+        seeds = [b"cross_chain_bridge"],
+        bump
+    )]
+    pub bridge_config: Account<'info, CrossChainBridgeConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + std::mem::size_of::<CrossChainBridgeStats>(),
+        seeds = [b"cross_chain_stats"],
+        bump
+    )]
+    pub bridge_stats: Account<'info, CrossChainBridgeStats>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + std::mem::size_of::<ChainRegistry>(),
+        seeds = [b"chain_registry"],
+        bump
+    )]
+    pub chain_registry: Account<'info, ChainRegistry>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// 管理链注册表所需的账户
+#[derive(Accounts)]
+pub struct ManageChainRegistry<'info> {
+    #[account(
+        mut,
+        seeds = [b"chain_registry"],
+        bump = chain_registry.bump,
+        has_one = admin @ ErrorCode::UnauthorizedOperation
+    )]
+    pub chain_registry: Account<'info, ChainRegistry>,
+
+    pub admin: Signer<'info>,
+}
+
+// 创建跨链订单所需的账户，转出的代币和手续费锁定在托管金库中
+#[derive(Accounts)]
+#[instruction(target_chain_id: u64, amount: u64, nonce: u64)]
+pub struct CreateCrossChainOrder<'info> {
+    #[account(mut)] // 用于分配事件序号
+    pub market: Account<'info, Market>,
+
+    #[account(seeds = [b"cross_chain_bridge"], bump = bridge_config.bump)]
+    pub bridge_config: Account<'info, CrossChainBridgeConfig>,
+
+    #[account(mut, seeds = [b"cross_chain_stats"], bump)]
+    pub bridge_stats: Account<'info, CrossChainBridgeStats>,
+
+    #[account(mut, seeds = [b"chain_registry"], bump = chain_registry.bump)]
+    pub chain_registry: Account<'info, ChainRegistry>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + std::mem::size_of::<CrossChainOrder>(),
+        seeds = [b"cross_chain_order", owner.key().as_ref(), &nonce.to_le_bytes()],
+        bump
+    )]
+    pub order: Account<'info, CrossChainOrder>,
+
+    // 只能转出市场的基础代币或报价代币
+    #[account(
+        constraint = token_mint.key() == market.base_mint
+            || token_mint.key() == market.quote_mint @ ErrorCode::InvalidMarketId
+    )]
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        seeds = [b"cross_chain_escrow", token_mint.key().as_ref()],
+        bump,
+        token::mint = token_mint,
+        token::authority = bridge_config,
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.mint == token_mint.key() @ ErrorCode::InvalidUserAccount
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+// 跨链订单退款所需的账户（任何人都可以调用，代币只会退回订单所有者）
+#[derive(Accounts)]
+pub struct RefundCrossChainOrder<'info> {
+    #[account(mut, address = order.market @ ErrorCode::InvalidMarketId)] // 用于分配事件序号
+    pub market: Account<'info, Market>,
+
+    #[account(seeds = [b"cross_chain_bridge"], bump = bridge_config.bump)]
+    pub bridge_config: Account<'info, CrossChainBridgeConfig>,
+
+    #[account(mut, seeds = [b"cross_chain_stats"], bump)]
+    pub bridge_stats: Account<'info, CrossChainBridgeStats>,
+
+    #[account(
+        mut,
+        seeds = [b"cross_chain_order", order.owner.as_ref(), &order.nonce.to_le_bytes()],
+        bump = order.bump
+    )]
+    pub order: Account<'info, CrossChainOrder>,
+
+    #[account(
+        mut,
+        seeds = [b"cross_chain_escrow", order.token_address.as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = owner_token_account.owner == order.owner @ ErrorCode::InvalidUserAccount,
+        constraint = owner_token_account.mint == order.token_address @ ErrorCode::InvalidUserAccount
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: 指令sysvar，用于读取验证失败证明签名的Ed25519指令
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+// 确认跨链订单已在目标链完成所需的账户（任何人都可以提交中继器的证明）
+#[derive(Accounts)]
+pub struct ConfirmCrossChainOrder<'info> {
+    #[account(mut, address = order.market @ ErrorCode::InvalidMarketId)] // 用于分配事件序号
+    pub market: Account<'info, Market>,
+
+    #[account(seeds = [b"cross_chain_bridge"], bump = bridge_config.bump)]
+    pub bridge_config: Account<'info, CrossChainBridgeConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"cross_chain_order", order.owner.as_ref(), &order.nonce.to_le_bytes()],
+        bump = order.bump
+    )]
+    pub order: Account<'info, CrossChainOrder>,
+
+    /// CHECK: 指令sysvar，用于读取验证完成证明签名的Ed25519指令
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+// 确认外部链转入所需的账户（任何人都可以提交，代币只会转给中继器签名的接收方）
+#[derive(Accounts)]
+#[instruction(source_chain_id: u64, tx_hash: [u8; 32])]
+pub struct ConfirmCrossChainTx<'info> {
+    #[account(mut)] // 用于分配事件序号
+    pub market: Account<'info, Market>,
+
+    #[account(mut, seeds = [b"cross_chain_bridge"], bump = bridge_config.bump)] // 异常转出时自动暂停
+    pub bridge_config: Account<'info, CrossChainBridgeConfig>,
+
+    #[account(mut, seeds = [b"cross_chain_stats"], bump)]
+    pub bridge_stats: Account<'info, CrossChainBridgeStats>,
+
+    #[account(mut, seeds = [b"chain_registry"], bump = chain_registry.bump)]
+    pub chain_registry: Account<'info, ChainRegistry>,
+
+    // 代币必须先由管理员设置转出限额
+    #[account(
+        mut,
+        seeds = [b"token_outflow_limit", token_mint.key().as_ref()],
+        bump = token_outflow_limit.bump
+    )]
+    pub token_outflow_limit: Account<'info, TokenOutflowLimit>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<InboundTransfer>(),
+        seeds = [b"inbound_transfer", &source_chain_id.to_le_bytes(), tx_hash.as_ref()],
+        bump
+    )]
+    pub inbound_transfer: Account<'info, InboundTransfer>,
+
+    #[account(
+        constraint = token_mint.key() == market.base_mint
+            || token_mint.key() == market.quote_mint @ ErrorCode::InvalidMarketId
+    )]
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"cross_chain_escrow", token_mint.key().as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = recipient_token_account.mint == token_mint.key() @ ErrorCode::InvalidUserAccount
+    )]
+    pub recipient_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: 指令sysvar，用于读取验证中继器签名的Ed25519指令
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+// 初始化跨链桥配置、统计和链注册表
+pub fn initialize_cross_chain_bridge(
+    ctx: Context<InitializeCrossChainBridge>,
+    relayers: [Pubkey; 5],
+    required_confirmations: u8,
+) -> Result<()> {
+    let admin = ctx.accounts.admin.key();
+
+    let config = &mut ctx.accounts.bridge_config;
+    CrossChain::initialize_bridge(config, admin, relayers, required_confirmations)?;
+    config.bump = *ctx.bumps.get("bridge_config").unwrap();
+
+    CrossChain::initialize_stats(&mut ctx.accounts.bridge_stats);
+
+    let registry = &mut ctx.accounts.chain_registry;
+    registry.schema_version = CURRENT_SCHEMA_VERSION;
+    registry.admin = admin;
+    registry.chain_count = 0;
+    registry.chains = [ChainConfig::default(); MAX_REGISTERED_CHAINS];
+    registry.bump = *ctx.bumps.get("chain_registry").unwrap();
+
+    Ok(())
+}
+
+// 注册外部链
+pub fn add_chain(
+    ctx: Context<ManageChainRegistry>,
+    chain_id: u64,
+    params: ChainParams,
+) -> Result<()> {
+    ctx.accounts.chain_registry.add_chain(chain_id, &params)
+}
+
+// 修改外部链参数（包括启用/停用）
+pub fn update_chain(
+    ctx: Context<ManageChainRegistry>,
+    chain_id: u64,
+    params: ChainParams,
+) -> Result<()> {
+    ctx.accounts.chain_registry.update_chain(chain_id, &params)
+}
+
+// 创建跨链订单：按链注册表检查目标链和金额，把金额和手续费转入托管金库
+// data的前32字节为目标链接收地址，不能为空
+pub fn create_cross_chain_order(
+    ctx: Context<CreateCrossChainOrder>,
+    target_chain_id: u64,
+    amount: u64,
+    nonce: u64,
+    data: [u8; 64],
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let accounts = &mut ctx.accounts;
+    let owner = accounts.owner.key();
+    let token_mint = accounts.token_mint.key();
+    let mut destination = [0u8; 32];
+    destination.copy_from_slice(&data[..32]);
+
+    require!(
+        accounts.market.schema_version == CURRENT_SCHEMA_VERSION,
+        ErrorCode::AccountNeedsMigration
+    );
+    require!(destination != [0u8; 32], ErrorCode::InvalidCrossChainParams);
+    CrossChain::check_bridge_active(&accounts.bridge_config)?;
+    let fee = accounts
+        .chain_registry
+        .reserve_transfer(target_chain_id, amount, now)?;
+
+    token::transfer(
+        CpiContext::new(
+            accounts.token_program.to_account_info(),
+            Transfer {
+                from: accounts.user_token_account.to_account_info(),
+                to: accounts.escrow_vault.to_account_info(),
+                authority: accounts.owner.to_account_info(),
+            },
+        ),
+        amount.checked_add(fee).ok_or(ErrorCode::InvalidCrossChainParams)?,
+    )?;
+
+    let order = &mut accounts.order;
+    CrossChain::create_cross_chain_order(
+        order,
+        owner,
+        SOLANA_CHAIN_ID,
+        target_chain_id,
+        token_mint,
+        amount,
+        fee,
+        nonce,
+        data,
+    )?;
+    order.market = accounts.market.key();
+    order.bump = *ctx.bumps.get("order").unwrap();
+
+    let chain = accounts
+        .chain_registry
+        .find_chain_mut(target_chain_id)
+        .ok_or(ErrorCode::UnsupportedChainId)?;
+    CrossChain::update_stats(&mut accounts.bridge_stats, chain, amount)?;
+
+    let market = &mut accounts.market;
+    let (base_amount, quote_amount) = if token_mint == market.base_mint {
+        (amount, 0)
+    } else {
+        (0, amount)
+    };
+    EventHandler::emit_cross_chain_order_created(
+        market.key(),
+        market.next_event_seq(),
+        target_chain_id,
+        nonce as u128,
+        owner,
+        token_mint,
+        base_amount,
+        quote_amount,
+        amount,
+        destination,
+    );
+
+    Ok(())
+}
+
+// 记录中继器对跨链订单已在目标链完成的证明，证明数量达到所需确认数时订单变为已确认
+// 证明可以分多次提交；只要有一个中继器证明过完成，订单就不能再按过期退款
+pub fn confirm_cross_chain_order(
+    ctx: Context<ConfirmCrossChainOrder>,
+    target_tx_hash: [u8; 32],
+    relayer_signers: Vec<Pubkey>,
+) -> Result<()> {
+    let accounts = &mut ctx.accounts;
+    let config = &accounts.bridge_config;
+    let order = &mut accounts.order;
+
+    require!(
+        order.status == CrossChainTxStatus::Pending as u8,
+        ErrorCode::CrossChainOrderNotPending
+    );
+    require!(!relayer_signers.is_empty(), ErrorCode::InsufficientRelayerSignatures);
+    // 同一订单的所有证明必须指向同一笔目标链交易
+    if let Some(recorded) = order.target_tx_hash {
+        require!(recorded == target_tx_hash, ErrorCode::DeliveryTxHashMismatch);
+    }
+
+    let indices = CrossChain::verify_relayer_attestations(
+        config,
+        CrossChain::generate_delivery_hash(order, &target_tx_hash),
+        &relayer_signers,
+        &accounts.instructions.to_account_info(),
+    )?;
+    for index in indices {
+        order.delivery_attestations |= 1 << index;
+    }
+    order.target_tx_hash = Some(target_tx_hash);
+
+    if order.delivery_attestations.count_ones() < config.required_confirmations as u32 {
+        return Ok(());
+    }
+
+    let source_tx_hash = order.source_tx_hash;
+    CrossChain::confirm_cross_chain_order(order, source_tx_hash, Some(target_tx_hash))?;
+
+    let market = &mut accounts.market;
+    EventHandler::emit_cross_chain_order_delivered(
+        market.key(),
+        market.next_event_seq(),
+        order.target_chain_id,
+        order.nonce as u128,
+        order.owner,
+        target_tx_hash,
+    );
+
+    Ok(())
+}
+
+// 退还未完成的跨链订单
+// 没有提供签名者时要求订单已过期且完成证明未达到所需确认数，金额和手续费全部退还；
+// 提供签名者时由中继器证明目标链执行失败，中继器已尝试执行，手续费不退。
+// 只要有任何中继器证明过完成，就不再接受失败证明
+pub fn refund_cross_chain_order(
+    ctx: Context<RefundCrossChainOrder>,
+    attesting_relayers: Vec<Pubkey>,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let accounts = &mut ctx.accounts;
+    let order = &mut accounts.order;
+
+    require!(
+        order.status == CrossChainTxStatus::Pending as u8,
+        ErrorCode::CrossChainOrderNotPending
+    );
+
+    let (reason, fee_refunded) = if attesting_relayers.is_empty() {
+        require!(
+            now.saturating_sub(order.created_at) > CROSS_CHAIN_ORDER_EXPIRY_SECS,
+            ErrorCode::CrossChainOrderNotExpired
+        );
+        // 单个中继器的完成证明不足以阻止过期退款，否则一个中继器就能锁住资金
+        require!(
+            order.delivery_attestations.count_ones()
+                < accounts.bridge_config.required_confirmations as u32,
+            ErrorCode::CrossChainOrderDeliveryAttested
+        );
+        (CrossChainRefundReason::Expired, order.fee)
+    } else {
+        require!(
+            order.delivery_attestations == 0 && order.target_tx_hash.is_none(),
+            ErrorCode::CrossChainOrderDeliveryAttested
+        );
+        CrossChain::verify_relayer_signatures(
+            &accounts.bridge_config,
+            CrossChain::generate_failure_hash(order),
+            &attesting_relayers,
+            &accounts.instructions.to_account_info(),
+        )?;
+        (CrossChainRefundReason::RelayerAttested, 0)
+    };
+
+    let refund = order.amount.saturating_add(fee_refunded);
+    token::transfer(
+        CpiContext::new_with_signer(
+            accounts.token_program.to_account_info(),
+            Transfer {
+                from: accounts.escrow_vault.to_account_info(),
+                to: accounts.owner_token_account.to_account_info(),
+                authority: accounts.bridge_config.to_account_info(),
+            },
+            &[&[b"cross_chain_bridge", &[accounts.bridge_config.bump]]],
+        ),
+        refund,
+    )?;
+
+    CrossChain::update_order_status(order, CrossChainTxStatus::Refunded)?;
+    CrossChain::record_refund(&mut accounts.bridge_stats, refund);
+
+    let market = &mut accounts.market;
+    EventHandler::emit_cross_chain_order_refunded(
+        market.key(),
+        market.next_event_seq(),
+        order.target_chain_id,
+        order.nonce as u128,
+        order.owner,
+        order.amount,
+        fee_refunded,
+        reason,
+    );
+
+    Ok(())
+}
+
+// 确认外部链转入：验证中继器签名后按链、代币和全局滚动窗口检查转出量，
+// 未超限时立即从托管金库转给接收方，超限时进入延迟队列并计入各窗口的排队量，
+// 已转出量加上排队量远超上限时同时暂停跨链桥
+pub fn confirm_transaction(
+    ctx: Context<ConfirmCrossChainTx>,
+    source_chain_id: u64,
+    tx_hash: [u8; 32],
+    amount: u64,
+    relayer_signers: Vec<Pubkey>,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let accounts = &mut ctx.accounts;
+    let recipient = accounts.recipient_token_account.owner;
+    let token_mint = accounts.token_mint.key();
+
+    require!(amount > 0, ErrorCode::InvalidParameters);
+    CrossChain::check_bridge_active(&accounts.bridge_config)?;
+    CrossChain::verify_relayer_signatures(
+        &accounts.bridge_config,
+        CrossChain::generate_inbound_hash(
+            source_chain_id,
+            &tx_hash,
+            &recipient,
+            &token_mint,
+            amount,
+        ),
+        &relayer_signers,
+        &accounts.instructions.to_account_info(),
+    )?;
+
+    let chain = accounts
+        .chain_registry
+        .find_chain_mut(source_chain_id)
+        .ok_or(ErrorCode::UnsupportedChainId)?;
+    require!(chain.enabled, ErrorCode::ChainDisabled);
+
+    let check = check_outflow(
+        [
+            &mut chain.outflow,
+            &mut accounts.token_outflow_limit.window,
+            &mut accounts.bridge_stats.global_outflow,
+        ],
+        amount,
+        now,
+    );
+
+    let transfer = &mut accounts.inbound_transfer;
+    transfer.schema_version = CURRENT_SCHEMA_VERSION;
+    transfer.market = accounts.market.key();
+    transfer.source_chain_id = source_chain_id;
+    transfer.tx_hash = tx_hash;
+    transfer.recipient = recipient;
+    transfer.mint = token_mint;
+    transfer.amount = amount;
+    transfer.confirmed_at = now;
+    transfer.bump = *ctx.bumps.get("inbound_transfer").unwrap();
+
+    let market = &mut accounts.market;
+    if check == OutflowCheck::WithinCap {
+        record_outflow(
+            [
+                &mut chain.outflow,
+                &mut accounts.token_outflow_limit.window,
+                &mut accounts.bridge_stats.global_outflow,
+            ],
+            amount,
+            now,
+        );
+        CrossChain::release_from_escrow(
+            &accounts.bridge_config,
+            &accounts.escrow_vault,
+            &accounts.recipient_token_account,
+            &accounts.token_program,
+            amount,
+        )?;
+        transfer.status = InboundTransferStatus::Released;
+        transfer.release_after = now;
+        CrossChain::update_stats(&mut accounts.bridge_stats, chain, amount)?;
+
+        EventHandler::emit_cross_chain_tx_confirmed(
+            market.key(),
+            market.next_event_seq(),
+            source_chain_id,
+            tx_hash,
+            transfer.order_id(),
+            recipient,
+        );
+        return Ok(());
+    }
+
+    queue_outflow(
+        [
+            &mut chain.outflow,
+            &mut accounts.token_outflow_limit.window,
+            &mut accounts.bridge_stats.global_outflow,
+        ],
+        amount,
+    );
+    transfer.status = InboundTransferStatus::Queued;
+    transfer.release_after = now.saturating_add(accounts.bridge_config.release_delay_secs);
+
+    let anomalous = check == OutflowCheck::Anomalous;
+    EventHandler::emit_cross_chain_transfer_queued(
+        market.key(),
+        market.next_event_seq(),
+        source_chain_id,
+        tx_hash,
+        recipient,
+        amount,
+        transfer.release_after,
+        anomalous,
+    );
+
+    // 暂停必须随本交易一起提交，因此这里不返回错误
+    if anomalous {
+        accounts.bridge_config.is_paused = true;
+        EventHandler::emit_bridge_pause_changed(
+            true,
+            accounts.bridge_config.key(),
+            BridgePauseReason::AnomalousOutflow,
+        );
+    }
+
+    Ok(())
+}
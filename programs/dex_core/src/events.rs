@@ -1,3 +1,5 @@
+use crate::access::AccessMode;
+use crate::migration::MigratableAccount;
 use crate::orderbook::Side;
 use anchor_lang::prelude::*;
 
@@ -8,6 +10,7 @@ impl EventHandler {
     // 发出市场创建事件
     pub fn emit_market_created(
         market: Pubkey,
+        seq: u64,
         base_mint: Pubkey,
         quote_mint: Pubkey,
         lot_size: u64,
@@ -15,6 +18,7 @@ impl EventHandler {
     ) {
         emit!(MarketCreatedEvent {
             market,
+            seq,
             base_mint,
             quote_mint,
             lot_size,
@@ -26,6 +30,7 @@ impl EventHandler {
     // 发出下单事件
     pub fn emit_order_placed(
         market: Pubkey,
+        seq: u64,
         order_id: u128,
         owner: Pubkey,
         side: Side,
@@ -34,6 +39,7 @@ impl EventHandler {
     ) {
         emit!(OrderPlacedEvent {
             market,
+            seq,
             order_id,
             owner,
             side,
@@ -46,6 +52,7 @@ impl EventHandler {
     // 发出取消订单事件
     pub fn emit_order_canceled(
         market: Pubkey,
+        seq: u64,
         order_id: u128,
         owner: Pubkey,
         side: Side,
@@ -54,6 +61,7 @@ impl EventHandler {
     ) {
         emit!(OrderCanceledEvent {
             market,
+            seq,
             order_id,
             owner,
             side,
@@ -66,6 +74,7 @@ impl EventHandler {
     // 发出交易事件
     pub fn emit_trade(
         market: Pubkey,
+        seq: u64,
         side: Side,
         maker_order_id: u128,
         taker_order_id: u128,
@@ -76,6 +85,7 @@ impl EventHandler {
     ) {
         emit!(TradeEvent {
             market,
+            seq,
             side,
             maker_order_id,
             taker_order_id,
@@ -87,10 +97,59 @@ impl EventHandler {
         });
     }
 
+    // 发出断线撤单事件
+    pub fn emit_cancel_on_disconnect(
+        market: Pubkey,
+        seq: u64,
+        owner: Pubkey,
+        keeper: Pubkey,
+        orders_canceled: u16,
+        completed: bool,
+        keeper_fee_lamports: u64,
+    ) {
+        emit!(CancelOnDisconnectEvent {
+            market,
+            seq,
+            owner,
+            keeper,
+            orders_canceled,
+            completed,
+            keeper_fee_lamports,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+    }
+
+    // 发出市场准入名单变更事件
+    pub fn emit_access_control_changed(
+        market: Pubkey,
+        seq: u64,
+        access_control: Pubkey,
+        action: AccessControlAction,
+        mode: AccessMode,
+        wallet: Option<Pubkey>,
+    ) {
+        emit!(AccessControlChangedEvent {
+            market,
+            seq,
+            access_control,
+            action,
+            mode,
+            wallet,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+    }
+
     // 发出资金结算事件
-    pub fn emit_funds_settled(market: Pubkey, owner: Pubkey, base_amount: u64, quote_amount: u64) {
+    pub fn emit_funds_settled(
+        market: Pubkey,
+        seq: u64,
+        owner: Pubkey,
+        base_amount: u64,
+        quote_amount: u64,
+    ) {
         emit!(FundsSettledEvent {
             market,
+            seq,
             owner,
             base_amount,
             quote_amount,
@@ -101,6 +160,7 @@ impl EventHandler {
     // 发出LP池流动性变化事件
     pub fn emit_liquidity_changed(
         market: Pubkey,
+        seq: u64,
         owner: Pubkey,
         is_addition: bool, // true: 添加流动性, false: 移除流动性
         base_amount: u64,
@@ -109,6 +169,7 @@ impl EventHandler {
     ) {
         emit!(LiquidityChangedEvent {
             market,
+            seq,
             owner,
             is_addition,
             base_amount,
@@ -119,10 +180,18 @@ impl EventHandler {
     }
 
     // 发出奖励领取事件
-    pub fn emit_rewards_claimed(market: Pubkey, owner: Pubkey, reward_amount: u64) {
+    pub fn emit_rewards_claimed(
+        market: Pubkey,
+        seq: u64,
+        owner: Pubkey,
+        reward_mint: Pubkey,
+        reward_amount: u64,
+    ) {
         emit!(RewardsClaimedEvent {
             market,
+            seq,
             owner,
+            reward_mint,
             reward_amount,
             timestamp: Clock::get().unwrap().unix_timestamp,
         });
@@ -131,19 +200,47 @@ impl EventHandler {
     // 发出跨链订单创建事件
     pub fn emit_cross_chain_order_created(
         source_market: Pubkey,
+        seq: u64,
         target_chain_id: u64,
         order_id: u128,
         owner: Pubkey,
+        token_mint: Pubkey,
         base_amount: u64,
         quote_amount: u64,
+        amount: u64,
+        destination: [u8; 32],
     ) {
         emit!(CrossChainOrderCreatedEvent {
             source_market,
+            seq,
             target_chain_id,
             order_id,
             owner,
+            token_mint,
             base_amount,
             quote_amount,
+            amount,
+            destination,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+    }
+
+    // 发出跨链订单已在目标链完成事件
+    pub fn emit_cross_chain_order_delivered(
+        source_market: Pubkey,
+        seq: u64,
+        target_chain_id: u64,
+        order_id: u128,
+        owner: Pubkey,
+        target_tx_hash: [u8; 32],
+    ) {
+        emit!(CrossChainOrderDeliveredEvent {
+            source_market,
+            seq,
+            target_chain_id,
+            order_id,
+            owner,
+            target_tx_hash,
             timestamp: Clock::get().unwrap().unix_timestamp,
         });
     }
@@ -151,6 +248,7 @@ impl EventHandler {
     // 发出跨链交易确认事件
     pub fn emit_cross_chain_tx_confirmed(
         target_market: Pubkey,
+        seq: u64,
         source_chain_id: u64,
         tx_hash: [u8; 32],
         order_id: u128,
@@ -158,10 +256,91 @@ impl EventHandler {
     ) {
         emit!(CrossChainTxConfirmedEvent {
             target_market,
+            seq,
+            source_chain_id,
+            tx_hash,
+            order_id,
+            owner,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+    }
+
+    // 发出外部链转入进入延迟队列事件
+    pub fn emit_cross_chain_transfer_queued(
+        target_market: Pubkey,
+        seq: u64,
+        source_chain_id: u64,
+        tx_hash: [u8; 32],
+        recipient: Pubkey,
+        amount: u64,
+        release_after: i64,
+        anomalous: bool,
+    ) {
+        emit!(CrossChainTransferQueuedEvent {
+            target_market,
+            seq,
+            source_chain_id,
+            tx_hash,
+            recipient,
+            amount,
+            release_after,
+            anomalous,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+    }
+
+    // 发出延迟转入被守护者取消事件
+    pub fn emit_cross_chain_transfer_cancelled(
+        target_market: Pubkey,
+        seq: u64,
+        source_chain_id: u64,
+        tx_hash: [u8; 32],
+        recipient: Pubkey,
+        amount: u64,
+        guardian: Pubkey,
+    ) {
+        emit!(CrossChainTransferCancelledEvent {
+            target_market,
+            seq,
             source_chain_id,
             tx_hash,
+            recipient,
+            amount,
+            guardian,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+    }
+
+    // 发出跨链桥暂停状态变更事件
+    pub fn emit_bridge_pause_changed(paused: bool, authority: Pubkey, reason: BridgePauseReason) {
+        emit!(BridgePauseChangedEvent {
+            paused,
+            authority,
+            reason,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+    }
+
+    // 发出跨链订单退款事件
+    pub fn emit_cross_chain_order_refunded(
+        source_market: Pubkey,
+        seq: u64,
+        target_chain_id: u64,
+        order_id: u128,
+        owner: Pubkey,
+        amount: u64,
+        fee_refunded: u64,
+        reason: CrossChainRefundReason,
+    ) {
+        emit!(CrossChainOrderRefundedEvent {
+            source_market,
+            seq,
+            target_chain_id,
             order_id,
             owner,
+            amount,
+            fee_refunded,
+            reason,
             timestamp: Clock::get().unwrap().unix_timestamp,
         });
     }
@@ -169,6 +348,7 @@ impl EventHandler {
     // 发出高级订单事件
     pub fn emit_advanced_order_created(
         market: Pubkey,
+        seq: u64,
         order_id: u128,
         owner: Pubkey,
         strategy_type: u8,
@@ -176,6 +356,7 @@ impl EventHandler {
     ) {
         emit!(AdvancedOrderCreatedEvent {
             market,
+            seq,
             order_id,
             owner,
             strategy_type,
@@ -185,9 +366,10 @@ impl EventHandler {
     }
 
     // 发出市场状态更新事件
-    pub fn emit_market_status_changed(market: Pubkey, is_active: bool, reason: String) {
+    pub fn emit_market_status_changed(market: Pubkey, seq: u64, is_active: bool, reason: String) {
         emit!(MarketStatusChangedEvent {
             market,
+            seq,
             is_active,
             reason,
             timestamp: Clock::get().unwrap().unix_timestamp,
@@ -197,6 +379,7 @@ impl EventHandler {
     // 发出风险警告事件
     pub fn emit_risk_warning(
         market: Pubkey,
+        seq: u64,
         owner: Pubkey,
         warning_type: RiskWarningType,
         severity: u8,
@@ -204,6 +387,7 @@ impl EventHandler {
     ) {
         emit!(RiskWarningEvent {
             market,
+            seq,
             owner,
             warning_type,
             severity,
@@ -215,19 +399,29 @@ impl EventHandler {
     // 发出市场状态监控事件
     pub fn emit_market_metrics(
         market: Pubkey,
+        seq: u64,
         volume_24h: u64,
         trades_count: u32,
         highest_bid: u64,
         lowest_ask: u64,
         liquidity_index: u32,
+        vwap_24h: u64,
+        high_24h: u64,
+        low_24h: u64,
+        book_imbalance_bps: i32,
     ) {
         emit!(MarketMetricsEvent {
             market,
+            seq,
             volume_24h,
             trades_count,
             highest_bid,
             lowest_ask,
             liquidity_index,
+            vwap_24h,
+            high_24h,
+            low_24h,
+            book_imbalance_bps,
             timestamp: Clock::get().unwrap().unix_timestamp,
         });
     }
@@ -269,6 +463,7 @@ impl EventHandler {
     // 发出存储优化事件
     pub fn emit_storage_optimization(
         market: Pubkey,
+        seq: u64,
         optimization_type: StorageOptimizationType,
         old_size: u32,
         new_size: u32,
@@ -276,6 +471,7 @@ impl EventHandler {
     ) {
         emit!(StorageOptimizationEvent {
             market,
+            seq,
             optimization_type,
             old_size,
             new_size,
@@ -283,12 +479,79 @@ impl EventHandler {
             timestamp: Clock::get().unwrap().unix_timestamp,
         });
     }
+
+    // 发出账户布局迁移事件
+    pub fn emit_account_migrated(
+        account: Pubkey,
+        account_type: MigratableAccount,
+        from_version: u8,
+        to_version: u8,
+        old_size: u32,
+        new_size: u32,
+    ) {
+        emit!(AccountMigratedEvent {
+            account,
+            account_type,
+            from_version,
+            to_version,
+            old_size,
+            new_size,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+    }
+
+    // 发出哈希时间锁锁定事件
+    pub fn emit_htlc_locked(
+        htlc: Pubkey,
+        sender: Pubkey,
+        recipient: Pubkey,
+        mint: Pubkey,
+        amount: u64,
+        hashlock: [u8; 32],
+        timelock: i64,
+    ) {
+        emit!(HtlcLockedEvent {
+            htlc,
+            sender,
+            recipient,
+            mint,
+            amount,
+            hashlock,
+            timelock,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+    }
+
+    // 发出哈希时间锁领取事件，公开的原像供对方链领取
+    pub fn emit_htlc_claimed(htlc: Pubkey, recipient: Pubkey, amount: u64, preimage: [u8; 32]) {
+        emit!(HtlcClaimedEvent {
+            htlc,
+            recipient,
+            amount,
+            preimage,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+    }
+
+    // 发出哈希时间锁退款事件
+    pub fn emit_htlc_refunded(htlc: Pubkey, sender: Pubkey, amount: u64) {
+        emit!(HtlcRefundedEvent {
+            htlc,
+            sender,
+            amount,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+    }
 }
 
 // 定义各种事件
+// 市场相关事件都带有seq字段，由Market.event_sequence分配，每个市场从1开始连续递增，
+// 链下消费者据此检测漏收的事件；账户活动、系统状态和账户迁移事件不属于单个市场，不带序号
 #[event]
+#[derive(Debug, Clone)]
 pub struct MarketCreatedEvent {
     pub market: Pubkey,
+    pub seq: u64,
     pub base_mint: Pubkey,
     pub quote_mint: Pubkey,
     pub lot_size: u64,
@@ -297,8 +560,10 @@ pub struct MarketCreatedEvent {
 }
 
 #[event]
+#[derive(Debug, Clone)]
 pub struct OrderPlacedEvent {
     pub market: Pubkey,
+    pub seq: u64,
     pub order_id: u128,
     pub owner: Pubkey,
     pub side: Side,
@@ -308,8 +573,10 @@ pub struct OrderPlacedEvent {
 }
 
 #[event]
+#[derive(Debug, Clone)]
 pub struct OrderCanceledEvent {
     pub market: Pubkey,
+    pub seq: u64,
     pub order_id: u128,
     pub owner: Pubkey,
     pub side: Side,
@@ -319,8 +586,10 @@ pub struct OrderCanceledEvent {
 }
 
 #[event]
+#[derive(Debug, Clone)]
 pub struct TradeEvent {
     pub market: Pubkey,
+    pub seq: u64,
     pub side: Side,
     pub maker_order_id: u128,
     pub taker_order_id: u128,
@@ -332,8 +601,35 @@ pub struct TradeEvent {
 }
 
 #[event]
+#[derive(Debug, Clone)]
+pub struct CancelOnDisconnectEvent {
+    pub market: Pubkey,
+    pub seq: u64,
+    pub owner: Pubkey,
+    pub keeper: Pubkey,
+    pub orders_canceled: u16,
+    pub completed: bool,
+    pub keeper_fee_lamports: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct AccessControlChangedEvent {
+    pub market: Pubkey,
+    pub seq: u64,
+    pub access_control: Pubkey,
+    pub action: AccessControlAction,
+    pub mode: AccessMode,
+    pub wallet: Option<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
 pub struct FundsSettledEvent {
     pub market: Pubkey,
+    pub seq: u64,
     pub owner: Pubkey,
     pub base_amount: u64,
     pub quote_amount: u64,
@@ -341,8 +637,10 @@ pub struct FundsSettledEvent {
 }
 
 #[event]
+#[derive(Debug, Clone)]
 pub struct LiquidityChangedEvent {
     pub market: Pubkey,
+    pub seq: u64,
     pub owner: Pubkey,
     pub is_addition: bool,
     pub base_amount: u64,
@@ -352,27 +650,49 @@ pub struct LiquidityChangedEvent {
 }
 
 #[event]
+#[derive(Debug, Clone)]
 pub struct RewardsClaimedEvent {
     pub market: Pubkey,
+    pub seq: u64,
     pub owner: Pubkey,
+    pub reward_mint: Pubkey,
     pub reward_amount: u64,
     pub timestamp: i64,
 }
 
 #[event]
+#[derive(Debug, Clone)]
 pub struct CrossChainOrderCreatedEvent {
     pub source_market: Pubkey,
+    pub seq: u64,
     pub target_chain_id: u64,
     pub order_id: u128,
     pub owner: Pubkey,
+    pub token_mint: Pubkey,
     pub base_amount: u64,
     pub quote_amount: u64,
+    pub amount: u64,           // 锁定的token_mint数量（不含手续费）
+    pub destination: [u8; 32], // 目标链接收地址，取自订单附加数据的前32字节
     pub timestamp: i64,
 }
 
 #[event]
+#[derive(Debug, Clone)]
+pub struct CrossChainOrderDeliveredEvent {
+    pub source_market: Pubkey,
+    pub seq: u64,
+    pub target_chain_id: u64,
+    pub order_id: u128,
+    pub owner: Pubkey,
+    pub target_tx_hash: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
 pub struct CrossChainTxConfirmedEvent {
     pub target_market: Pubkey,
+    pub seq: u64,
     pub source_chain_id: u64,
     pub tx_hash: [u8; 32],
     pub order_id: u128,
@@ -381,8 +701,60 @@ pub struct CrossChainTxConfirmedEvent {
 }
 
 #[event]
+#[derive(Debug, Clone)]
+pub struct CrossChainOrderRefundedEvent {
+    pub source_market: Pubkey,
+    pub seq: u64,
+    pub target_chain_id: u64,
+    pub order_id: u128,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub fee_refunded: u64,
+    pub reason: CrossChainRefundReason,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct CrossChainTransferQueuedEvent {
+    pub target_market: Pubkey,
+    pub seq: u64,
+    pub source_chain_id: u64,
+    pub tx_hash: [u8; 32],
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub release_after: i64,
+    pub anomalous: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct CrossChainTransferCancelledEvent {
+    pub target_market: Pubkey,
+    pub seq: u64,
+    pub source_chain_id: u64,
+    pub tx_hash: [u8; 32],
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub guardian: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct BridgePauseChangedEvent {
+    pub paused: bool,
+    pub authority: Pubkey,
+    pub reason: BridgePauseReason,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
 pub struct AdvancedOrderCreatedEvent {
     pub market: Pubkey,
+    pub seq: u64,
     pub order_id: u128,
     pub owner: Pubkey,
     pub strategy_type: u8,
@@ -391,15 +763,17 @@ pub struct AdvancedOrderCreatedEvent {
 }
 
 #[event]
+#[derive(Debug, Clone)]
 pub struct MarketStatusChangedEvent {
     pub market: Pubkey,
+    pub seq: u64,
     pub is_active: bool,
     pub reason: String,
     pub timestamp: i64,
 }
 
 // 定义风险警告类型
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum RiskWarningType {
     PriceSurge = 0,
     PriceCollapse = 1,
@@ -412,7 +786,7 @@ pub enum RiskWarningType {
 }
 
 // 定义账户活动类型
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum AccountActivityType {
     Deposit = 0,
     Withdrawal = 1,
@@ -424,7 +798,7 @@ pub enum AccountActivityType {
 }
 
 // 定义系统状态类型
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum SystemStatusType {
     OrderbookStatus = 0,
     LiquidityPoolStatus = 1,
@@ -434,18 +808,42 @@ pub enum SystemStatusType {
     ProtocolUpgrade = 5,
 }
 
+// 跨链订单退款原因
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CrossChainRefundReason {
+    Expired = 0,         // 超时未确认
+    RelayerAttested = 1, // 中继器签名证明目标链执行失败
+}
+
+// 跨链桥暂停状态变更原因
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BridgePauseReason {
+    Admin = 0,            // 管理员暂停或恢复
+    Guardian = 1,         // 守护者暂停
+    AnomalousOutflow = 2, // 检测到异常转出后自动暂停
+}
+
+// 定义准入名单变更类型
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AccessControlAction {
+    Initialized = 0,
+    MemberAdded = 1,
+    MemberRemoved = 2,
+}
+
 // 定义存储优化类型
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum StorageOptimizationType {
     Compression = 0,
     Defragmentation = 1,
     HistoryTruncation = 2,
     CachePruning = 3,
     IndexRebuild = 4,
+    TierMigration = 5,
 }
 
 // 系统指标结构
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
 pub struct SystemMetrics {
     pub order_processing_latency_ms: u32,
     pub transaction_count_per_second: u32,
@@ -456,8 +854,10 @@ pub struct SystemMetrics {
 
 // 新增的事件定义
 #[event]
+#[derive(Debug, Clone)]
 pub struct RiskWarningEvent {
     pub market: Pubkey,
+    pub seq: u64,
     pub owner: Pubkey,
     pub warning_type: RiskWarningType,
     pub severity: u8, // 0-100, 数字越大表示严重性越高
@@ -466,17 +866,24 @@ pub struct RiskWarningEvent {
 }
 
 #[event]
+#[derive(Debug, Clone)]
 pub struct MarketMetricsEvent {
     pub market: Pubkey,
+    pub seq: u64,
     pub volume_24h: u64,
     pub trades_count: u32,
     pub highest_bid: u64,
     pub lowest_ask: u64,
     pub liquidity_index: u32, // 衡量市场深度的指标
+    pub vwap_24h: u64,
+    pub high_24h: u64,
+    pub low_24h: u64,
+    pub book_imbalance_bps: i32, // 正值表示买盘更厚
     pub timestamp: i64,
 }
 
 #[event]
+#[derive(Debug, Clone)]
 pub struct AccountActivityEvent {
     pub owner: Pubkey,
     pub activity_type: AccountActivityType,
@@ -487,6 +894,7 @@ pub struct AccountActivityEvent {
 }
 
 #[event]
+#[derive(Debug, Clone)]
 pub struct SystemStatusEvent {
     pub status_type: SystemStatusType,
     pub is_healthy: bool,
@@ -496,11 +904,57 @@ pub struct SystemStatusEvent {
 }
 
 #[event]
+#[derive(Debug, Clone)]
 pub struct StorageOptimizationEvent {
     pub market: Pubkey,
+    pub seq: u64,
     pub optimization_type: StorageOptimizationType,
     pub old_size: u32,
     pub new_size: u32,
     pub success: bool,
     pub timestamp: i64,
 }
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct AccountMigratedEvent {
+    pub account: Pubkey,
+    pub account_type: MigratableAccount,
+    pub from_version: u8,
+    pub to_version: u8,
+    pub old_size: u32,
+    pub new_size: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct HtlcLockedEvent {
+    pub htlc: Pubkey,
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub hashlock: [u8; 32],
+    pub timelock: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct HtlcClaimedEvent {
+    pub htlc: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub preimage: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Debug, Clone)]
+pub struct HtlcRefundedEvent {
+    pub htlc: Pubkey,
+    pub sender: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
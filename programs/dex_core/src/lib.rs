@@ -3,24 +3,65 @@ use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 use std::cmp;
 
 // 声明子模块
+pub mod access;
 pub mod advanced_orders;
+pub mod calendar;
 pub mod core;
 pub mod cross_chain;
 pub mod events;
+pub mod limits;
 pub mod lp_mining;
+pub mod maker_mining;
+pub mod migration;
 pub mod orderbook;
 pub mod risk;
 pub mod storage;
 
 // 重新导出主要类型，方便使用
+pub use access::{
+    AccessMode, AddAccessMember, InitializeAccessControl, MarketAccessControl, RemoveAccessMember,
+};
 pub use advanced_orders::{AdvancedOrderType, TradingStrategy};
-pub use core::{Market, OpenOrders};
-pub use cross_chain::{ChainId, CrossChainBridge};
+pub use calendar::{
+    InitializeTradingCalendar, TradingCalendar, TradingPhase, TradingSession, UpdateTradingCalendar,
+};
+pub use core::{CancelOnDisconnect, ConfigureHeartbeat, Heartbeat, Market, OpenOrders};
+pub use cross_chain::{
+    ChainConfig, ChainParams, ChainRegistry, ConfirmCrossChainOrder, ConfirmCrossChainTx,
+    CreateCrossChainOrder,
+    CrossChainBridgeConfig, CrossChainBridgeStats, CrossChainOrder, InitializeCrossChainBridge,
+    ManageChainRegistry, RefundCrossChainOrder,
+};
+pub use cross_chain::htlc::{HtlcClaim, HtlcEscrow, HtlcLock, HtlcRefund, HtlcStatus};
+pub use cross_chain::limits::{
+    CancelDelayedTransfer, ConfigureBridgeLimits, ExecuteDelayedTransfer, InboundTransfer,
+    InboundTransferStatus, OutflowWindow, SetBridgePaused, SetTokenOutflowLimit,
+    TokenOutflowLimit,
+};
 pub use events::EventHandler;
-pub use lp_mining::{LpPool, UserStake};
+pub use limits::{
+    InitializeTradingLimits, SetUserTradingLimits, TradingLimitConfig, UpdateTradingLimits,
+};
+pub use lp_mining::{
+    AddLiquidity, AddRewardStream, ClaimRewards, ExtendStakeLock, InitializeLiquidityPool,
+    LiquidityPool, LiquidityPoolState, LockTier, RefreshStakeBoost, RemoveLiquidity, RewardConfig,
+    RewardStream, StakeLpTokens, StakingInfo, UnstakeLpTokens, UserPosition, MAX_REWARD_STREAMS,
+};
+pub use maker_mining::{
+    ClaimMakerRewards, InitializeMakerRewards, MakerEpoch, MakerRewardConfig, SampleMakerQuotes,
+};
+pub use migration::{MigrateAccount, MigratableAccount, MigrateRiskParametersToPda};
 pub use orderbook::{Order, OrderBook, OrderType, Side};
-pub use risk::RiskEngine;
-pub use storage::OptimizedStorage;
+pub use risk::{
+    InitializeRiskParams, RiskEngine, RiskParameters, SnapshotMarketMetrics, UpdateRiskParams,
+    WithdrawSpamFees,
+};
+pub use storage::{
+    AllocateTradeHistoryPage, ArchiveTradeHistory, CleanupExpiredPages, CompactStoragePages, CompressColdPage,
+    DataTierConfig, FlushTradeHistory, InitializeDataTierConfig, InitializePriceLevelCache,
+    InitializeUserTradeHistory, OptimizedStorage, StorageIndex, StoragePage, TradeHistoryPage,
+    UpdateDataTierConfig, UserTradeHistory,
+};
 
 declare_id!("DEX1111111111111111111111111111111111111111");
 
@@ -52,8 +93,8 @@ pub mod dex_core {
     }
 
     // 下单
-    pub fn place_order(
-        ctx: Context<PlaceOrder>,
+    pub fn place_order<'info>(
+        ctx: Context<'_, '_, 'info, 'info, PlaceOrder<'info>>,
         side: Side,
         limit_price: u64,
         max_quantity: u64,
@@ -82,9 +123,273 @@ pub mod dex_core {
         core::settle_funds(ctx)
     }
 
+    // 配置断线撤单心跳
+    pub fn configure_heartbeat(
+        ctx: Context<ConfigureHeartbeat>,
+        timeout_secs: u32,
+        keeper_fee_lamports: u64,
+    ) -> Result<()> {
+        core::configure_heartbeat(ctx, timeout_secs, keeper_fee_lamports)
+    }
+
+    // 发送心跳
+    pub fn heartbeat(ctx: Context<Heartbeat>) -> Result<()> {
+        core::heartbeat(ctx)
+    }
+
+    // 心跳超时后撤销用户全部挂单
+    pub fn cancel_on_disconnect(ctx: Context<CancelOnDisconnect>) -> Result<()> {
+        core::cancel_on_disconnect(ctx)
+    }
+
+    // 初始化市场风控参数
+    pub fn initialize_risk_parameters(
+        ctx: Context<InitializeRiskParams>,
+        price_limit_percent: u8,
+        max_open_orders_per_user: u16,
+        max_position_size: u64,
+        market_open_hour: u8,
+        market_close_hour: u8,
+    ) -> Result<()> {
+        RiskEngine::initialize_risk_parameters(
+            ctx,
+            price_limit_percent,
+            max_open_orders_per_user,
+            max_position_size,
+            market_open_hour,
+            market_close_hour,
+        )
+    }
+
+    // 更新市场风控参数
+    pub fn update_risk_parameters(
+        ctx: Context<UpdateRiskParams>,
+        param_type: u8,
+        value: u64,
+    ) -> Result<()> {
+        RiskEngine::update_risk_parameters(ctx, param_type, value)
+    }
+
+    // 提取反刷单下单费
+    pub fn withdraw_spam_fees(ctx: Context<WithdrawSpamFees>, amount: u64) -> Result<()> {
+        risk::withdraw_spam_fees(ctx, amount)
+    }
+
+    // 初始化市场交易限额
+    pub fn initialize_trading_limits(
+        ctx: Context<InitializeTradingLimits>,
+        max_order_notional: u64,
+        max_rolling_notional: u64,
+    ) -> Result<()> {
+        limits::initialize_trading_limits(ctx, max_order_notional, max_rolling_notional)
+    }
+
+    // 更新市场交易限额
+    pub fn update_trading_limits(
+        ctx: Context<UpdateTradingLimits>,
+        max_order_notional: u64,
+        max_rolling_notional: u64,
+    ) -> Result<()> {
+        limits::update_trading_limits(ctx, max_order_notional, max_rolling_notional)
+    }
+
+    // 设置用户交易限额覆盖值
+    pub fn set_user_trading_limits(
+        ctx: Context<SetUserTradingLimits>,
+        max_order_notional: u64,
+        max_rolling_notional: u64,
+    ) -> Result<()> {
+        limits::set_user_trading_limits(ctx, max_order_notional, max_rolling_notional)
+    }
+
+    // 为市场启用准入控制
+    pub fn initialize_access_control(
+        ctx: Context<InitializeAccessControl>,
+        mode: AccessMode,
+    ) -> Result<()> {
+        access::initialize_access_control(ctx, mode)
+    }
+
+    // 添加准入名单成员
+    pub fn add_access_member(ctx: Context<AddAccessMember>, wallet: Pubkey) -> Result<()> {
+        access::add_access_member(ctx, wallet)
+    }
+
+    // 移除准入名单成员
+    pub fn remove_access_member(ctx: Context<RemoveAccessMember>, wallet: Pubkey) -> Result<()> {
+        access::remove_access_member(ctx, wallet)
+    }
+
+    // 初始化交易日历
+    pub fn initialize_trading_calendar(
+        ctx: Context<InitializeTradingCalendar>,
+        utc_offset_minutes: i16,
+        sessions: [TradingSession; 7],
+    ) -> Result<()> {
+        calendar::initialize_trading_calendar(ctx, utc_offset_minutes, sessions)
+    }
+
+    // 设置某个工作日的交易时段
+    pub fn set_trading_session(
+        ctx: Context<UpdateTradingCalendar>,
+        weekday: u8,
+        session: TradingSession,
+    ) -> Result<()> {
+        calendar::set_trading_session(ctx, weekday, session)
+    }
+
+    // 更新交易日历时区和启用状态
+    pub fn update_trading_calendar(
+        ctx: Context<UpdateTradingCalendar>,
+        utc_offset_minutes: i16,
+        is_active: bool,
+    ) -> Result<()> {
+        calendar::update_trading_calendar(ctx, utc_offset_minutes, is_active)
+    }
+
+    // 添加休市日
+    pub fn add_trading_holiday(
+        ctx: Context<UpdateTradingCalendar>,
+        year: u16,
+        month: u8,
+        day: u8,
+    ) -> Result<()> {
+        calendar::add_trading_holiday(ctx, year, month, day)
+    }
+
+    // 移除休市日
+    pub fn remove_trading_holiday(
+        ctx: Context<UpdateTradingCalendar>,
+        year: u16,
+        month: u8,
+        day: u8,
+    ) -> Result<()> {
+        calendar::remove_trading_holiday(ctx, year, month, day)
+    }
+
+    // 初始化数据分层存储配置
+    pub fn initialize_data_tier_config(
+        ctx: Context<InitializeDataTierConfig>,
+        hot_data_max_age_slots: u64,
+        warm_data_max_age_slots: u64,
+        cold_data_compression: bool,
+        auto_archive_enabled: bool,
+        auto_archive_age_slots: u64,
+    ) -> Result<()> {
+        storage::initialize_data_tier_config(
+            ctx,
+            hot_data_max_age_slots,
+            warm_data_max_age_slots,
+            cold_data_compression,
+            auto_archive_enabled,
+            auto_archive_age_slots,
+        )
+    }
+
+    // 更新数据分层存储配置
+    pub fn update_data_tier_config(
+        ctx: Context<UpdateDataTierConfig>,
+        hot_data_max_age_slots: u64,
+        warm_data_max_age_slots: u64,
+        cold_data_compression: bool,
+        auto_archive_enabled: bool,
+        auto_archive_age_slots: u64,
+    ) -> Result<()> {
+        storage::update_data_tier_config(
+            ctx,
+            hot_data_max_age_slots,
+            warm_data_max_age_slots,
+            cold_data_compression,
+            auto_archive_enabled,
+            auto_archive_age_slots,
+        )
+    }
+
+    // 为已有市场补建价格档位缓存（无需权限）
+    pub fn initialize_price_level_cache(ctx: Context<InitializePriceLevelCache>) -> Result<()> {
+        storage::initialize_price_level_cache(ctx)
+    }
+
+    // 初始化用户交易历史
+    pub fn initialize_user_trade_history(ctx: Context<InitializeUserTradeHistory>) -> Result<()> {
+        storage::initialize_user_trade_history(ctx)
+    }
+
+    // 将热数据归档到存储页面
+    pub fn archive_trade_history(ctx: Context<ArchiveTradeHistory>, page_id: u64) -> Result<()> {
+        storage::archive_trade_history(ctx, page_id)
+    }
+
+    // 归档交易历史缓冲区中最旧的记录
+    pub fn flush_trade_history(ctx: Context<FlushTradeHistory>, page_id: u64) -> Result<()> {
+        storage::flush_trade_history(ctx, page_id)
+    }
+
+    // 预先创建下一个交易历史归档页面，缓冲区在成交时写满后使用
+    pub fn allocate_trade_history_page(
+        ctx: Context<AllocateTradeHistoryPage>,
+        page_id: u64,
+    ) -> Result<()> {
+        storage::allocate_trade_history_page(ctx, page_id)
+    }
+
+    // 压缩冷数据页面
+    pub fn compress_cold_page(ctx: Context<CompressColdPage>) -> Result<()> {
+        storage::compress_cold_page(ctx)
+    }
+
+    // 合并碎片化的存储页面
+    pub fn compact_storage_pages(ctx: Context<CompactStoragePages>) -> Result<()> {
+        storage::compact_storage_pages(ctx)
+    }
+
+    // 清理过期的归档页面
+    pub fn cleanup_expired_pages(
+        ctx: Context<CleanupExpiredPages>,
+        max_age_slots: u64,
+    ) -> Result<()> {
+        storage::cleanup_expired_pages(ctx, max_age_slots)
+    }
+
+    // 将旧布局账户迁移到当前版本
+    pub fn migrate_account(
+        ctx: Context<MigrateAccount>,
+        account_type: MigratableAccount,
+    ) -> Result<()> {
+        migration::migrate_account(ctx, account_type)
+    }
+
+    // 将密钥对地址上的旧风控参数账户迁移到PDA
+    pub fn migrate_risk_parameters_to_pda(ctx: Context<MigrateRiskParametersToPda>) -> Result<()> {
+        migration::migrate_risk_parameters_to_pda(ctx)
+    }
+
+    // 生成市场指标快照（无需权限，按slot限频）
+    pub fn snapshot_market_metrics(ctx: Context<SnapshotMarketMetrics>) -> Result<()> {
+        risk::snapshot_market_metrics(ctx)
+    }
+
+    // 初始化流动性池
+    pub fn initialize_liquidity_pool(
+        ctx: Context<InitializeLiquidityPool>,
+        fee_rate: u16,
+    ) -> Result<()> {
+        lp_mining::initialize_liquidity_pool(ctx, fee_rate)
+    }
+
+    // 为流动性池追加奖励流
+    pub fn add_reward_stream(
+        ctx: Context<AddRewardStream>,
+        reward_rate: u64,
+        rewards_start_ts: i64,
+        rewards_end_ts: i64,
+    ) -> Result<()> {
+        lp_mining::add_reward_stream(ctx, reward_rate, rewards_start_ts, rewards_end_ts)
+    }
+
     // 添加流动性
-    pub fn add_liquidity(
-        ctx: Context<AddLiquidity>,
+    pub fn add_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddLiquidity<'info>>,
         base_amount: u64,
         quote_amount: u64,
     ) -> Result<()> {
@@ -92,15 +397,74 @@ pub mod dex_core {
     }
 
     // 移除流动性
-    pub fn remove_liquidity(ctx: Context<RemoveLiquidity>, lp_amount: u64) -> Result<()> {
+    pub fn remove_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, RemoveLiquidity<'info>>,
+        lp_amount: u64,
+    ) -> Result<()> {
         lp_mining::remove_liquidity(ctx, lp_amount)
     }
 
-    // 领取流动性挖矿奖励
-    pub fn claim_lp_rewards(ctx: Context<ClaimRewards>) -> Result<()> {
+    // 领取流动性挖矿奖励（所有奖励流）
+    pub fn claim_lp_rewards<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimRewards<'info>>,
+    ) -> Result<()> {
         lp_mining::claim_rewards(ctx)
     }
 
+    // 质押LP份额
+    pub fn stake_lp_tokens<'info>(
+        ctx: Context<'_, '_, '_, 'info, StakeLpTokens<'info>>,
+        amount: u64,
+        lock_tier: LockTier,
+    ) -> Result<()> {
+        lp_mining::stake_lp_tokens(ctx, amount, lock_tier)
+    }
+
+    // 解除LP份额质押
+    pub fn unstake_lp_tokens<'info>(
+        ctx: Context<'_, '_, '_, 'info, UnstakeLpTokens<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        lp_mining::unstake_lp_tokens(ctx, amount)
+    }
+
+    // 延长LP质押的锁定期
+    pub fn extend_stake_lock(ctx: Context<ExtendStakeLock>, lock_tier: LockTier) -> Result<()> {
+        lp_mining::extend_stake_lock(ctx, lock_tier)
+    }
+
+    // 按剩余锁定时间刷新质押的提升因子
+    pub fn refresh_stake_boost(ctx: Context<RefreshStakeBoost>) -> Result<()> {
+        lp_mining::refresh_stake_boost(ctx)
+    }
+
+    // 开启做市挖矿
+    pub fn initialize_maker_rewards(
+        ctx: Context<InitializeMakerRewards>,
+        epoch_duration_secs: i64,
+        rewards_per_epoch: u64,
+        max_tick_distance: u32,
+    ) -> Result<()> {
+        maker_mining::initialize_maker_rewards(
+            ctx,
+            epoch_duration_secs,
+            rewards_per_epoch,
+            max_tick_distance,
+        )
+    }
+
+    // 采样中间价附近的做市挂单并累计积分（无需权限，按时间限频）
+    pub fn sample_maker_quotes<'info>(
+        ctx: Context<'_, '_, '_, 'info, SampleMakerQuotes<'info>>,
+    ) -> Result<()> {
+        maker_mining::sample_maker_quotes(ctx)
+    }
+
+    // 领取做市挖矿奖励
+    pub fn claim_maker_rewards(ctx: Context<ClaimMakerRewards>) -> Result<()> {
+        maker_mining::claim_maker_rewards(ctx)
+    }
+
     // 创建高级订单
     pub fn create_advanced_order(
         ctx: Context<CreateAdvancedOrder>,
@@ -110,23 +474,126 @@ pub mod dex_core {
         advanced_orders::create_advanced_order(ctx, advanced_order_type, params)
     }
 
+    // 初始化跨链桥和链注册表
+    pub fn initialize_cross_chain_bridge(
+        ctx: Context<InitializeCrossChainBridge>,
+        relayers: [Pubkey; 5],
+        required_confirmations: u8,
+    ) -> Result<()> {
+        cross_chain::initialize_cross_chain_bridge(ctx, relayers, required_confirmations)
+    }
+
+    // 在链注册表中添加外部链
+    pub fn add_chain(
+        ctx: Context<ManageChainRegistry>,
+        chain_id: u64,
+        params: ChainParams,
+    ) -> Result<()> {
+        cross_chain::add_chain(ctx, chain_id, params)
+    }
+
+    // 修改链注册表中的外部链参数
+    pub fn update_chain(
+        ctx: Context<ManageChainRegistry>,
+        chain_id: u64,
+        params: ChainParams,
+    ) -> Result<()> {
+        cross_chain::update_chain(ctx, chain_id, params)
+    }
+
     // 创建跨链订单
     pub fn create_cross_chain_order(
         ctx: Context<CreateCrossChainOrder>,
-        target_chain_id: ChainId,
-        order_data: Vec<u8>, // 序列化的订单数据
+        target_chain_id: u64,
+        amount: u64,
+        nonce: u64,
+        data: [u8; 64], // 目标链接收方等附加数据
+    ) -> Result<()> {
+        cross_chain::create_cross_chain_order(ctx, target_chain_id, amount, nonce, data)
+    }
+
+    // 提交中继器对跨链订单已在目标链完成的证明
+    pub fn confirm_cross_chain_order(
+        ctx: Context<ConfirmCrossChainOrder>,
+        target_tx_hash: [u8; 32],
+        relayer_signers: Vec<Pubkey>,
+    ) -> Result<()> {
+        cross_chain::confirm_cross_chain_order(ctx, target_tx_hash, relayer_signers)
+    }
+
+    // 退还过期或经中继器证明失败的跨链订单
+    pub fn refund_cross_chain_order(
+        ctx: Context<RefundCrossChainOrder>,
+        attesting_relayers: Vec<Pubkey>,
+    ) -> Result<()> {
+        cross_chain::refund_cross_chain_order(ctx, attesting_relayers)
+    }
+
+    // 用哈希时间锁锁定代币（原子跨链交换）
+    pub fn htlc_lock(
+        ctx: Context<HtlcLock>,
+        hashlock: [u8; 32],
+        recipient: Pubkey,
+        amount: u64,
+        timelock: i64,
     ) -> Result<()> {
-        cross_chain::create_cross_chain_order(ctx, target_chain_id, order_data)
+        cross_chain::htlc::htlc_lock(ctx, hashlock, recipient, amount, timelock)
+    }
+
+    // 凭原像领取哈希时间锁中的代币
+    pub fn htlc_claim(ctx: Context<HtlcClaim>, preimage: [u8; 32]) -> Result<()> {
+        cross_chain::htlc::htlc_claim(ctx, preimage)
+    }
+
+    // 哈希时间锁超时后退款
+    pub fn htlc_refund(ctx: Context<HtlcRefund>) -> Result<()> {
+        cross_chain::htlc::htlc_refund(ctx)
     }
 
-    // 确认跨链交易
+    // 确认外部链转入，超过转出上限时进入延迟队列
     pub fn confirm_cross_chain_transaction(
         ctx: Context<ConfirmCrossChainTx>,
-        source_chain_id: ChainId,
+        source_chain_id: u64,
         tx_hash: [u8; 32],
-        proof_data: Vec<u8>,
+        amount: u64,
+        relayer_signers: Vec<Pubkey>,
     ) -> Result<()> {
-        cross_chain::confirm_transaction(ctx, source_chain_id, tx_hash, proof_data)
+        cross_chain::confirm_transaction(ctx, source_chain_id, tx_hash, amount, relayer_signers)
+    }
+
+    // 设置跨链桥守护者、超限转入延迟时间和全局转出上限
+    pub fn configure_bridge_limits(
+        ctx: Context<ConfigureBridgeLimits>,
+        guardian: Pubkey,
+        release_delay_secs: i64,
+        global_outflow_cap: u64,
+    ) -> Result<()> {
+        cross_chain::limits::configure_bridge_limits(
+            ctx,
+            guardian,
+            release_delay_secs,
+            global_outflow_cap,
+        )
+    }
+
+    // 设置代币的24小时转出上限
+    pub fn set_token_outflow_limit(ctx: Context<SetTokenOutflowLimit>, cap: u64) -> Result<()> {
+        cross_chain::limits::set_token_outflow_limit(ctx, cap)
+    }
+
+    // 暂停或恢复跨链桥
+    pub fn set_bridge_paused(ctx: Context<SetBridgePaused>, paused: bool) -> Result<()> {
+        cross_chain::limits::set_bridge_paused(ctx, paused)
+    }
+
+    // 延迟期满后执行队列中的转入
+    pub fn execute_delayed_transfer(ctx: Context<ExecuteDelayedTransfer>) -> Result<()> {
+        cross_chain::limits::execute_delayed_transfer(ctx)
+    }
+
+    // 守护者取消队列中的转入
+    pub fn cancel_delayed_transfer(ctx: Context<CancelDelayedTransfer>) -> Result<()> {
+        cross_chain::limits::cancel_delayed_transfer(ctx)
     }
 }
 
@@ -174,4 +641,98 @@ pub enum ErrorCode {
     InvalidProofData,
     #[msg("存储已满")]
     StorageFull,
+    #[msg("市场休市")]
+    MarketClosed,
+    #[msg("收盘前只允许撤单")]
+    CancelOnlyPeriod,
+    #[msg("集合竞价期间只接受挂单")]
+    AuctionRestingOrdersOnly,
+    #[msg("未启用心跳")]
+    HeartbeatNotEnabled,
+    #[msg("心跳尚未超时")]
+    HeartbeatNotExpired,
+    #[msg("超出交易限额")]
+    TradingLimitExceeded,
+    #[msg("该钱包无权在此市场交易")]
+    WalletNotPermitted,
+    #[msg("撤单/成交比过高，下单过于频繁")]
+    OrderThrottled,
+    #[msg("存储数据损坏")]
+    StorageDataCorrupted,
+    #[msg("没有需要迁移的数据")]
+    NoAgedData,
+    #[msg("交易历史已满，请先归档")]
+    TradeHistoryFull,
+    #[msg("账户布局版本过旧，请先调用migrate_account")]
+    AccountNeedsMigration,
+    #[msg("账户已是最新布局版本")]
+    AccountAlreadyMigrated,
+    #[msg("无法识别的账户布局")]
+    UnknownAccountLayout,
+    #[msg("距离上次指标快照的间隔不足")]
+    MetricsSnapshotTooFrequent,
+    #[msg("流动性池未激活")]
+    PoolNotActive,
+    #[msg("没有可领取的奖励")]
+    NoRewardsToClaim,
+    #[msg("奖励流数量已达上限")]
+    TooManyRewardStreams,
+    #[msg("缺少奖励流的金库或用户奖励账户")]
+    MissingRewardAccounts,
+    #[msg("质押仍在锁定期内")]
+    StakeStillLocked,
+    #[msg("新的解锁时间必须晚于当前解锁时间")]
+    LockNotExtended,
+    #[msg("距离上次做市采样的间隔不足")]
+    MakerSampleTooFrequent,
+    #[msg("缺少需要计分的做市商OpenOrders账户")]
+    MissingMakerAccounts,
+    #[msg("中继器签名数量不足")]
+    InsufficientRelayerSignatures,
+    #[msg("签名者不是中继器或签名未经Ed25519程序验证")]
+    InvalidRelayerSignature,
+    #[msg("同一中继器的签名重复")]
+    DuplicateRelayerSignature,
+    #[msg("该链已在注册表中")]
+    ChainAlreadyRegistered,
+    #[msg("链注册表已满")]
+    ChainRegistryFull,
+    #[msg("该链已停用")]
+    ChainDisabled,
+    #[msg("转账金额超出该链的限制")]
+    TransferAmountOutOfRange,
+    #[msg("超过该链的每日转出上限")]
+    ChainDailyCapExceeded,
+    #[msg("跨链桥已暂停")]
+    BridgePaused,
+    #[msg("跨链订单不是待处理状态")]
+    CrossChainOrderNotPending,
+    #[msg("跨链订单尚未过期")]
+    CrossChainOrderNotExpired,
+    #[msg("哈希时间锁的超时时间超出允许范围")]
+    InvalidHtlcTimelock,
+    #[msg("哈希时间锁不是锁定状态")]
+    HtlcNotLocked,
+    #[msg("原像与hashlock不匹配")]
+    InvalidHtlcPreimage,
+    #[msg("哈希时间锁已超时，只能退款")]
+    HtlcExpired,
+    #[msg("哈希时间锁尚未超时")]
+    HtlcNotExpired,
+    #[msg("转入记录不在延迟队列中")]
+    InboundTransferNotQueued,
+    #[msg("延迟转入的等待时间未满")]
+    ReleaseDelayNotElapsed,
+    #[msg("仍有挂单时不能降低撤单奖励")]
+    KeeperFeeLocked,
+    #[msg("中继器证明的目标链交易与已记录的不一致")]
+    DeliveryTxHashMismatch,
+    #[msg("已有中继器证明跨链订单完成，不能退款")]
+    CrossChainOrderDeliveryAttested,
+    #[msg("交易历史缓冲区已满，需要传入下一个归档页面")]
+    TradeHistoryFull,
+    #[msg("风控参数账户不在市场PDA上，请调用migrate_risk_parameters_to_pda")]
+    RiskParametersNotPda,
+    #[msg("窗口内的转出量仍超过上限，需等待窗口滚动后再执行")]
+    OutflowCapExceeded,
 }
@@ -0,0 +1,300 @@
+use crate::core::{Market, OpenOrders};
+use crate::migration::CURRENT_SCHEMA_VERSION;
+use crate::risk::RiskParameters;
+use crate::ErrorCode;
+use anchor_lang::prelude::*;
+
+// 交易限额 - 按用户统计滚动24小时名义成交额并限制单笔订单规模
+pub struct TradingLimits;
+
+// 每个计数桶覆盖的秒数（1小时）
+// 按unix时间分桶，撤单时可以用订单簿中记录的下单时间找回下单时记入的桶
+pub const VOLUME_BUCKET_SECS: i64 = 3_600;
+// 计数桶数量，合计覆盖24小时
+pub const VOLUME_BUCKETS: usize = 24;
+
+// 市场级交易限额配置（0表示不限制）
+#[account]
+#[derive(Default)]
+pub struct TradingLimitConfig {
+    pub schema_version: u8,        // 账户布局版本
+    pub market: Pubkey,            // 市场公钥
+    pub max_order_notional: u64,   // 单笔订单最大名义金额（报价代币）
+    pub max_rolling_notional: u64, // 滚动24小时最大名义金额（报价代币）
+    pub bump: u8,                  // PDA bump值
+}
+
+impl TradingLimitConfig {
+    pub const LEN: usize = 1 + 32 + 8 + 8 + 1;
+}
+
+// 用户滚动成交额计数，存放在OpenOrders中
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct UserTradingLimits {
+    pub bucket_ids: [u64; VOLUME_BUCKETS], // 每个桶对应的 unix时间 / VOLUME_BUCKET_SECS
+    pub notionals: [u64; VOLUME_BUCKETS],  // 每个桶内累计的名义金额
+    pub max_order_notional_override: u64,  // 用户单笔限额覆盖值（0表示使用市场配置）
+    pub max_rolling_notional_override: u64, // 用户滚动限额覆盖值（0表示使用市场配置）
+}
+
+impl UserTradingLimits {
+    pub const LEN: usize = Self::INIT_SPACE;
+
+    // 时间戳所在的桶
+    fn bucket_id(ts: i64) -> u64 {
+        (ts.max(0) / VOLUME_BUCKET_SECS) as u64
+    }
+
+    // 当前滚动窗口内最早的桶
+    fn oldest_bucket(current_ts: i64) -> u64 {
+        Self::bucket_id(current_ts).saturating_sub(VOLUME_BUCKETS as u64 - 1)
+    }
+
+    // 计算当前滚动窗口内的累计名义金额
+    pub fn rolling_notional(&self, current_ts: i64) -> u64 {
+        let current_bucket = Self::bucket_id(current_ts);
+        let oldest_bucket = Self::oldest_bucket(current_ts);
+
+        self.bucket_ids
+            .iter()
+            .zip(self.notionals.iter())
+            .filter(|(&id, _)| id >= oldest_bucket && id <= current_bucket)
+            .fold(0u64, |acc, (_, &notional)| acc.saturating_add(notional))
+    }
+
+    // 将名义金额记入时间戳所在的桶
+    pub fn record(&mut self, ts: i64, notional: u64) {
+        let bucket_id = Self::bucket_id(ts);
+        let idx = (bucket_id % VOLUME_BUCKETS as u64) as usize;
+
+        // 桶已过期则重置
+        if self.bucket_ids[idx] != bucket_id {
+            self.bucket_ids[idx] = bucket_id;
+            self.notionals[idx] = 0;
+        }
+
+        self.notionals[idx] = self.notionals[idx].saturating_add(notional);
+    }
+
+    // 撤单后退回未成交部分的名义金额
+    // 只从下单时记入的桶扣减；该桶已移出滚动窗口时额度已随桶过期，不再退回，
+    // 以免扣减之后新记入的成交额
+    pub fn release(&mut self, placed_ts: i64, current_ts: i64, notional: u64) {
+        let bucket_id = Self::bucket_id(placed_ts);
+        if bucket_id < Self::oldest_bucket(current_ts) {
+            return;
+        }
+
+        let idx = (bucket_id % VOLUME_BUCKETS as u64) as usize;
+        if self.bucket_ids[idx] == bucket_id {
+            self.notionals[idx] = self.notionals[idx].saturating_sub(notional);
+        }
+    }
+}
+
+impl TradingLimits {
+    // 计算订单名义金额（报价代币）
+    pub fn order_notional(price: u64, quantity: u64, lot_size: u64, tick_size: u64) -> u64 {
+        let notional = (price as u128)
+            .saturating_mul(quantity as u128)
+            .saturating_mul(lot_size as u128)
+            .checked_div(tick_size as u128)
+            .unwrap_or(0);
+
+        u64::try_from(notional).unwrap_or(u64::MAX)
+    }
+
+    // 检查订单是否超出限额，市场未创建限额配置时只应用用户覆盖值
+    // 滚动计数在撮合后按成交和挂单部分记入，撤单时退回，因此未成交即撤销的订单不占用额度
+    pub fn check(
+        config: Option<&TradingLimitConfig>,
+        limits: &UserTradingLimits,
+        notional: u64,
+        current_ts: i64,
+    ) -> Result<()> {
+        let max_order = if limits.max_order_notional_override > 0 {
+            limits.max_order_notional_override
+        } else {
+            config.map_or(0, |c| c.max_order_notional)
+        };
+        let max_rolling = if limits.max_rolling_notional_override > 0 {
+            limits.max_rolling_notional_override
+        } else {
+            config.map_or(0, |c| c.max_rolling_notional)
+        };
+
+        // 检查单笔订单限额
+        if max_order > 0 {
+            require!(notional <= max_order, ErrorCode::TradingLimitExceeded);
+        }
+
+        // 检查滚动24小时限额
+        if max_rolling > 0 {
+            let rolling = limits.rolling_notional(current_ts);
+            require!(
+                rolling.saturating_add(notional) <= max_rolling,
+                ErrorCode::TradingLimitExceeded
+            );
+        }
+
+        Ok(())
+    }
+}
+
+// 初始化交易限额配置所需的账户
+#[derive(Accounts)]
+pub struct InitializeTradingLimits<'info> {
+    pub market: Account<'info, Market>,
+
+    #[account(
+        seeds = [b"risk_parameters", market.key().as_ref()],
+        bump = risk_parameters.bump,
+        has_one = authority @ ErrorCode::UnauthorizedOperation
+    )]
+    pub risk_parameters: Account<'info, RiskParameters>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + TradingLimitConfig::LEN,
+        seeds = [b"trading_limits", market.key().as_ref()],
+        bump
+    )]
+    pub trading_limit_config: Account<'info, TradingLimitConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// 更新交易限额配置所需的账户
+#[derive(Accounts)]
+pub struct UpdateTradingLimits<'info> {
+    pub market: Account<'info, Market>,
+
+    #[account(
+        seeds = [b"risk_parameters", market.key().as_ref()],
+        bump = risk_parameters.bump,
+        has_one = authority @ ErrorCode::UnauthorizedOperation
+    )]
+    pub risk_parameters: Account<'info, RiskParameters>,
+
+    #[account(
+        mut,
+        seeds = [b"trading_limits", market.key().as_ref()],
+        bump = trading_limit_config.bump
+    )]
+    pub trading_limit_config: Account<'info, TradingLimitConfig>,
+
+    pub authority: Signer<'info>,
+}
+
+// 设置用户限额覆盖值所需的账户
+#[derive(Accounts)]
+pub struct SetUserTradingLimits<'info> {
+    pub market: Account<'info, Market>,
+
+    #[account(
+        seeds = [b"risk_parameters", market.key().as_ref()],
+        bump = risk_parameters.bump,
+        has_one = authority @ ErrorCode::UnauthorizedOperation
+    )]
+    pub risk_parameters: Account<'info, RiskParameters>,
+
+    #[account(
+        mut,
+        seeds = [b"open_orders", owner.key().as_ref(), market.key().as_ref()],
+        bump = open_orders.bump
+    )]
+    pub open_orders: Account<'info, OpenOrders>,
+
+    /// CHECK: 仅用于推导OpenOrders的PDA
+    pub owner: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+}
+
+// 初始化市场交易限额
+pub fn initialize_trading_limits(
+    ctx: Context<InitializeTradingLimits>,
+    max_order_notional: u64,
+    max_rolling_notional: u64,
+) -> Result<()> {
+    let config = &mut ctx.accounts.trading_limit_config;
+    config.schema_version = CURRENT_SCHEMA_VERSION;
+    config.market = ctx.accounts.market.key();
+    config.max_order_notional = max_order_notional;
+    config.max_rolling_notional = max_rolling_notional;
+    config.bump = *ctx.bumps.get("trading_limit_config").unwrap();
+
+    Ok(())
+}
+
+// 更新市场交易限额
+pub fn update_trading_limits(
+    ctx: Context<UpdateTradingLimits>,
+    max_order_notional: u64,
+    max_rolling_notional: u64,
+) -> Result<()> {
+    let config = &mut ctx.accounts.trading_limit_config;
+    config.max_order_notional = max_order_notional;
+    config.max_rolling_notional = max_rolling_notional;
+
+    Ok(())
+}
+
+// 设置用户的限额覆盖值（0表示恢复为市场配置）
+pub fn set_user_trading_limits(
+    ctx: Context<SetUserTradingLimits>,
+    max_order_notional: u64,
+    max_rolling_notional: u64,
+) -> Result<()> {
+    let limits = &mut ctx.accounts.open_orders.trading_limits;
+    limits.max_order_notional_override = max_order_notional;
+    limits.max_rolling_notional_override = max_rolling_notional;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = VOLUME_BUCKET_SECS;
+    const PLACED_TS: i64 = 1_700_000_000;
+
+    #[test]
+    fn release_only_from_placement_bucket() {
+        let mut limits = UserTradingLimits::default();
+        limits.record(PLACED_TS, 1_000);
+        // 两小时后的成交记入另一个桶
+        limits.record(PLACED_TS + 2 * HOUR, 500);
+
+        // 撤单退回的额度大于下单桶内的剩余额度，也不能扣减之后的成交
+        limits.release(PLACED_TS, PLACED_TS + 2 * HOUR, 1_200);
+        assert_eq!(limits.rolling_notional(PLACED_TS + 2 * HOUR), 500);
+    }
+
+    #[test]
+    fn release_after_placement_bucket_aged_out_is_noop() {
+        let mut limits = UserTradingLimits::default();
+        limits.record(PLACED_TS, 1_000);
+        let now = PLACED_TS + VOLUME_BUCKETS as i64 * HOUR;
+        assert_eq!(limits.rolling_notional(now), 0);
+
+        limits.record(now, 300);
+        limits.release(PLACED_TS, now, 1_000);
+        assert_eq!(limits.rolling_notional(now), 300);
+    }
+
+    #[test]
+    fn release_within_window_frees_placement_notional() {
+        let mut limits = UserTradingLimits::default();
+        limits.record(PLACED_TS, 1_000);
+        limits.record(PLACED_TS + HOUR, 200);
+
+        limits.release(PLACED_TS, PLACED_TS + HOUR, 400);
+        assert_eq!(limits.rolling_notional(PLACED_TS + HOUR), 800);
+    }
+}
@@ -1,4 +1,10 @@
+use crate::core::Market;
+use crate::events::EventHandler;
+use crate::migration::CURRENT_SCHEMA_VERSION;
+use crate::risk::RiskParameters;
+use crate::ErrorCode;
 use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Burn, Mint, MintTo, Token, TokenAccount, Transfer};
 
 // LP挖矿系统 - 提供流动性挖矿奖励
 pub struct LpMining;
@@ -7,22 +13,28 @@ pub struct LpMining;
 #[account]
 #[derive(Default)]
 pub struct LiquidityPool {
+    pub schema_version: u8,        // 账户布局版本
     pub market: Pubkey,            // 关联的市场
     pub token_a: Pubkey,           // A代币Mint
     pub token_b: Pubkey,           // B代币Mint
     pub token_a_vault: Pubkey,     // A代币金库
     pub token_b_vault: Pubkey,     // B代币金库
     pub lp_mint: Pubkey,           // LP代币Mint
+    pub lp_stake_vault: Pubkey,    // 质押LP代币的金库
     pub fee_rate: u16,             // 手续费率（基点 - 1bp = 0.01%）
     pub total_value_locked: u64,   // 总锁定价值 (USD)
     pub total_shares: u64,         // 总份额
+    pub total_staked: u64,         // 已质押的份额
+    pub total_boosted_stake: u64,  // 已质押份额按提升因子加权后的总量
     pub created_at: i64,           // 创建时间
     pub last_update_ts: i64,       // 最后更新时间戳
+    pub bump: u8,                  // PDA bump（池PDA同时是金库和LP Mint的权限账户）
 }
 
 // 流动性池状态
 #[account]
 pub struct LiquidityPoolState {
+    pub schema_version: u8,        // 账户布局版本
     pub pool: Pubkey,              // 流动性池
     pub a_reserve: u64,            // A代币储备
     pub b_reserve: u64,            // B代币储备
@@ -44,14 +56,31 @@ pub struct DailyData {
     pub fees: u64,                 // 产生的手续费
 }
 
-// 奖励配置
+// 池价格 (B/A) 的精度
+pub const PRICE_PRECISION: u128 = 1_000_000_000;
+
+// 每个流动性池最多同时存在的奖励流数量
+pub const MAX_REWARD_STREAMS: usize = 4;
+
+// 奖励配置（每个流动性池一份）
 #[account]
 pub struct RewardConfig {
+    pub schema_version: u8,        // 账户布局版本
+    pub pool: Pubkey,              // 流动性池
     pub admin: Pubkey,             // 管理员
+    pub stream_count: u8,          // 已创建的奖励流数量
+    // 奖励流，槽位只追加不复用，用户的奖励债务按相同下标记录
+    pub streams: [RewardStream; MAX_REWARD_STREAMS],
+    pub bump: u8,                  // PDA bump
+}
+
+// 奖励流 - 在一段时间内按固定速率发放一种奖励代币
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct RewardStream {
     pub reward_mint: Pubkey,       // 奖励代币Mint
     pub reward_vault: Pubkey,      // 奖励代币金库
+    pub funder: Pubkey,            // 出资方
     pub reward_rate: u64,          // 每秒奖励率 (每秒发放的奖励代币数量)
-    pub reward_duration: u64,      // 奖励持续时间 (秒)
     pub rewards_start_ts: i64,     // 奖励开始时间
     pub rewards_end_ts: i64,       // 奖励结束时间
     pub last_update_ts: i64,       // 最后更新时间戳
@@ -63,31 +92,64 @@ pub struct RewardConfig {
 #[account]
 #[derive(Default)]
 pub struct UserPosition {
+    pub schema_version: u8,        // 账户布局版本
     pub owner: Pubkey,             // 所有者
     pub pool: Pubkey,              // 流动性池
     pub shares: u64,               // 份额数量
     pub token_a_deposited: u64,    // 存入A代币数量
     pub token_b_deposited: u64,    // 存入B代币数量
-    pub reward_debt: u128,         // 奖励债务
-    pub reward_claimed: u64,       // 已提取奖励
+    pub entry_price: u64,          // 按存入价值加权的平均入场价格 (B/A, 1e9精度)
+    pub entry_value: u64,          // 持有份额（含已质押份额）存入时的价值（以B代币计）
+    pub reward_debts: [u128; MAX_REWARD_STREAMS], // 每个奖励流的奖励债务
+    pub rewards_claimed: [u64; MAX_REWARD_STREAMS], // 每个奖励流已提取的奖励
+    pub unclaimed_rewards: [u64; MAX_REWARD_STREAMS], // 已结算但因金库余额不足尚未发放的奖励
     pub last_claim_ts: i64,        // 最后提取时间
     pub creation_ts: i64,          // 创建时间
+    pub bump: u8,                  // PDA bump
 }
 
 // 质押信息
 #[account]
 pub struct StakingInfo {
+    pub schema_version: u8,        // 账户布局版本
     pub owner: Pubkey,             // 所有者
     pub pool: Pubkey,              // 流动性池
     pub staked_amount: u64,        // 质押数量
-    pub reward_debt: u128,         // 奖励债务
-    pub reward_claimed: u64,       // 已提取奖励
+    pub reward_debts: [u128; MAX_REWARD_STREAMS], // 每个奖励流的奖励债务
+    pub rewards_claimed: [u64; MAX_REWARD_STREAMS], // 每个奖励流已提取的奖励
     pub last_update_ts: i64,       // 最后更新时间
     pub lock_period: u64,          // 锁定期 (秒)
     pub unlock_time: i64,          // 解锁时间
-    pub boost_factor: u16,         // 提升因子 (基点表示，10000=1倍)
+    pub boost_factor: u16,         // 提升因子 (基点表示，10000=1倍，上次检查点时的值)
+    pub unclaimed_rewards: [u64; MAX_REWARD_STREAMS], // 检查点时已结算但尚未发放的奖励
+    pub bump: u8,                  // PDA bump
+}
+
+// 锁定档位
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum LockTier {
+    OneWeek = 0,     // 1周
+    OneMonth = 1,    // 1个月
+    ThreeMonths = 2, // 3个月
+    OneYear = 3,     // 1年
+}
+
+impl LockTier {
+    // 锁定时长（秒）
+    pub fn duration(&self) -> i64 {
+        match self {
+            LockTier::OneWeek => 7 * 86400,
+            LockTier::OneMonth => 30 * 86400,
+            LockTier::ThreeMonths => 90 * 86400,
+            LockTier::OneYear => MAX_LOCK_SECS,
+        }
+    }
 }
 
+// 剩余锁定时间达到最长锁定期时的提升因子（2.5倍），随剩余时间线性衰减到1倍
+pub const MAX_LOCK_BOOST: u16 = 25000;
+pub const MAX_LOCK_SECS: i64 = 365 * 86400;
+
 impl LpMining {
     // 初始化流动性池
     pub fn initialize_pool(
@@ -102,6 +164,7 @@ impl LpMining {
     ) -> Result<()> {
         let clock = Clock::get()?;
         
+        pool.schema_version = CURRENT_SCHEMA_VERSION;
         pool.market = market;
         pool.token_a = token_a;
         pool.token_b = token_b;
@@ -111,6 +174,8 @@ impl LpMining {
         pool.fee_rate = fee_rate;
         pool.total_value_locked = 0;
         pool.total_shares = 0;
+        pool.total_staked = 0;
+        pool.total_boosted_stake = 0;
         pool.created_at = clock.unix_timestamp;
         pool.last_update_ts = clock.unix_timestamp;
         
@@ -124,6 +189,7 @@ impl LpMining {
     ) -> Result<()> {
         let clock = Clock::get()?;
         
+        state.schema_version = CURRENT_SCHEMA_VERSION;
         state.pool = pool;
         state.a_reserve = 0;
         state.b_reserve = 0;
@@ -150,29 +216,56 @@ impl LpMining {
         Ok(())
     }
     
-    // 初始化奖励配置
+    // 初始化奖励配置（不含奖励流）
     pub fn initialize_reward_config(
         config: &mut RewardConfig,
+        pool: Pubkey,
         admin: Pubkey,
+    ) -> Result<()> {
+        config.schema_version = CURRENT_SCHEMA_VERSION;
+        config.pool = pool;
+        config.admin = admin;
+        config.stream_count = 0;
+        config.streams = [RewardStream::default(); MAX_REWARD_STREAMS];
+        
+        Ok(())
+    }
+    
+    // 追加奖励流，返回其下标
+    pub fn add_reward_stream(
+        config: &mut RewardConfig,
         reward_mint: Pubkey,
         reward_vault: Pubkey,
+        funder: Pubkey,
         reward_rate: u64,
-        reward_duration: u64,
-    ) -> Result<()> {
-        let clock = Clock::get()?;
+        rewards_start_ts: i64,
+        rewards_end_ts: i64,
+    ) -> Result<usize> {
+        let index = config.stream_count as usize;
+        require!(index < MAX_REWARD_STREAMS, ErrorCode::TooManyRewardStreams);
+        require!(
+            rewards_end_ts > rewards_start_ts && rewards_end_ts > Clock::get()?.unix_timestamp,
+            ErrorCode::InvalidParameters
+        );
         
-        config.admin = admin;
-        config.reward_mint = reward_mint;
-        config.reward_vault = reward_vault;
-        config.reward_rate = reward_rate;
-        config.reward_duration = reward_duration;
-        config.rewards_start_ts = clock.unix_timestamp;
-        config.rewards_end_ts = clock.unix_timestamp + reward_duration as i64;
-        config.last_update_ts = clock.unix_timestamp;
-        config.reward_per_share = 0;
-        config.total_reward_emissions = reward_rate * reward_duration;
+        let total_reward_emissions = reward_rate
+            .checked_mul((rewards_end_ts - rewards_start_ts) as u64)
+            .ok_or(ErrorCode::InvalidParameters)?;
         
-        Ok(())
+        config.streams[index] = RewardStream {
+            reward_mint,
+            reward_vault,
+            funder,
+            reward_rate,
+            rewards_start_ts,
+            rewards_end_ts,
+            last_update_ts: rewards_start_ts,
+            reward_per_share: 0,
+            total_reward_emissions,
+        };
+        config.stream_count += 1;
+        
+        Ok(index)
     }
     
     // 创建用户流动性位置
@@ -186,13 +279,17 @@ impl LpMining {
     ) -> Result<()> {
         let clock = Clock::get()?;
         
+        position.schema_version = CURRENT_SCHEMA_VERSION;
         position.owner = owner;
         position.pool = pool;
         position.shares = shares;
         position.token_a_deposited = token_a_amount;
         position.token_b_deposited = token_b_amount;
-        position.reward_debt = 0;
-        position.reward_claimed = 0;
+        position.entry_price = 0;
+        position.entry_value = 0;
+        position.reward_debts = [0; MAX_REWARD_STREAMS];
+        position.rewards_claimed = [0; MAX_REWARD_STREAMS];
+        position.unclaimed_rewards = [0; MAX_REWARD_STREAMS];
         position.last_claim_ts = clock.unix_timestamp;
         position.creation_ts = clock.unix_timestamp;
         
@@ -200,15 +297,17 @@ impl LpMining {
     }
     
     // 更新用户流动性位置
+    // 调用前需先用update_reward_state结算累加器；paid为本次实际发放给该位置的奖励，未发放的部分记为待领取
     pub fn update_user_position(
         position: &mut UserPosition,
         shares_delta: i64,
         token_a_delta: i64,
         token_b_delta: i64,
         reward_config: &RewardConfig,
+        paid: [u64; MAX_REWARD_STREAMS],
     ) -> Result<()> {
         // 先计算累积的奖励
-        let pending_reward = Self::calculate_pending_reward(position, reward_config)?;
+        let pending_rewards = Self::calculate_pending_reward(position, reward_config)?;
         
         // 更新位置
         if shares_delta > 0 {
@@ -230,14 +329,16 @@ impl LpMining {
         }
         
         // 更新奖励债务
-        position.reward_debt = reward_config.reward_per_share
-            .saturating_mul(position.shares as u128)
-            .checked_div(1_000_000_000_000)
-            .unwrap_or(0);
+        position.reward_debts = Self::accumulated_rewards(reward_config, position.shares);
         
-        // 如果有待领取的奖励，更新已领取奖励
-        if pending_reward > 0 {
-            position.reward_claimed = position.reward_claimed.saturating_add(pending_reward);
+        // 已发放的部分计入已领取奖励，其余留待下次发放
+        Self::settle_paid_rewards(
+            &mut position.rewards_claimed,
+            &mut position.unclaimed_rewards,
+            pending_rewards,
+            paid,
+        );
+        if paid.iter().any(|&r| r > 0) {
             position.last_claim_ts = Clock::get()?.unix_timestamp;
         }
         
@@ -254,78 +355,65 @@ impl LpMining {
     ) -> Result<()> {
         let clock = Clock::get()?;
         
+        staking.schema_version = CURRENT_SCHEMA_VERSION;
         staking.owner = owner;
         staking.pool = pool;
         staking.staked_amount = staked_amount;
-        staking.reward_debt = 0;
-        staking.reward_claimed = 0;
+        staking.reward_debts = [0; MAX_REWARD_STREAMS];
+        staking.rewards_claimed = [0; MAX_REWARD_STREAMS];
+        staking.unclaimed_rewards = [0; MAX_REWARD_STREAMS];
         staking.last_update_ts = clock.unix_timestamp;
         staking.lock_period = lock_period;
         staking.unlock_time = clock.unix_timestamp + lock_period as i64;
         
-        // 根据锁定期计算提升因子
-        // 例如：30天 = 100%，60天 = 120%，90天 = 150%，180天 = 200%
-        let boost_factor = match lock_period {
-            p if p >= 15552000 => 20000, // 180天 - 2倍
-            p if p >= 7776000 => 15000,  // 90天 - 1.5倍
-            p if p >= 5184000 => 12000,  // 60天 - 1.2倍
-            _ => 10000,                  // 默认 - 1倍
-        };
-        
-        staking.boost_factor = boost_factor;
+        // 根据剩余锁定时间计算提升因子
+        staking.boost_factor = Self::lock_boost(staking.unlock_time, clock.unix_timestamp);
         
         Ok(())
     }
     
-    // 更新奖励状态
+    // 更新所有奖励流的累加器
     pub fn update_reward_state(
         config: &mut RewardConfig,
         pool: &LiquidityPool,
     ) -> Result<()> {
-        let clock = Clock::get()?;
-        let current_time = clock.unix_timestamp;
+        let current_time = Clock::get()?.unix_timestamp;
+        let stream_count = config.stream_count as usize;
         
-        // 如果没有总份额，仅更新时间戳
-        if pool.total_shares == 0 {
-            config.last_update_ts = current_time;
-            return Ok(());
-        }
-        
-        // 如果奖励已经结束，不更新
-        if current_time > config.rewards_end_ts {
-            if config.last_update_ts < config.rewards_end_ts {
-                // 计算从上次更新到奖励结束期间的奖励
-                let time_delta = config.rewards_end_ts - config.last_update_ts;
-                
-                if time_delta > 0 {
-                    let reward = (config.reward_rate as u128)
-                        .saturating_mul(time_delta as u128)
-                        .saturating_mul(1_000_000_000_000)
-                        .checked_div(pool.total_shares as u128)
-                        .unwrap_or(0);
-                    
-                    config.reward_per_share = config.reward_per_share.saturating_add(reward);
-                    config.last_update_ts = config.rewards_end_ts;
-                }
-            }
-            return Ok(());
+        let weight = Self::reward_weight(pool);
+        for stream in config.streams[..stream_count].iter_mut() {
+            Self::update_stream_state(stream, weight, current_time);
         }
         
-        // 计算新增奖励
-        let time_delta = current_time - config.last_update_ts;
+        Ok(())
+    }
+    
+    // 参与奖励分配的总权重：未质押的份额按1倍计，质押的份额按提升后的数量计
+    pub fn reward_weight(pool: &LiquidityPool) -> u64 {
+        pool.total_shares
+            .saturating_sub(pool.total_staked)
+            .saturating_add(pool.total_boosted_stake)
+    }
+    
+    // 更新单个奖励流的累加器，只累计奖励区间内的时间
+    pub fn update_stream_state(stream: &mut RewardStream, total_shares: u64, current_time: i64) {
+        let from = stream.last_update_ts.max(stream.rewards_start_ts);
+        let to = current_time.min(stream.rewards_end_ts);
         
-        if time_delta > 0 {
-            let reward = (config.reward_rate as u128)
-                .saturating_mul(time_delta as u128)
+        // 没有份额时该段奖励不发放，仅推进时间戳
+        if total_shares > 0 && to > from {
+            let reward = (stream.reward_rate as u128)
+                .saturating_mul((to - from) as u128)
                 .saturating_mul(1_000_000_000_000)
-                .checked_div(pool.total_shares as u128)
+                .checked_div(total_shares as u128)
                 .unwrap_or(0);
             
-            config.reward_per_share = config.reward_per_share.saturating_add(reward);
-            config.last_update_ts = current_time;
+            stream.reward_per_share = stream.reward_per_share.saturating_add(reward);
         }
         
-        Ok(())
+        if current_time > stream.last_update_ts {
+            stream.last_update_ts = current_time;
+        }
     }
     
     // 更新池状态
@@ -342,18 +430,7 @@ impl LpMining {
         let clock = Clock::get()?;
         
         // 更新储备和价格
-        state.a_reserve = a_reserve;
-        state.b_reserve = b_reserve;
-        
-        // 计算价格 (B/A)
-        state.current_price = if a_reserve > 0 {
-            (b_reserve as u128)
-                .saturating_mul(1_000_000_000)
-                .checked_div(a_reserve as u128)
-                .unwrap_or(0) as u64
-        } else {
-            0
-        };
+        Self::update_reserves(state, a_reserve, b_reserve);
         
         // 更新交易量和手续费
         state.volume_24h = volume_delta;
@@ -394,52 +471,140 @@ impl LpMining {
         Ok(())
     }
     
-    // 重置奖励计划
+    // 记录新存入流动性的入场价值，入场价格按存入价值加权平均
+    pub fn record_entry(position: &mut UserPosition, a_amount: u64, b_amount: u64) {
+        if a_amount == 0 {
+            return;
+        }
+        let price = (b_amount as u128 * PRICE_PRECISION / a_amount as u128) as u64;
+        let value = (a_amount as u128 * price as u128 / PRICE_PRECISION) as u64 + b_amount;
+        let total_value = position.entry_value.saturating_add(value);
+        if total_value == 0 {
+            return;
+        }
+
+        position.entry_price = ((position.entry_price as u128 * position.entry_value as u128
+            + price as u128 * value as u128)
+            / total_value as u128) as u64;
+        position.entry_value = total_value;
+    }
+
+    // 移除流动性时按移除份额占持有份额（含已质押份额）的比例扣减入场价值
+    pub fn reduce_entry(position: &mut UserPosition, removed_shares: u64, owned_shares: u64) {
+        if owned_shares == 0 {
+            return;
+        }
+        let removed_value =
+            (position.entry_value as u128 * removed_shares as u128 / owned_shares as u128) as u64;
+        position.entry_value = position.entry_value.saturating_sub(removed_value);
+        if position.entry_value == 0 {
+            position.entry_price = 0;
+        }
+    }
+
+    // 更新储备并按储备重新计算价格 (B/A)
+    pub fn update_reserves(state: &mut LiquidityPoolState, a_reserve: u64, b_reserve: u64) {
+        state.a_reserve = a_reserve;
+        state.b_reserve = b_reserve;
+        state.current_price = if a_reserve > 0 {
+            (b_reserve as u128)
+                .saturating_mul(PRICE_PRECISION)
+                .checked_div(a_reserve as u128)
+                .unwrap_or(0) as u64
+        } else {
+            0
+        };
+    }
+    
+    // 重置奖励流的发放计划（调用前需先用update_reward_state结算累加器）
     pub fn reset_reward_schedule(
         config: &mut RewardConfig,
+        stream_index: usize,
         reward_rate: u64,
         reward_duration: u64,
     ) -> Result<()> {
+        require!(stream_index < config.stream_count as usize, ErrorCode::InvalidParameters);
         let clock = Clock::get()?;
+        let stream = &mut config.streams[stream_index];
         
-        // 需要结束当前奖励周期
-        config.rewards_end_ts = clock.unix_timestamp;
-        
-        // 强制更新奖励状态
-        config.last_update_ts = clock.unix_timestamp;
-        
-        // 设置新的奖励计划
-        config.reward_rate = reward_rate;
-        config.reward_duration = reward_duration;
-        config.rewards_start_ts = clock.unix_timestamp;
-        config.rewards_end_ts = clock.unix_timestamp + reward_duration as i64;
-        config.total_reward_emissions = reward_rate * reward_duration;
+        // 结束当前奖励周期并设置新的奖励计划
+        stream.last_update_ts = clock.unix_timestamp;
+        stream.reward_rate = reward_rate;
+        stream.rewards_start_ts = clock.unix_timestamp;
+        stream.rewards_end_ts = clock.unix_timestamp + reward_duration as i64;
+        stream.total_reward_emissions = reward_rate.saturating_mul(reward_duration);
         
         Ok(())
     }
     
-    // 计算待领取的奖励
+    // 计算各奖励流待领取的奖励（包含此前因金库余额不足尚未发放的部分）
     pub fn calculate_pending_reward(
         position: &UserPosition,
         reward_config: &RewardConfig,
-    ) -> Result<u64> {
-        if position.shares == 0 {
-            return Ok(0);
+    ) -> Result<[u64; MAX_REWARD_STREAMS]> {
+        let mut pending =
+            Self::pending_rewards(reward_config, position.shares, &position.reward_debts);
+        for (p, unclaimed) in pending.iter_mut().zip(position.unclaimed_rewards) {
+            *p = p.saturating_add(unclaimed);
+        }
+        Ok(pending)
+    }
+    
+    // 按实际发放数量结算待领取奖励：发放的部分计入已领取，未发放的余额保留为待领取
+    pub fn settle_paid_rewards(
+        rewards_claimed: &mut [u64; MAX_REWARD_STREAMS],
+        unclaimed_rewards: &mut [u64; MAX_REWARD_STREAMS],
+        pending: [u64; MAX_REWARD_STREAMS],
+        paid: [u64; MAX_REWARD_STREAMS],
+    ) {
+        for i in 0..MAX_REWARD_STREAMS {
+            let paid = paid[i].min(pending[i]);
+            rewards_claimed[i] = rewards_claimed[i].saturating_add(paid);
+            unclaimed_rewards[i] = pending[i] - paid;
+        }
+    }
+    
+    // 按权重计算各奖励流的累计奖励
+    pub fn accumulated_rewards(
+        reward_config: &RewardConfig,
+        weight: u64,
+    ) -> [u128; MAX_REWARD_STREAMS] {
+        let mut accumulated = [0u128; MAX_REWARD_STREAMS];
+        let stream_count = reward_config.stream_count as usize;
+        
+        for (i, stream) in reward_config.streams[..stream_count].iter().enumerate() {
+            accumulated[i] = stream.reward_per_share
+                .saturating_mul(weight as u128)
+                .checked_div(1_000_000_000_000)
+                .unwrap_or(0);
         }
         
-        let accumulated_reward = reward_config.reward_per_share
-            .saturating_mul(position.shares as u128)
-            .checked_div(1_000_000_000_000)
-            .unwrap_or(0);
+        accumulated
+    }
+    
+    // 累计奖励减去奖励债务即为待领取奖励
+    fn pending_rewards(
+        reward_config: &RewardConfig,
+        weight: u64,
+        reward_debts: &[u128; MAX_REWARD_STREAMS],
+    ) -> [u64; MAX_REWARD_STREAMS] {
+        let mut pending = [0u64; MAX_REWARD_STREAMS];
+        if weight == 0 {
+            return pending;
+        }
         
-        let pending = accumulated_reward.saturating_sub(position.reward_debt);
+        let accumulated = Self::accumulated_rewards(reward_config, weight);
+        for i in 0..MAX_REWARD_STREAMS {
+            pending[i] = accumulated[i].saturating_sub(reward_debts[i]) as u64;
+        }
         
-        Ok(pending as u64)
+        pending
     }
     
     // 计算流动性份额
     pub fn calculate_liquidity_shares(
         pool: &LiquidityPool,
+        state: &LiquidityPoolState,
         token_a_amount: u64,
         token_b_amount: u64,
     ) -> u64 {
@@ -448,10 +613,8 @@ impl LpMining {
             return (token_a_amount as f64 * token_b_amount as f64).sqrt() as u64;
         }
         
-        // 获取池中当前储备（这里简化了，实际上需要从池状态获取）
-        // 这里假设我们有一个函数来获取当前储备
-        let a_reserve = 1_000_000; // 假设值
-        let b_reserve = 2_000_000; // 假设值
+        let a_reserve = state.a_reserve;
+        let b_reserve = state.b_reserve;
         
         // 计算要铸造的份额
         let share_a = (token_a_amount as u128)
@@ -563,6 +726,76 @@ impl LpMining {
         amount_in
     }
     
+    // 更新质押数量和解锁时间，并按当前剩余锁定时间重新计算提升因子（检查点）
+    // 调用前需先用update_reward_state结算累加器；paid为本次实际发放给质押的奖励，未发放的部分留到下次发放
+    pub fn update_staking_info(
+        staking: &mut StakingInfo,
+        pool: &mut LiquidityPool,
+        amount_delta: i64,
+        unlock_time: i64,
+        reward_config: &RewardConfig,
+        paid: [u64; MAX_REWARD_STREAMS],
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let pending_rewards = Self::calculate_staking_reward(staking, reward_config)?;
+        
+        // 先从池的加权总量中移除旧的权重
+        pool.total_staked = pool.total_staked.saturating_sub(staking.staked_amount);
+        pool.total_boosted_stake = pool.total_boosted_stake.saturating_sub(Self::boosted_amount(staking));
+        
+        if amount_delta > 0 {
+            staking.staked_amount = staking.staked_amount.saturating_add(amount_delta as u64);
+        } else if amount_delta < 0 {
+            staking.staked_amount = staking.staked_amount.saturating_sub((-amount_delta) as u64);
+        }
+        
+        // 解锁时间只能延后
+        if unlock_time > staking.unlock_time {
+            staking.unlock_time = unlock_time;
+            staking.lock_period = (unlock_time - now) as u64;
+        }
+        staking.boost_factor = Self::lock_boost(staking.unlock_time, now);
+        
+        pool.total_staked = pool.total_staked.saturating_add(staking.staked_amount);
+        pool.total_boosted_stake = pool.total_boosted_stake.saturating_add(Self::boosted_amount(staking));
+        
+        // 按新的提升后数量更新奖励债务
+        staking.reward_debts = Self::accumulated_rewards(reward_config, Self::boosted_amount(staking));
+        
+        Self::settle_paid_rewards(
+            &mut staking.rewards_claimed,
+            &mut staking.unclaimed_rewards,
+            pending_rewards,
+            paid,
+        );
+        staking.last_update_ts = now;
+        
+        Ok(())
+    }
+    
+    // 按剩余锁定时间线性计算提升因子，剩余时间超过最长锁定期时按最长计
+    pub fn lock_boost(unlock_time: i64, now: i64) -> u16 {
+        if unlock_time <= now {
+            return 10000;
+        }
+        
+        let remaining = (unlock_time - now).min(MAX_LOCK_SECS) as u128;
+        let extra = ((MAX_LOCK_BOOST - 10000) as u128)
+            .saturating_mul(remaining)
+            .checked_div(MAX_LOCK_SECS as u128)
+            .unwrap_or(0);
+        
+        10000 + extra as u16
+    }
+    
+    // 应用提升因子后的质押数量
+    pub fn boosted_amount(staking: &StakingInfo) -> u64 {
+        (staking.staked_amount as u128)
+            .saturating_mul(staking.boost_factor as u128)
+            .checked_div(10000)
+            .unwrap_or(0) as u64
+    }
+    
     // 计算年化收益率 (APR)
     pub fn calculate_apr(
         daily_fees: u64,
@@ -582,30 +815,1111 @@ impl LpMining {
         daily_rate.saturating_mul(365)
     }
     
-    // 计算质押奖励
+    // 计算质押奖励（包含检查点时结算但尚未发放的部分）
     pub fn calculate_staking_reward(
         staking: &StakingInfo,
         reward_config: &RewardConfig,
-    ) -> Result<u64> {
-        if staking.staked_amount == 0 {
-            return Ok(0);
+    ) -> Result<[u64; MAX_REWARD_STREAMS]> {
+        let mut pending = Self::pending_rewards(
+            reward_config,
+            Self::boosted_amount(staking),
+            &staking.reward_debts,
+        );
+        for (p, unclaimed) in pending.iter_mut().zip(staking.unclaimed_rewards) {
+            *p = p.saturating_add(unclaimed);
         }
-        
-        // 应用提升因子
-        let boosted_amount = (staking.staked_amount as u128)
-            .saturating_mul(staking.boost_factor as u128)
-            .checked_div(10000)
-            .unwrap_or(0) as u64;
-        
-        // 计算累积奖励
-        let accumulated_reward = reward_config.reward_per_share
-            .saturating_mul(boosted_amount as u128)
-            .checked_div(1_000_000_000_000)
-            .unwrap_or(0);
-        
-        // 计算待领取奖励
-        let pending = accumulated_reward.saturating_sub(staking.reward_debt);
-        
-        Ok(pending as u64)
+        Ok(pending)
     }
-}
\ No newline at end of file
+}
+
+// 初始化流动性池所需的账户
+#[derive(Accounts)]
+pub struct InitializeLiquidityPool<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    // 只有市场的风控管理员可以创建流动性池
+    #[account(
+        seeds = [b"risk_parameters", market.key().as_ref()],
+        bump = risk_parameters.bump,
+        has_one = authority @ ErrorCode::UnauthorizedOperation
+    )]
+    pub risk_parameters: Account<'info, RiskParameters>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + std::mem::size_of::<LiquidityPool>(),
+        seeds = [b"liquidity_pool", market.key().as_ref()],
+        bump
+    )]
+    pub pool: Account<'info, LiquidityPool>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + std::mem::size_of::<LiquidityPoolState>(),
+        seeds = [b"pool_state", pool.key().as_ref()],
+        bump
+    )]
+    pub pool_state: Account<'info, LiquidityPoolState>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + std::mem::size_of::<RewardConfig>(),
+        seeds = [b"reward_config", pool.key().as_ref()],
+        bump
+    )]
+    pub reward_config: Account<'info, RewardConfig>,
+
+    #[account(address = market.base_mint @ ErrorCode::InvalidMarketId)]
+    pub base_mint: Account<'info, Mint>,
+
+    #[account(address = market.quote_mint @ ErrorCode::InvalidMarketId)]
+    pub quote_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"pool_vault", pool.key().as_ref(), base_mint.key().as_ref()],
+        bump,
+        token::mint = base_mint,
+        token::authority = pool,
+    )]
+    pub token_a_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"pool_vault", pool.key().as_ref(), quote_mint.key().as_ref()],
+        bump,
+        token::mint = quote_mint,
+        token::authority = pool,
+    )]
+    pub token_b_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"lp_mint", pool.key().as_ref()],
+        bump,
+        mint::decimals = base_mint.decimals,
+        mint::authority = pool,
+    )]
+    pub lp_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"lp_stake_vault", pool.key().as_ref()],
+        bump,
+        token::mint = lp_mint,
+        token::authority = pool,
+    )]
+    pub lp_stake_vault: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+// 追加奖励流所需的账户，创建时由出资方按奖励总量注资
+#[derive(Accounts)]
+pub struct AddRewardStream<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    // 只有市场的风控管理员可以为流动性池追加奖励流
+    #[account(
+        seeds = [b"risk_parameters", market.key().as_ref()],
+        bump = risk_parameters.bump,
+        has_one = authority @ ErrorCode::UnauthorizedOperation
+    )]
+    pub risk_parameters: Account<'info, RiskParameters>,
+
+    #[account(
+        seeds = [b"liquidity_pool", market.key().as_ref()],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, LiquidityPool>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_config", pool.key().as_ref()],
+        bump = reward_config.bump
+    )]
+    pub reward_config: Account<'info, RewardConfig>,
+
+    pub reward_mint: Account<'info, Mint>,
+
+    // 同一种奖励代币只能有一个奖励流
+    #[account(
+        init,
+        payer = funder,
+        seeds = [b"reward_vault", pool.key().as_ref(), reward_mint.key().as_ref()],
+        bump,
+        token::mint = reward_mint,
+        token::authority = pool,
+    )]
+    pub reward_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = funding_account.mint == reward_mint.key() @ ErrorCode::InvalidUserAccount,
+        constraint = funding_account.owner == funder.key() @ ErrorCode::InvalidUserAccount
+    )]
+    pub funding_account: Account<'info, TokenAccount>,
+
+    // 出资方可以是合作项目方，不必是市场管理员
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+// 添加流动性所需的账户（remaining_accounts同ClaimRewards）
+#[derive(Accounts)]
+pub struct AddLiquidity<'info> {
+    #[account(mut)] // 用于分配事件序号
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"liquidity_pool", market.key().as_ref()],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, LiquidityPool>,
+
+    #[account(mut, constraint = pool_state.pool == pool.key() @ ErrorCode::InvalidParameters)]
+    pub pool_state: Account<'info, LiquidityPoolState>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_config", pool.key().as_ref()],
+        bump = reward_config.bump
+    )]
+    pub reward_config: Account<'info, RewardConfig>,
+
+    #[account(mut, address = pool.token_a_vault)]
+    pub token_a_vault: Account<'info, TokenAccount>,
+
+    #[account(mut, address = pool.token_b_vault)]
+    pub token_b_vault: Account<'info, TokenAccount>,
+
+    #[account(mut, address = pool.lp_mint)]
+    pub lp_mint: Account<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + std::mem::size_of::<UserPosition>(),
+        seeds = [b"user_position", pool.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub user_position: Account<'info, UserPosition>,
+
+    #[account(mut, constraint = user_token_a.mint == pool.token_a @ ErrorCode::InvalidUserAccount)]
+    pub user_token_a: Account<'info, TokenAccount>,
+
+    #[account(mut, constraint = user_token_b.mint == pool.token_b @ ErrorCode::InvalidUserAccount)]
+    pub user_token_b: Account<'info, TokenAccount>,
+
+    #[account(mut, constraint = user_lp_account.mint == pool.lp_mint @ ErrorCode::InvalidUserAccount)]
+    pub user_lp_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+// 移除流动性所需的账户（remaining_accounts同ClaimRewards）
+#[derive(Accounts)]
+pub struct RemoveLiquidity<'info> {
+    #[account(mut)] // 用于分配事件序号
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"liquidity_pool", market.key().as_ref()],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, LiquidityPool>,
+
+    #[account(mut, constraint = pool_state.pool == pool.key() @ ErrorCode::InvalidParameters)]
+    pub pool_state: Account<'info, LiquidityPoolState>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_config", pool.key().as_ref()],
+        bump = reward_config.bump
+    )]
+    pub reward_config: Account<'info, RewardConfig>,
+
+    #[account(mut, address = pool.token_a_vault)]
+    pub token_a_vault: Account<'info, TokenAccount>,
+
+    #[account(mut, address = pool.token_b_vault)]
+    pub token_b_vault: Account<'info, TokenAccount>,
+
+    #[account(mut, address = pool.lp_mint)]
+    pub lp_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"user_position", pool.key().as_ref(), owner.key().as_ref()],
+        bump = user_position.bump
+    )]
+    pub user_position: Account<'info, UserPosition>,
+
+    // 有质押份额时传入，用于按持有份额比例扣减入场价值
+    #[account(
+        seeds = [b"staking_info", pool.key().as_ref(), owner.key().as_ref()],
+        bump = staking_info.bump
+    )]
+    pub staking_info: Option<Account<'info, StakingInfo>>,
+
+    #[account(mut, constraint = user_token_a.mint == pool.token_a @ ErrorCode::InvalidUserAccount)]
+    pub user_token_a: Account<'info, TokenAccount>,
+
+    #[account(mut, constraint = user_token_b.mint == pool.token_b @ ErrorCode::InvalidUserAccount)]
+    pub user_token_b: Account<'info, TokenAccount>,
+
+    #[account(mut, constraint = user_lp_account.mint == pool.lp_mint @ ErrorCode::InvalidUserAccount)]
+    pub user_lp_account: Account<'info, TokenAccount>,
+
+    pub owner: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+// 领取流动性挖矿奖励所需的账户（有质押时一并领取质押奖励）
+// remaining_accounts依次为每个奖励流的(奖励金库, 用户奖励代币账户)
+#[derive(Accounts)]
+pub struct ClaimRewards<'info> {
+    #[account(mut)] // 用于分配事件序号
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"liquidity_pool", market.key().as_ref()],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, LiquidityPool>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_config", pool.key().as_ref()],
+        bump = reward_config.bump
+    )]
+    pub reward_config: Account<'info, RewardConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"user_position", pool.key().as_ref(), owner.key().as_ref()],
+        bump = user_position.bump
+    )]
+    pub user_position: Account<'info, UserPosition>,
+
+    #[account(
+        mut,
+        seeds = [b"staking_info", pool.key().as_ref(), owner.key().as_ref()],
+        bump = staking_info.bump
+    )]
+    pub staking_info: Option<Account<'info, StakingInfo>>,
+
+    pub owner: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+// 质押LP份额所需的账户（remaining_accounts同ClaimRewards）
+#[derive(Accounts)]
+pub struct StakeLpTokens<'info> {
+    #[account(mut)] // 用于分配事件序号
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"liquidity_pool", market.key().as_ref()],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, LiquidityPool>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_config", pool.key().as_ref()],
+        bump = reward_config.bump
+    )]
+    pub reward_config: Account<'info, RewardConfig>,
+
+    #[account(mut, address = pool.lp_stake_vault)]
+    pub lp_stake_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"user_position", pool.key().as_ref(), owner.key().as_ref()],
+        bump = user_position.bump
+    )]
+    pub user_position: Account<'info, UserPosition>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + std::mem::size_of::<StakingInfo>(),
+        seeds = [b"staking_info", pool.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub staking_info: Account<'info, StakingInfo>,
+
+    #[account(mut, constraint = user_lp_account.mint == pool.lp_mint @ ErrorCode::InvalidUserAccount)]
+    pub user_lp_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+// 解除LP份额质押所需的账户（remaining_accounts同ClaimRewards）
+#[derive(Accounts)]
+pub struct UnstakeLpTokens<'info> {
+    #[account(mut)] // 用于分配事件序号
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"liquidity_pool", market.key().as_ref()],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, LiquidityPool>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_config", pool.key().as_ref()],
+        bump = reward_config.bump
+    )]
+    pub reward_config: Account<'info, RewardConfig>,
+
+    #[account(mut, address = pool.lp_stake_vault)]
+    pub lp_stake_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"user_position", pool.key().as_ref(), owner.key().as_ref()],
+        bump = user_position.bump
+    )]
+    pub user_position: Account<'info, UserPosition>,
+
+    #[account(
+        mut,
+        seeds = [b"staking_info", pool.key().as_ref(), owner.key().as_ref()],
+        bump = staking_info.bump
+    )]
+    pub staking_info: Account<'info, StakingInfo>,
+
+    #[account(mut, constraint = user_lp_account.mint == pool.lp_mint @ ErrorCode::InvalidUserAccount)]
+    pub user_lp_account: Account<'info, TokenAccount>,
+
+    pub owner: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+// 延长质押锁定所需的账户
+#[derive(Accounts)]
+pub struct ExtendStakeLock<'info> {
+    #[account(
+        mut,
+        seeds = [b"liquidity_pool", pool.market.as_ref()],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, LiquidityPool>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_config", pool.key().as_ref()],
+        bump = reward_config.bump
+    )]
+    pub reward_config: Account<'info, RewardConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"staking_info", pool.key().as_ref(), owner.key().as_ref()],
+        bump = staking_info.bump
+    )]
+    pub staking_info: Account<'info, StakingInfo>,
+
+    pub owner: Signer<'info>,
+}
+
+// 刷新质押提升因子所需的账户（任何人都可以调用，让衰减后的提升因子计入池权重）
+#[derive(Accounts)]
+pub struct RefreshStakeBoost<'info> {
+    #[account(
+        mut,
+        seeds = [b"liquidity_pool", pool.market.as_ref()],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, LiquidityPool>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_config", pool.key().as_ref()],
+        bump = reward_config.bump
+    )]
+    pub reward_config: Account<'info, RewardConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"staking_info", pool.key().as_ref(), staking_info.owner.as_ref()],
+        bump = staking_info.bump
+    )]
+    pub staking_info: Account<'info, StakingInfo>,
+}
+
+// 按奖励流下标依次发放奖励，金库余额不足时只发放剩余部分，返回各奖励流实际发放数量
+// 未发放的部分由调用方通过settle_paid_rewards记为待领取，金库补充后可以继续领取
+// remaining_accounts必须覆盖所有奖励流
+fn pay_rewards<'info>(
+    pool: &Account<'info, LiquidityPool>,
+    reward_config: &RewardConfig,
+    remaining_accounts: &[AccountInfo<'info>],
+    token_program: &Program<'info, Token>,
+    pending: [u64; MAX_REWARD_STREAMS],
+) -> Result<[u64; MAX_REWARD_STREAMS]> {
+    let stream_count = reward_config.stream_count as usize;
+    require!(
+        remaining_accounts.len() >= stream_count * 2,
+        ErrorCode::MissingRewardAccounts
+    );
+
+    let mut paid = [0u64; MAX_REWARD_STREAMS];
+    for (i, stream) in reward_config.streams[..stream_count].iter().enumerate() {
+        let vault_info = &remaining_accounts[i * 2];
+        let user_info = &remaining_accounts[i * 2 + 1];
+        require_keys_eq!(vault_info.key(), stream.reward_vault, ErrorCode::MissingRewardAccounts);
+
+        let vault = Account::<TokenAccount>::try_from(vault_info)?;
+        let user_account = Account::<TokenAccount>::try_from(user_info)?;
+        require_keys_eq!(user_account.mint, stream.reward_mint, ErrorCode::InvalidUserAccount);
+
+        let amount = pending[i].min(vault.amount);
+        if amount == 0 {
+            continue;
+        }
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                Transfer {
+                    from: vault_info.clone(),
+                    to: user_info.clone(),
+                    authority: pool.to_account_info(),
+                },
+                &[&[b"liquidity_pool", pool.market.as_ref(), &[pool.bump]]],
+            ),
+            amount,
+        )?;
+        paid[i] = amount;
+    }
+
+    Ok(paid)
+}
+
+// 每个有发放的奖励流各发出一条奖励领取事件
+fn emit_rewards_paid(
+    market: &mut Account<Market>,
+    reward_config: &RewardConfig,
+    owner: Pubkey,
+    paid: &[u64; MAX_REWARD_STREAMS],
+) {
+    let stream_count = reward_config.stream_count as usize;
+    for (stream, &amount) in reward_config.streams[..stream_count].iter().zip(paid) {
+        if amount > 0 {
+            EventHandler::emit_rewards_claimed(
+                market.key(),
+                market.next_event_seq(),
+                owner,
+                stream.reward_mint,
+                amount,
+            );
+        }
+    }
+}
+
+// 把合并发放的奖励拆分给流动性位置和质押：先满足first，剩余部分归另一方
+fn split_paid(
+    paid: [u64; MAX_REWARD_STREAMS],
+    first_pending: [u64; MAX_REWARD_STREAMS],
+) -> ([u64; MAX_REWARD_STREAMS], [u64; MAX_REWARD_STREAMS]) {
+    let mut first = [0u64; MAX_REWARD_STREAMS];
+    let mut rest = [0u64; MAX_REWARD_STREAMS];
+    for i in 0..MAX_REWARD_STREAMS {
+        first[i] = paid[i].min(first_pending[i]);
+        rest[i] = paid[i] - first[i];
+    }
+    (first, rest)
+}
+
+// 合并两组待领取奖励
+fn sum_rewards(
+    a: [u64; MAX_REWARD_STREAMS],
+    b: [u64; MAX_REWARD_STREAMS],
+) -> [u64; MAX_REWARD_STREAMS] {
+    let mut total = a;
+    for (t, r) in total.iter_mut().zip(b) {
+        *t = t.saturating_add(r);
+    }
+    total
+}
+
+// 从池金库转出代币
+fn transfer_from_pool<'info>(
+    pool: &Account<'info, LiquidityPool>,
+    from: &Account<'info, TokenAccount>,
+    to: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+    amount: u64,
+) -> Result<()> {
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Transfer {
+                from: from.to_account_info(),
+                to: to.to_account_info(),
+                authority: pool.to_account_info(),
+            },
+            &[&[b"liquidity_pool", pool.market.as_ref(), &[pool.bump]]],
+        ),
+        amount,
+    )
+}
+
+// 由用户签名转入代币
+fn transfer_from_user<'info>(
+    from: &Account<'info, TokenAccount>,
+    to: &Account<'info, TokenAccount>,
+    owner: &Signer<'info>,
+    token_program: &Program<'info, Token>,
+    amount: u64,
+) -> Result<()> {
+    token::transfer(
+        CpiContext::new(
+            token_program.to_account_info(),
+            Transfer {
+                from: from.to_account_info(),
+                to: to.to_account_info(),
+                authority: owner.to_account_info(),
+            },
+        ),
+        amount,
+    )
+}
+
+// 流动性变化后更新池的总锁定价值（以报价代币计，两侧价值相等）
+fn refresh_pool_value(pool: &mut LiquidityPool, state: &LiquidityPoolState) -> Result<()> {
+    pool.total_value_locked = state.b_reserve.saturating_mul(2);
+    pool.last_update_ts = Clock::get()?.unix_timestamp;
+    Ok(())
+}
+
+// 初始化市场的流动性池及其奖励配置
+pub fn initialize_liquidity_pool(ctx: Context<InitializeLiquidityPool>, fee_rate: u16) -> Result<()> {
+    require!(fee_rate < 10000, ErrorCode::InvalidParameters);
+
+    let pool = &mut ctx.accounts.pool;
+    LpMining::initialize_pool(
+        pool,
+        ctx.accounts.market.key(),
+        ctx.accounts.base_mint.key(),
+        ctx.accounts.quote_mint.key(),
+        ctx.accounts.token_a_vault.key(),
+        ctx.accounts.token_b_vault.key(),
+        ctx.accounts.lp_mint.key(),
+        fee_rate,
+    )?;
+    pool.lp_stake_vault = ctx.accounts.lp_stake_vault.key();
+    pool.bump = *ctx.bumps.get("pool").unwrap();
+
+    LpMining::initialize_pool_state(&mut ctx.accounts.pool_state, pool.key())?;
+
+    let config = &mut ctx.accounts.reward_config;
+    LpMining::initialize_reward_config(config, pool.key(), ctx.accounts.authority.key())?;
+    config.bump = *ctx.bumps.get("reward_config").unwrap();
+
+    ctx.accounts.market.lp_token_mint = Some(ctx.accounts.lp_mint.key());
+
+    Ok(())
+}
+
+// 为流动性池追加奖励流并由出资方注入全部奖励
+pub fn add_reward_stream(
+    ctx: Context<AddRewardStream>,
+    reward_rate: u64,
+    rewards_start_ts: i64,
+    rewards_end_ts: i64,
+) -> Result<()> {
+    let accounts = &mut ctx.accounts;
+
+    // 先按旧的奖励流结算到当前时间
+    LpMining::update_reward_state(&mut accounts.reward_config, &accounts.pool)?;
+
+    let index = LpMining::add_reward_stream(
+        &mut accounts.reward_config,
+        accounts.reward_mint.key(),
+        accounts.reward_vault.key(),
+        accounts.funder.key(),
+        reward_rate,
+        rewards_start_ts,
+        rewards_end_ts,
+    )?;
+
+    transfer_from_user(
+        &accounts.funding_account,
+        &accounts.reward_vault,
+        &accounts.funder,
+        &accounts.token_program,
+        accounts.reward_config.streams[index].total_reward_emissions,
+    )?;
+
+    // 市场上只记录第一个奖励代币
+    if accounts.market.reward_mint.is_none() {
+        accounts.market.reward_mint = Some(accounts.reward_mint.key());
+    }
+
+    Ok(())
+}
+
+// 添加流动性：按当前储备比例存入两种代币并铸造LP代币
+pub fn add_liquidity<'info>(
+    ctx: Context<'_, '_, '_, 'info, AddLiquidity<'info>>,
+    base_amount: u64,
+    quote_amount: u64,
+) -> Result<()> {
+    require!(base_amount > 0 && quote_amount > 0, ErrorCode::InvalidOrderQuantity);
+    require!(ctx.accounts.pool_state.is_active, ErrorCode::PoolNotActive);
+
+    let accounts = &mut ctx.accounts;
+    let owner = accounts.owner.key();
+
+    // 非首次存入时按储备比例截取，多出的部分不转入
+    let state = &accounts.pool_state;
+    let (a_amount, b_amount) = if accounts.pool.total_shares == 0 {
+        (base_amount, quote_amount)
+    } else {
+        let b_needed =
+            LpMining::calculate_tokens_for_liquidity(state.a_reserve, state.b_reserve, base_amount);
+        if b_needed <= quote_amount {
+            (base_amount, b_needed)
+        } else {
+            let a_needed = LpMining::calculate_tokens_for_liquidity(
+                state.b_reserve,
+                state.a_reserve,
+                quote_amount,
+            );
+            (a_needed, quote_amount)
+        }
+    };
+    let shares = LpMining::calculate_liquidity_shares(&accounts.pool, state, a_amount, b_amount);
+    require!(shares > 0, ErrorCode::InsufficientLiquidity);
+
+    // 首次存入时初始化用户流动性位置
+    let position = &mut accounts.user_position;
+    if position.owner == Pubkey::default() {
+        LpMining::create_user_position(position, owner, accounts.pool.key(), 0, 0, 0)?;
+        position.bump = *ctx.bumps.get("user_position").unwrap();
+    }
+
+    // 份额变化前先结算已累积的奖励
+    LpMining::update_reward_state(&mut accounts.reward_config, &accounts.pool)?;
+    let pending = LpMining::calculate_pending_reward(position, &accounts.reward_config)?;
+    let paid = pay_rewards(
+        &accounts.pool,
+        &accounts.reward_config,
+        ctx.remaining_accounts,
+        &accounts.token_program,
+        pending,
+    )?;
+
+    transfer_from_user(
+        &accounts.user_token_a,
+        &accounts.token_a_vault,
+        &accounts.owner,
+        &accounts.token_program,
+        a_amount,
+    )?;
+    transfer_from_user(
+        &accounts.user_token_b,
+        &accounts.token_b_vault,
+        &accounts.owner,
+        &accounts.token_program,
+        b_amount,
+    )?;
+
+    let pool = &accounts.pool;
+    token::mint_to(
+        CpiContext::new_with_signer(
+            accounts.token_program.to_account_info(),
+            MintTo {
+                mint: accounts.lp_mint.to_account_info(),
+                to: accounts.user_lp_account.to_account_info(),
+                authority: pool.to_account_info(),
+            },
+            &[&[b"liquidity_pool", pool.market.as_ref(), &[pool.bump]]],
+        ),
+        shares,
+    )?;
+
+    LpMining::update_user_position(
+        position,
+        shares as i64,
+        a_amount as i64,
+        b_amount as i64,
+        &accounts.reward_config,
+        paid,
+    )?;
+    LpMining::record_entry(position, a_amount, b_amount);
+
+    let state = &mut accounts.pool_state;
+    let a_reserve = state.a_reserve.saturating_add(a_amount);
+    let b_reserve = state.b_reserve.saturating_add(b_amount);
+    LpMining::update_reserves(state, a_reserve, b_reserve);
+    let pool = &mut accounts.pool;
+    pool.total_shares = pool.total_shares.saturating_add(shares);
+    refresh_pool_value(pool, state)?;
+
+    let market = &mut accounts.market;
+    emit_rewards_paid(market, &accounts.reward_config, owner, &paid);
+    EventHandler::emit_liquidity_changed(
+        market.key(),
+        market.next_event_seq(),
+        owner,
+        true,
+        a_amount,
+        b_amount,
+        shares,
+    );
+
+    Ok(())
+}
+
+// 移除流动性：销毁LP代币并按份额比例取回两种代币
+pub fn remove_liquidity<'info>(
+    ctx: Context<'_, '_, '_, 'info, RemoveLiquidity<'info>>,
+    lp_amount: u64,
+) -> Result<()> {
+    require!(lp_amount > 0, ErrorCode::InvalidOrderQuantity);
+
+    let accounts = &mut ctx.accounts;
+    let owner = accounts.owner.key();
+    let position = &mut accounts.user_position;
+    require!(position.shares >= lp_amount, ErrorCode::InsufficientLpTokens);
+
+    let state = &accounts.pool_state;
+    let (a_amount, b_amount) = LpMining::calculate_tokens_from_shares(
+        &accounts.pool,
+        state.a_reserve,
+        state.b_reserve,
+        lp_amount,
+    );
+    require!(a_amount > 0 || b_amount > 0, ErrorCode::InsufficientLiquidity);
+
+    // 份额变化前先结算已累积的奖励
+    LpMining::update_reward_state(&mut accounts.reward_config, &accounts.pool)?;
+    let pending = LpMining::calculate_pending_reward(position, &accounts.reward_config)?;
+    let paid = pay_rewards(
+        &accounts.pool,
+        &accounts.reward_config,
+        ctx.remaining_accounts,
+        &accounts.token_program,
+        pending,
+    )?;
+
+    token::burn(
+        CpiContext::new(
+            accounts.token_program.to_account_info(),
+            Burn {
+                mint: accounts.lp_mint.to_account_info(),
+                from: accounts.user_lp_account.to_account_info(),
+                authority: accounts.owner.to_account_info(),
+            },
+        ),
+        lp_amount,
+    )?;
+
+    transfer_from_pool(
+        &accounts.pool,
+        &accounts.token_a_vault,
+        &accounts.user_token_a,
+        &accounts.token_program,
+        a_amount,
+    )?;
+    transfer_from_pool(
+        &accounts.pool,
+        &accounts.token_b_vault,
+        &accounts.user_token_b,
+        &accounts.token_program,
+        b_amount,
+    )?;
+
+    let staked_shares = accounts
+        .staking_info
+        .as_ref()
+        .map_or(0, |staking| staking.staked_amount);
+    LpMining::reduce_entry(position, lp_amount, position.shares.saturating_add(staked_shares));
+    LpMining::update_user_position(
+        position,
+        -(lp_amount as i64),
+        -(a_amount as i64),
+        -(b_amount as i64),
+        &accounts.reward_config,
+        paid,
+    )?;
+
+    let state = &mut accounts.pool_state;
+    let a_reserve = state.a_reserve.saturating_sub(a_amount);
+    let b_reserve = state.b_reserve.saturating_sub(b_amount);
+    LpMining::update_reserves(state, a_reserve, b_reserve);
+    let pool = &mut accounts.pool;
+    pool.total_shares = pool.total_shares.saturating_sub(lp_amount);
+    refresh_pool_value(pool, state)?;
+
+    let market = &mut accounts.market;
+    emit_rewards_paid(market, &accounts.reward_config, owner, &paid);
+    EventHandler::emit_liquidity_changed(
+        market.key(),
+        market.next_event_seq(),
+        owner,
+        false,
+        a_amount,
+        b_amount,
+        lp_amount,
+    );
+
+    Ok(())
+}
+
+// 一次领取所有奖励流的流动性挖矿奖励
+pub fn claim_rewards<'info>(ctx: Context<'_, '_, '_, 'info, ClaimRewards<'info>>) -> Result<()> {
+    let accounts = &mut ctx.accounts;
+    let owner = accounts.owner.key();
+    LpMining::update_reward_state(&mut accounts.reward_config, &accounts.pool)?;
+
+    let position_pending = LpMining::calculate_pending_reward(&accounts.user_position, &accounts.reward_config)?;
+    let staking_pending = match accounts.staking_info.as_ref() {
+        Some(staking) => LpMining::calculate_staking_reward(staking, &accounts.reward_config)?,
+        None => [0; MAX_REWARD_STREAMS],
+    };
+
+    let paid = pay_rewards(
+        &accounts.pool,
+        &accounts.reward_config,
+        ctx.remaining_accounts,
+        &accounts.token_program,
+        sum_rewards(position_pending, staking_pending),
+    )?;
+    require!(paid.iter().any(|&p| p > 0), ErrorCode::NoRewardsToClaim);
+
+    let (position_paid, staking_paid) = split_paid(paid, position_pending);
+    LpMining::update_user_position(
+        &mut accounts.user_position,
+        0,
+        0,
+        0,
+        &accounts.reward_config,
+        position_paid,
+    )?;
+    if let Some(staking) = accounts.staking_info.as_mut() {
+        let unlock_time = staking.unlock_time;
+        LpMining::update_staking_info(
+            staking,
+            &mut accounts.pool,
+            0,
+            unlock_time,
+            &accounts.reward_config,
+            staking_paid,
+        )?;
+    }
+
+    emit_rewards_paid(&mut accounts.market, &accounts.reward_config, owner, &paid);
+
+    Ok(())
+}
+
+// 质押LP份额并锁定：LP代币转入质押金库，份额从流动性位置转入质押记录
+// 锁定作用于全部质押，解锁时间取现有解锁时间和按档位计算的解锁时间中较晚的一个
+pub fn stake_lp_tokens<'info>(
+    ctx: Context<'_, '_, '_, 'info, StakeLpTokens<'info>>,
+    amount: u64,
+    lock_tier: LockTier,
+) -> Result<()> {
+    require!(amount > 0, ErrorCode::InvalidOrderQuantity);
+
+    let accounts = &mut ctx.accounts;
+    let owner = accounts.owner.key();
+    require!(accounts.user_position.shares >= amount, ErrorCode::InsufficientLpTokens);
+
+    // 首次质押时初始化质押记录
+    let staking = &mut accounts.staking_info;
+    if staking.owner == Pubkey::default() {
+        LpMining::create_staking_info(staking, owner, accounts.pool.key(), 0, 0)?;
+        staking.bump = *ctx.bumps.get("staking_info").unwrap();
+    }
+
+    // 两边的份额都会变化，先结算已累积的奖励
+    LpMining::update_reward_state(&mut accounts.reward_config, &accounts.pool)?;
+    let position_pending = LpMining::calculate_pending_reward(&accounts.user_position, &accounts.reward_config)?;
+    let paid = pay_rewards(
+        &accounts.pool,
+        &accounts.reward_config,
+        ctx.remaining_accounts,
+        &accounts.token_program,
+        sum_rewards(
+            position_pending,
+            LpMining::calculate_staking_reward(staking, &accounts.reward_config)?,
+        ),
+    )?;
+    let (position_paid, staking_paid) = split_paid(paid, position_pending);
+
+    transfer_from_user(
+        &accounts.user_lp_account,
+        &accounts.lp_stake_vault,
+        &accounts.owner,
+        &accounts.token_program,
+        amount,
+    )?;
+
+    LpMining::update_user_position(
+        &mut accounts.user_position,
+        -(amount as i64),
+        0,
+        0,
+        &accounts.reward_config,
+        position_paid,
+    )?;
+    let unlock_time = Clock::get()?.unix_timestamp + lock_tier.duration();
+    LpMining::update_staking_info(
+        staking,
+        &mut accounts.pool,
+        amount as i64,
+        unlock_time,
+        &accounts.reward_config,
+        staking_paid,
+    )?;
+
+    emit_rewards_paid(&mut accounts.market, &accounts.reward_config, owner, &paid);
+
+    Ok(())
+}
+
+// 解除质押：LP代币从质押金库取回，份额返还流动性位置
+pub fn unstake_lp_tokens<'info>(
+    ctx: Context<'_, '_, '_, 'info, UnstakeLpTokens<'info>>,
+    amount: u64,
+) -> Result<()> {
+    require!(amount > 0, ErrorCode::InvalidOrderQuantity);
+
+    let accounts = &mut ctx.accounts;
+    let owner = accounts.owner.key();
+    let staking = &mut accounts.staking_info;
+    require!(staking.staked_amount >= amount, ErrorCode::InsufficientLpTokens);
+    require!(
+        Clock::get()?.unix_timestamp >= staking.unlock_time,
+        ErrorCode::StakeStillLocked
+    );
+
+    LpMining::update_reward_state(&mut accounts.reward_config, &accounts.pool)?;
+    let position_pending = LpMining::calculate_pending_reward(&accounts.user_position, &accounts.reward_config)?;
+    let paid = pay_rewards(
+        &accounts.pool,
+        &accounts.reward_config,
+        ctx.remaining_accounts,
+        &accounts.token_program,
+        sum_rewards(
+            position_pending,
+            LpMining::calculate_staking_reward(staking, &accounts.reward_config)?,
+        ),
+    )?;
+    let (position_paid, staking_paid) = split_paid(paid, position_pending);
+
+    transfer_from_pool(
+        &accounts.pool,
+        &accounts.lp_stake_vault,
+        &accounts.user_lp_account,
+        &accounts.token_program,
+        amount,
+    )?;
+
+    let unlock_time = staking.unlock_time;
+    LpMining::update_staking_info(
+        staking,
+        &mut accounts.pool,
+        -(amount as i64),
+        unlock_time,
+        &accounts.reward_config,
+        staking_paid,
+    )?;
+    LpMining::update_user_position(
+        &mut accounts.user_position,
+        amount as i64,
+        0,
+        0,
+        &accounts.reward_config,
+        position_paid,
+    )?;
+
+    emit_rewards_paid(&mut accounts.market, &accounts.reward_config, owner, &paid);
+
+    Ok(())
+}
+
+// 延长质押锁定，新的解锁时间必须晚于当前解锁时间；待领取奖励留到下次领取时发放
+pub fn extend_stake_lock(ctx: Context<ExtendStakeLock>, lock_tier: LockTier) -> Result<()> {
+    let accounts = &mut ctx.accounts;
+    let unlock_time = Clock::get()?.unix_timestamp + lock_tier.duration();
+    require!(
+        unlock_time > accounts.staking_info.unlock_time,
+        ErrorCode::LockNotExtended
+    );
+
+    LpMining::update_reward_state(&mut accounts.reward_config, &accounts.pool)?;
+    LpMining::update_staking_info(
+        &mut accounts.staking_info,
+        &mut accounts.pool,
+        0,
+        unlock_time,
+        &accounts.reward_config,
+        [0; MAX_REWARD_STREAMS],
+    )?;
+
+    Ok(())
+}
+
+// 按剩余锁定时间刷新质押的提升因子；待领取奖励留到下次领取时发放
+pub fn refresh_stake_boost(ctx: Context<RefreshStakeBoost>) -> Result<()> {
+    let accounts = &mut ctx.accounts;
+    LpMining::update_reward_state(&mut accounts.reward_config, &accounts.pool)?;
+
+    let unlock_time = accounts.staking_info.unlock_time;
+    LpMining::update_staking_info(
+        &mut accounts.staking_info,
+        &mut accounts.pool,
+        0,
+        unlock_time,
+        &accounts.reward_config,
+        [0; MAX_REWARD_STREAMS],
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,367 @@
+use crate::core::{Market, OpenOrders};
+use crate::events::EventHandler;
+use crate::migration::CURRENT_SCHEMA_VERSION;
+use crate::orderbook::{OrderBook, Side};
+use crate::risk::RiskParameters;
+use crate::ErrorCode;
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+use std::cmp;
+
+// 做市挖矿 - 按挂单数量和挂单时长奖励在中间价附近提供流动性的做市商
+pub struct MakerMining;
+
+// 保留最近结束的周期数量，超过该数量仍未结算的积分作废
+pub const MAKER_EPOCH_HISTORY: usize = 8;
+
+// 每次采样最多计分的做市商数量（按本次积分从高到低）
+pub const MAX_SCORED_MAKERS: usize = 16;
+
+// 两次采样之间的最小间隔（秒）
+const MIN_MAKER_SAMPLE_INTERVAL_SECS: i64 = 30;
+
+// 单次采样最多计入的时长（秒），避免长时间无人采样后按当前挂单一次性计入
+// 采样间隔内的挂单变化无法观察到，取值越小，撤掉的挂单被多计的时长越短
+const MAX_MAKER_SAMPLE_GAP_SECS: i64 = 60;
+
+// 已结束周期的积分汇总
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct MakerEpoch {
+    pub epoch: u64,        // 周期编号
+    pub total_score: u128, // 该周期所有做市商的积分总和
+    pub rewards: u64,      // 该周期发放的奖励总量
+}
+
+// 做市挖矿配置（每个市场一个）
+#[account]
+pub struct MakerRewardConfig {
+    pub schema_version: u8,         // 账户布局版本
+    pub market: Pubkey,             // 所属市场
+    pub authority: Pubkey,          // 管理员
+    pub reward_mint: Pubkey,        // 奖励代币Mint
+    pub reward_vault: Pubkey,       // 奖励代币金库
+    pub epoch_duration_secs: i64,   // 每个周期的时长（秒）
+    pub rewards_per_epoch: u64,     // 每个周期发放的奖励
+    pub max_tick_distance: u32,     // 只统计距中间价不超过该tick数的挂单
+    pub start_ts: i64,              // 第0个周期的开始时间
+    pub current_epoch: u64,         // 当前周期编号
+    pub current_epoch_score: u128,  // 当前周期的积分总和
+    pub last_sample_ts: i64,        // 上次采样时间
+    pub total_rewards_claimed: u64, // 累计已领取的奖励
+    // 最近结束的周期，下标为epoch % MAKER_EPOCH_HISTORY
+    pub epochs: [MakerEpoch; MAKER_EPOCH_HISTORY],
+    pub bump: u8, // PDA bump值
+}
+
+impl MakerRewardConfig {
+    // 某个时间点所在的周期
+    pub fn epoch_at(&self, ts: i64) -> u64 {
+        (ts.saturating_sub(self.start_ts).max(0) / self.epoch_duration_secs) as u64
+    }
+
+    // 某个周期的开始时间
+    pub fn epoch_start_ts(&self, epoch: u64) -> i64 {
+        self.start_ts + epoch as i64 * self.epoch_duration_secs
+    }
+
+    // 进入新周期时把当前周期的积分总和存入历史
+    // 中间没有任何采样的周期没有积分，对应的奖励留在金库中
+    pub fn roll_epoch(&mut self, now: i64) {
+        let epoch = self.epoch_at(now);
+        if epoch <= self.current_epoch {
+            return;
+        }
+
+        let slot = (self.current_epoch % MAKER_EPOCH_HISTORY as u64) as usize;
+        self.epochs[slot] = MakerEpoch {
+            epoch: self.current_epoch,
+            total_score: self.current_epoch_score,
+            rewards: self.rewards_per_epoch,
+        };
+        self.current_epoch = epoch;
+        self.current_epoch_score = 0;
+    }
+}
+
+impl MakerMining {
+    // 把用户在已结束周期的积分按比例折算为待领取奖励
+    pub fn settle_maker_score(config: &MakerRewardConfig, open_orders: &mut OpenOrders) {
+        if open_orders.maker_score_epoch == config.current_epoch {
+            return;
+        }
+
+        if open_orders.maker_score > 0 {
+            let slot = (open_orders.maker_score_epoch % MAKER_EPOCH_HISTORY as u64) as usize;
+            let finished = &config.epochs[slot];
+            if finished.epoch == open_orders.maker_score_epoch && finished.total_score > 0 {
+                let reward =
+                    open_orders.maker_score * finished.rewards as u128 / finished.total_score;
+                open_orders.maker_rewards_owed =
+                    open_orders.maker_rewards_owed.saturating_add(reward as u64);
+            }
+        }
+
+        open_orders.maker_score_epoch = config.current_epoch;
+        open_orders.maker_score = 0;
+    }
+}
+
+// 初始化做市挖矿所需的账户
+#[derive(Accounts)]
+pub struct InitializeMakerRewards<'info> {
+    pub market: Account<'info, Market>,
+
+    // 只有市场的风控管理员可以开启做市挖矿
+    #[account(
+        seeds = [b"risk_parameters", market.key().as_ref()],
+        bump = risk_parameters.bump,
+        has_one = authority @ ErrorCode::UnauthorizedOperation
+    )]
+    pub risk_parameters: Account<'info, RiskParameters>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + std::mem::size_of::<MakerRewardConfig>(),
+        seeds = [b"maker_rewards", market.key().as_ref()],
+        bump
+    )]
+    pub maker_config: Account<'info, MakerRewardConfig>,
+
+    pub reward_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"maker_reward_vault", market.key().as_ref()],
+        bump,
+        token::mint = reward_mint,
+        token::authority = maker_config,
+    )]
+    pub reward_vault: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+// 采样做市商挂单所需的账户（任何人都可以调用）
+// remaining_accounts: 本次计分的做市商的OpenOrders账户（可写），顺序不限
+#[derive(Accounts)]
+pub struct SampleMakerQuotes<'info> {
+    pub market: Account<'info, Market>,
+
+    #[account(has_one = market @ ErrorCode::InvalidMarketId)]
+    pub order_book: AccountLoader<'info, OrderBook>,
+
+    #[account(
+        mut,
+        seeds = [b"maker_rewards", market.key().as_ref()],
+        bump = maker_config.bump
+    )]
+    pub maker_config: Account<'info, MakerRewardConfig>,
+}
+
+// 领取做市奖励所需的账户
+#[derive(Accounts)]
+pub struct ClaimMakerRewards<'info> {
+    #[account(mut)] // 用于分配事件序号
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"maker_rewards", market.key().as_ref()],
+        bump = maker_config.bump
+    )]
+    pub maker_config: Account<'info, MakerRewardConfig>,
+
+    #[account(mut, address = maker_config.reward_vault)]
+    pub reward_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"open_orders", owner.key().as_ref(), market.key().as_ref()],
+        bump = open_orders.bump
+    )]
+    pub open_orders: Account<'info, OpenOrders>,
+
+    #[account(
+        mut,
+        constraint = user_reward_account.mint == maker_config.reward_mint @ ErrorCode::InvalidUserAccount
+    )]
+    pub user_reward_account: Account<'info, TokenAccount>,
+
+    pub owner: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+// 开启做市挖矿，奖励由管理员或其他人直接转入金库
+pub fn initialize_maker_rewards(
+    ctx: Context<InitializeMakerRewards>,
+    epoch_duration_secs: i64,
+    rewards_per_epoch: u64,
+    max_tick_distance: u32,
+) -> Result<()> {
+    require!(
+        ctx.accounts.market.schema_version == CURRENT_SCHEMA_VERSION,
+        ErrorCode::AccountNeedsMigration
+    );
+    require!(
+        epoch_duration_secs >= MAX_MAKER_SAMPLE_GAP_SECS && max_tick_distance > 0,
+        ErrorCode::InvalidParameters
+    );
+
+    let now = Clock::get()?.unix_timestamp;
+    let config = &mut ctx.accounts.maker_config;
+    config.schema_version = CURRENT_SCHEMA_VERSION;
+    config.market = ctx.accounts.market.key();
+    config.authority = ctx.accounts.authority.key();
+    config.reward_mint = ctx.accounts.reward_mint.key();
+    config.reward_vault = ctx.accounts.reward_vault.key();
+    config.epoch_duration_secs = epoch_duration_secs;
+    config.rewards_per_epoch = rewards_per_epoch;
+    config.max_tick_distance = max_tick_distance;
+    config.start_ts = now;
+    config.current_epoch = 0;
+    config.current_epoch_score = 0;
+    config.last_sample_ts = now;
+    config.total_rewards_claimed = 0;
+    config.epochs = [MakerEpoch::default(); MAKER_EPOCH_HISTORY];
+    config.bump = *ctx.bumps.get("maker_config").unwrap();
+
+    Ok(())
+}
+
+// 采样订单簿：距中间价max_tick_distance个tick以内、未过期的挂单按 数量 × 挂单秒数 计分
+// 挂单秒数取 距上次采样的秒数 和 下单至今的秒数 中较小的一个，刚挂出就采样再撤单的订单几乎不得分
+// 本次积分最高的前MAX_SCORED_MAKERS个做市商都必须提供OpenOrders账户，调用者不能有选择地跳过
+pub fn sample_maker_quotes<'info>(
+    ctx: Context<'_, '_, '_, 'info, SampleMakerQuotes<'info>>,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let market = &ctx.accounts.market;
+    let config = &mut ctx.accounts.maker_config;
+    let order_book = ctx.accounts.order_book.load()?;
+
+    require!(
+        market.schema_version == CURRENT_SCHEMA_VERSION
+            && order_book.schema_version == CURRENT_SCHEMA_VERSION,
+        ErrorCode::AccountNeedsMigration
+    );
+    require!(
+        now >= config.last_sample_ts + MIN_MAKER_SAMPLE_INTERVAL_SECS,
+        ErrorCode::MakerSampleTooFrequent
+    );
+
+    // 跨周期时只计入新周期开始之后的时长
+    config.roll_epoch(now);
+    let sample_start = cmp::max(
+        config.last_sample_ts,
+        config.epoch_start_ts(config.current_epoch),
+    );
+    let from = cmp::max(sample_start, now - MAX_MAKER_SAMPLE_GAP_SECS);
+    config.last_sample_ts = now;
+
+    // 单边无挂单时没有中间价，本次不计分
+    let (best_bid, best_ask) = match (
+        order_book.get_best_price(Side::Bid),
+        order_book.get_best_price(Side::Ask),
+    ) {
+        (Some(bid), Some(ask)) => (bid, ask),
+        _ => return Ok(()),
+    };
+    if now <= from {
+        return Ok(());
+    }
+
+    let mid = (best_bid / 2) + (best_ask / 2);
+    let distance = (config.max_tick_distance as u64).saturating_mul(market.tick_size);
+    let mut makers = order_book.resting_score_by_owner(
+        mid.saturating_sub(distance),
+        mid.saturating_add(distance),
+        from,
+        now,
+    );
+    drop(order_book);
+
+    makers.sort_by(|a, b| b.1.cmp(&a.1));
+    makers.truncate(MAX_SCORED_MAKERS);
+
+    for (owner, score) in makers {
+        let info = ctx
+            .remaining_accounts
+            .iter()
+            .find(|info| {
+                Account::<OpenOrders>::try_from(info)
+                    .map(|oo| oo.owner == owner && oo.market == market.key())
+                    .unwrap_or(false)
+            })
+            .ok_or(ErrorCode::MissingMakerAccounts)?;
+        let mut open_orders = Account::<OpenOrders>::try_from(info)?;
+        require!(
+            open_orders.schema_version == CURRENT_SCHEMA_VERSION,
+            ErrorCode::AccountNeedsMigration
+        );
+
+        MakerMining::settle_maker_score(config, &mut open_orders);
+        open_orders.maker_score = open_orders.maker_score.saturating_add(score);
+        config.current_epoch_score = config.current_epoch_score.saturating_add(score);
+        open_orders.exit(&crate::ID)?;
+    }
+
+    Ok(())
+}
+
+// 领取已结束周期的做市奖励，金库余额不足时先发放可用部分，其余保留到下次领取
+pub fn claim_maker_rewards(ctx: Context<ClaimMakerRewards>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let config = &mut ctx.accounts.maker_config;
+    let open_orders = &mut ctx.accounts.open_orders;
+
+    require!(
+        open_orders.schema_version == CURRENT_SCHEMA_VERSION,
+        ErrorCode::AccountNeedsMigration
+    );
+
+    config.roll_epoch(now);
+    MakerMining::settle_maker_score(config, open_orders);
+
+    let amount = cmp::min(
+        open_orders.maker_rewards_owed,
+        ctx.accounts.reward_vault.amount,
+    );
+    require!(amount > 0, ErrorCode::NoRewardsToClaim);
+
+    let market_key = ctx.accounts.market.key();
+    token::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.reward_vault.to_account_info(),
+                to: ctx.accounts.user_reward_account.to_account_info(),
+                authority: config.to_account_info(),
+            },
+            &[&[b"maker_rewards", market_key.as_ref(), &[config.bump]]],
+        ),
+        amount,
+    )?;
+
+    open_orders.maker_rewards_owed -= amount;
+    open_orders.reward_tokens_earned = open_orders.reward_tokens_earned.saturating_add(amount);
+    config.total_rewards_claimed = config.total_rewards_claimed.saturating_add(amount);
+
+    let market = &mut ctx.accounts.market;
+    EventHandler::emit_rewards_claimed(
+        market.key(),
+        market.next_event_seq(),
+        ctx.accounts.owner.key(),
+        config.reward_mint,
+        amount,
+    );
+
+    Ok(())
+}
@@ -0,0 +1,449 @@
+use crate::core::{Market, OpenOrders};
+use crate::events::EventHandler;
+use crate::limits::UserTradingLimits;
+use crate::orderbook::{OrderBook, OrderNode, PriceNode};
+use crate::risk::RiskParameters;
+use crate::ErrorCode;
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer as SystemTransfer};
+use anchor_lang::Discriminator;
+use std::mem;
+
+// 账户布局迁移 - 将旧布局的账户原地扩容并转换为当前布局，无需重建市场或让用户迁移资金
+pub struct AccountMigration;
+
+// 当前账户布局版本，所有账户创建时写入schema_version
+// 版本0表示引入schema_version之前上线的布局；某个账户布局再次变化时为其单独定义版本常量
+pub const CURRENT_SCHEMA_VERSION: u8 = 1;
+
+// 需要迁移的账户类型（其余账户在引入版本号之后才创建，直接以当前版本初始化）
+// MarketRiskMetrics不在其中：此前没有任何指令创建该账户，链上第一批账户由snapshot_market_metrics以当前布局创建
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MigratableAccount {
+    Market = 0,         // 市场
+    OpenOrders = 1,     // 开放订单
+    OrderBook = 2,      // 订单簿
+    RiskParameters = 3, // 风控参数
+}
+
+// 版本0的市场布局
+#[derive(AnchorSerialize, AnchorDeserialize)]
+struct MarketV0 {
+    base_mint: Pubkey,
+    quote_mint: Pubkey,
+    lot_size: u64,
+    tick_size: u64,
+    base_decimals: u8,
+    quote_decimals: u8,
+    maker_fee: i64,
+    taker_fee: i64,
+    base_vault: Pubkey,
+    quote_vault: Pubkey,
+    lp_token_mint: Option<Pubkey>,
+    reward_mint: Option<Pubkey>,
+    name: String,
+    active: bool,
+    last_traded_price: Option<u64>,
+    total_volume: u64,
+    total_fees_collected: u64,
+    min_base_order_size: u64,
+    min_quote_order_size: u64,
+    lp_reward_rate: u64,
+    cross_chain_enabled: bool,
+    stress_test_mode: bool,
+    market_authority_bump: u8,
+}
+
+impl MarketV0 {
+    const LEN: usize = 32
+        + 32
+        + 8
+        + 8
+        + 1
+        + 1
+        + 8
+        + 8
+        + 32
+        + 32
+        + (1 + 32)
+        + (1 + 32)
+        + 32
+        + 1
+        + 9
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 1
+        + 1
+        + 1;
+}
+
+// 版本0的开放订单布局
+#[derive(AnchorSerialize, AnchorDeserialize)]
+struct OpenOrdersV0 {
+    owner: Pubkey,
+    market: Pubkey,
+    locked_base_tokens: u64,
+    locked_quote_tokens: u64,
+    filled_base_quantity: u64,
+    filled_quote_quantity: u64,
+    fees_paid: u64,
+    lp_tokens_earned: u64,
+    reward_tokens_earned: u64,
+    last_update_slot: u64,
+    strategies_count: u8,
+    bump: u8,
+    orders_bitmap: [u64; 4],
+    order_count: u16,
+}
+
+impl OpenOrdersV0 {
+    const LEN: usize = 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + (8 * 4) + 2;
+}
+
+// 版本0订单簿的分段大小：订单节点没有side字段，末尾没有订单哈希索引
+const ORDER_NODES: usize = 1024;
+const ORDER_NODE_V0_SIZE: usize = 16 + 32 + 8 + 4 + 4 + 4 + 8 + 8;
+const ORDER_BOOK_HEAD_SIZE: usize = 32 + 4 + 4 + (256 * mem::size_of::<PriceNode>());
+const ORDER_BOOK_TAIL_SIZE: usize = (256 * 4) + (1024 * 4) + 4 + 4 + 4 + 4 + 8 + 8 + 8 + 8;
+const ORDER_BOOK_V0_SPACE: usize =
+    8 + 8 + ORDER_BOOK_HEAD_SIZE + (ORDER_NODES * ORDER_NODE_V0_SIZE) + ORDER_BOOK_TAIL_SIZE;
+
+impl AccountMigration {
+    // 校验账户鉴别器与声明的账户类型一致
+    fn check_discriminator(info: &AccountInfo, discriminator: [u8; 8]) -> Result<()> {
+        let data = info.try_borrow_data()?;
+        require!(
+            data.len() >= 8 && data[..8] == discriminator,
+            ErrorCode::UnknownAccountLayout
+        );
+        Ok(())
+    }
+
+    // 根据账户大小识别布局版本（版本号位于鉴别器之后的第一个字节）
+    fn detect_version(info: &AccountInfo, legacy_space: usize, current_space: usize) -> Result<u8> {
+        let data = info.try_borrow_data()?;
+        if data.len() == legacy_space {
+            return Ok(0);
+        }
+        require!(data.len() == current_space, ErrorCode::UnknownAccountLayout);
+        Ok(data[8])
+    }
+
+    // 扩容账户，不足的租金由payer补齐
+    fn grow_account<'info>(
+        info: &AccountInfo<'info>,
+        payer: &AccountInfo<'info>,
+        system_program: &AccountInfo<'info>,
+        new_space: usize,
+    ) -> Result<()> {
+        let required_lamports = Rent::get()?.minimum_balance(new_space);
+        let current_lamports = info.lamports();
+        if required_lamports > current_lamports {
+            system_program::transfer(
+                CpiContext::new(
+                    system_program.clone(),
+                    SystemTransfer {
+                        from: payer.clone(),
+                        to: info.clone(),
+                    },
+                ),
+                required_lamports - current_lamports,
+            )?;
+        }
+
+        info.realloc(new_space, true)?;
+        Ok(())
+    }
+
+    // 迁移市场账户
+    pub fn migrate_market<'info>(
+        info: &AccountInfo<'info>,
+        payer: &AccountInfo<'info>,
+        system_program: &AccountInfo<'info>,
+    ) -> Result<u8> {
+        Self::check_discriminator(info, Market::discriminator())?;
+        let version = Self::detect_version(info, 8 + MarketV0::LEN, 8 + Market::LEN)?;
+        require!(
+            version < CURRENT_SCHEMA_VERSION,
+            ErrorCode::AccountAlreadyMigrated
+        );
+
+        let legacy = MarketV0::deserialize(&mut &info.try_borrow_data()?[8..])?;
+        let market = Market {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            base_mint: legacy.base_mint,
+            quote_mint: legacy.quote_mint,
+            lot_size: legacy.lot_size,
+            tick_size: legacy.tick_size,
+            base_decimals: legacy.base_decimals,
+            quote_decimals: legacy.quote_decimals,
+            maker_fee: legacy.maker_fee,
+            taker_fee: legacy.taker_fee,
+            base_vault: legacy.base_vault,
+            quote_vault: legacy.quote_vault,
+            lp_token_mint: legacy.lp_token_mint,
+            reward_mint: legacy.reward_mint,
+            name: legacy.name,
+            active: legacy.active,
+            last_traded_price: legacy.last_traded_price,
+            total_volume: legacy.total_volume,
+            total_quote_volume: 0, // 旧布局未记录成交额
+            total_trades: 0,
+            total_fees_collected: legacy.total_fees_collected,
+            min_base_order_size: legacy.min_base_order_size,
+            min_quote_order_size: legacy.min_quote_order_size,
+            lp_reward_rate: legacy.lp_reward_rate,
+            cross_chain_enabled: legacy.cross_chain_enabled,
+            stress_test_mode: legacy.stress_test_mode,
+            market_authority_bump: legacy.market_authority_bump,
+            access_control: None, // 旧市场不限制交易钱包
+            event_sequence: 0,
+        };
+
+        Self::grow_account(info, payer, system_program, 8 + Market::LEN)?;
+        let mut data = info.try_borrow_mut_data()?;
+        let mut writer: &mut [u8] = &mut data[..];
+        market.try_serialize(&mut writer)?;
+
+        Ok(version)
+    }
+
+    // 迁移开放订单账户
+    pub fn migrate_open_orders<'info>(
+        info: &AccountInfo<'info>,
+        payer: &AccountInfo<'info>,
+        system_program: &AccountInfo<'info>,
+    ) -> Result<u8> {
+        Self::check_discriminator(info, OpenOrders::discriminator())?;
+        let version = Self::detect_version(info, 8 + OpenOrdersV0::LEN, 8 + OpenOrders::LEN)?;
+        require!(
+            version < CURRENT_SCHEMA_VERSION,
+            ErrorCode::AccountAlreadyMigrated
+        );
+
+        let legacy = OpenOrdersV0::deserialize(&mut &info.try_borrow_data()?[8..])?;
+        // 心跳、反刷单统计和限额计数从零开始
+        let open_orders = OpenOrders {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            owner: legacy.owner,
+            market: legacy.market,
+            locked_base_tokens: legacy.locked_base_tokens,
+            locked_quote_tokens: legacy.locked_quote_tokens,
+            filled_base_quantity: legacy.filled_base_quantity,
+            filled_quote_quantity: legacy.filled_quote_quantity,
+            fees_paid: legacy.fees_paid,
+            lp_tokens_earned: legacy.lp_tokens_earned,
+            reward_tokens_earned: legacy.reward_tokens_earned,
+            last_update_slot: legacy.last_update_slot,
+            strategies_count: legacy.strategies_count,
+            bump: legacy.bump,
+            orders_bitmap: legacy.orders_bitmap,
+            order_count: legacy.order_count,
+            heartbeat_timeout_secs: 0,
+            last_heartbeat_ts: 0,
+            keeper_fee_lamports: 0,
+            activity_epoch: 0,
+            epoch_placements: 0,
+            epoch_cancels: 0,
+            epoch_fills: 0,
+            last_place_slot: 0,
+            maker_score_epoch: 0,
+            maker_score: 0,
+            maker_rewards_owed: 0,
+            trading_limits: UserTradingLimits::default(),
+        };
+
+        Self::grow_account(info, payer, system_program, 8 + OpenOrders::LEN)?;
+        let mut data = info.try_borrow_mut_data()?;
+        let mut writer: &mut [u8] = &mut data[..];
+        open_orders.try_serialize(&mut writer)?;
+
+        Ok(version)
+    }
+
+    // 迁移订单簿账户
+    // 订单簿为零拷贝布局，直接在账户数据中搬移字段：插入版本号、为每个订单节点补side字段、
+    // 追加哈希索引，共增加 1 + 1024 + 8192 字节，不超过单条指令10KB的扩容上限
+    pub fn migrate_order_book<'info>(
+        info: &AccountInfo<'info>,
+        payer: &AccountInfo<'info>,
+        system_program: &AccountInfo<'info>,
+    ) -> Result<u8> {
+        Self::check_discriminator(info, OrderBook::discriminator())?;
+        let version = Self::detect_version(info, ORDER_BOOK_V0_SPACE, 8 + OrderBook::LEN)?;
+        require!(
+            version < CURRENT_SCHEMA_VERSION,
+            ErrorCode::AccountAlreadyMigrated
+        );
+
+        Self::grow_account(info, payer, system_program, 8 + OrderBook::LEN)?;
+        {
+            let mut data = info.try_borrow_mut_data()?;
+            let node_size = mem::size_of::<OrderNode>();
+            let old_nodes = 8 + ORDER_BOOK_HEAD_SIZE;
+            let new_nodes = 9 + ORDER_BOOK_HEAD_SIZE;
+            let old_tail = old_nodes + ORDER_NODES * ORDER_NODE_V0_SIZE;
+            let new_tail = new_nodes + ORDER_NODES * node_size;
+
+            // 从后往前搬移，避免覆盖尚未搬移的数据
+            data.copy_within(old_tail..old_tail + ORDER_BOOK_TAIL_SIZE, new_tail);
+            for i in (0..ORDER_NODES).rev() {
+                let src = old_nodes + i * ORDER_NODE_V0_SIZE;
+                let dst = new_nodes + i * node_size;
+                data.copy_within(src..src + ORDER_NODE_V0_SIZE, dst);
+                data[dst + ORDER_NODE_V0_SIZE] = 0; // side稍后根据价格树回填
+            }
+            data.copy_within(8..8 + ORDER_BOOK_HEAD_SIZE, 9);
+            data[8] = CURRENT_SCHEMA_VERSION;
+        }
+
+        // 回填订单方向并重建订单ID哈希索引
+        let order_book = AccountLoader::<OrderBook>::try_from(info)?;
+        order_book.load_mut()?.rebuild_order_index();
+
+        Ok(version)
+    }
+
+    // 迁移风控参数账户
+    // 版本号、bump和反刷单参数取自原保留字段，大小不变；旧账户保留字段全为0，反刷单读出为未启用。
+    // 只能原地迁移位于市场PDA上的账户，密钥对地址上的账户需用migrate_risk_parameters_to_pda复制到PDA
+    pub fn migrate_risk_parameters(info: &AccountInfo) -> Result<u8> {
+        let mut risk_params = Account::<RiskParameters>::try_from(info)?;
+        let version = risk_params.schema_version;
+        require!(
+            version < CURRENT_SCHEMA_VERSION,
+            ErrorCode::AccountAlreadyMigrated
+        );
+
+        let (pda, bump) = Pubkey::find_program_address(
+            &[b"risk_parameters", risk_params.market.as_ref()],
+            &crate::ID,
+        );
+        require_keys_eq!(info.key(), pda, ErrorCode::RiskParametersNotPda);
+
+        risk_params.schema_version = CURRENT_SCHEMA_VERSION;
+        risk_params.bump = bump;
+        risk_params.exit(&crate::ID)?;
+
+        Ok(version)
+    }
+}
+
+// Anchor账户验证结构定义
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    /// CHECK: 鉴别器和布局在处理函数中按account_type校验
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+
+    // 迁移只做确定性的布局转换，任何人都可以发起，扩容所需租金由payer支付
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// 将账户迁移到当前布局版本
+pub fn migrate_account(
+    ctx: Context<MigrateAccount>,
+    account_type: MigratableAccount,
+) -> Result<()> {
+    let info = ctx.accounts.account.to_account_info();
+    let payer = ctx.accounts.payer.to_account_info();
+    let system_program = ctx.accounts.system_program.to_account_info();
+    let old_size = info.data_len();
+
+    let from_version = match account_type {
+        MigratableAccount::Market => {
+            AccountMigration::migrate_market(&info, &payer, &system_program)?
+        }
+        MigratableAccount::OpenOrders => {
+            AccountMigration::migrate_open_orders(&info, &payer, &system_program)?
+        }
+        MigratableAccount::OrderBook => {
+            AccountMigration::migrate_order_book(&info, &payer, &system_program)?
+        }
+        MigratableAccount::RiskParameters => AccountMigration::migrate_risk_parameters(&info)?,
+    };
+
+    EventHandler::emit_account_migrated(
+        info.key(),
+        account_type,
+        from_version,
+        CURRENT_SCHEMA_VERSION,
+        old_size as u32,
+        info.data_len() as u32,
+    );
+
+    Ok(())
+}
+
+// 将按密钥对地址创建的旧风控参数账户复制到PDA所需的账户
+// 引入PDA之前任何人都可以为任意市场创建风控参数账户，因此与initialize_risk_parameters一样只允许程序升级权限执行
+#[derive(Accounts)]
+pub struct MigrateRiskParametersToPda<'info> {
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        close = legacy_authority,
+        constraint = legacy_risk_parameters.market == market.key() @ ErrorCode::InvalidMarketId
+    )]
+    pub legacy_risk_parameters: Account<'info, RiskParameters>,
+
+    /// CHECK: 旧账户记录的管理员，只接收关闭旧账户退回的租金
+    #[account(mut, address = legacy_risk_parameters.authority)]
+    pub legacy_authority: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + mem::size_of::<RiskParameters>(),
+        seeds = [b"risk_parameters", market.key().as_ref()],
+        bump
+    )]
+    pub risk_parameters: Account<'info, RiskParameters>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::DexCore>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key())
+            @ ErrorCode::UnauthorizedOperation
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// 将旧风控参数复制到PDA并关闭旧账户
+// 旧账户的参数和管理员原样保留，只写入当前版本号和PDA的bump
+pub fn migrate_risk_parameters_to_pda(ctx: Context<MigrateRiskParametersToPda>) -> Result<()> {
+    let legacy = &ctx.accounts.legacy_risk_parameters;
+    let from_version = legacy.schema_version;
+
+    let risk_params = &mut ctx.accounts.risk_parameters;
+    risk_params.set_inner(RiskParameters {
+        schema_version: CURRENT_SCHEMA_VERSION,
+        bump: *ctx.bumps.get("risk_parameters").unwrap(),
+        ..(**legacy).clone()
+    });
+
+    let size = risk_params.to_account_info().data_len() as u32;
+    EventHandler::emit_account_migrated(
+        risk_params.key(),
+        MigratableAccount::RiskParameters,
+        from_version,
+        CURRENT_SCHEMA_VERSION,
+        size,
+        size,
+    );
+
+    Ok(())
+}
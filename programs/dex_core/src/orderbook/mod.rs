@@ -1,4 +1,5 @@
 use crate::core::SelfTradeBehavior;
+use crate::migration::CURRENT_SCHEMA_VERSION;
 use crate::ErrorCode;
 use anchor_lang::prelude::*;
 use std::cmp;
@@ -85,6 +86,7 @@ pub struct Trade {
 #[account(zero_copy)]
 #[repr(packed)]
 pub struct OrderBook {
+    pub schema_version: u8, // 账户布局版本
     pub market: Pubkey, // 所属市场
 
     // 存储价格树的根节点索引
@@ -110,8 +112,15 @@ pub struct OrderBook {
     // 最后更新信息
     pub last_update_slot: u64,
     pub last_purge_slot: u64, // 上次清理过期订单的slot
+
+    // 订单ID -> 订单节点索引的开放寻址哈希表（线性探测，空槽为u32::MAX）
+    pub order_index: [u32; ORDER_INDEX_SLOTS],
 }
 
+// 哈希表槽位数，为订单节点数的两倍以保持负载因子不超过0.5
+pub const ORDER_INDEX_SLOTS: usize = 2048;
+const EMPTY_SLOT: u32 = u32::MAX;
+
 // 价格节点 - 表示单个价格层级
 #[zero_copy]
 #[repr(packed)]
@@ -139,10 +148,12 @@ pub struct OrderNode {
     pub prev: u32,         // 同一价格下的上一个订单
     pub timestamp: i64,    // 时间戳
     pub max_ts_valid: i64, // 最大有效时间戳
+    pub side: u8,          // 订单方向 (0=买, 1=卖)
 }
 
 impl OrderBook {
     pub const LEN: usize = 8
+        + 1
         + 32
         + 4
         + 4
@@ -157,10 +168,13 @@ impl OrderBook {
         + 8
         + 8
         + 8
-        + 8;
+        + 8
+        + (ORDER_INDEX_SLOTS * 4);
 
     // 初始化订单簿
     pub fn initialize(&mut self) {
+        self.schema_version = CURRENT_SCHEMA_VERSION;
+
         // 初始化根节点为无效值
         self.bid_price_tree_root = u32::MAX;
         self.ask_price_tree_root = u32::MAX;
@@ -185,21 +199,29 @@ impl OrderBook {
         // 初始化最后更新信息
         self.last_update_slot = 0;
         self.last_purge_slot = 0;
+
+        // 清空订单哈希索引
+        for i in 0..ORDER_INDEX_SLOTS {
+            self.order_index[i] = EMPTY_SLOT;
+        }
     }
 
     // 处理订单
+    // 返回 (成交列表, 剩余挂单, 是否清理了过期订单)，清理过期订单可能改变前N档价格
     pub fn process_order(
         &mut self,
         order: Order,
         self_trade_behavior: SelfTradeBehavior,
-    ) -> Result<(Vec<Trade>, Option<Order>)> {
+    ) -> Result<(Vec<Trade>, Option<Order>, bool)> {
         // 更新最后更新时间
         self.last_update_slot = Clock::get()?.slot;
 
         // 定期清理过期订单
-        if self.last_update_slot.saturating_sub(self.last_purge_slot) > 100 {
-            self.purge_expired_orders()?;
-        }
+        let purged = if self.last_update_slot.saturating_sub(self.last_purge_slot) > 100 {
+            self.purge_expired_orders()?
+        } else {
+            false
+        };
 
         // 创建可变订单副本
         let mut remaining_order = order.clone();
@@ -226,7 +248,7 @@ impl OrderBook {
             OrderType::PostOnly => {
                 // PostOnly如果会立即成交则被拒绝
                 if self.would_match(remaining_order.side, remaining_order.price) {
-                    return Ok((trades, None));
+                    return Ok((trades, None, purged));
                 }
                 // 添加到订单簿
                 self.add_order(&mut remaining_order)?;
@@ -246,7 +268,7 @@ impl OrderBook {
                     remaining_order.remaining_quantity,
                 ) {
                     // 不能完全成交，取消订单
-                    return Ok((Vec::new(), None));
+                    return Ok((Vec::new(), None, purged));
                 }
 
                 // 尝试撮合
@@ -256,16 +278,16 @@ impl OrderBook {
                 if remaining_order.remaining_quantity > 0 {
                     // 未能全部成交，清除所有交易并返回取消
                     trades.clear();
-                    return Ok((trades, None));
+                    return Ok((trades, None, purged));
                 }
             }
         }
 
         // 如果订单完全成交，返回None表示没有剩余订单
         if remaining_order.remaining_quantity == 0 {
-            Ok((trades, None))
+            Ok((trades, None, purged))
         } else {
-            Ok((trades, Some(remaining_order)))
+            Ok((trades, Some(remaining_order), purged))
         }
     }
 
@@ -563,6 +585,7 @@ impl OrderBook {
         order_node.max_ts_valid = order.max_ts_valid;
         order_node.next = u32::MAX;
         order_node.prev = u32::MAX;
+        order_node.side = order.side as u8;
 
         // 查找或创建价格节点
         let price_idx = self.find_or_create_price_node(order.price, order.side)?;
@@ -587,6 +610,9 @@ impl OrderBook {
         price_node.quantity += order.remaining_quantity;
         price_node.orders_count += 1;
 
+        // 加入订单哈希索引
+        self.index_insert(order.order_id, order_idx);
+
         // 更新全局统计
         match order.side {
             Side::Bid => {
@@ -715,51 +741,27 @@ impl OrderBook {
         // 更新最后更新时间
         self.last_update_slot = Clock::get()?.slot;
 
-        // 根据订单方向选择价格树根节点
-        let root_idx = match side {
-            Side::Bid => self.bid_price_tree_root,
-            Side::Ask => self.ask_price_tree_root,
-        };
-
-        if root_idx == u32::MAX {
-            return Err(ErrorCode::OrderNotFound.into());
-        }
+        // 通过哈希索引直接定位订单节点
+        let order_idx = self
+            .find_order_index(order_id)
+            .ok_or(ErrorCode::OrderNotFound)?;
+        let order_node = &self.order_nodes[order_idx as usize];
+        require!(order_node.side == side as u8, ErrorCode::OrderNotFound);
 
-        // 遍历价格树查找订单
-        let mut found = false;
-        let mut price_idx = u32::MAX;
-        let mut order_idx = u32::MAX;
-        let mut prev_order_idx = u32::MAX;
-        let mut price = 0;
-        let mut quantity = 0;
-        let mut owner = Pubkey::default();
-
-        // 查找订单的函数
-        self.find_order(
-            root_idx,
-            order_id,
-            &mut found,
-            &mut price_idx,
-            &mut order_idx,
-            &mut prev_order_idx,
-            &mut price,
-            &mut quantity,
-            &mut owner,
-        )?;
-
-        if !found {
-            return Err(ErrorCode::OrderNotFound.into());
-        }
+        let price_idx = order_node.price_index;
+        let prev_order_idx = order_node.prev;
 
-        // 创建订单对象
-        let order = Order::new(
+        // 创建订单对象，保留下单时间和有效期
+        let mut order = Order::new(
             order_id,
-            owner,
+            order_node.owner,
             side,
-            price,
-            quantity,
+            self.price_nodes[price_idx as usize].price,
+            order_node.quantity,
             OrderType::Limit, // 默认为限价单
         );
+        order.timestamp = order_node.timestamp;
+        order.max_ts_valid = order_node.max_ts_valid;
 
         // 从订单簿中移除订单
         self.remove_order_node(price_idx, order_idx, prev_order_idx)?;
@@ -772,63 +774,202 @@ impl OrderBook {
         Ok(order)
     }
 
-    // 查找订单
-    fn find_order(
-        &self,
-        root_idx: u32,
-        order_id: u128,
-        found: &mut bool,
-        price_idx: &mut u32,
-        order_idx: &mut u32,
-        prev_order_idx: &mut u32,
-        price: &mut u64,
-        quantity: &mut u64,
-        owner: &mut Pubkey,
-    ) -> Result<()> {
-        // 使用非递归方法遍历价格树
+    // 通过哈希索引查找订单节点索引
+    pub fn find_order_index(&self, order_id: u128) -> Option<u32> {
+        let mut slot = Self::index_home(order_id);
+        for _ in 0..ORDER_INDEX_SLOTS {
+            let order_idx = self.order_index[slot];
+            if order_idx == EMPTY_SLOT {
+                return None;
+            }
+            if self.order_nodes[order_idx as usize].order_id == order_id {
+                return Some(order_idx);
+            }
+            slot = (slot + 1) & (ORDER_INDEX_SLOTS - 1);
+        }
+        None
+    }
+
+    // 订单ID的初始哈希槽位（订单ID本身是哈希值，直接折叠即可）
+    fn index_home(order_id: u128) -> usize {
+        ((order_id as u64) ^ ((order_id >> 64) as u64)) as usize & (ORDER_INDEX_SLOTS - 1)
+    }
+
+    // 插入哈希索引（槽位数是订单节点数的两倍，总能找到空槽）
+    fn index_insert(&mut self, order_id: u128, order_idx: u32) {
+        let mut slot = Self::index_home(order_id);
+        while self.order_index[slot] != EMPTY_SLOT {
+            slot = (slot + 1) & (ORDER_INDEX_SLOTS - 1);
+        }
+        self.order_index[slot] = order_idx;
+    }
+
+    // 从哈希索引删除，使用向后移位删除而不是墓碑，避免探测链随时间变长
+    fn index_remove(&mut self, order_id: u128) {
+        let mask = ORDER_INDEX_SLOTS - 1;
+        let mut hole = Self::index_home(order_id);
+        loop {
+            let order_idx = self.order_index[hole];
+            if order_idx == EMPTY_SLOT {
+                return; // 不在索引中
+            }
+            if self.order_nodes[order_idx as usize].order_id == order_id {
+                break;
+            }
+            hole = (hole + 1) & mask;
+        }
+
+        // 将后续探测链中可以前移的条目移入空洞
+        let mut next = (hole + 1) & mask;
+        loop {
+            let order_idx = self.order_index[next];
+            if order_idx == EMPTY_SLOT {
+                break;
+            }
+            let home = Self::index_home(self.order_nodes[order_idx as usize].order_id);
+            // 条目的初始槽位不在(hole, next]区间内时可以移到hole
+            let movable = if hole <= next {
+                home <= hole || home > next
+            } else {
+                home <= hole && home > next
+            };
+            if movable {
+                self.order_index[hole] = order_idx;
+                hole = next;
+            }
+            next = (next + 1) & mask;
+        }
+        self.order_index[hole] = EMPTY_SLOT;
+    }
+
+    // 收集某个用户在指定方向上的挂单ID（最多limit个）
+    pub fn collect_owner_orders(&self, owner: &Pubkey, side: Side, limit: usize) -> Vec<u128> {
+        let root_idx = match side {
+            Side::Bid => self.bid_price_tree_root,
+            Side::Ask => self.ask_price_tree_root,
+        };
+
+        let mut result = Vec::new();
         let mut stack = Vec::new();
         let mut current = root_idx;
 
-        while !*found && (current != u32::MAX || !stack.is_empty()) {
+        // 使用非递归方法遍历价格树
+        while result.len() < limit && (current != u32::MAX || !stack.is_empty()) {
             while current != u32::MAX {
                 stack.push(current);
                 current = self.price_nodes[current as usize].left;
             }
 
-            if !stack.is_empty() {
-                current = stack.pop().unwrap();
+            if let Some(price_idx) = stack.pop() {
+                let price_node = &self.price_nodes[price_idx as usize];
+                let mut order_idx = price_node.first_order;
 
-                // 检查当前价格节点下的所有订单
-                let price_node = &self.price_nodes[current as usize];
-                let mut curr_order_idx = price_node.first_order;
-                let mut prev_idx = u32::MAX;
-
-                while curr_order_idx != u32::MAX {
-                    let order_node = &self.order_nodes[curr_order_idx as usize];
-
-                    if order_node.order_id == order_id {
-                        // 找到订单
-                        *found = true;
-                        *price_idx = current;
-                        *order_idx = curr_order_idx;
-                        *prev_order_idx = prev_idx;
-                        *price = price_node.price;
-                        *quantity = order_node.quantity;
-                        *owner = order_node.owner;
-                        return Ok(());
+                while order_idx != u32::MAX && result.len() < limit {
+                    let order_node = &self.order_nodes[order_idx as usize];
+                    if order_node.owner == *owner {
+                        result.push(order_node.order_id);
                     }
-
-                    // 移动到下一个订单
-                    prev_idx = curr_order_idx;
-                    curr_order_idx = order_node.next;
+                    order_idx = order_node.next;
                 }
 
-                // 继续遍历右子树
                 current = price_node.right;
             }
         }
 
-        Ok(())
+        result
+    }
+
+    // 统计价格在[min_price, max_price]内、未过期的挂单，按所有者汇总 剩余数量 × 挂单秒数
+    // 挂单秒数从window_start和下单时间中较晚的一个算起，窗口内新挂出的订单只按实际挂单时长计入
+    pub fn resting_score_by_owner(
+        &self,
+        min_price: u64,
+        max_price: u64,
+        window_start: i64,
+        current_ts: i64,
+    ) -> Vec<(Pubkey, u128)> {
+        let mut result: Vec<(Pubkey, u128)> = Vec::new();
+
+        for root_idx in [self.bid_price_tree_root, self.ask_price_tree_root] {
+            let mut stack = Vec::new();
+            if root_idx != u32::MAX {
+                stack.push(root_idx);
+            }
+
+            while let Some(price_idx) = stack.pop() {
+                let price_node = &self.price_nodes[price_idx as usize];
+                let price = price_node.price;
+
+                // 两侧价格树的左右方向相反，这里遍历全部节点，只按价格过滤
+                if price_node.left != u32::MAX {
+                    stack.push(price_node.left);
+                }
+                if price_node.right != u32::MAX {
+                    stack.push(price_node.right);
+                }
+                if price < min_price || price > max_price {
+                    continue;
+                }
+
+                let mut order_idx = price_node.first_order;
+                while order_idx != u32::MAX {
+                    let order_node = &self.order_nodes[order_idx as usize];
+                    let max_ts_valid = order_node.max_ts_valid;
+                    let expired = max_ts_valid > 0 && current_ts > max_ts_valid;
+                    let rested_from = cmp::max(order_node.timestamp, window_start);
+                    let rested_secs = current_ts.saturating_sub(rested_from).max(0) as u128;
+                    if !expired && order_node.quantity > 0 && rested_secs > 0 {
+                        let owner = order_node.owner;
+                        let score = order_node.quantity as u128 * rested_secs;
+                        match result.iter_mut().find(|(o, _)| *o == owner) {
+                            Some((_, total)) => *total = total.saturating_add(score),
+                            None => result.push((owner, score)),
+                        }
+                    }
+                    order_idx = order_node.next;
+                }
+            }
+        }
+
+        result
+    }
+
+    // 根据价格树回填订单方向并重建哈希索引（旧布局迁移时使用）
+    pub fn rebuild_order_index(&mut self) {
+        for i in 0..ORDER_INDEX_SLOTS {
+            self.order_index[i] = EMPTY_SLOT;
+        }
+
+        let roots = [
+            (self.bid_price_tree_root, Side::Bid),
+            (self.ask_price_tree_root, Side::Ask),
+        ];
+        for (root_idx, side) in roots {
+            let mut stack = Vec::new();
+            if root_idx != u32::MAX {
+                stack.push(root_idx);
+            }
+
+            // 遍历顺序无关，使用先序遍历即可
+            while let Some(price_idx) = stack.pop() {
+                let price_node = self.price_nodes[price_idx as usize];
+                let mut order_idx = price_node.first_order;
+
+                while order_idx != u32::MAX {
+                    self.order_nodes[order_idx as usize].side = side as u8;
+                    let order_id = self.order_nodes[order_idx as usize].order_id;
+                    self.index_insert(order_id, order_idx);
+                    order_idx = self.order_nodes[order_idx as usize].next;
+                }
+
+                if price_node.left != u32::MAX {
+                    stack.push(price_node.left);
+                }
+                if price_node.right != u32::MAX {
+                    stack.push(price_node.right);
+                }
+            }
+        }
     }
 
     // 查找或创建价格节点
@@ -1034,6 +1175,8 @@ impl OrderBook {
         let order_node = &self.order_nodes[order_idx as usize];
         let next_order_idx = order_node.next;
         let order_quantity = order_node.quantity;
+        let order_id = order_node.order_id;
+        let order_side = order_node.side;
 
         // 更新链表
         if prev_order_idx == u32::MAX {
@@ -1055,25 +1198,17 @@ impl OrderBook {
         self.price_nodes[price_idx as usize].orders_count -= 1;
 
         // 更新全局统计
-        let side = if self.bid_price_tree_root == price_idx
-            || self.is_in_tree(self.bid_price_tree_root, price_idx)
-        {
-            Side::Bid
+        if order_side == Side::Bid as u8 {
+            self.bid_orders_count -= 1;
+            self.bid_volume -= order_quantity;
         } else {
-            Side::Ask
-        };
-
-        match side {
-            Side::Bid => {
-                self.bid_orders_count -= 1;
-                self.bid_volume -= order_quantity;
-            }
-            Side::Ask => {
-                self.ask_orders_count -= 1;
-                self.ask_volume -= order_quantity;
-            }
+            self.ask_orders_count -= 1;
+            self.ask_volume -= order_quantity;
         }
 
+        // 从订单哈希索引中移除
+        self.index_remove(order_id);
+
         // 回收订单节点
         self.free_order_nodes[self.free_order_nodes_count as usize] = order_idx;
         self.free_order_nodes_count += 1;
@@ -1081,20 +1216,6 @@ impl OrderBook {
         Ok(())
     }
 
-    // 检查节点是否在树中
-    fn is_in_tree(&self, root: u32, node_idx: u32) -> bool {
-        if root == u32::MAX {
-            return false;
-        }
-
-        if root == node_idx {
-            return true;
-        }
-
-        self.is_in_tree(self.price_nodes[root as usize].left, node_idx)
-            || self.is_in_tree(self.price_nodes[root as usize].right, node_idx)
-    }
-
     // 移除价格节点
     fn remove_price_node(&mut self, node_idx: u32, side: Side) -> Result<()> {
         // 选择合适的价格树根节点
@@ -1131,49 +1252,51 @@ impl OrderBook {
     }
 
     // 从树中删除节点
+    // 通过父指针移接子树，节点本身不移动，订单节点的price_index和价格链表保持有效
     fn delete_node_from_tree(&mut self, root: u32, node_idx: u32) -> Result<u32> {
         if root == u32::MAX {
             return Ok(u32::MAX);
         }
 
-        if node_idx < root {
-            self.price_nodes[root as usize].left =
-                self.delete_node_from_tree(self.price_nodes[root as usize].left, node_idx)?;
-        } else if node_idx > root {
-            self.price_nodes[root as usize].right =
-                self.delete_node_from_tree(self.price_nodes[root as usize].right, node_idx)?;
-        } else {
-            // 找到要删除的节点
+        let mut root = root;
+        let left = self.price_nodes[node_idx as usize].left;
+        let right = self.price_nodes[node_idx as usize].right;
 
-            // 没有子节点或只有一个子节点
-            if self.price_nodes[root as usize].left == u32::MAX {
-                return Ok(self.price_nodes[root as usize].right);
-            } else if self.price_nodes[root as usize].right == u32::MAX {
-                return Ok(self.price_nodes[root as usize].left);
+        if left == u32::MAX {
+            self.transplant(&mut root, node_idx, right);
+        } else if right == u32::MAX {
+            self.transplant(&mut root, node_idx, left);
+        } else {
+            // 有两个子节点，用右子树中最左的节点替代
+            let successor = self.find_min_price_node(right)?;
+            if self.price_nodes[successor as usize].parent != node_idx {
+                let successor_right = self.price_nodes[successor as usize].right;
+                self.transplant(&mut root, successor, successor_right);
+                self.price_nodes[successor as usize].right = right;
+                self.price_nodes[right as usize].parent = successor;
             }
+            self.transplant(&mut root, node_idx, successor);
+            self.price_nodes[successor as usize].left = left;
+            self.price_nodes[left as usize].parent = successor;
+        }
 
-            // 有两个子节点
-            // 找到右子树中的最小节点作为替代
-            let mut successor = self.price_nodes[root as usize].right;
-            while self.price_nodes[successor as usize].left != u32::MAX {
-                successor = self.price_nodes[successor as usize].left;
-            }
+        Ok(root)
+    }
 
-            // 复制继任者的数据到当前节点
-            self.price_nodes[root as usize].price = self.price_nodes[successor as usize].price;
-            self.price_nodes[root as usize].quantity =
-                self.price_nodes[successor as usize].quantity;
-            self.price_nodes[root as usize].orders_count =
-                self.price_nodes[successor as usize].orders_count;
-            self.price_nodes[root as usize].first_order =
-                self.price_nodes[successor as usize].first_order;
-
-            // 从右子树中删除继任者
-            self.price_nodes[root as usize].right =
-                self.delete_node_from_tree(self.price_nodes[root as usize].right, successor)?;
+    // 用子树new_idx替换子树old_idx在父节点中的位置
+    fn transplant(&mut self, root: &mut u32, old_idx: u32, new_idx: u32) {
+        let parent = self.price_nodes[old_idx as usize].parent;
+        if parent == u32::MAX {
+            *root = new_idx;
+        } else if self.price_nodes[parent as usize].left == old_idx {
+            self.price_nodes[parent as usize].left = new_idx;
+        } else {
+            self.price_nodes[parent as usize].right = new_idx;
         }
 
-        Ok(root)
+        if new_idx != u32::MAX {
+            self.price_nodes[new_idx as usize].parent = parent;
+        }
     }
 
     // 查找最小价格节点
@@ -1218,54 +1341,53 @@ impl OrderBook {
             self.price_nodes[price_idx as usize].quantity - old_quantity + new_quantity;
 
         // 更新全局统计
-        let side = if self.bid_price_tree_root == price_idx
-            || self.is_in_tree(self.bid_price_tree_root, price_idx)
-        {
-            Side::Bid
+        if self.order_nodes[order_idx as usize].side == Side::Bid as u8 {
+            self.bid_volume = self.bid_volume - old_quantity + new_quantity;
         } else {
-            Side::Ask
-        };
-
-        match side {
-            Side::Bid => {
-                self.bid_volume = self.bid_volume - old_quantity + new_quantity;
-            }
-            Side::Ask => {
-                self.ask_volume = self.ask_volume - old_quantity + new_quantity;
-            }
+            self.ask_volume = self.ask_volume - old_quantity + new_quantity;
         }
 
         Ok(())
     }
 
-    // 清理过期订单
-    fn purge_expired_orders(&mut self) -> Result<()> {
+    // 清理过期订单，返回是否移除了订单
+    fn purge_expired_orders(&mut self) -> Result<bool> {
         self.last_purge_slot = Clock::get()?.slot;
         let current_ts = Clock::get()?.unix_timestamp;
+        let mut purged = false;
 
         // 清理买单
         if self.bid_price_tree_root != u32::MAX {
-            self.purge_expired_orders_for_side(self.bid_price_tree_root, current_ts, Side::Bid)?;
+            purged |= self.purge_expired_orders_for_side(
+                self.bid_price_tree_root,
+                current_ts,
+                Side::Bid,
+            )?;
         }
 
         // 清理卖单
         if self.ask_price_tree_root != u32::MAX {
-            self.purge_expired_orders_for_side(self.ask_price_tree_root, current_ts, Side::Ask)?;
+            purged |= self.purge_expired_orders_for_side(
+                self.ask_price_tree_root,
+                current_ts,
+                Side::Ask,
+            )?;
         }
 
-        Ok(())
+        Ok(purged)
     }
 
-    // 清理指定方向的过期订单
+    // 清理指定方向的过期订单，返回是否移除了订单
     fn purge_expired_orders_for_side(
         &mut self,
         root: u32,
         current_ts: i64,
         side: Side,
-    ) -> Result<()> {
+    ) -> Result<bool> {
         if root == u32::MAX {
-            return Ok(());
+            return Ok(false);
         }
+        let mut purged = false;
 
         // 非递归实现，使用栈
         let mut stack = Vec::new();
@@ -1293,6 +1415,7 @@ impl OrderBook {
                     if order_node.max_ts_valid > 0 && current_ts > order_node.max_ts_valid {
                         // 移除过期订单
                         self.remove_order_node(price_idx, order_idx, prev_order_idx)?;
+                        purged = true;
                         order_idx = next_order_idx;
                     } else {
                         // 移动到下一个订单
@@ -1318,7 +1441,37 @@ impl OrderBook {
             }
         }
 
-        Ok(())
+        Ok(purged)
+    }
+
+    // 按从优到劣的顺序获取前limit个价格档位 (价格, 数量, 订单数)
+    // 两侧的价格树都把更优的价格放在左子树，中序遍历即为从优到劣
+    pub fn get_price_levels(&self, side: Side, limit: usize) -> Vec<(u64, u64, u32)> {
+        let root_idx = match side {
+            Side::Bid => self.bid_price_tree_root,
+            Side::Ask => self.ask_price_tree_root,
+        };
+
+        let mut result = Vec::with_capacity(limit);
+        let mut stack = Vec::new();
+        let mut current = root_idx;
+
+        while result.len() < limit && (current != u32::MAX || !stack.is_empty()) {
+            while current != u32::MAX {
+                stack.push(current);
+                current = self.price_nodes[current as usize].left;
+            }
+
+            if let Some(price_idx) = stack.pop() {
+                let price_node = &self.price_nodes[price_idx as usize];
+                if price_node.orders_count > 0 {
+                    result.push((price_node.price, price_node.quantity, price_node.orders_count));
+                }
+                current = price_node.right;
+            }
+        }
+
+        result
     }
 
     // 获取市场深度
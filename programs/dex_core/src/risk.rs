@@ -1,6 +1,7 @@
-use crate::core::PlaceOrder;
-use crate::events::{EventHandler, RiskWarningType};
-use crate::orderbook::{Order, Side};
+use crate::core::{Market, OpenOrders, PlaceOrder};
+use crate::events::{EventHandler, RiskWarningType, SystemMetrics, SystemStatusType};
+use crate::migration::CURRENT_SCHEMA_VERSION;
+use crate::orderbook::{Order, OrderBook, Side};
 use crate::ErrorCode;
 use anchor_lang::prelude::*;
 use std::collections::HashMap;
@@ -36,10 +37,23 @@ pub struct RiskParameters {
     pub min_order_size: u64,                  // 最小订单规模
     pub max_order_size: u64,                  // 最大订单规模
     pub is_active: bool,                      // 是否启用风控系统
+    // 反刷单参数（spam_epoch_slots为0表示不启用）
+    pub spam_epoch_slots: u64,            // 统计周期长度（slot）
+    pub max_cancel_to_fill_ratio: u16,    // 最大撤单/成交比（百分比，例如2000表示20:1）
+    pub spam_min_cancels: u16,            // 周期内撤单数低于该值时不处罚
+    pub spam_penalty_mode: u8,            // 处罚方式 (0=收取下单费, 1=限速)
+    pub spam_placement_fee_lamports: u64, // 每笔下单费（lamports）
+    pub spam_throttle_slots: u64,         // 限速时两次下单之间的最小slot间隔
+    pub schema_version: u8,               // 账户布局版本（取自保留字段，旧账户读出为0）
+    pub bump: u8,                         // PDA bump值（取自保留字段）
     // 保留字段，用于将来的扩展
-    pub reserved: [u8; 64],
+    pub reserved: [u8; 33],
 }
 
+// 刷单处罚方式
+pub const SPAM_PENALTY_FEE: u8 = 0;
+pub const SPAM_PENALTY_THROTTLE: u8 = 1;
+
 // 用户风险记录
 #[account]
 #[derive(Default)]
@@ -54,11 +68,14 @@ pub struct UserRiskProfile {
     pub is_restricted: bool,          // 是否受限
     pub markets_traded: [Pubkey; 10], // 交易过的市场
     pub markets_count: u8,            // 交易市场数量
+    pub schema_version: u8,           // 账户布局版本（取自保留字段）
     // 保留字段，用于将来的扩展
-    pub reserved: [u8; 64],
+    pub reserved: [u8; 63],
 }
 
 // 市场风险指标
+// 布局变化前链上不存在该类账户（snapshot_market_metrics之前没有创建它的指令），因此无需迁移
+// 以后再调整布局时需要加入MigratableAccount
 #[account]
 #[derive(Default)]
 pub struct MarketRiskMetrics {
@@ -75,8 +92,43 @@ pub struct MarketRiskMetrics {
     pub max_sell_size: u64,              // 最大卖单规模
     pub avg_execution_time_ms: u32,      // 平均执行时间(毫秒)
     pub active_users_count: u32,         // 活跃用户数
+    // 周期快照（由snapshot_market_metrics维护）
+    pub vwap_24h: u64,                 // 24小时成交均价
+    pub high_24h: u64,                 // 24小时最高价（按快照采样）
+    pub low_24h: u64,                  // 24小时最低价（按快照采样）
+    pub trades_24h: u32,               // 24小时成交笔数
+    pub book_imbalance_bps: i32,       // 买卖盘失衡度（基点，正值表示买盘更厚）
+    pub last_snapshot_ts: i64,         // 上次快照时间
+    pub last_total_volume: u64,        // 上次快照时市场累计成交量
+    pub last_total_quote_volume: u128, // 上次快照时市场累计成交额
+    pub last_total_trades: u64,        // 上次快照时市场累计成交笔数
+    // 按小时聚合的环形缓冲区，下标为hour % METRICS_BUCKET_COUNT
+    pub hourly_buckets: [MetricsBucket; METRICS_BUCKET_COUNT],
+    pub schema_version: u8, // 账户布局版本（取自保留字段）
     // 保留字段，用于将来的扩展
-    pub reserved: [u8; 64],
+    pub reserved: [u8; 63],
+}
+
+// 24小时窗口按小时分桶
+pub const METRICS_BUCKET_COUNT: usize = 24;
+const METRICS_BUCKET_SECONDS: i64 = 3600;
+
+// 两次指标快照之间的最小slot间隔（约1分钟）
+pub const METRICS_SNAPSHOT_INTERVAL_SLOTS: u64 = 150;
+
+// 订单节点使用率达到该比例（百分比）时报告订单簿不健康
+const ORDER_BOOK_UNHEALTHY_USAGE_PERCENT: u8 = 90;
+
+// 单个小时的成交统计
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct MetricsBucket {
+    pub hour: i64,          // 所属小时（unix时间戳 / 3600）
+    pub open_price: u64,    // 该小时首次快照时的成交价
+    pub high: u64,          // 最高价
+    pub low: u64,           // 最低价
+    pub volume: u64,        // 成交量（基础代币）
+    pub quote_volume: u128, // 成交额（报价代币）
+    pub trades: u32,        // 成交笔数
 }
 
 impl RiskEngine {
@@ -116,6 +168,93 @@ impl RiskEngine {
         Ok(())
     }
 
+    // 进入新的统计周期时重置反刷单计数，所有读写周期计数的路径都要先调用
+    pub fn roll_activity_epoch(
+        risk_params: &RiskParameters,
+        open_orders: &mut OpenOrders,
+        current_slot: u64,
+    ) {
+        if risk_params.spam_epoch_slots == 0 {
+            return;
+        }
+
+        let epoch = current_slot / risk_params.spam_epoch_slots;
+        if epoch != open_orders.activity_epoch {
+            open_orders.activity_epoch = epoch;
+            open_orders.epoch_placements = 0;
+            open_orders.epoch_cancels = 0;
+            open_orders.epoch_fills = 0;
+        }
+    }
+
+    // 记录一次撤单
+    pub fn record_cancel(
+        risk_params: &RiskParameters,
+        open_orders: &mut OpenOrders,
+        current_slot: u64,
+    ) {
+        Self::roll_activity_epoch(risk_params, open_orders, current_slot);
+        open_orders.epoch_cancels = open_orders.epoch_cancels.saturating_add(1);
+    }
+
+    // 记录一次成交（吃单方按订单计，做市方按被成交的挂单计）
+    pub fn record_fill(
+        risk_params: &RiskParameters,
+        open_orders: &mut OpenOrders,
+        current_slot: u64,
+    ) {
+        Self::roll_activity_epoch(risk_params, open_orders, current_slot);
+        open_orders.epoch_fills = open_orders.epoch_fills.saturating_add(1);
+    }
+
+    // 反刷单检查 - 撤单/成交比过高的账户需要支付下单费或被限速
+    // 返回本次下单需要支付的费用（lamports）
+    pub fn check_order_spam(
+        risk_params: &RiskParameters,
+        open_orders: &mut OpenOrders,
+        current_slot: u64,
+    ) -> Result<u64> {
+        if !risk_params.is_active || risk_params.spam_epoch_slots == 0 {
+            return Ok(0);
+        }
+
+        Self::roll_activity_epoch(risk_params, open_orders, current_slot);
+
+        let mut fee = 0;
+        if Self::is_spamming(risk_params, open_orders) {
+            match risk_params.spam_penalty_mode {
+                SPAM_PENALTY_THROTTLE => {
+                    require!(
+                        current_slot
+                            >= open_orders
+                                .last_place_slot
+                                .saturating_add(risk_params.spam_throttle_slots),
+                        ErrorCode::OrderThrottled
+                    );
+                }
+                _ => fee = risk_params.spam_placement_fee_lamports,
+            }
+        }
+
+        open_orders.epoch_placements = open_orders.epoch_placements.saturating_add(1);
+        open_orders.last_place_slot = current_slot;
+
+        Ok(fee)
+    }
+
+    // 判断本周期撤单/成交比是否超过阈值（没有成交时按1笔计算）
+    pub fn is_spamming(risk_params: &RiskParameters, open_orders: &OpenOrders) -> bool {
+        if risk_params.max_cancel_to_fill_ratio == 0
+            || open_orders.epoch_cancels < risk_params.spam_min_cancels as u32
+        {
+            return false;
+        }
+
+        let cancels = open_orders.epoch_cancels as u64 * 100;
+        let fills = open_orders.epoch_fills.max(1) as u64;
+        cancels > fills * risk_params.max_cancel_to_fill_ratio as u64
+    }
+
     // 计算买单所需的报价代币数量
     pub fn calculate_required_quote_for_bid(
         price: u64,
@@ -287,6 +426,14 @@ impl RiskEngine {
         risk_params.min_order_size = 1;
         risk_params.max_order_size = u64::MAX;
         risk_params.is_active = true;
+        risk_params.spam_epoch_slots = 0; // 默认不启用反刷单
+        risk_params.max_cancel_to_fill_ratio = 2000; // 默认20:1
+        risk_params.spam_min_cancels = 100;
+        risk_params.spam_penalty_mode = SPAM_PENALTY_FEE;
+        risk_params.spam_placement_fee_lamports = 5000;
+        risk_params.spam_throttle_slots = 10;
+        risk_params.schema_version = CURRENT_SCHEMA_VERSION;
+        risk_params.bump = *ctx.bumps.get("risk_parameters").unwrap();
 
         Ok(())
     }
@@ -319,6 +466,24 @@ impl RiskEngine {
             9 => risk_params.min_order_size = value,
             10 => risk_params.max_order_size = value,
             11 => risk_params.is_active = value != 0,
+            12 => risk_params.spam_epoch_slots = value,
+            13 => {
+                require!(value <= u16::MAX as u64, ErrorCode::InvalidParameters);
+                risk_params.max_cancel_to_fill_ratio = value as u16
+            }
+            14 => {
+                require!(value <= u16::MAX as u64, ErrorCode::InvalidParameters);
+                risk_params.spam_min_cancels = value as u16
+            }
+            15 => {
+                require!(
+                    value <= SPAM_PENALTY_THROTTLE as u64,
+                    ErrorCode::InvalidParameters
+                );
+                risk_params.spam_penalty_mode = value as u8
+            }
+            16 => risk_params.spam_placement_fee_lamports = value,
+            17 => risk_params.spam_throttle_slots = value,
             _ => return Err(ErrorCode::InvalidParameters.into()),
         }
 
@@ -386,7 +551,7 @@ impl RiskEngine {
 
     // 检测价格异常波动
     pub fn detect_price_anomalies(
-        market: Pubkey,
+        market: &mut Account<Market>,
         current_price: u64,
         avg_price_24h: u64,
         risk_params: &RiskParameters,
@@ -417,7 +582,8 @@ impl RiskEngine {
             };
 
             EventHandler::emit_risk_warning(
-                market,
+                market.key(),
+                market.next_event_seq(),
                 Pubkey::default(), // 系统级警告，无特定用户
                 warning_type,
                 95, // 高严重性
@@ -531,7 +697,7 @@ impl RiskEngine {
 
     // 检测市场操纵行为
     pub fn detect_market_manipulation(
-        market: Pubkey,
+        market: &mut Account<Market>,
         user: Pubkey,
         recent_trades: &[(u64, u64, Side)], // (价格,数量,方向)
         market_metrics: &MarketRiskMetrics,
@@ -571,7 +737,8 @@ impl RiskEngine {
         if wash_trading_suspected || price_manipulation_suspected {
             // 发出风险警告
             EventHandler::emit_risk_warning(
-                market,
+                market.key(),
+                market.next_event_seq(),
                 user,
                 RiskWarningType::MarketManipulation,
                 90, // 高严重性
@@ -679,20 +846,36 @@ impl RiskEngine {
 }
 
 // 初始化风险参数所需的账户
+// 每个市场只有一个风控参数PDA，其管理员同时是该市场各项配置的管理员，
+// 因此只允许程序升级权限创建，避免被抢先初始化；
+// 引入PDA之前按密钥对地址创建的账户通过migrate_risk_parameters_to_pda复制到PDA
 #[derive(Accounts)]
 pub struct InitializeRiskParams<'info> {
-    #[account(mut)]
-    pub market: AccountInfo<'info>,
+    pub market: Account<'info, Market>,
 
     #[account(
         init,
-        payer = authority,
-        space = 8 + std::mem::size_of::<RiskParameters>()
+        payer = admin,
+        space = 8 + std::mem::size_of::<RiskParameters>(),
+        seeds = [b"risk_parameters", market.key().as_ref()],
+        bump
     )]
     pub risk_parameters: Account<'info, RiskParameters>,
 
+    /// CHECK: 市场的风控管理员，只记录其公钥
+    pub authority: UncheckedAccount<'info>,
+
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub admin: Signer<'info>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::DexCore>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key())
+            @ ErrorCode::UnauthorizedOperation
+    )]
+    pub program_data: Account<'info, ProgramData>,
 
     pub system_program: Program<'info, System>,
 }
@@ -700,16 +883,260 @@ pub struct InitializeRiskParams<'info> {
 // 更新风险参数所需的账户
 #[derive(Accounts)]
 pub struct UpdateRiskParams<'info> {
-    #[account(mut)]
-    pub market: AccountInfo<'info>,
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"risk_parameters", market.key().as_ref()],
+        bump = risk_parameters.bump
+    )]
+    pub risk_parameters: Account<'info, RiskParameters>,
 
     #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+// 提取反刷单下单费所需的账户
+#[derive(Accounts)]
+pub struct WithdrawSpamFees<'info> {
+    pub market: Account<'info, Market>,
+
+    // 下单费直接存放在风控参数账户中
+    #[account(
+        mut,
+        seeds = [b"risk_parameters", market.key().as_ref()],
+        bump = risk_parameters.bump,
+        has_one = authority @ ErrorCode::UnauthorizedOperation
+    )]
     pub risk_parameters: Account<'info, RiskParameters>,
 
+    /// CHECK: 只接收lamports
     #[account(mut)]
+    pub destination: UncheckedAccount<'info>,
+
     pub authority: Signer<'info>,
 }
 
+// 提取已收取的反刷单下单费，风控参数账户保留免租金所需的最低余额
+pub fn withdraw_spam_fees(ctx: Context<WithdrawSpamFees>, amount: u64) -> Result<()> {
+    let info = ctx.accounts.risk_parameters.to_account_info();
+    let rent_exempt = Rent::get()?.minimum_balance(info.data_len());
+    let available = info.lamports().saturating_sub(rent_exempt);
+    require!(
+        amount > 0 && amount <= available,
+        ErrorCode::InsufficientFunds
+    );
+
+    **info.try_borrow_mut_lamports()? -= amount;
+    **ctx
+        .accounts
+        .destination
+        .to_account_info()
+        .try_borrow_mut_lamports()? += amount;
+
+    Ok(())
+}
+
+// 生成市场指标快照所需的账户（任何人都可以调用）
+#[derive(Accounts)]
+pub struct SnapshotMarketMetrics<'info> {
+    #[account(mut)] // 用于分配事件序号
+    pub market: Account<'info, Market>,
+
+    #[account(has_one = market @ ErrorCode::InvalidMarketId)]
+    pub order_book: AccountLoader<'info, OrderBook>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + std::mem::size_of::<MarketRiskMetrics>(),
+        seeds = [b"market_metrics", market.key().as_ref()],
+        bump
+    )]
+    pub market_metrics: Account<'info, MarketRiskMetrics>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// 生成市场指标快照：把两次快照之间的成交累加到当前小时桶，再汇总最近24小时
+pub fn snapshot_market_metrics(ctx: Context<SnapshotMarketMetrics>) -> Result<()> {
+    let clock = Clock::get()?;
+    let market = &mut ctx.accounts.market;
+    let metrics = &mut ctx.accounts.market_metrics;
+    let order_book = ctx.accounts.order_book.load()?;
+
+    require!(
+        market.schema_version == CURRENT_SCHEMA_VERSION
+            && order_book.schema_version == CURRENT_SCHEMA_VERSION,
+        ErrorCode::AccountNeedsMigration
+    );
+    let book_market = order_book.market;
+    require_keys_eq!(book_market, market.key(), ErrorCode::InvalidMarketId);
+
+    if metrics.schema_version == 0 {
+        // 首次快照：从市场当前的累计值开始统计
+        metrics.market = market.key();
+        metrics.schema_version = CURRENT_SCHEMA_VERSION;
+        metrics.last_total_volume = market.total_volume;
+        metrics.last_total_quote_volume = market.total_quote_volume;
+        metrics.last_total_trades = market.total_trades;
+        metrics.last_snapshot_ts = clock.unix_timestamp;
+    } else {
+        require!(
+            clock.slot >= metrics.last_update_slot + METRICS_SNAPSHOT_INTERVAL_SLOTS,
+            ErrorCode::MetricsSnapshotTooFrequent
+        );
+    }
+
+    // 两次快照之间新增的成交
+    let volume = market
+        .total_volume
+        .saturating_sub(metrics.last_total_volume);
+    let quote_volume = market
+        .total_quote_volume
+        .saturating_sub(metrics.last_total_quote_volume);
+    let trades = market
+        .total_trades
+        .saturating_sub(metrics.last_total_trades);
+    let elapsed_secs = clock
+        .unix_timestamp
+        .saturating_sub(metrics.last_snapshot_ts);
+    let current_price = market.last_traded_price.unwrap_or(0);
+
+    // 累加到当前小时桶，桶属于更早的小时时先清空
+    let hour = clock.unix_timestamp / METRICS_BUCKET_SECONDS;
+    let bucket = &mut metrics.hourly_buckets[hour as usize % METRICS_BUCKET_COUNT];
+    if bucket.hour != hour {
+        *bucket = MetricsBucket {
+            hour,
+            ..Default::default()
+        };
+    }
+    bucket.volume = bucket.volume.saturating_add(volume);
+    bucket.quote_volume = bucket.quote_volume.saturating_add(quote_volume);
+    bucket.trades = bucket.trades.saturating_add(trades as u32);
+
+    // 高低价按快照时的最新成交价和区间均价采样
+    let mut samples = [current_price, 0];
+    if volume > 0 {
+        samples[1] = (quote_volume / volume as u128) as u64;
+    }
+    for price in samples.into_iter().filter(|&p| p > 0) {
+        if bucket.open_price == 0 {
+            bucket.open_price = price;
+        }
+        bucket.high = bucket.high.max(price);
+        bucket.low = if bucket.low == 0 {
+            price
+        } else {
+            bucket.low.min(price)
+        };
+    }
+
+    // 汇总最近24小时
+    let mut volume_24h = 0u64;
+    let mut quote_volume_24h = 0u128;
+    let mut trades_24h = 0u32;
+    let mut high_24h = 0u64;
+    let mut low_24h = 0u64;
+    let mut open_24h = (i64::MAX, 0u64); // (最早的小时, 开盘价)
+    for b in metrics.hourly_buckets.iter() {
+        if b.hour <= hour - METRICS_BUCKET_COUNT as i64 || b.hour > hour {
+            continue;
+        }
+        volume_24h = volume_24h.saturating_add(b.volume);
+        quote_volume_24h = quote_volume_24h.saturating_add(b.quote_volume);
+        trades_24h = trades_24h.saturating_add(b.trades);
+        high_24h = high_24h.max(b.high);
+        if b.low > 0 && (low_24h == 0 || b.low < low_24h) {
+            low_24h = b.low;
+        }
+        if b.open_price > 0 && b.hour < open_24h.0 {
+            open_24h = (b.hour, b.open_price);
+        }
+    }
+
+    // 订单簿深度和买卖盘失衡度
+    let bid_volume = order_book.bid_volume;
+    let ask_volume = order_book.ask_volume;
+    let depth = bid_volume as i128 + ask_volume as i128;
+    let book_imbalance_bps = if depth > 0 {
+        ((bid_volume as i128 - ask_volume as i128) * 10000 / depth) as i32
+    } else {
+        0
+    };
+    let liquidity_index = depth.min(u32::MAX as i128) as u32;
+
+    let execution_time_ms = metrics.avg_execution_time_ms;
+    let active_users = metrics.active_users_count;
+    RiskEngine::update_market_risk_metrics(
+        metrics,
+        current_price,
+        open_24h.1,
+        volume_24h,
+        liquidity_index,
+        execution_time_ms,
+        active_users,
+    );
+    metrics.vwap_24h = if volume_24h > 0 {
+        (quote_volume_24h / volume_24h as u128) as u64
+    } else {
+        0
+    };
+    metrics.high_24h = high_24h;
+    metrics.low_24h = low_24h;
+    metrics.trades_24h = trades_24h;
+    metrics.book_imbalance_bps = book_imbalance_bps;
+    metrics.last_snapshot_ts = clock.unix_timestamp;
+    metrics.last_total_volume = market.total_volume;
+    metrics.last_total_quote_volume = market.total_quote_volume;
+    metrics.last_total_trades = market.total_trades;
+
+    EventHandler::emit_market_metrics(
+        market.key(),
+        market.next_event_seq(),
+        volume_24h,
+        trades_24h,
+        order_book.get_best_price(Side::Bid).unwrap_or(0),
+        order_book.get_best_price(Side::Ask).unwrap_or(0),
+        liquidity_index,
+        metrics.vwap_24h,
+        high_24h,
+        low_24h,
+        book_imbalance_bps,
+    );
+
+    // 订单节点接近用尽时报告订单簿不健康
+    let orders_count = order_book.bid_orders_count + order_book.ask_orders_count;
+    let usage_percent = (orders_count as usize * 100 / order_book.order_nodes.len()) as u8;
+    if usage_percent >= ORDER_BOOK_UNHEALTHY_USAGE_PERCENT {
+        EventHandler::emit_system_status(
+            SystemStatusType::OrderbookStatus,
+            false,
+            SystemMetrics {
+                order_processing_latency_ms: execution_time_ms,
+                transaction_count_per_second: if elapsed_secs > 0 {
+                    (trades / elapsed_secs as u64) as u32
+                } else {
+                    0
+                },
+                memory_usage_percentage: usage_percent,
+                cpu_usage_percentage: 0, // 链上无法获取
+                active_users_count: active_users,
+            },
+            Some(format!(
+                "市场{}订单簿容量已使用{}%",
+                market.name, usage_percent
+            )),
+        );
+    }
+
+    Ok(())
+}
+
 // 在用户交易历史中查找洗盘交易模式
 pub fn find_wash_trading_patterns(user_trades: &[UserTrade], threshold_percent: u8) -> bool {
     if user_trades.len() < 10 {
@@ -1,4 +1,9 @@
+use crate::core::Market;
 use crate::events::{EventHandler, StorageOptimizationType};
+use crate::migration::CURRENT_SCHEMA_VERSION;
+use crate::orderbook::{OrderBook, Side};
+use crate::risk::RiskParameters;
+use crate::ErrorCode;
 use anchor_lang::prelude::*;
 use std::collections::HashMap;
 use std::mem;
@@ -10,6 +15,7 @@ pub struct OptimizedStorage;
 #[account]
 #[repr(packed)]
 pub struct CompressedIdMap {
+    pub schema_version: u8,         // 账户布局版本
     pub market: Pubkey,             // 市场公钥
     pub order_count: u32,           // 订单数量
     pub entries: [IdMapEntry; 128], // 订单映射条目
@@ -30,6 +36,7 @@ pub struct IdMapEntry {
 #[account]
 #[repr(packed)]
 pub struct OrderBatch {
+    pub schema_version: u8,            // 账户布局版本
     pub market: Pubkey,                // 市场公钥
     pub batch_id: u64,                 // 批次ID
     pub order_count: u32,              // 订单数量
@@ -53,6 +60,7 @@ pub struct BatchOrderEntry {
 #[account]
 #[repr(packed)]
 pub struct PriceLevelCache {
+    pub schema_version: u8,           // 账户布局版本
     pub market: Pubkey,               // 市场公钥
     pub last_update_slot: u64,        // 最后更新的slot
     pub bid_levels_count: u8,         // 买单价格级别数量
@@ -74,10 +82,67 @@ pub struct PriceLevel {
 #[account]
 #[repr(packed)]
 pub struct UserTradeHistory {
+    pub schema_version: u8,      // 账户布局版本
+    pub market: Pubkey,          // 所属市场
     pub owner: Pubkey,           // 用户公钥
     pub last_update_slot: u64,   // 最后更新的slot
     pub trade_count: u16,        // 交易数量
-    pub trades: [UserTrade; 64], // 交易历史
+    pub trades: [UserTrade; 64], // 交易历史（环形缓冲区）
+    pub head: u16,               // 下一条记录写入的位置
+    pub next_sequence: u64,      // 下一条记录的序号（单调递增，最新记录序号为next_sequence-1）
+    // 归档页面链（最新页面在链头）
+    pub archived_page_count: u64, // 已创建的归档页面数量（用于推导页面PDA）
+    pub archive_head: Option<Pubkey>, // 最新的归档页面
+    pub bump: u8,                 // PDA bump值
+}
+
+impl UserTradeHistory {
+    pub const CAPACITY: usize = 64;
+
+    // 缓冲区中最旧记录的序号
+    pub fn oldest_sequence(&self) -> u64 {
+        self.next_sequence - self.trade_count as u64
+    }
+
+    // 第i旧的记录（0为最旧）
+    pub fn get(&self, i: usize) -> UserTrade {
+        let start =
+            (self.head as usize + Self::CAPACITY - self.trade_count as usize) % Self::CAPACITY;
+        self.trades[(start + i) % Self::CAPACITY]
+    }
+
+    // 缓冲区是否已满
+    pub fn is_full(&self) -> bool {
+        self.trade_count as usize >= Self::CAPACITY
+    }
+
+    // 写入新记录，返回记录的序号
+    // 缓冲区已满时返回TradeHistoryFull且不修改缓冲区，需要先把最旧的记录归档到页面中
+    pub fn push(&mut self, trade: UserTrade) -> Result<u64> {
+        require!(!self.is_full(), ErrorCode::TradeHistoryFull);
+
+        self.trades[self.head as usize] = trade;
+        self.head = ((self.head as usize + 1) % Self::CAPACITY) as u16;
+        self.trade_count += 1;
+
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        Ok(sequence)
+    }
+
+    // 取出最旧的n条记录
+    pub fn pop_oldest(&mut self, n: usize) -> Vec<UserTrade> {
+        let n = n.min(self.trade_count as usize);
+        let trades = (0..n).map(|i| self.get(i)).collect();
+        self.trade_count -= n as u16;
+        trades
+    }
+}
+
+// 分页查询结果
+pub struct TradeHistoryPage {
+    pub trades: Vec<(u64, UserTrade)>, // (序号, 记录)，从新到旧
+    pub next_cursor: Option<u64>,      // 继续查询更旧记录的游标
 }
 
 // 用户交易记录
@@ -97,6 +162,7 @@ pub struct UserTrade {
 #[account]
 #[repr(packed)]
 pub struct StoragePage {
+    pub schema_version: u8,         // 账户布局版本
     pub market: Pubkey,             // 市场公钥
     pub page_type: StoragePageType, // 页面类型
     pub page_id: u64,               // 页面ID
@@ -104,9 +170,16 @@ pub struct StoragePage {
     pub total_size: u16,            // 总容量字节数
     pub next_page: Option<Pubkey>,  // 下一页（链表）
     pub last_access_slot: u64,      // 最后访问slot
+    pub first_sequence: u64,        // 页面中第一条记录的序号（归档页面使用）
+    pub format_version: u8,         // 数据格式版本 (0=原始字节, 1=记录编码v1)
+    pub checksum: u32,              // 已使用数据的Adler-32校验和
     pub data: [u8; 1024],           // 实际数据存储区
 }
 
+// 存储页面数据格式版本
+pub const STORAGE_FORMAT_RAW: u8 = 0;
+pub const STORAGE_FORMAT_CODEC_V1: u8 = 1;
+
 // 页面类型
 #[derive(Copy, Clone, AnchorSerialize, AnchorDeserialize, PartialEq)]
 #[repr(u8)]
@@ -123,6 +196,7 @@ pub enum StoragePageType {
 #[account]
 #[repr(packed)]
 pub struct DataTierConfig {
+    pub schema_version: u8,           // 账户布局版本
     pub market: Pubkey,               // 市场公钥
     pub hot_data_max_age_slots: u64,  // 热数据最大年龄（slot）
     pub warm_data_max_age_slots: u64, // 温数据最大年龄（slot）
@@ -130,16 +204,28 @@ pub struct DataTierConfig {
     pub auto_archive_enabled: bool,   // 是否启用自动归档
     pub auto_archive_age_slots: u64,  // 自动归档年龄（slot）
     pub last_optimization_slot: u64,  // 最后一次优化的slot
+    pub authority: Pubkey,            // 分层存储管理员
+    pub bump: u8,                     // PDA bump值
 }
 
-// 区块链数据索引
+// 单条交易记录的原始序列化长度
+pub const USER_TRADE_RAW_LEN: usize = 8 + 16 + 8 + 8 + 1 + 8 + 8;
+// 每个slot的近似时长（毫秒），用于将slot阈值换算为时间戳
+pub const SLOT_DURATION_MS: u64 = 400;
+// 索引条目标志位：页面已压缩（冷数据）
+pub const INDEX_FLAG_COMPRESSED: u8 = 1;
+
+// 归档页面索引，每个用户交易历史一个（PDA: ["storage_index", user_trade_history]）
 #[account]
 #[repr(packed)]
 pub struct StorageIndex {
+    pub schema_version: u8,               // 账户布局版本
     pub market: Pubkey,                   // 市场公钥
+    pub user_trade_history: Pubkey,       // 所属的用户交易历史
     pub last_update_slot: u64,            // 最后更新的slot
     pub index_entries_count: u16,         // 索引条目数量
     pub entries: [StorageIndexEntry; 64], // 索引条目
+    pub bump: u8,                         // PDA bump值
 }
 
 // 索引条目
@@ -171,8 +257,21 @@ impl OptimizedStorage {
         8 + mem::size_of::<UserTradeHistory>()
     }
 
+    pub fn get_storage_page_size() -> usize {
+        8 + mem::size_of::<StoragePage>()
+    }
+
+    pub fn get_storage_index_size() -> usize {
+        8 + mem::size_of::<StorageIndex>()
+    }
+
+    pub fn get_data_tier_config_size() -> usize {
+        8 + mem::size_of::<DataTierConfig>()
+    }
+
     // 初始化压缩ID映射
     pub fn initialize_compressed_id_map(id_map: &mut CompressedIdMap, market: Pubkey) {
+        id_map.schema_version = CURRENT_SCHEMA_VERSION;
         id_map.market = market;
         id_map.order_count = 0;
 
@@ -247,6 +346,7 @@ impl OptimizedStorage {
 
     // 初始化订单批次
     pub fn initialize_order_batch(batch: &mut OrderBatch, market: Pubkey, batch_id: u64) {
+        batch.schema_version = CURRENT_SCHEMA_VERSION;
         batch.market = market;
         batch.batch_id = batch_id;
         batch.order_count = 0;
@@ -322,6 +422,7 @@ impl OptimizedStorage {
 
     // 初始化价格级别缓存
     pub fn initialize_price_level_cache(cache: &mut PriceLevelCache, market: Pubkey) {
+        cache.schema_version = CURRENT_SCHEMA_VERSION;
         cache.market = market;
         cache.last_update_slot = 0;
         cache.bid_levels_count = 0;
@@ -383,11 +484,52 @@ impl OptimizedStorage {
         Ok(())
     }
 
+    // 判断某个价格的变动是否会影响缓存的档位
+    // 缓存未满，或价格不劣于缓存中最差的档位时需要刷新
+    pub fn price_level_cache_touched(cache: &PriceLevelCache, side: Side, price: u64) -> bool {
+        let (levels, count) = match side {
+            Side::Bid => (&cache.bid_levels, cache.bid_levels_count as usize),
+            Side::Ask => (&cache.ask_levels, cache.ask_levels_count as usize),
+        };
+
+        if count < levels.len() {
+            return true;
+        }
+
+        let worst = levels[count - 1].price;
+        match side {
+            Side::Bid => price >= worst,
+            Side::Ask => price <= worst,
+        }
+    }
+
+    // 根据订单簿重新生成缓存的前N档价格
+    pub fn refresh_price_level_cache(
+        cache: &mut PriceLevelCache,
+        order_book: &OrderBook,
+        slot: u64,
+    ) -> Result<()> {
+        let bid_levels = order_book.get_price_levels(Side::Bid, cache.bid_levels.len());
+        let ask_levels = order_book.get_price_levels(Side::Ask, cache.ask_levels.len());
+
+        Self::update_price_level_cache(cache, &bid_levels, &ask_levels, slot)
+    }
+
     // 初始化用户交易历史
-    pub fn initialize_user_trade_history(history: &mut UserTradeHistory, owner: Pubkey) {
+    pub fn initialize_user_trade_history(
+        history: &mut UserTradeHistory,
+        market: Pubkey,
+        owner: Pubkey,
+    ) {
+        history.schema_version = CURRENT_SCHEMA_VERSION;
+        history.market = market;
         history.owner = owner;
         history.last_update_slot = 0;
         history.trade_count = 0;
+        history.head = 0;
+        history.next_sequence = 0;
+        history.archived_page_count = 0;
+        history.archive_head = None;
 
         // 初始化所有交易记录
         for i in 0..history.trades.len() {
@@ -403,7 +545,8 @@ impl OptimizedStorage {
         }
     }
 
-    // 添加交易记录到用户历史
+    // 添加交易记录到用户历史，返回记录的序号
+    // 缓冲区已满时失败，调用方需先通过archive_full_history把最旧的记录归档
     pub fn add_to_user_history(
         history: &mut UserTradeHistory,
         market: Pubkey,
@@ -413,43 +556,73 @@ impl OptimizedStorage {
         side: u8,
         timestamp: i64,
         fee: u64,
-    ) -> Result<()> {
-        // 验证是否有空间
-        if history.trade_count as usize >= history.trades.len() {
-            // 如果已满，删除最旧的记录腾出空间 (FIFO)
-            for i in 0..(history.trades.len() - 1) {
-                history.trades[i] = history.trades[i + 1];
-            }
-            history.trade_count = history.trades.len() as u16 - 1;
-        }
-
-        // 压缩市场公钥 - 取前8字节
-        let mut compressed_market = [0u8; 8];
-        compressed_market.copy_from_slice(&market.to_bytes()[0..8]);
-
-        // 添加新交易记录到末尾
-        history.trades[history.trade_count as usize] = UserTrade {
-            market: compressed_market,
+    ) -> Result<u64> {
+        let sequence = history.push(UserTrade {
+            market: Self::compress_pubkey(&market),
             order_id,
             price,
             quantity,
             side,
             timestamp,
             fee,
-        };
+        })?;
 
-        history.trade_count += 1;
-        history.last_update_slot = Clock::get().unwrap().slot;
+        history.last_update_slot = Clock::get()?.slot;
 
-        Ok(())
+        Ok(sequence)
     }
 
-    // 查询用户最近的交易
-    pub fn get_recent_trades(history: &UserTradeHistory, limit: usize) -> Vec<&UserTrade> {
-        let count = std::cmp::min(limit, history.trade_count as usize);
-        let start = history.trade_count as usize - count;
+    // 按游标分页查询交易记录，从新到旧
+    // cursor为None时从最新记录开始，否则返回序号小于cursor的记录
+    // archive_pages按归档链顺序（从链头开始）传入，缓冲区不够时继续从归档页面读取
+    pub fn get_recent_trades(
+        history: &UserTradeHistory,
+        archive_pages: &[StoragePage],
+        cursor: Option<u64>,
+        limit: usize,
+    ) -> Result<TradeHistoryPage> {
+        let cursor = cursor
+            .unwrap_or(history.next_sequence)
+            .min(history.next_sequence);
+        let mut trades = Vec::with_capacity(limit);
+
+        // 先从缓冲区读取
+        let oldest = history.oldest_sequence();
+        let mut sequence = cursor;
+        while sequence > oldest && trades.len() < limit {
+            sequence -= 1;
+            trades.push((sequence, history.get((sequence - oldest) as usize)));
+        }
+
+        // 再按链顺序读取归档页面
+        for page in archive_pages {
+            if trades.len() >= limit || sequence == 0 {
+                break;
+            }
+
+            let page_trades = Self::read_page_trades(page)?;
+            let first = page.first_sequence;
+            let end = first + page_trades.len() as u64;
+            // 页面必须与当前游标相接，否则说明传入的归档链不完整
+            if end < sequence {
+                break;
+            }
+
+            while sequence > first && trades.len() < limit {
+                sequence -= 1;
+                trades.push((sequence, page_trades[(sequence - first) as usize]));
+            }
+        }
+
+        let next_cursor = match trades.last() {
+            Some(&(last, _)) if last > 0 => Some(last),
+            _ => None,
+        };
 
-        history.trades[start..(start + count)].iter().collect()
+        Ok(TradeHistoryPage {
+            trades,
+            next_cursor,
+        })
     }
 
     // 将完整公钥转换为压缩版本（取前8字节）
@@ -473,6 +646,7 @@ impl OptimizedStorage {
         page_type: StoragePageType,
         page_id: u64,
     ) {
+        page.schema_version = CURRENT_SCHEMA_VERSION;
         page.market = market;
         page.page_type = page_type;
         page.page_id = page_id;
@@ -480,6 +654,9 @@ impl OptimizedStorage {
         page.total_size = page.data.len() as u16;
         page.next_page = None;
         page.last_access_slot = Clock::get().unwrap().slot;
+        page.first_sequence = 0;
+        page.format_version = STORAGE_FORMAT_RAW;
+        page.checksum = adler32(&[]);
 
         // 清空数据区
         for i in 0..page.data.len() {
@@ -505,6 +682,10 @@ impl OptimizedStorage {
             page.data[offset as usize + i] = byte;
         }
 
+        // 原始写入后页面不再是编码格式，重新计算校验和
+        page.format_version = STORAGE_FORMAT_RAW;
+        page.checksum = adler32(&page.data[..page.used_size as usize]);
+
         // 更新最后访问时间
         page.last_access_slot = Clock::get().unwrap().slot;
 
@@ -544,99 +725,555 @@ impl OptimizedStorage {
         Ok(())
     }
 
-    // 对数据进行压缩 (模拟实现)
+    // 通用字节压缩 (PackBits风格的游程编码)
+    // 控制字节 0..=127 表示后面跟随 n+1 个原样字节，128..=255 表示下一个字节重复 n-126 次
+    // 不可压缩的数据每128字节只增加1字节，不会像成对RLE那样膨胀一倍
     pub fn compress_data(data: &[u8]) -> Vec<u8> {
-        // 这里是简化的压缩算法示例，真实环境应该使用成熟的压缩算法如 LZ77、DEFLATE 等
-        // 由于区块链环境限制，可能需要使用简单的压缩策略
+        let mut compressed = Vec::with_capacity(data.len() + data.len() / 128 + 1);
 
-        // 简单的游程编码 (RLE) 实现示例
-        let mut compressed = Vec::with_capacity(data.len());
+        let mut i = 0;
+        while i < data.len() {
+            // 统计从i开始的重复长度
+            let mut run = 1;
+            while i + run < data.len() && data[i + run] == data[i] && run < 129 {
+                run += 1;
+            }
 
-        if data.is_empty() {
-            return compressed;
+            if run >= 2 {
+                compressed.push((run + 126) as u8);
+                compressed.push(data[i]);
+                i += run;
+                continue;
+            }
+
+            // 收集原样字节，直到遇到至少2个重复字节或达到128字节
+            let start = i;
+            while i < data.len() && i - start < 128 {
+                if i + 1 < data.len() && data[i] == data[i + 1] {
+                    break;
+                }
+                i += 1;
+            }
+            compressed.push((i - start - 1) as u8);
+            compressed.extend_from_slice(&data[start..i]);
         }
 
-        let mut current_byte = data[0];
-        let mut count: u8 = 1;
+        compressed
+    }
+
+    // 解压缩数据 (与上面的压缩方法对应)，输入被截断时返回错误而不是静默丢弃
+    pub fn decompress_data(compressed: &[u8]) -> Result<Vec<u8>> {
+        let mut decompressed = Vec::with_capacity(compressed.len());
 
-        for &byte in &data[1..] {
-            if byte == current_byte && count < 255 {
-                count += 1;
+        let mut i = 0;
+        while i < compressed.len() {
+            let control = compressed[i] as usize;
+            i += 1;
+
+            if control < 128 {
+                let len = control + 1;
+                require!(i + len <= compressed.len(), ErrorCode::StorageDataCorrupted);
+                decompressed.extend_from_slice(&compressed[i..i + len]);
+                i += len;
             } else {
-                compressed.push(count);
-                compressed.push(current_byte);
-                current_byte = byte;
-                count = 1;
+                require!(i < compressed.len(), ErrorCode::StorageDataCorrupted);
+                let byte = compressed[i];
+                decompressed.resize(decompressed.len() + control - 126, byte);
+                i += 1;
             }
         }
 
-        // 处理最后一组字节
-        compressed.push(count);
-        compressed.push(current_byte);
+        Ok(decompressed)
+    }
 
-        compressed
+    // 编码用户交易记录
+    // 格式: 记录数 | 市场前缀字典 | 每条记录(方向, 字典索引, 订单ID, 价格差值, 数量, 时间差值, 手续费)
+    // 价格和时间戳相对上一条记录做差值+zigzag，整数使用varint
+    // 测试codec_compression_ratio保证：64条同一市场、价格在±50 tick内波动、数量<1e6、间隔1-30秒的成交
+    // 原始3648字节编码后不超过1681字节（压缩比不低于2.17:1），且小于通用compress_data的输出
+    // 订单ID是哈希值无法压缩，每条固定16字节，因此一个1024字节页面约可存放40条交易
+    pub fn encode_user_trades(trades: &[UserTrade]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(trades.len() * 28 + 16);
+
+        // 构建市场前缀字典
+        let mut dictionary: Vec<[u8; 8]> = Vec::new();
+        let mut indices = Vec::with_capacity(trades.len());
+        for trade in trades {
+            let market = trade.market;
+            let idx = match dictionary.iter().position(|m| *m == market) {
+                Some(idx) => idx,
+                None => {
+                    dictionary.push(market);
+                    dictionary.len() - 1
+                }
+            };
+            indices.push(idx);
+        }
+
+        write_varint(&mut out, trades.len() as u64);
+        write_varint(&mut out, dictionary.len() as u64);
+        for market in &dictionary {
+            out.extend_from_slice(market);
+        }
+
+        let mut prev_price = 0u64;
+        let mut prev_timestamp = 0i64;
+        for (trade, idx) in trades.iter().zip(indices) {
+            let price = trade.price;
+            let timestamp = trade.timestamp;
+
+            out.push(trade.side);
+            write_varint(&mut out, idx as u64);
+            out.extend_from_slice(&{ trade.order_id }.to_le_bytes());
+            write_varint(&mut out, zigzag(price.wrapping_sub(prev_price) as i64));
+            write_varint(&mut out, trade.quantity);
+            write_varint(&mut out, zigzag(timestamp.wrapping_sub(prev_timestamp)));
+            write_varint(&mut out, trade.fee);
+
+            prev_price = price;
+            prev_timestamp = timestamp;
+        }
+
+        out
     }
 
-    // 解压缩数据 (与上面的压缩方法对应)
-    pub fn decompress_data(compressed: &[u8]) -> Vec<u8> {
-        let mut decompressed = Vec::new();
+    // 解码用户交易记录
+    pub fn decode_user_trades(data: &[u8]) -> Result<Vec<UserTrade>> {
+        let mut reader = ByteReader::new(data);
 
-        let mut i = 0;
-        while i < compressed.len() / 2 * 2 {
-            // 确保按对处理
-            let count = compressed[i];
-            let byte = compressed[i + 1];
+        let count = reader.read_varint()? as usize;
+        let dict_len = reader.read_varint()? as usize;
+        // 防止损坏的长度字段导致超大分配
+        require!(
+            count <= data.len() && dict_len <= data.len() / 8,
+            ErrorCode::StorageDataCorrupted
+        );
 
-            for _ in 0..count {
-                decompressed.push(byte);
-            }
+        let mut dictionary = Vec::with_capacity(dict_len);
+        for _ in 0..dict_len {
+            let mut market = [0u8; 8];
+            market.copy_from_slice(reader.read_bytes(8)?);
+            dictionary.push(market);
+        }
+
+        let mut trades = Vec::with_capacity(count);
+        let mut prev_price = 0u64;
+        let mut prev_timestamp = 0i64;
+        for _ in 0..count {
+            let side = reader.read_u8()?;
+            let idx = reader.read_varint()? as usize;
+            let market = *dictionary.get(idx).ok_or(ErrorCode::StorageDataCorrupted)?;
+            let order_id = reader.read_u128()?;
+            let price = prev_price.wrapping_add(unzigzag(reader.read_varint()?) as u64);
+            let quantity = reader.read_varint()?;
+            let timestamp = prev_timestamp.wrapping_add(unzigzag(reader.read_varint()?));
+            let fee = reader.read_varint()?;
+
+            trades.push(UserTrade {
+                market,
+                order_id,
+                price,
+                quantity,
+                side,
+                timestamp,
+                fee,
+            });
 
-            i += 2;
+            prev_price = price;
+            prev_timestamp = timestamp;
         }
 
-        decompressed
+        require!(reader.is_empty(), ErrorCode::StorageDataCorrupted);
+
+        Ok(trades)
     }
 
-    // 迁移冷数据到长期存储
-    pub fn migrate_cold_data(market: Pubkey, config: &DataTierConfig) -> Result<()> {
-        // 获取当前slot
-        let current_slot = Clock::get()?.slot;
+    // 编码批次订单记录
+    // 格式: 记录数 | 所有者公钥字典 | 每条记录(字典索引, 订单ID, 价格差值, 数量, 标志位, 时间差值)
+    // 测试codec_compression_ratio保证：64条订单、8个不同所有者、价格在±50 tick内波动
+    // 原始4864字节编码后不超过1801字节（压缩比不低于2.70:1）
+    pub fn encode_batch_orders(orders: &[BatchOrderEntry]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(orders.len() * 28 + 64);
+
+        // 构建所有者公钥字典
+        let mut dictionary: Vec<Pubkey> = Vec::new();
+        let mut indices = Vec::with_capacity(orders.len());
+        for order in orders {
+            let owner = order.owner;
+            let idx = match dictionary.iter().position(|o| *o == owner) {
+                Some(idx) => idx,
+                None => {
+                    dictionary.push(owner);
+                    dictionary.len() - 1
+                }
+            };
+            indices.push(idx);
+        }
 
-        // 计算冷数据边界
-        let cold_data_boundary = current_slot.saturating_sub(config.warm_data_max_age_slots);
+        write_varint(&mut out, orders.len() as u64);
+        write_varint(&mut out, dictionary.len() as u64);
+        for owner in &dictionary {
+            out.extend_from_slice(owner.as_ref());
+        }
 
-        // 查找需要迁移的存储页面
-        // 在实际实现中，这需要一个账户查询机制，这里简单模拟
+        let mut prev_price = 0u64;
+        let mut prev_timestamp = 0i64;
+        for (order, idx) in orders.iter().zip(indices) {
+            let price = order.price;
+            let timestamp = order.timestamp;
+
+            write_varint(&mut out, idx as u64);
+            out.extend_from_slice(&{ order.order_id }.to_le_bytes());
+            write_varint(&mut out, zigzag(price.wrapping_sub(prev_price) as i64));
+            write_varint(&mut out, order.quantity);
+            write_varint(&mut out, order.flags as u64);
+            write_varint(&mut out, zigzag(timestamp.wrapping_sub(prev_timestamp)));
+
+            prev_price = price;
+            prev_timestamp = timestamp;
+        }
 
-        // 发送存储优化事件 - 这里仅作为示例
-        EventHandler::emit_storage_optimization(
-            market,
-            StorageOptimizationType::Compression,
-            0,    // 旧大小
-            0,    // 新大小
-            true, // 是否成功
+        out
+    }
+
+    // 解码批次订单记录
+    pub fn decode_batch_orders(data: &[u8]) -> Result<Vec<BatchOrderEntry>> {
+        let mut reader = ByteReader::new(data);
+
+        let count = reader.read_varint()? as usize;
+        let dict_len = reader.read_varint()? as usize;
+        require!(
+            count <= data.len() && dict_len <= data.len() / 32,
+            ErrorCode::StorageDataCorrupted
         );
 
+        let mut dictionary = Vec::with_capacity(dict_len);
+        for _ in 0..dict_len {
+            let bytes = reader.read_bytes(32)?;
+            dictionary.push(Pubkey::try_from(bytes).map_err(|_| ErrorCode::StorageDataCorrupted)?);
+        }
+
+        let mut orders = Vec::with_capacity(count);
+        let mut prev_price = 0u64;
+        let mut prev_timestamp = 0i64;
+        for _ in 0..count {
+            let idx = reader.read_varint()? as usize;
+            let owner = *dictionary.get(idx).ok_or(ErrorCode::StorageDataCorrupted)?;
+            let order_id = reader.read_u128()?;
+            let price = prev_price.wrapping_add(unzigzag(reader.read_varint()?) as u64);
+            let quantity = reader.read_varint()?;
+            let flags = u32::try_from(reader.read_varint()?)
+                .map_err(|_| ErrorCode::StorageDataCorrupted)?;
+            let timestamp = prev_timestamp.wrapping_add(unzigzag(reader.read_varint()?));
+
+            orders.push(BatchOrderEntry {
+                order_id,
+                owner,
+                price,
+                quantity,
+                flags,
+                timestamp,
+            });
+
+            prev_price = price;
+            prev_timestamp = timestamp;
+        }
+
+        require!(reader.is_empty(), ErrorCode::StorageDataCorrupted);
+
+        Ok(orders)
+    }
+
+    // 将编码后的数据写入页面，并记录格式版本和校验和
+    pub fn store_encoded_page(page: &mut StoragePage, encoded: &[u8]) -> Result<()> {
+        require!(encoded.len() <= page.data.len(), ErrorCode::StorageFull);
+
+        page.data[..encoded.len()].copy_from_slice(encoded);
+        for byte in page.data[encoded.len()..].iter_mut() {
+            *byte = 0;
+        }
+
+        page.used_size = encoded.len() as u16;
+        page.format_version = STORAGE_FORMAT_CODEC_V1;
+        page.checksum = adler32(encoded);
+        page.last_access_slot = Clock::get()?.slot;
+
         Ok(())
     }
 
-    // 整理碎片化存储
-    pub fn defragment_storage(market: Pubkey, page_type: StoragePageType) -> Result<u32> {
-        // 在实际实现中，这需要遍历所有存储页面并重新组织数据
-        // 这里仅作为示例说明功能
+    // 读取页面中的编码数据，校验格式版本和校验和
+    pub fn load_encoded_page(page: &StoragePage) -> Result<&[u8]> {
+        require!(
+            page.format_version == STORAGE_FORMAT_CODEC_V1,
+            ErrorCode::StorageDataCorrupted
+        );
+        let used = page.used_size as usize;
+        require!(used <= page.data.len(), ErrorCode::StorageDataCorrupted);
 
-        let space_reclaimed = 0u32; // 实际回收的空间
+        let payload = &page.data[..used];
+        require!(
+            adler32(payload) == page.checksum,
+            ErrorCode::StorageDataCorrupted
+        );
 
-        // 发送存储优化事件
-        EventHandler::emit_storage_optimization(
-            market,
-            StorageOptimizationType::Defragmentation,
-            0,    // 旧大小
-            0,    // 新大小
-            true, // 是否成功
+        Ok(payload)
+    }
+
+    // 将交易记录编码写入交易历史页面
+    pub fn store_user_trades(page: &mut StoragePage, trades: &[UserTrade]) -> Result<()> {
+        require!(
+            page.page_type == StoragePageType::TradeHistory,
+            ErrorCode::InvalidParameters
+        );
+        Self::store_encoded_page(page, &Self::encode_user_trades(trades))
+    }
+
+    // 从交易历史页面读取交易记录
+    pub fn load_user_trades(page: &StoragePage) -> Result<Vec<UserTrade>> {
+        require!(
+            page.page_type == StoragePageType::TradeHistory,
+            ErrorCode::InvalidParameters
+        );
+        Self::decode_user_trades(Self::load_encoded_page(page)?)
+    }
+
+    // 将订单记录编码写入订单数据页面
+    pub fn store_batch_orders(page: &mut StoragePage, orders: &[BatchOrderEntry]) -> Result<()> {
+        require!(
+            page.page_type == StoragePageType::OrderData,
+            ErrorCode::InvalidParameters
+        );
+        Self::store_encoded_page(page, &Self::encode_batch_orders(orders))
+    }
+
+    // 从订单数据页面读取订单记录
+    pub fn load_batch_orders(page: &StoragePage) -> Result<Vec<BatchOrderEntry>> {
+        require!(
+            page.page_type == StoragePageType::OrderData,
+            ErrorCode::InvalidParameters
+        );
+        Self::decode_batch_orders(Self::load_encoded_page(page)?)
+    }
+
+    // 将slot数量换算为秒数
+    pub fn slots_to_secs(slots: u64) -> i64 {
+        (slots.saturating_mul(SLOT_DURATION_MS) / 1000).min(i64::MAX as u64) as i64
+    }
+
+    // 归档页面的PDA地址
+    pub fn archive_page_address(history: &Pubkey, page_id: u64) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"storage_page", history.as_ref(), &page_id.to_le_bytes()],
+            &crate::ID,
+        )
+        .0
+    }
+
+    // 以定长原始格式序列化交易记录（温数据页面使用，便于直接读取）
+    pub fn encode_raw_user_trades(trades: &[UserTrade]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(trades.len() * USER_TRADE_RAW_LEN);
+        for trade in trades {
+            out.extend_from_slice(&trade.market);
+            out.extend_from_slice(&{ trade.order_id }.to_le_bytes());
+            out.extend_from_slice(&{ trade.price }.to_le_bytes());
+            out.extend_from_slice(&{ trade.quantity }.to_le_bytes());
+            out.push(trade.side);
+            out.extend_from_slice(&{ trade.timestamp }.to_le_bytes());
+            out.extend_from_slice(&{ trade.fee }.to_le_bytes());
+        }
+        out
+    }
+
+    // 反序列化定长原始格式的交易记录
+    pub fn decode_raw_user_trades(data: &[u8]) -> Result<Vec<UserTrade>> {
+        require!(
+            data.len() % USER_TRADE_RAW_LEN == 0,
+            ErrorCode::StorageDataCorrupted
+        );
+
+        let mut trades = Vec::with_capacity(data.len() / USER_TRADE_RAW_LEN);
+        for chunk in data.chunks(USER_TRADE_RAW_LEN) {
+            let mut reader = ByteReader::new(chunk);
+            let mut market = [0u8; 8];
+            market.copy_from_slice(reader.read_bytes(8)?);
+            let order_id = reader.read_u128()?;
+            let price = reader.read_u64()?;
+            let quantity = reader.read_u64()?;
+            let side = reader.read_u8()?;
+            let timestamp = reader.read_u64()? as i64;
+            let fee = reader.read_u64()?;
+
+            trades.push(UserTrade {
+                market,
+                order_id,
+                price,
+                quantity,
+                side,
+                timestamp,
+                fee,
+            });
+        }
+
+        Ok(trades)
+    }
+
+    // 读取交易历史页面中的记录（支持原始格式和编码格式）
+    pub fn read_page_trades(page: &StoragePage) -> Result<Vec<UserTrade>> {
+        require!(
+            page.page_type == StoragePageType::TradeHistory,
+            ErrorCode::InvalidParameters
         );
 
-        Ok(space_reclaimed)
+        match page.format_version {
+            STORAGE_FORMAT_RAW => {
+                let payload = &page.data[..page.used_size as usize];
+                require!(
+                    adler32(payload) == page.checksum,
+                    ErrorCode::StorageDataCorrupted
+                );
+                Self::decode_raw_user_trades(payload)
+            }
+            _ => Self::load_user_trades(page),
+        }
+    }
+
+    // 查找页面对应的索引条目
+    pub fn find_index_entry(index: &StorageIndex, page_key: &Pubkey) -> Option<usize> {
+        let compressed_key = Self::compress_pubkey(page_key);
+        (0..index.index_entries_count as usize)
+            .find(|&i| index.entries[i].page_key == compressed_key)
+    }
+
+    // 新增或更新页面的索引条目
+    pub fn upsert_index_entry(
+        index: &mut StorageIndex,
+        page_key: &Pubkey,
+        page_type: StoragePageType,
+        item_count: u16,
+        data_flags: u8,
+        slot: u64,
+    ) -> Result<()> {
+        match Self::find_index_entry(index, page_key) {
+            Some(i) => {
+                index.entries[i].item_count = item_count;
+                index.entries[i].data_flags = data_flags;
+            }
+            None => {
+                let i = index.index_entries_count as usize;
+                require!(i < index.entries.len(), ErrorCode::StorageFull);
+                index.entries[i] = StorageIndexEntry {
+                    page_key: Self::compress_pubkey(page_key),
+                    page_type: page_type as u8,
+                    item_count,
+                    data_flags,
+                    create_slot: slot,
+                };
+                index.index_entries_count += 1;
+            }
+        }
+
+        index.last_update_slot = slot;
+
+        Ok(())
+    }
+
+    // 删除页面的索引条目（用最后一个条目填补空位）
+    pub fn remove_index_entry(index: &mut StorageIndex, page_key: &Pubkey, slot: u64) {
+        if let Some(i) = Self::find_index_entry(index, page_key) {
+            let last = index.index_entries_count as usize - 1;
+            if i < last {
+                index.entries[i] = index.entries[last];
+            }
+            index.index_entries_count -= 1;
+            index.last_update_slot = slot;
+        }
+    }
+
+    // 将热数据中超过热数据年龄的交易迁移到新的温数据页面
+    // 返回迁移的记录数
+    pub fn archive_aged_trades(
+        history: &mut UserTradeHistory,
+        page: &mut StoragePage,
+        config: &DataTierConfig,
+        current_ts: i64,
+    ) -> Result<usize> {
+        let cutoff = current_ts.saturating_sub(Self::slots_to_secs(config.hot_data_max_age_slots));
+
+        // 交易历史按时间顺序存放，超龄记录是最旧的一段
+        let count = history.trade_count as usize;
+        let mut aged = 0;
+        while aged < count && history.get(aged).timestamp <= cutoff {
+            aged += 1;
+        }
+
+        Self::flush_oldest_trades(history, page, aged)
+    }
+
+    // 将缓冲区中最旧的至多limit条记录写入新的归档页面
+    // 返回迁移的记录数
+    pub fn flush_oldest_trades(
+        history: &mut UserTradeHistory,
+        page: &mut StoragePage,
+        limit: usize,
+    ) -> Result<usize> {
+        let moved = limit.min(page.data.len() / USER_TRADE_RAW_LEN);
+        require!(moved > 0, ErrorCode::NoAgedData);
+
+        page.first_sequence = history.oldest_sequence();
+        let trades = history.pop_oldest(moved);
+
+        let raw = Self::encode_raw_user_trades(&trades);
+        page.data[..raw.len()].copy_from_slice(&raw);
+        page.used_size = raw.len() as u16;
+        page.format_version = STORAGE_FORMAT_RAW;
+        page.checksum = adler32(&raw);
+
+        Ok(trades.len())
+    }
+
+    // 将超过温数据年龄的页面压缩为冷数据
+    // 返回压缩前后的字节数
+    pub fn migrate_cold_data(
+        config: &DataTierConfig,
+        page: &mut StoragePage,
+        create_slot: u64,
+        current_slot: u64,
+    ) -> Result<(u32, u32)> {
+        require!(config.cold_data_compression, ErrorCode::InvalidParameters);
+        require!(
+            page.format_version == STORAGE_FORMAT_RAW,
+            ErrorCode::InvalidParameters
+        );
+        require!(
+            current_slot.saturating_sub(create_slot) >= config.warm_data_max_age_slots,
+            ErrorCode::NoAgedData
+        );
+
+        let old_size = page.used_size as u32;
+        let trades = Self::read_page_trades(page)?;
+        Self::store_user_trades(page, &trades)?;
+
+        Ok((old_size, page.used_size as u32))
+    }
+
+    // 将链中相邻的两个冷数据页面合并到前一个页面中
+    // 返回合并后的记录数
+    pub fn defragment_storage(page: &mut StoragePage, next_page: &StoragePage) -> Result<usize> {
+        require!(
+            page.format_version == STORAGE_FORMAT_CODEC_V1
+                && next_page.format_version == STORAGE_FORMAT_CODEC_V1,
+            ErrorCode::InvalidParameters
+        );
+
+        // 链表中后面的页面更旧，按时间顺序合并
+        let mut trades = Self::read_page_trades(next_page)?;
+        trades.extend(Self::read_page_trades(page)?);
+
+        Self::store_user_trades(page, &trades)?;
+        page.next_page = next_page.next_page;
+        page.first_sequence = next_page.first_sequence;
+
+        Ok(trades.len())
     }
 
     // 创建存储索引
@@ -645,6 +1282,7 @@ impl OptimizedStorage {
         market: Pubkey,
         pages: &[StoragePage],
     ) -> Result<()> {
+        index.schema_version = CURRENT_SCHEMA_VERSION;
         index.market = market;
         index.last_update_slot = Clock::get()?.slot;
         index.index_entries_count = 0;
@@ -719,7 +1357,7 @@ impl OptimizedStorage {
     // 在存储页面间平衡负载
     pub fn balance_storage_load(
         pages: &mut [StoragePage],
-        market: Pubkey,
+        market: &mut Account<Market>,
         page_type: StoragePageType,
     ) -> Result<()> {
         // 计算平均使用率
@@ -727,7 +1365,7 @@ impl OptimizedStorage {
         let mut page_count = 0u32;
 
         for page in pages.iter() {
-            if page.page_type == page_type && page.market == market {
+            if page.page_type == page_type && page.market == market.key() {
                 total_usage += page.used_size as u32;
                 page_count += 1;
             }
@@ -748,7 +1386,8 @@ impl OptimizedStorage {
 
         // 发送存储优化事件
         EventHandler::emit_storage_optimization(
-            market,
+            market.key(),
+            market.next_event_seq(),
             StorageOptimizationType::Defragmentation,
             total_usage as u32,
             total_usage as u32, // 实际应该是优化后的大小
@@ -817,27 +1456,26 @@ impl OptimizedStorage {
         result
     }
 
-    // 清理过期数据
+    // 检查链尾页面是否已过期，可以清理
     pub fn cleanup_expired_data(
-        market: Pubkey,
-        page_type: StoragePageType,
+        config: &DataTierConfig,
+        page: &StoragePage,
+        create_slot: u64,
         max_age_slots: u64,
-    ) -> Result<u32> {
-        // 在实际实现中，这需要识别并删除过期的数据项
-        // 这里仅作为示例说明功能
-
-        let cleaned_items = 0u32; // 实际清理的项目数
-
-        // 发送存储优化事件
-        EventHandler::emit_storage_optimization(
-            market,
-            StorageOptimizationType::HistoryTruncation,
-            0,    // 旧大小
-            0,    // 新大小
-            true, // 是否成功
+        current_slot: u64,
+    ) -> Result<()> {
+        // 只能清理已经进入冷数据层的页面
+        require!(
+            max_age_slots > config.warm_data_max_age_slots,
+            ErrorCode::InvalidParameters
+        );
+        require!(page.next_page.is_none(), ErrorCode::InvalidParameters);
+        require!(
+            current_slot.saturating_sub(create_slot) >= max_age_slots,
+            ErrorCode::NoAgedData
         );
 
-        Ok(cleaned_items)
+        Ok(())
     }
 
     // 计算最佳页面大小
@@ -870,3 +1508,1090 @@ pub struct StorageHealthResult {
     pub oldest_page_age_slots: u64,
     pub oversized_pages_count: u16,
 }
+
+// 初始化数据分层配置所需的账户
+#[derive(Accounts)]
+pub struct InitializeDataTierConfig<'info> {
+    pub market: Account<'info, Market>,
+
+    // 只有市场的风控管理员可以配置分层存储
+    #[account(
+        seeds = [b"risk_parameters", market.key().as_ref()],
+        bump = risk_parameters.bump,
+        has_one = authority @ ErrorCode::UnauthorizedOperation
+    )]
+    pub risk_parameters: Account<'info, RiskParameters>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = OptimizedStorage::get_data_tier_config_size(),
+        seeds = [b"data_tier_config", market.key().as_ref()],
+        bump
+    )]
+    pub data_tier_config: Account<'info, DataTierConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// 更新数据分层配置所需的账户
+#[derive(Accounts)]
+pub struct UpdateDataTierConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"data_tier_config", data_tier_config.market.as_ref()],
+        bump = data_tier_config.bump,
+        has_one = authority @ ErrorCode::UnauthorizedOperation
+    )]
+    pub data_tier_config: Account<'info, DataTierConfig>,
+
+    pub authority: Signer<'info>,
+}
+
+// 为在引入价格档位缓存之前创建的市场补建缓存所需的账户（任何人都可以调用，由调用者支付租金）
+#[derive(Accounts)]
+pub struct InitializePriceLevelCache<'info> {
+    pub market: Account<'info, Market>,
+
+    #[account(has_one = market @ ErrorCode::InvalidMarketId)]
+    pub order_book: AccountLoader<'info, OrderBook>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = OptimizedStorage::get_price_level_cache_size(),
+        seeds = [b"price_level_cache", market.key().as_ref()],
+        bump
+    )]
+    pub price_level_cache: Account<'info, PriceLevelCache>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// 初始化用户交易历史所需的账户（每个用户在每个市场各一个，同时创建其归档页面索引）
+#[derive(Accounts)]
+pub struct InitializeUserTradeHistory<'info> {
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = OptimizedStorage::get_user_trade_history_size(),
+        seeds = [b"trade_history", market.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub user_trade_history: Account<'info, UserTradeHistory>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = OptimizedStorage::get_storage_index_size(),
+        seeds = [b"storage_index", user_trade_history.key().as_ref()],
+        bump
+    )]
+    pub storage_index: Account<'info, StorageIndex>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// 将热数据归档到新的存储页面所需的账户
+#[derive(Accounts)]
+#[instruction(page_id: u64)]
+pub struct ArchiveTradeHistory<'info> {
+    #[account(
+        seeds = [b"data_tier_config", data_tier_config.market.as_ref()],
+        bump = data_tier_config.bump,
+        has_one = authority @ ErrorCode::UnauthorizedOperation
+    )]
+    pub data_tier_config: Account<'info, DataTierConfig>,
+
+    // 用于分配事件序号
+    #[account(mut, address = data_tier_config.market @ ErrorCode::InvalidMarketId)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"storage_index", user_trade_history.key().as_ref()],
+        bump = storage_index.bump
+    )]
+    pub storage_index: Account<'info, StorageIndex>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"trade_history",
+            data_tier_config.market.as_ref(),
+            user_trade_history.owner.as_ref()
+        ],
+        bump = user_trade_history.bump
+    )]
+    pub user_trade_history: Account<'info, UserTradeHistory>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = OptimizedStorage::get_storage_page_size(),
+        seeds = [b"storage_page", user_trade_history.key().as_ref(), &page_id.to_le_bytes()],
+        bump
+    )]
+    pub archive_page: Account<'info, StoragePage>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// 用户主动归档交易历史所需的账户
+#[derive(Accounts)]
+#[instruction(page_id: u64)]
+pub struct FlushTradeHistory<'info> {
+    #[account(
+        seeds = [b"data_tier_config", data_tier_config.market.as_ref()],
+        bump = data_tier_config.bump
+    )]
+    pub data_tier_config: Account<'info, DataTierConfig>,
+
+    // 用于分配事件序号
+    #[account(mut, address = data_tier_config.market @ ErrorCode::InvalidMarketId)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"storage_index", user_trade_history.key().as_ref()],
+        bump = storage_index.bump
+    )]
+    pub storage_index: Account<'info, StorageIndex>,
+
+    #[account(
+        mut,
+        seeds = [b"trade_history", data_tier_config.market.as_ref(), owner.key().as_ref()],
+        bump = user_trade_history.bump,
+        has_one = owner @ ErrorCode::UnauthorizedOperation
+    )]
+    pub user_trade_history: Account<'info, UserTradeHistory>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = OptimizedStorage::get_storage_page_size(),
+        seeds = [b"storage_page", user_trade_history.key().as_ref(), &page_id.to_le_bytes()],
+        bump
+    )]
+    pub archive_page: Account<'info, StoragePage>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// 预先创建下一个归档页面所需的账户，缓冲区在成交时写满后由交易指令把最旧的记录归档到该页面
+#[derive(Accounts)]
+#[instruction(page_id: u64)]
+pub struct AllocateTradeHistoryPage<'info> {
+    #[account(
+        seeds = [b"trade_history", user_trade_history.market.as_ref(), owner.key().as_ref()],
+        bump = user_trade_history.bump,
+        has_one = owner @ ErrorCode::UnauthorizedOperation
+    )]
+    pub user_trade_history: Account<'info, UserTradeHistory>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = OptimizedStorage::get_storage_page_size(),
+        seeds = [b"storage_page", user_trade_history.key().as_ref(), &page_id.to_le_bytes()],
+        bump
+    )]
+    pub archive_page: Account<'info, StoragePage>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// 压缩冷数据页面所需的账户
+#[derive(Accounts)]
+pub struct CompressColdPage<'info> {
+    #[account(
+        seeds = [b"data_tier_config", data_tier_config.market.as_ref()],
+        bump = data_tier_config.bump,
+        has_one = authority @ ErrorCode::UnauthorizedOperation
+    )]
+    pub data_tier_config: Account<'info, DataTierConfig>,
+
+    // 用于分配事件序号
+    #[account(mut, address = data_tier_config.market @ ErrorCode::InvalidMarketId)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"storage_index", user_trade_history.key().as_ref()],
+        bump = storage_index.bump
+    )]
+    pub storage_index: Account<'info, StorageIndex>,
+
+    #[account(
+        seeds = [
+            b"trade_history",
+            data_tier_config.market.as_ref(),
+            user_trade_history.owner.as_ref()
+        ],
+        bump = user_trade_history.bump
+    )]
+    pub user_trade_history: Account<'info, UserTradeHistory>,
+
+    #[account(
+        mut,
+        seeds = [b"storage_page", user_trade_history.key().as_ref(), &page.page_id.to_le_bytes()],
+        bump
+    )]
+    pub page: Account<'info, StoragePage>,
+
+    pub authority: Signer<'info>,
+}
+
+// 合并相邻冷数据页面所需的账户
+#[derive(Accounts)]
+pub struct CompactStoragePages<'info> {
+    #[account(
+        seeds = [b"data_tier_config", data_tier_config.market.as_ref()],
+        bump = data_tier_config.bump,
+        has_one = authority @ ErrorCode::UnauthorizedOperation
+    )]
+    pub data_tier_config: Account<'info, DataTierConfig>,
+
+    // 用于分配事件序号
+    #[account(mut, address = data_tier_config.market @ ErrorCode::InvalidMarketId)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"storage_index", user_trade_history.key().as_ref()],
+        bump = storage_index.bump
+    )]
+    pub storage_index: Account<'info, StorageIndex>,
+
+    #[account(
+        seeds = [
+            b"trade_history",
+            data_tier_config.market.as_ref(),
+            user_trade_history.owner.as_ref()
+        ],
+        bump = user_trade_history.bump
+    )]
+    pub user_trade_history: Account<'info, UserTradeHistory>,
+
+    #[account(
+        mut,
+        seeds = [b"storage_page", user_trade_history.key().as_ref(), &page.page_id.to_le_bytes()],
+        bump,
+        constraint = page.next_page == Some(next_page.key()) @ ErrorCode::InvalidParameters
+    )]
+    pub page: Account<'info, StoragePage>,
+
+    // 被合并的旧页面，合并后关闭并把租金退还给交易历史的所有者
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"storage_page", user_trade_history.key().as_ref(), &next_page.page_id.to_le_bytes()],
+        bump
+    )]
+    pub next_page: Account<'info, StoragePage>,
+
+    /// CHECK: 交易历史的所有者，只接收关闭页面退还的租金
+    #[account(mut, address = user_trade_history.owner)]
+    pub owner: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+}
+
+// 清理过期冷数据页面所需的账户
+#[derive(Accounts)]
+pub struct CleanupExpiredPages<'info> {
+    #[account(
+        seeds = [b"data_tier_config", data_tier_config.market.as_ref()],
+        bump = data_tier_config.bump,
+        has_one = authority @ ErrorCode::UnauthorizedOperation
+    )]
+    pub data_tier_config: Account<'info, DataTierConfig>,
+
+    // 用于分配事件序号
+    #[account(mut, address = data_tier_config.market @ ErrorCode::InvalidMarketId)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"storage_index", user_trade_history.key().as_ref()],
+        bump = storage_index.bump
+    )]
+    pub storage_index: Account<'info, StorageIndex>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"trade_history",
+            data_tier_config.market.as_ref(),
+            user_trade_history.owner.as_ref()
+        ],
+        bump = user_trade_history.bump
+    )]
+    pub user_trade_history: Account<'info, UserTradeHistory>,
+
+    // 链中指向待清理页面的前一个页面；待清理页面是链头时不传
+    #[account(
+        mut,
+        seeds = [b"storage_page", user_trade_history.key().as_ref(), &prev_page.page_id.to_le_bytes()],
+        bump,
+        constraint = prev_page.next_page == Some(page.key()) @ ErrorCode::InvalidParameters
+    )]
+    pub prev_page: Option<Account<'info, StoragePage>>,
+
+    // 清理后关闭并把租金退还给交易历史的所有者
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"storage_page", user_trade_history.key().as_ref(), &page.page_id.to_le_bytes()],
+        bump
+    )]
+    pub page: Account<'info, StoragePage>,
+
+    /// CHECK: 交易历史的所有者，只接收关闭页面退还的租金
+    #[account(mut, address = user_trade_history.owner)]
+    pub owner: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+}
+
+// 初始化数据分层配置
+pub fn initialize_data_tier_config(
+    ctx: Context<InitializeDataTierConfig>,
+    hot_data_max_age_slots: u64,
+    warm_data_max_age_slots: u64,
+    cold_data_compression: bool,
+    auto_archive_enabled: bool,
+    auto_archive_age_slots: u64,
+) -> Result<()> {
+    require!(
+        hot_data_max_age_slots <= warm_data_max_age_slots,
+        ErrorCode::InvalidParameters
+    );
+
+    let market = ctx.accounts.market.key();
+
+    let config = &mut ctx.accounts.data_tier_config;
+    config.schema_version = CURRENT_SCHEMA_VERSION;
+    config.market = market;
+    config.authority = ctx.accounts.authority.key();
+    config.bump = *ctx.bumps.get("data_tier_config").unwrap();
+    OptimizedStorage::update_data_tier_config(
+        config,
+        hot_data_max_age_slots,
+        warm_data_max_age_slots,
+        cold_data_compression,
+        auto_archive_enabled,
+        auto_archive_age_slots,
+    )?;
+
+    Ok(())
+}
+
+// 更新数据分层配置
+pub fn update_data_tier_config(
+    ctx: Context<UpdateDataTierConfig>,
+    hot_data_max_age_slots: u64,
+    warm_data_max_age_slots: u64,
+    cold_data_compression: bool,
+    auto_archive_enabled: bool,
+    auto_archive_age_slots: u64,
+) -> Result<()> {
+    require!(
+        hot_data_max_age_slots <= warm_data_max_age_slots,
+        ErrorCode::InvalidParameters
+    );
+
+    OptimizedStorage::update_data_tier_config(
+        &mut ctx.accounts.data_tier_config,
+        hot_data_max_age_slots,
+        warm_data_max_age_slots,
+        cold_data_compression,
+        auto_archive_enabled,
+        auto_archive_age_slots,
+    )
+}
+
+// 创建价格档位缓存并按当前订单簿填充前N档
+pub fn initialize_price_level_cache(ctx: Context<InitializePriceLevelCache>) -> Result<()> {
+    let cache = &mut ctx.accounts.price_level_cache;
+    OptimizedStorage::initialize_price_level_cache(cache, ctx.accounts.market.key());
+
+    let order_book = ctx.accounts.order_book.load()?;
+    OptimizedStorage::refresh_price_level_cache(cache, &order_book, Clock::get()?.slot)
+}
+
+// 初始化用户交易历史
+pub fn initialize_user_trade_history(ctx: Context<InitializeUserTradeHistory>) -> Result<()> {
+    let market = ctx.accounts.market.key();
+    let history_key = ctx.accounts.user_trade_history.key();
+
+    let history = &mut ctx.accounts.user_trade_history;
+    OptimizedStorage::initialize_user_trade_history(history, market, ctx.accounts.owner.key());
+    history.bump = *ctx.bumps.get("user_trade_history").unwrap();
+
+    let index = &mut ctx.accounts.storage_index;
+    index.schema_version = CURRENT_SCHEMA_VERSION;
+    index.market = market;
+    index.user_trade_history = history_key;
+    index.last_update_slot = Clock::get()?.slot;
+    index.index_entries_count = 0;
+    index.bump = *ctx.bumps.get("storage_index").unwrap();
+
+    Ok(())
+}
+
+// 将超过热数据年龄的交易记录迁移到新的温数据页面，并挂到归档链头
+pub fn archive_trade_history(ctx: Context<ArchiveTradeHistory>, page_id: u64) -> Result<()> {
+    let history = &mut ctx.accounts.user_trade_history;
+    require!(
+        page_id == history.archived_page_count,
+        ErrorCode::InvalidParameters
+    );
+
+    let clock = Clock::get()?;
+    let config = &ctx.accounts.data_tier_config;
+    let page_key = ctx.accounts.archive_page.key();
+    let page = &mut ctx.accounts.archive_page;
+
+    OptimizedStorage::initialize_storage_page(
+        page,
+        config.market,
+        StoragePageType::TradeHistory,
+        page_id,
+    );
+    let moved = OptimizedStorage::archive_aged_trades(history, page, config, clock.unix_timestamp)?;
+
+    link_archive_page(
+        history,
+        page,
+        page_key,
+        &mut ctx.accounts.storage_index,
+        &mut ctx.accounts.market,
+        moved,
+        clock.slot,
+    )
+}
+
+// 用户主动将交易历史缓冲区中最旧的记录归档，为新记录腾出空间
+pub fn flush_trade_history(ctx: Context<FlushTradeHistory>, page_id: u64) -> Result<()> {
+    let history = &mut ctx.accounts.user_trade_history;
+    require!(
+        page_id == history.archived_page_count,
+        ErrorCode::InvalidParameters
+    );
+
+    let clock = Clock::get()?;
+    let market = ctx.accounts.market.key();
+    let page_key = ctx.accounts.archive_page.key();
+    let page = &mut ctx.accounts.archive_page;
+
+    OptimizedStorage::initialize_storage_page(page, market, StoragePageType::TradeHistory, page_id);
+    let count = history.trade_count as usize;
+    let moved = OptimizedStorage::flush_oldest_trades(history, page, count)?;
+
+    link_archive_page(
+        history,
+        page,
+        page_key,
+        &mut ctx.accounts.storage_index,
+        &mut ctx.accounts.market,
+        moved,
+        clock.slot,
+    )
+}
+
+// 创建下一个归档页面，暂不挂到归档链上
+pub fn allocate_trade_history_page(
+    ctx: Context<AllocateTradeHistoryPage>,
+    page_id: u64,
+) -> Result<()> {
+    let history = &ctx.accounts.user_trade_history;
+    require!(
+        page_id == history.archived_page_count,
+        ErrorCode::InvalidParameters
+    );
+
+    let market = history.market;
+    OptimizedStorage::initialize_storage_page(
+        &mut ctx.accounts.archive_page,
+        market,
+        StoragePageType::TradeHistory,
+        page_id,
+    );
+
+    Ok(())
+}
+
+// 交易历史缓冲区已满时，把最旧的记录归档到预先创建的下一个归档页面并挂到链头
+// 缓冲区未满时不做任何事；已满但没有可用页面时返回TradeHistoryFull，缓冲区保持不变
+pub fn archive_full_history<'info>(
+    history: &mut Account<'info, UserTradeHistory>,
+    archive_page: Option<&mut Account<'info, StoragePage>>,
+    storage_index: Option<&mut Account<'info, StorageIndex>>,
+    market: &mut Account<'info, Market>,
+    slot: u64,
+) -> Result<()> {
+    if !history.is_full() {
+        return Ok(());
+    }
+    let (page, index) = archive_page
+        .zip(storage_index)
+        .ok_or(ErrorCode::TradeHistoryFull)?;
+
+    // 页面必须是该交易历史的下一个归档页面，且尚未写入或挂到链上
+    let history_key = history.key();
+    let page_id = history.archived_page_count;
+    let expected_page = Pubkey::find_program_address(
+        &[
+            b"storage_page",
+            history_key.as_ref(),
+            &page_id.to_le_bytes(),
+        ],
+        &crate::ID,
+    )
+    .0;
+    require!(
+        page.key() == expected_page
+            && page.page_type == StoragePageType::TradeHistory
+            && page.used_size == 0
+            && page.next_page.is_none(),
+        ErrorCode::TradeHistoryFull
+    );
+    require!(
+        index.user_trade_history == history_key,
+        ErrorCode::InvalidParameters
+    );
+
+    let page_key = page.key();
+    let moved = OptimizedStorage::flush_oldest_trades(history, page, UserTradeHistory::CAPACITY)?;
+    link_archive_page(history, page, page_key, index, market, moved, slot)
+}
+
+// 将新归档页面挂到链头并登记索引
+fn link_archive_page(
+    history: &mut UserTradeHistory,
+    page: &mut StoragePage,
+    page_key: Pubkey,
+    index: &mut StorageIndex,
+    market: &mut Account<Market>,
+    moved: usize,
+    slot: u64,
+) -> Result<()> {
+    // 新页面成为链头，指向之前的链头
+    page.next_page = history.archive_head;
+    history.archive_head = Some(page_key);
+    history.archived_page_count += 1;
+    history.last_update_slot = slot;
+
+    OptimizedStorage::upsert_index_entry(
+        index,
+        &page_key,
+        StoragePageType::TradeHistory,
+        moved as u16,
+        0,
+        slot,
+    )?;
+
+    // 热数据账户中释放的字节数 -> 温数据页面实际写入的字节数
+    EventHandler::emit_storage_optimization(
+        market.key(),
+        market.next_event_seq(),
+        StorageOptimizationType::TierMigration,
+        (moved * USER_TRADE_RAW_LEN) as u32,
+        page.used_size as u32,
+        true,
+    );
+
+    Ok(())
+}
+
+// 压缩超过温数据年龄的页面，转为冷数据
+pub fn compress_cold_page(ctx: Context<CompressColdPage>) -> Result<()> {
+    let current_slot = Clock::get()?.slot;
+    let page_key = ctx.accounts.page.key();
+    let index = &mut ctx.accounts.storage_index;
+
+    let entry = OptimizedStorage::find_index_entry(index, &page_key)
+        .ok_or(ErrorCode::StorageDataCorrupted)?;
+    let create_slot = index.entries[entry].create_slot;
+
+    let page = &mut ctx.accounts.page;
+    let (old_size, new_size) = OptimizedStorage::migrate_cold_data(
+        &ctx.accounts.data_tier_config,
+        page,
+        create_slot,
+        current_slot,
+    )?;
+
+    let item_count = index.entries[entry].item_count;
+    OptimizedStorage::upsert_index_entry(
+        index,
+        &page_key,
+        StoragePageType::TradeHistory,
+        item_count,
+        INDEX_FLAG_COMPRESSED,
+        current_slot,
+    )?;
+
+    EventHandler::emit_storage_optimization(
+        ctx.accounts.market.key(),
+        ctx.accounts.market.next_event_seq(),
+        StorageOptimizationType::Compression,
+        old_size,
+        new_size,
+        true,
+    );
+
+    Ok(())
+}
+
+// 将链中相邻的两个冷数据页面合并，关闭旧页面回收账户空间
+pub fn compact_storage_pages(ctx: Context<CompactStoragePages>) -> Result<()> {
+    let current_slot = Clock::get()?.slot;
+    let page_key = ctx.accounts.page.key();
+    let next_key = ctx.accounts.next_page.key();
+
+    let item_count =
+        OptimizedStorage::defragment_storage(&mut ctx.accounts.page, &ctx.accounts.next_page)?;
+
+    let index = &mut ctx.accounts.storage_index;
+    OptimizedStorage::remove_index_entry(index, &next_key, current_slot);
+    OptimizedStorage::upsert_index_entry(
+        index,
+        &page_key,
+        StoragePageType::TradeHistory,
+        item_count as u16,
+        INDEX_FLAG_COMPRESSED,
+        current_slot,
+    )?;
+
+    // 合并前占用两个页面账户，合并后只剩一个
+    let page_size = OptimizedStorage::get_storage_page_size() as u32;
+    EventHandler::emit_storage_optimization(
+        ctx.accounts.market.key(),
+        ctx.accounts.market.next_event_seq(),
+        StorageOptimizationType::Defragmentation,
+        page_size * 2,
+        page_size,
+        true,
+    );
+
+    Ok(())
+}
+
+// 清理归档链尾部已过期的页面
+pub fn cleanup_expired_pages(ctx: Context<CleanupExpiredPages>, max_age_slots: u64) -> Result<()> {
+    let current_slot = Clock::get()?.slot;
+    let page_key = ctx.accounts.page.key();
+    let index = &mut ctx.accounts.storage_index;
+
+    let create_slot = OptimizedStorage::find_index_entry(index, &page_key)
+        .map(|i| index.entries[i].create_slot)
+        .ok_or(ErrorCode::StorageDataCorrupted)?;
+
+    OptimizedStorage::cleanup_expired_data(
+        &ctx.accounts.data_tier_config,
+        &ctx.accounts.page,
+        create_slot,
+        max_age_slots,
+        current_slot,
+    )?;
+
+    // 从链中摘除该页面
+    match ctx.accounts.prev_page.as_mut() {
+        Some(prev_page) => prev_page.next_page = None,
+        None => {
+            let history = &mut ctx.accounts.user_trade_history;
+            require!(
+                history.archive_head == Some(page_key),
+                ErrorCode::InvalidParameters
+            );
+            history.archive_head = None;
+        }
+    }
+
+    OptimizedStorage::remove_index_entry(index, &page_key, current_slot);
+
+    EventHandler::emit_storage_optimization(
+        ctx.accounts.market.key(),
+        ctx.accounts.market.next_event_seq(),
+        StorageOptimizationType::HistoryTruncation,
+        OptimizedStorage::get_storage_page_size() as u32,
+        0,
+        true,
+    );
+
+    Ok(())
+}
+
+// 写入无符号LEB128变长整数
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+// zigzag编码，使绝对值小的负数也编码为短varint
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+// Adler-32校验和
+pub fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    let mut a = 1u32;
+    let mut b = 0u32;
+    for &byte in data {
+        a = (a + byte as u32) % MOD_ADLER;
+        b = (b + a) % MOD_ADLER;
+    }
+    (b << 16) | a
+}
+
+// 编码数据读取游标，越界时返回数据损坏错误
+struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos == self.data.len()
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        require!(
+            self.pos + len <= self.data.len(),
+            ErrorCode::StorageDataCorrupted
+        );
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u64(&mut self) -> Result<u64> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(self.read_bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn read_u128(&mut self) -> Result<u128> {
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(self.read_bytes(16)?);
+        Ok(u128::from_le_bytes(bytes))
+    }
+
+    fn read_varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.read_u8()?;
+            require!(shift < 64, ErrorCode::StorageDataCorrupted);
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 固定种子的线性同余生成器，保证测试数据可复现
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            self.0 >> 33
+        }
+
+        fn range(&mut self, low: u64, high: u64) -> u64 {
+            low + self.next() % (high - low + 1)
+        }
+    }
+
+    // 64条同一市场的成交：价格在±50 tick内波动、数量<1e6、间隔1-30秒
+    fn sample_trades() -> Vec<UserTrade> {
+        let mut rng = Lcg(7);
+        let market = OptimizedStorage::compress_pubkey(&Pubkey::new_unique());
+        let tick = 100u64;
+        let mut timestamp = 1_700_000_000i64;
+
+        (0..64)
+            .map(|_| {
+                timestamp += rng.range(1, 30) as i64;
+                let order_id = ((rng.next() as u128) << 96)
+                    | ((rng.next() as u128) << 64)
+                    | ((rng.next() as u128) << 32)
+                    | rng.next() as u128;
+                UserTrade {
+                    market,
+                    order_id,
+                    price: 2_000_000 + rng.range(0, 100) * tick,
+                    quantity: rng.range(1, 999_999),
+                    side: (rng.next() % 2) as u8,
+                    timestamp,
+                    fee: rng.range(0, 5_000),
+                }
+            })
+            .collect()
+    }
+
+    // 64条订单、8个不同所有者、价格在±50 tick内波动
+    fn sample_orders() -> Vec<BatchOrderEntry> {
+        let mut rng = Lcg(11);
+        let owners: Vec<Pubkey> = (0..8).map(|_| Pubkey::new_unique()).collect();
+        let tick = 100u64;
+        let mut timestamp = 1_700_000_000i64;
+
+        (0..64)
+            .map(|_| {
+                timestamp += rng.range(0, 10) as i64;
+                BatchOrderEntry {
+                    order_id: ((rng.next() as u128) << 64) | rng.next() as u128,
+                    owner: owners[rng.range(0, 7) as usize],
+                    price: 2_000_000 + rng.range(0, 100) * tick,
+                    quantity: rng.range(1, 999_999),
+                    flags: rng.range(0, 15) as u32,
+                    timestamp,
+                }
+            })
+            .collect()
+    }
+
+    fn assert_trades_eq(left: &[UserTrade], right: &[UserTrade]) {
+        assert_eq!(left.len(), right.len());
+        for (a, b) in left.iter().zip(right) {
+            assert_eq!({ a.market }, { b.market });
+            assert_eq!({ a.order_id }, { b.order_id });
+            assert_eq!({ a.price }, { b.price });
+            assert_eq!({ a.quantity }, { b.quantity });
+            assert_eq!({ a.side }, { b.side });
+            assert_eq!({ a.timestamp }, { b.timestamp });
+            assert_eq!({ a.fee }, { b.fee });
+        }
+    }
+
+    #[test]
+    fn user_trades_round_trip() {
+        let trades = sample_trades();
+        let encoded = OptimizedStorage::encode_user_trades(&trades);
+        let decoded = OptimizedStorage::decode_user_trades(&encoded).unwrap();
+        assert_trades_eq(&trades, &decoded);
+    }
+
+    #[test]
+    fn user_trades_round_trip_extreme_values() {
+        let trades = vec![
+            UserTrade {
+                market: [0xff; 8],
+                order_id: u128::MAX,
+                price: u64::MAX,
+                quantity: u64::MAX,
+                side: 1,
+                timestamp: i64::MAX,
+                fee: u64::MAX,
+            },
+            UserTrade {
+                market: [0; 8],
+                order_id: 0,
+                price: 0,
+                quantity: 0,
+                side: 0,
+                timestamp: i64::MIN,
+                fee: 0,
+            },
+        ];
+        let encoded = OptimizedStorage::encode_user_trades(&trades);
+        let decoded = OptimizedStorage::decode_user_trades(&encoded).unwrap();
+        assert_trades_eq(&trades, &decoded);
+
+        assert!(
+            OptimizedStorage::decode_user_trades(&OptimizedStorage::encode_user_trades(&[]))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn batch_orders_round_trip() {
+        let orders = sample_orders();
+        let encoded = OptimizedStorage::encode_batch_orders(&orders);
+        let decoded = OptimizedStorage::decode_batch_orders(&encoded).unwrap();
+
+        assert_eq!(orders.len(), decoded.len());
+        for (a, b) in orders.iter().zip(&decoded) {
+            assert_eq!({ a.order_id }, { b.order_id });
+            assert_eq!({ a.owner }, { b.owner });
+            assert_eq!({ a.price }, { b.price });
+            assert_eq!({ a.quantity }, { b.quantity });
+            assert_eq!({ a.flags }, { b.flags });
+            assert_eq!({ a.timestamp }, { b.timestamp });
+        }
+    }
+
+    fn empty_archive_page(market: Pubkey) -> StoragePage {
+        StoragePage {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            market,
+            page_type: StoragePageType::TradeHistory,
+            page_id: 0,
+            used_size: 0,
+            total_size: 1024,
+            next_page: None,
+            last_access_slot: 0,
+            first_sequence: 0,
+            format_version: STORAGE_FORMAT_RAW,
+            checksum: adler32(&[]),
+            data: [0; 1024],
+        }
+    }
+
+    #[test]
+    fn full_history_rejects_push_until_archived() {
+        let trades = sample_trades();
+        let mut history = UserTradeHistory {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            market: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            last_update_slot: 0,
+            trade_count: 0,
+            trades: [trades[0]; UserTradeHistory::CAPACITY],
+            head: 0,
+            next_sequence: 0,
+            archived_page_count: 0,
+            archive_head: None,
+            bump: 0,
+        };
+
+        for i in 0..UserTradeHistory::CAPACITY {
+            assert_eq!(history.push(trades[i % trades.len()]).unwrap(), i as u64);
+        }
+        assert!(history.is_full());
+
+        // 写满后不覆盖，缓冲区保持不变
+        assert!(history.push(trades[0]).is_err());
+        assert_eq!({ history.trade_count } as usize, UserTradeHistory::CAPACITY);
+        assert_eq!({ history.next_sequence }, UserTradeHistory::CAPACITY as u64);
+        assert_eq!(history.oldest_sequence(), 0);
+        assert_trades_eq(&[history.get(0)], &[trades[0]]);
+
+        // 归档最旧的记录后可以继续写入，序号连续
+        let mut page = empty_archive_page(history.market);
+        let moved = OptimizedStorage::flush_oldest_trades(
+            &mut history,
+            &mut page,
+            UserTradeHistory::CAPACITY,
+        )
+        .unwrap();
+        assert_eq!(moved, page.data.len() / USER_TRADE_RAW_LEN);
+        assert_eq!(
+            history.push(trades[0]).unwrap(),
+            UserTradeHistory::CAPACITY as u64
+        );
+
+        // 缓冲区和归档页面合起来没有序号空缺
+        let last = UserTradeHistory::CAPACITY as u64;
+        let result = OptimizedStorage::get_recent_trades(
+            &history,
+            &[page],
+            None,
+            UserTradeHistory::CAPACITY + 1,
+        )
+        .unwrap();
+        let sequences: Vec<u64> = result.trades.iter().map(|&(seq, _)| seq).collect();
+        let expected: Vec<u64> = (0..=last).rev().collect();
+        assert_eq!(sequences, expected);
+        assert_eq!(result.next_cursor, None);
+    }
+
+    #[test]
+    fn corrupted_data_is_rejected() {
+        let encoded = OptimizedStorage::encode_user_trades(&sample_trades());
+
+        // 截断和尾部多余字节都视为损坏
+        assert!(OptimizedStorage::decode_user_trades(&encoded[..encoded.len() - 1]).is_err());
+        let mut padded = encoded.clone();
+        padded.push(0);
+        assert!(OptimizedStorage::decode_user_trades(&padded).is_err());
+
+        // 超长的varint
+        assert!(OptimizedStorage::decode_user_trades(&[0xff; 11]).is_err());
+    }
+
+    // 压缩比以百分数比较（217表示2.17:1），与encode_user_trades/encode_batch_orders文档中的数字一致
+    fn ratio_percent(raw: usize, encoded: usize) -> usize {
+        raw * 100 / encoded
+    }
+
+    #[test]
+    fn codec_compression_ratio() {
+        let trades = sample_trades();
+        let raw = trades.len() * USER_TRADE_RAW_LEN;
+        let encoded = OptimizedStorage::encode_user_trades(&trades).len();
+        assert_eq!(raw, 3648);
+        assert!(
+            ratio_percent(raw, encoded) >= 217,
+            "user trades: {} -> {} bytes",
+            raw,
+            encoded
+        );
+
+        // 对照：定长原始格式再经过通用compress_data
+        let generic =
+            OptimizedStorage::compress_data(&OptimizedStorage::encode_raw_user_trades(&trades));
+        assert!(generic.len() > encoded);
+
+        let orders = sample_orders();
+        let raw = orders.len() * mem::size_of::<BatchOrderEntry>();
+        let encoded = OptimizedStorage::encode_batch_orders(&orders).len();
+        assert_eq!(raw, 4864);
+        assert!(
+            ratio_percent(raw, encoded) >= 270,
+            "batch orders: {} -> {} bytes",
+            raw,
+            encoded
+        );
+    }
+}
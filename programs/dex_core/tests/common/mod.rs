@@ -0,0 +1,325 @@
+// 集成测试共用的账户构造和交易发送工具
+// 每个测试文件只用到其中一部分
+#![allow(dead_code)]
+
+use anchor_lang::error::ERROR_CODE_OFFSET;
+use anchor_lang::{AccountSerialize, Discriminator};
+use anchor_spl::token::spl_token;
+use dex_core::migration::CURRENT_SCHEMA_VERSION;
+use dex_core::storage::{OptimizedStorage, PriceLevel, PriceLevelCache};
+use dex_core::{ErrorCode, Market, OrderBook};
+use solana_program::program_option::COption;
+use solana_program::program_pack::Pack;
+use solana_program::rent::Rent;
+use solana_program::system_program;
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::account_info::AccountInfo;
+use solana_sdk::entrypoint::ProgramResult;
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+
+// Anchor生成的entry要求账户切片与AccountInfo同生命周期，这里复制一份账户列表以适配processor!
+fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    dex_core::entry(program_id, accounts, data)
+}
+
+// 以原生方式运行程序，不需要预先构建BPF文件，但不统计计算单元
+pub fn program_test() -> ProgramTest {
+    ProgramTest::new("dex_core", dex_core::ID, processor!(process_instruction))
+}
+
+// 加载 cargo build-sbf 生成的 dex_core.so，用于统计计算单元
+pub fn bpf_program_test() -> ProgramTest {
+    let mut program_test = ProgramTest::new("dex_core", dex_core::ID, None);
+    program_test.prefer_bpf(true);
+    program_test
+}
+
+fn rent_exempt_account(data: Vec<u8>, owner: Pubkey) -> Account {
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+// 写入一个带有SOL余额的系统账户
+pub fn add_wallet(program_test: &mut ProgramTest, wallet: &Pubkey, lamports: u64) {
+    program_test.add_account(
+        *wallet,
+        Account {
+            lamports,
+            data: vec![],
+            owner: system_program::ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
+
+// 写入一个已初始化的SPL代币铸币账户
+pub fn add_mint(program_test: &mut ProgramTest, mint: &Pubkey, authority: &Pubkey, decimals: u8) {
+    let mut data = vec![0u8; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+        mint_authority: COption::Some(*authority),
+        supply: u64::MAX / 2,
+        decimals,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    program_test.add_account(*mint, rent_exempt_account(data, spl_token::ID));
+}
+
+// 写入一个已初始化的SPL代币账户
+pub fn add_token_account(
+    program_test: &mut ProgramTest,
+    address: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) {
+    let mut data = vec![0u8; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        delegate: COption::None,
+        state: spl_token::state::AccountState::Initialized,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    program_test.add_account(*address, rent_exempt_account(data, spl_token::ID));
+}
+
+// 写入一个dex_core拥有的Anchor账户，space不含8字节鉴别符
+pub fn add_program_account<T: AccountSerialize>(
+    program_test: &mut ProgramTest,
+    address: &Pubkey,
+    value: &T,
+    space: usize,
+) {
+    let mut data = Vec::with_capacity(8 + space);
+    value.try_serialize(&mut data).unwrap();
+    assert!(data.len() <= 8 + space);
+    data.resize(8 + space, 0);
+    program_test.add_account(*address, rent_exempt_account(data, dex_core::ID));
+}
+
+// 读取dex_core拥有的Anchor账户
+pub async fn get_program_account<T: anchor_lang::AccountDeserialize>(
+    context: &mut ProgramTestContext,
+    address: &Pubkey,
+) -> T {
+    let account = context
+        .banks_client
+        .get_account(*address)
+        .await
+        .unwrap()
+        .expect("account not found");
+    T::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+// 读取SPL代币账户余额
+pub async fn token_balance(context: &mut ProgramTestContext, address: &Pubkey) -> u64 {
+    let account = context
+        .banks_client
+        .get_account(*address)
+        .await
+        .unwrap()
+        .expect("token account not found");
+    spl_token::state::Account::unpack(&account.data)
+        .unwrap()
+        .amount
+}
+
+// 由测试直接写入的市场账户及其PDA
+// 订单簿超过CPI创建账户的10KB上限，因此测试中不经过initialize_market
+pub struct MarketFixture {
+    pub market: Pubkey,
+    pub order_book: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub base_vault: Pubkey,
+    pub quote_vault: Pubkey,
+    pub market_authority: Pubkey,
+    pub price_level_cache: Pubkey,
+}
+
+impl MarketFixture {
+    pub fn pda(&self, seed: &[u8]) -> Pubkey {
+        Pubkey::find_program_address(&[seed, self.market.as_ref()], &dex_core::ID).0
+    }
+
+    pub fn open_orders(&self, owner: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"open_orders", owner.as_ref(), self.market.as_ref()],
+            &dex_core::ID,
+        )
+        .0
+    }
+}
+
+// 写入一个lot_size和tick_size都为1、不收手续费的市场
+pub fn add_market(program_test: &mut ProgramTest) -> MarketFixture {
+    let market = Pubkey::new_unique();
+    let (market_authority, market_authority_bump) =
+        Pubkey::find_program_address(&[b"market_authority", market.as_ref()], &dex_core::ID);
+    let fixture = MarketFixture {
+        market,
+        order_book: Pubkey::new_unique(),
+        base_mint: Pubkey::new_unique(),
+        quote_mint: Pubkey::new_unique(),
+        base_vault: Pubkey::new_unique(),
+        quote_vault: Pubkey::new_unique(),
+        market_authority,
+        price_level_cache: Pubkey::find_program_address(
+            &[b"price_level_cache", market.as_ref()],
+            &dex_core::ID,
+        )
+        .0,
+    };
+
+    add_mint(program_test, &fixture.base_mint, &market_authority, 6);
+    add_mint(program_test, &fixture.quote_mint, &market_authority, 6);
+    add_token_account(
+        program_test,
+        &fixture.base_vault,
+        &fixture.base_mint,
+        &market_authority,
+        0,
+    );
+    add_token_account(
+        program_test,
+        &fixture.quote_vault,
+        &fixture.quote_mint,
+        &market_authority,
+        0,
+    );
+
+    let market_account = Market {
+        schema_version: CURRENT_SCHEMA_VERSION,
+        base_mint: fixture.base_mint,
+        quote_mint: fixture.quote_mint,
+        lot_size: 1,
+        tick_size: 1,
+        base_decimals: 6,
+        quote_decimals: 6,
+        maker_fee: 0,
+        taker_fee: 0,
+        base_vault: fixture.base_vault,
+        quote_vault: fixture.quote_vault,
+        lp_token_mint: None,
+        reward_mint: None,
+        name: "TEST/USDC".to_string(),
+        active: true,
+        last_traded_price: None,
+        total_volume: 0,
+        total_quote_volume: 0,
+        total_trades: 0,
+        total_fees_collected: 0,
+        min_base_order_size: 1,
+        min_quote_order_size: 1,
+        lp_reward_rate: 0,
+        cross_chain_enabled: false,
+        stress_test_mode: false,
+        market_authority_bump,
+        access_control: None,
+        event_sequence: 0,
+    };
+    add_program_account(program_test, &market, &market_account, Market::LEN);
+
+    let mut data = vec![0u8; 8 + std::mem::size_of::<OrderBook>()];
+    data[..8].copy_from_slice(&OrderBook::DISCRIMINATOR);
+    let order_book: &mut OrderBook = bytemuck::from_bytes_mut(&mut data[8..]);
+    order_book.initialize();
+    order_book.market = market;
+    program_test.add_account(fixture.order_book, rent_exempt_account(data, dex_core::ID));
+
+    let empty_level = PriceLevel {
+        price: 0,
+        quantity: 0,
+        orders_count: 0,
+    };
+    let mut cache = PriceLevelCache {
+        schema_version: 0,
+        market: Pubkey::default(),
+        last_update_slot: 0,
+        bid_levels_count: 0,
+        ask_levels_count: 0,
+        bid_levels: [empty_level; 20],
+        ask_levels: [empty_level; 20],
+    };
+    OptimizedStorage::initialize_price_level_cache(&mut cache, market);
+    add_program_account(
+        program_test,
+        &fixture.price_level_cache,
+        &cache,
+        OptimizedStorage::get_price_level_cache_size() - 8,
+    );
+
+    fixture
+}
+
+// 发送交易，payer之外的签名者由调用方给出
+pub async fn send(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), BanksClientError> {
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(transaction).await
+}
+
+// 发送交易并返回消耗的计算单元
+pub async fn send_measured(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> u64 {
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        context.last_blockhash,
+    );
+    let result = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    result.result.unwrap();
+    result.metadata.unwrap().compute_units_consumed
+}
+
+// 断言交易因dex_core的指定错误码失败
+pub fn assert_program_error(result: Result<(), BanksClientError>, error: ErrorCode) {
+    let code = ERROR_CODE_OFFSET + error as u32;
+    match result.unwrap_err().unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(actual)) => {
+            assert_eq!(actual, code)
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
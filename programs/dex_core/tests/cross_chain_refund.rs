@@ -0,0 +1,324 @@
+// 跨链订单的完成证明与退款：完成证明达到所需确认数后不能过期退款，有任何完成证明后不能按失败证明退款
+mod common;
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use common::MarketFixture;
+use dex_core::cross_chain::{
+    CrossChain, CrossChainBridgeConfig, CrossChainBridgeStats, CrossChainOrder, CrossChainTxStatus,
+};
+use dex_core::migration::CURRENT_SCHEMA_VERSION;
+use dex_core::ErrorCode;
+use solana_program::sysvar;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::ed25519_instruction::new_ed25519_instruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const AMOUNT: u64 = 1_000_000;
+const FEE: u64 = 1_000;
+const NONCE: u64 = 42;
+const TARGET_TX_HASH: [u8; 32] = [9; 32];
+// 两个中继器，需要两个证明才能确认
+const RELAYER_COUNT: usize = 2;
+const REQUIRED_CONFIRMATIONS: u8 = 2;
+
+struct Bridge {
+    market: MarketFixture,
+    relayers: Vec<Keypair>,
+    bridge_config: Pubkey,
+    bridge_stats: Pubkey,
+    order: Pubkey,
+    escrow_vault: Pubkey,
+    owner_token_account: Pubkey,
+}
+
+// 写入跨链桥配置、统计、一笔创建于很久以前（已过期）的待处理订单和持有该订单资金的托管金库
+async fn setup() -> (ProgramTestContext, Bridge) {
+    let mut program_test = common::program_test();
+    let market = common::add_market(&mut program_test);
+    let relayers: Vec<Keypair> = (0..RELAYER_COUNT).map(|_| Keypair::new()).collect();
+    let owner = Pubkey::new_unique();
+
+    let (bridge_config, bridge_bump) =
+        Pubkey::find_program_address(&[b"cross_chain_bridge"], &dex_core::ID);
+    let mut config = CrossChainBridgeConfig {
+        schema_version: CURRENT_SCHEMA_VERSION,
+        required_confirmations: REQUIRED_CONFIRMATIONS,
+        bump: bridge_bump,
+        ..Default::default()
+    };
+    for (slot, relayer) in config.relayers.iter_mut().zip(&relayers) {
+        *slot = relayer.pubkey();
+    }
+    common::add_program_account(
+        &mut program_test,
+        &bridge_config,
+        &config,
+        std::mem::size_of::<CrossChainBridgeConfig>(),
+    );
+
+    let bridge_stats = Pubkey::find_program_address(&[b"cross_chain_stats"], &dex_core::ID).0;
+    let stats = CrossChainBridgeStats {
+        schema_version: CURRENT_SCHEMA_VERSION,
+        total_volume: AMOUNT,
+        total_tx_count: 1,
+        chain_volumes: [0; 10],
+        daily_stats: Default::default(),
+        total_refunded: 0,
+        refund_count: 0,
+        global_outflow: Default::default(),
+    };
+    common::add_program_account(
+        &mut program_test,
+        &bridge_stats,
+        &stats,
+        std::mem::size_of::<CrossChainBridgeStats>(),
+    );
+
+    let (order, order_bump) = Pubkey::find_program_address(
+        &[b"cross_chain_order", owner.as_ref(), &NONCE.to_le_bytes()],
+        &dex_core::ID,
+    );
+    let order_account = CrossChainOrder {
+        schema_version: CURRENT_SCHEMA_VERSION,
+        owner,
+        source_chain_id: 0,
+        target_chain_id: 1,
+        source_tx_hash: [0; 32],
+        target_tx_hash: None,
+        token_address: market.base_mint,
+        amount: AMOUNT,
+        fee: FEE,
+        nonce: NONCE,
+        status: CrossChainTxStatus::Pending as u8,
+        created_at: 0,
+        confirmed_at: None,
+        data: [0; 64],
+        market: market.market,
+        delivery_attestations: 0,
+        bump: order_bump,
+    };
+    common::add_program_account(
+        &mut program_test,
+        &order,
+        &order_account,
+        std::mem::size_of::<CrossChainOrder>(),
+    );
+
+    let escrow_vault = Pubkey::find_program_address(
+        &[b"cross_chain_escrow", market.base_mint.as_ref()],
+        &dex_core::ID,
+    )
+    .0;
+    common::add_token_account(
+        &mut program_test,
+        &escrow_vault,
+        &market.base_mint,
+        &bridge_config,
+        AMOUNT + FEE,
+    );
+    let owner_token_account = Pubkey::new_unique();
+    common::add_token_account(
+        &mut program_test,
+        &owner_token_account,
+        &market.base_mint,
+        &owner,
+        0,
+    );
+
+    let context = program_test.start_with_context().await;
+    let bridge = Bridge {
+        market,
+        relayers,
+        bridge_config,
+        bridge_stats,
+        order,
+        escrow_vault,
+        owner_token_account,
+    };
+    (context, bridge)
+}
+
+fn ed25519_ix(signer: &Keypair, message: &[u8]) -> Instruction {
+    let keypair = ed25519_dalek::Keypair::from_bytes(&signer.to_bytes()).unwrap();
+    new_ed25519_instruction(&keypair, message)
+}
+
+// 中继器对订单完成证明的签名指令，后面跟着提交证明的confirm_cross_chain_order指令
+async fn confirm_ixs(
+    context: &mut ProgramTestContext,
+    bridge: &Bridge,
+    relayers: &[&Keypair],
+) -> Vec<Instruction> {
+    let order: CrossChainOrder = common::get_program_account(context, &bridge.order).await;
+    let delivery_hash = CrossChain::generate_delivery_hash(&order, &TARGET_TX_HASH);
+
+    let mut ixs: Vec<Instruction> = relayers
+        .iter()
+        .map(|relayer| ed25519_ix(relayer, &delivery_hash))
+        .collect();
+    ixs.push(Instruction {
+        program_id: dex_core::ID,
+        accounts: dex_core::accounts::ConfirmCrossChainOrder {
+            market: bridge.market.market,
+            bridge_config: bridge.bridge_config,
+            order: bridge.order,
+            instructions: sysvar::instructions::ID,
+        }
+        .to_account_metas(None),
+        data: dex_core::instruction::ConfirmCrossChainOrder {
+            target_tx_hash: TARGET_TX_HASH,
+            relayer_signers: relayers.iter().map(|relayer| relayer.pubkey()).collect(),
+        }
+        .data(),
+    });
+    ixs
+}
+
+// 退款指令，attesting_relayers为空时是过期退款，否则是中继器证明失败的退款
+fn refund_ix(bridge: &Bridge, attesting_relayers: Vec<Pubkey>) -> Instruction {
+    Instruction {
+        program_id: dex_core::ID,
+        accounts: dex_core::accounts::RefundCrossChainOrder {
+            market: bridge.market.market,
+            bridge_config: bridge.bridge_config,
+            bridge_stats: bridge.bridge_stats,
+            order: bridge.order,
+            escrow_vault: bridge.escrow_vault,
+            owner_token_account: bridge.owner_token_account,
+            instructions: sysvar::instructions::ID,
+            token_program: anchor_spl::token::ID,
+        }
+        .to_account_metas(None),
+        data: dex_core::instruction::RefundCrossChainOrder { attesting_relayers }.data(),
+    }
+}
+
+fn expiry_refund_ix(bridge: &Bridge) -> Instruction {
+    refund_ix(bridge, vec![])
+}
+
+// 中继器对订单执行失败证明的签名指令，后面跟着按失败证明退款的指令
+async fn failure_refund_ixs(
+    context: &mut ProgramTestContext,
+    bridge: &Bridge,
+    relayers: &[&Keypair],
+) -> Vec<Instruction> {
+    let order: CrossChainOrder = common::get_program_account(context, &bridge.order).await;
+    let failure_hash = CrossChain::generate_failure_hash(&order);
+
+    let mut ixs: Vec<Instruction> = relayers
+        .iter()
+        .map(|relayer| ed25519_ix(relayer, &failure_hash))
+        .collect();
+    ixs.push(refund_ix(
+        bridge,
+        relayers.iter().map(|relayer| relayer.pubkey()).collect(),
+    ));
+    ixs
+}
+
+#[tokio::test]
+async fn delivered_order_cannot_be_refunded() {
+    let (mut context, bridge) = setup().await;
+    let relayers: Vec<&Keypair> = bridge.relayers.iter().collect();
+
+    let ixs = confirm_ixs(&mut context, &bridge, &relayers).await;
+    common::send(&mut context, &ixs, &[]).await.unwrap();
+
+    let order: CrossChainOrder = common::get_program_account(&mut context, &bridge.order).await;
+    assert_eq!(order.status, CrossChainTxStatus::Confirmed as u8);
+    assert_eq!(order.target_tx_hash, Some(TARGET_TX_HASH));
+    assert!(order.confirmed_at.is_some());
+
+    // 订单早已超过24小时，但目标链已经交付，托管资金不能再退回
+    let result = common::send(&mut context, &[expiry_refund_ix(&bridge)], &[]).await;
+    common::assert_program_error(result, ErrorCode::CrossChainOrderNotPending);
+    assert_eq!(
+        common::token_balance(&mut context, &bridge.escrow_vault).await,
+        AMOUNT + FEE
+    );
+    assert_eq!(
+        common::token_balance(&mut context, &bridge.owner_token_account).await,
+        0
+    );
+}
+
+#[tokio::test]
+async fn partially_attested_order_is_refunded_as_expired() {
+    let (mut context, bridge) = setup().await;
+
+    // 只有一个中继器提交了完成证明，未达到所需确认数，订单仍是待处理状态
+    let ixs = confirm_ixs(&mut context, &bridge, &[&bridge.relayers[0]]).await;
+    common::send(&mut context, &ixs, &[]).await.unwrap();
+    let order: CrossChainOrder = common::get_program_account(&mut context, &bridge.order).await;
+    assert_eq!(order.status, CrossChainTxStatus::Pending as u8);
+    assert_eq!(order.delivery_attestations, 0b01);
+
+    // 单个中继器不能锁住过期订单的资金
+    common::send(&mut context, &[expiry_refund_ix(&bridge)], &[])
+        .await
+        .unwrap();
+
+    let order: CrossChainOrder = common::get_program_account(&mut context, &bridge.order).await;
+    assert_eq!(order.status, CrossChainTxStatus::Refunded as u8);
+    assert_eq!(
+        common::token_balance(&mut context, &bridge.owner_token_account).await,
+        AMOUNT + FEE
+    );
+}
+
+#[tokio::test]
+async fn partially_attested_order_cannot_be_refunded_as_failed() {
+    let (mut context, bridge) = setup().await;
+
+    let ixs = confirm_ixs(&mut context, &bridge, &[&bridge.relayers[0]]).await;
+    common::send(&mut context, &ixs, &[]).await.unwrap();
+
+    // 已有中继器证明订单在目标链完成，其余中继器的失败证明不能再退款
+    let relayers: Vec<&Keypair> = bridge.relayers.iter().collect();
+    let ixs = failure_refund_ixs(&mut context, &bridge, &relayers).await;
+    let result = common::send(&mut context, &ixs, &[]).await;
+    common::assert_program_error(result, ErrorCode::CrossChainOrderDeliveryAttested);
+
+    let order: CrossChainOrder = common::get_program_account(&mut context, &bridge.order).await;
+    assert_eq!(order.status, CrossChainTxStatus::Pending as u8);
+    assert_eq!(
+        common::token_balance(&mut context, &bridge.escrow_vault).await,
+        AMOUNT + FEE
+    );
+}
+
+#[tokio::test]
+async fn unattested_order_is_refunded_as_failed() {
+    let (mut context, bridge) = setup().await;
+
+    let relayers: Vec<&Keypair> = bridge.relayers.iter().collect();
+    let ixs = failure_refund_ixs(&mut context, &bridge, &relayers).await;
+    common::send(&mut context, &ixs, &[]).await.unwrap();
+
+    // 失败证明退款不退手续费
+    let order: CrossChainOrder = common::get_program_account(&mut context, &bridge.order).await;
+    assert_eq!(order.status, CrossChainTxStatus::Refunded as u8);
+    assert_eq!(
+        common::token_balance(&mut context, &bridge.owner_token_account).await,
+        AMOUNT
+    );
+}
+
+#[tokio::test]
+async fn unattested_expired_order_is_refunded() {
+    let (mut context, bridge) = setup().await;
+
+    common::send(&mut context, &[expiry_refund_ix(&bridge)], &[])
+        .await
+        .unwrap();
+
+    let order: CrossChainOrder = common::get_program_account(&mut context, &bridge.order).await;
+    assert_eq!(order.status, CrossChainTxStatus::Refunded as u8);
+    assert_eq!(
+        common::token_balance(&mut context, &bridge.owner_token_account).await,
+        AMOUNT + FEE
+    );
+}
@@ -0,0 +1,208 @@
+// 哈希时间锁：凭正确/错误原像领取，超时前/后退款
+mod common;
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use dex_core::cross_chain::htlc::MIN_HTLC_TIMELOCK_SECS;
+use dex_core::{ErrorCode, HtlcEscrow, HtlcStatus};
+use solana_program::hash::hashv;
+use solana_program::{system_program, sysvar};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::clock::Clock;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const AMOUNT: u64 = 1_000_000;
+const PREIMAGE: [u8; 32] = [7; 32];
+
+struct Htlc {
+    sender_token_account: Pubkey,
+    recipient_token_account: Pubkey,
+    htlc: Pubkey,
+    vault: Pubkey,
+    timelock: i64,
+}
+
+// 写入发送方和接收方的代币账户，并由发送方用sha256(PREIMAGE)锁定AMOUNT
+async fn setup() -> (ProgramTestContext, Htlc) {
+    let mut program_test = common::program_test();
+    let sender = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let sender_token_account = Pubkey::new_unique();
+    let recipient_token_account = Pubkey::new_unique();
+
+    common::add_wallet(&mut program_test, &sender.pubkey(), 10_000_000_000);
+    common::add_mint(&mut program_test, &mint, &Pubkey::new_unique(), 6);
+    common::add_token_account(
+        &mut program_test,
+        &sender_token_account,
+        &mint,
+        &sender.pubkey(),
+        AMOUNT,
+    );
+    common::add_token_account(
+        &mut program_test,
+        &recipient_token_account,
+        &mint,
+        &recipient,
+        0,
+    );
+
+    let mut context = program_test.start_with_context().await;
+
+    let hashlock = hashv(&[&PREIMAGE]).to_bytes();
+    let htlc = Pubkey::find_program_address(
+        &[b"htlc", sender.pubkey().as_ref(), hashlock.as_ref()],
+        &dex_core::ID,
+    )
+    .0;
+    let vault = Pubkey::find_program_address(&[b"htlc_vault", htlc.as_ref()], &dex_core::ID).0;
+    let clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    let timelock = clock.unix_timestamp + MIN_HTLC_TIMELOCK_SECS;
+
+    let lock = Instruction {
+        program_id: dex_core::ID,
+        accounts: dex_core::accounts::HtlcLock {
+            htlc,
+            vault,
+            mint,
+            sender_token_account,
+            sender: sender.pubkey(),
+            system_program: system_program::ID,
+            token_program: anchor_spl::token::ID,
+            rent: sysvar::rent::ID,
+        }
+        .to_account_metas(None),
+        data: dex_core::instruction::HtlcLock {
+            hashlock,
+            recipient,
+            amount: AMOUNT,
+            timelock,
+        }
+        .data(),
+    };
+    common::send(&mut context, &[lock], &[&sender])
+        .await
+        .unwrap();
+    assert_eq!(common::token_balance(&mut context, &vault).await, AMOUNT);
+
+    let fixture = Htlc {
+        sender_token_account,
+        recipient_token_account,
+        htlc,
+        vault,
+        timelock,
+    };
+    (context, fixture)
+}
+
+fn claim_ix(htlc: &Htlc, preimage: [u8; 32]) -> Instruction {
+    Instruction {
+        program_id: dex_core::ID,
+        accounts: dex_core::accounts::HtlcClaim {
+            htlc: htlc.htlc,
+            vault: htlc.vault,
+            recipient_token_account: htlc.recipient_token_account,
+            token_program: anchor_spl::token::ID,
+        }
+        .to_account_metas(None),
+        data: dex_core::instruction::HtlcClaim { preimage }.data(),
+    }
+}
+
+fn refund_ix(htlc: &Htlc) -> Instruction {
+    Instruction {
+        program_id: dex_core::ID,
+        accounts: dex_core::accounts::HtlcRefund {
+            htlc: htlc.htlc,
+            vault: htlc.vault,
+            sender_token_account: htlc.sender_token_account,
+            token_program: anchor_spl::token::ID,
+        }
+        .to_account_metas(None),
+        data: dex_core::instruction::HtlcRefund {}.data(),
+    }
+}
+
+// 把链上时间调到指定的unix时间戳
+async fn warp_to_timestamp(context: &mut ProgramTestContext, unix_timestamp: i64) {
+    let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp = unix_timestamp;
+    context.set_sysvar(&clock);
+}
+
+#[tokio::test]
+async fn claim_with_correct_preimage() {
+    let (mut context, htlc) = setup().await;
+
+    common::send(&mut context, &[claim_ix(&htlc, PREIMAGE)], &[])
+        .await
+        .unwrap();
+
+    let escrow: HtlcEscrow = common::get_program_account(&mut context, &htlc.htlc).await;
+    assert_eq!(escrow.status, HtlcStatus::Claimed);
+    assert_eq!(escrow.preimage, PREIMAGE);
+    assert_eq!(
+        common::token_balance(&mut context, &htlc.recipient_token_account).await,
+        AMOUNT
+    );
+    assert_eq!(common::token_balance(&mut context, &htlc.vault).await, 0);
+}
+
+#[tokio::test]
+async fn claim_with_wrong_preimage_fails() {
+    let (mut context, htlc) = setup().await;
+
+    let result = common::send(&mut context, &[claim_ix(&htlc, [8; 32])], &[]).await;
+    common::assert_program_error(result, ErrorCode::InvalidHtlcPreimage);
+
+    let escrow: HtlcEscrow = common::get_program_account(&mut context, &htlc.htlc).await;
+    assert_eq!(escrow.status, HtlcStatus::Locked);
+    assert_eq!(escrow.preimage, [0; 32]);
+    assert_eq!(
+        common::token_balance(&mut context, &htlc.vault).await,
+        AMOUNT
+    );
+}
+
+#[tokio::test]
+async fn refund_before_timelock_fails() {
+    let (mut context, htlc) = setup().await;
+
+    // 距超时还差1秒
+    warp_to_timestamp(&mut context, htlc.timelock - 1).await;
+    let result = common::send(&mut context, &[refund_ix(&htlc)], &[]).await;
+    common::assert_program_error(result, ErrorCode::HtlcNotExpired);
+
+    assert_eq!(
+        common::token_balance(&mut context, &htlc.vault).await,
+        AMOUNT
+    );
+    assert_eq!(
+        common::token_balance(&mut context, &htlc.sender_token_account).await,
+        0
+    );
+}
+
+#[tokio::test]
+async fn refund_after_timelock() {
+    let (mut context, htlc) = setup().await;
+
+    warp_to_timestamp(&mut context, htlc.timelock).await;
+    common::send(&mut context, &[refund_ix(&htlc)], &[])
+        .await
+        .unwrap();
+
+    let escrow: HtlcEscrow = common::get_program_account(&mut context, &htlc.htlc).await;
+    assert_eq!(escrow.status, HtlcStatus::Refunded);
+    assert_eq!(
+        common::token_balance(&mut context, &htlc.sender_token_account).await,
+        AMOUNT
+    );
+    assert_eq!(common::token_balance(&mut context, &htlc.vault).await, 0);
+
+    // 超时后原像也不能再领取
+    let result = common::send(&mut context, &[claim_ix(&htlc, PREIMAGE)], &[]).await;
+    common::assert_program_error(result, ErrorCode::HtlcNotLocked);
+}
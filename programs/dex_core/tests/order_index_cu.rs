@@ -0,0 +1,171 @@
+// 撤单计算单元基准：在10、256、1024个挂单的订单簿上撤销最后挂出的订单，
+// 断言消耗的计算单元与10个挂单时相差不超过固定余量
+// 需要先构建BPF程序再运行：
+//   cargo build-sbf --manifest-path programs/dex_core/Cargo.toml
+//   cargo test -p dex_core --test order_index_cu -- --ignored
+// 改动前（按价格树查找订单）的对照数字需要在引入订单ID索引之前的提交上，按当时的账户列表运行同样的步骤
+mod common;
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use common::MarketFixture;
+use dex_core::{OrderBook, OrderType, SelfTradeBehavior, Side};
+use solana_program::{system_program, sysvar};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+// OpenOrders的位图最多记录256个挂单，超过时分给多个用户
+const ORDERS_PER_USER: usize = 256;
+// 订单簿最多256个价格节点
+const PRICE_LEVELS: u64 = 256;
+const BASE_PRICE: u64 = 1_000;
+// 按订单ID索引查找是常数时间，剩余差异只来自价位内链表和价格树的调整
+const CU_MARGIN: u64 = 5_000;
+
+fn place_ask_ix(
+    market: &MarketFixture,
+    user: &Pubkey,
+    user_token_account: &Pubkey,
+    price: u64,
+    client_order_id: u64,
+) -> Instruction {
+    Instruction {
+        program_id: dex_core::ID,
+        accounts: dex_core::accounts::PlaceOrder {
+            market: market.market,
+            order_book: market.order_book,
+            open_orders: market.open_orders(user),
+            user_token_account: *user_token_account,
+            price_level_cache: market.price_level_cache,
+            trading_calendar: market.pda(b"trading_calendar"),
+            trading_limit_config: market.pda(b"trading_limits"),
+            access_control: None,
+            access_member: None,
+            user_trade_history: None,
+            trade_history_archive_page: None,
+            storage_index: None,
+            risk_parameters: market.pda(b"risk_parameters"),
+            authority: *user,
+            token_program: anchor_spl::token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        }
+        .to_account_metas(None),
+        data: dex_core::instruction::PlaceOrder {
+            side: Side::Ask,
+            limit_price: price,
+            max_quantity: 1,
+            order_type: OrderType::Limit,
+            self_trade_behavior: SelfTradeBehavior::DecrementTake,
+            client_order_id,
+        }
+        .data(),
+    }
+}
+
+fn cancel_ix(market: &MarketFixture, user: &Pubkey, order_id: u128) -> Instruction {
+    Instruction {
+        program_id: dex_core::ID,
+        accounts: dex_core::accounts::CancelOrder {
+            market: market.market,
+            order_book: market.order_book,
+            open_orders: market.open_orders(user),
+            price_level_cache: market.price_level_cache,
+            risk_parameters: market.pda(b"risk_parameters"),
+            authority: *user,
+        }
+        .to_account_metas(None),
+        data: dex_core::instruction::CancelOrder {
+            order_id,
+            side: Side::Ask,
+        }
+        .data(),
+    }
+}
+
+// 在订单簿中查找某个用户在指定价格上的挂单ID
+async fn find_resting_order(
+    context: &mut ProgramTestContext,
+    market: &MarketFixture,
+    owner: &Pubkey,
+    price: u64,
+) -> u128 {
+    let account = context
+        .banks_client
+        .get_account(market.order_book)
+        .await
+        .unwrap()
+        .unwrap();
+    let order_book: &OrderBook = bytemuck::from_bytes(&account.data[8..]);
+    order_book
+        .order_nodes
+        .iter()
+        .find(|node| {
+            let (node_owner, quantity) = (node.owner, node.quantity);
+            let node_price = order_book.price_nodes[node.price_index as usize].price;
+            node_owner == *owner && quantity > 0 && node_price == price
+        })
+        .map(|node| node.order_id)
+        .expect("resting order not found")
+}
+
+async fn cancel_cu_with_resting_orders(resting: usize) -> u64 {
+    let mut program_test = common::bpf_program_test();
+    let market = common::add_market(&mut program_test);
+
+    let user_count = (resting + ORDERS_PER_USER - 1) / ORDERS_PER_USER;
+    let users: Vec<(Keypair, Pubkey)> = (0..user_count)
+        .map(|_| (Keypair::new(), Pubkey::new_unique()))
+        .collect();
+    for (user, token_account) in &users {
+        common::add_wallet(&mut program_test, &user.pubkey(), 10_000_000_000);
+        common::add_token_account(
+            &mut program_test,
+            token_account,
+            &market.base_mint,
+            &user.pubkey(),
+            1_000_000,
+        );
+    }
+
+    let mut context = program_test.start_with_context().await;
+    let compute_limit = ComputeBudgetInstruction::set_compute_unit_limit(1_400_000);
+
+    // 卖单依次分布在各个价位上，最后一笔位于最深的价位
+    let mut last_price = BASE_PRICE;
+    for i in 0..resting {
+        let (user, token_account) = &users[i / ORDERS_PER_USER];
+        last_price = BASE_PRICE + i as u64 % PRICE_LEVELS;
+        let place = place_ask_ix(&market, &user.pubkey(), token_account, last_price, i as u64);
+        common::send(&mut context, &[compute_limit.clone(), place], &[user])
+            .await
+            .unwrap();
+    }
+
+    let (last_user, _) = users.last().unwrap();
+    let order_id = find_resting_order(&mut context, &market, &last_user.pubkey(), last_price).await;
+    common::send_measured(
+        &mut context,
+        &[cancel_ix(&market, &last_user.pubkey(), order_id)],
+        &[last_user],
+    )
+    .await
+}
+
+#[tokio::test]
+#[ignore = "需要先用 cargo build-sbf 构建 dex_core.so"]
+async fn cancel_compute_units_by_book_depth() {
+    let baseline = cancel_cu_with_resting_orders(10).await;
+    for resting in [256, 1024] {
+        let units = cancel_cu_with_resting_orders(resting).await;
+        assert!(
+            units.abs_diff(baseline) <= CU_MARGIN,
+            "cancel_order with {} resting orders used {} CU, with 10 it used {} CU",
+            resting,
+            units,
+            baseline
+        );
+    }
+}
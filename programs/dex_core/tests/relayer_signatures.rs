@@ -0,0 +1,230 @@
+// 中继器签名校验：签名由同一交易中的Ed25519程序指令验证，dex_core通过指令sysvar读取
+// 这里直接构造指令sysvar账户，覆盖各种伪造方式
+use anchor_lang::error::Error;
+use anchor_lang::prelude::*;
+use dex_core::cross_chain::{CrossChain, CrossChainBridgeConfig};
+use dex_core::ErrorCode;
+use solana_program::instruction::Instruction;
+use solana_program::sysvar::instructions::{
+    self as instructions_sysvar, construct_instructions_data, store_current_index,
+    BorrowedAccountMeta, BorrowedInstruction,
+};
+use solana_sdk::ed25519_instruction::new_ed25519_instruction;
+use solana_sdk::signature::{Keypair, Signer};
+
+// Ed25519指令偏移量表中公钥和消息所在指令索引的位置（第一个签名）
+const PUBKEY_INSTRUCTION_INDEX_OFFSET: usize = 2 + 6;
+const MESSAGE_INSTRUCTION_INDEX_OFFSET: usize = 2 + 12;
+
+fn bridge_config(relayers: &[&Keypair]) -> CrossChainBridgeConfig {
+    let mut config = CrossChainBridgeConfig {
+        required_confirmations: relayers.len() as u8,
+        ..Default::default()
+    };
+    for (slot, relayer) in config.relayers.iter_mut().zip(relayers) {
+        *slot = relayer.pubkey();
+    }
+    config
+}
+
+fn ed25519_ix(signer: &Keypair, message: &[u8]) -> Instruction {
+    let keypair = ed25519_dalek::Keypair::from_bytes(&signer.to_bytes()).unwrap();
+    new_ed25519_instruction(&keypair, message)
+}
+
+// 本指令本身，只用来占据sysvar中的当前指令位置
+fn dex_ix() -> Instruction {
+    Instruction {
+        program_id: dex_core::ID,
+        accounts: vec![],
+        data: vec![0; 8],
+    }
+}
+
+// 按交易中的指令顺序生成指令sysvar的数据，current为正在执行的指令下标
+fn sysvar_data(instructions: &[Instruction], current: u16) -> Vec<u8> {
+    let metas: Vec<Vec<BorrowedAccountMeta>> = instructions
+        .iter()
+        .map(|ix| {
+            ix.accounts
+                .iter()
+                .map(|meta| BorrowedAccountMeta {
+                    pubkey: &meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect()
+        })
+        .collect();
+    let borrowed: Vec<BorrowedInstruction> = instructions
+        .iter()
+        .zip(metas)
+        .map(|(ix, accounts)| BorrowedInstruction {
+            program_id: &ix.program_id,
+            accounts,
+            data: &ix.data,
+        })
+        .collect();
+    let mut data = construct_instructions_data(&borrowed);
+    store_current_index(&mut data, current);
+    data
+}
+
+// 以给定地址作为指令sysvar调用verify_relayer_signatures
+fn verify_at(
+    sysvar_key: Pubkey,
+    config: &CrossChainBridgeConfig,
+    hash: [u8; 32],
+    signers: &[Pubkey],
+    instructions: &[Instruction],
+    current: u16,
+) -> Result<()> {
+    let mut data = sysvar_data(instructions, current);
+    let mut lamports = 1_000_000;
+    let owner = solana_program::sysvar::ID;
+    let info = AccountInfo::new(
+        &sysvar_key,
+        false,
+        false,
+        &mut lamports,
+        &mut data,
+        &owner,
+        false,
+        0,
+    );
+    CrossChain::verify_relayer_signatures(config, hash, signers, &info)
+}
+
+fn verify(
+    config: &CrossChainBridgeConfig,
+    hash: [u8; 32],
+    signers: &[Pubkey],
+    instructions: &[Instruction],
+) -> Result<()> {
+    let current = (instructions.len() - 1) as u16;
+    verify_at(
+        instructions_sysvar::ID,
+        config,
+        hash,
+        signers,
+        instructions,
+        current,
+    )
+}
+
+fn invalid_signature() -> Error {
+    error!(ErrorCode::InvalidRelayerSignature)
+}
+
+#[test]
+fn valid_ed25519_instruction_is_accepted() {
+    let relayer = Keypair::new();
+    let config = bridge_config(&[&relayer]);
+    let hash = [7u8; 32];
+
+    let instructions = [ed25519_ix(&relayer, &hash), dex_ix()];
+    verify(&config, hash, &[relayer.pubkey()], &instructions).unwrap();
+}
+
+#[test]
+fn wrong_signer_is_rejected() {
+    let relayer = Keypair::new();
+    let outsider = Keypair::new();
+    let config = bridge_config(&[&relayer]);
+    let hash = [7u8; 32];
+    let instructions = [ed25519_ix(&outsider, &hash), dex_ix()];
+
+    // 声明为中继器，但签名来自其他密钥
+    assert_eq!(
+        verify(&config, hash, &[relayer.pubkey()], &instructions).unwrap_err(),
+        invalid_signature()
+    );
+    // 签名有效，但签名者不在中继器列表中
+    assert_eq!(
+        verify(&config, hash, &[outsider.pubkey()], &instructions).unwrap_err(),
+        invalid_signature()
+    );
+}
+
+#[test]
+fn offsets_pointing_at_another_instruction_are_rejected() {
+    let relayer = Keypair::new();
+    let outsider = Keypair::new();
+    let config = bridge_config(&[&relayer]);
+    let hash = [7u8; 32];
+
+    // 运行时会从指令1中读取公钥或消息，dex_core读到的与实际验证的不一致，必须拒绝
+    for index_offset in [
+        PUBKEY_INSTRUCTION_INDEX_OFFSET,
+        MESSAGE_INSTRUCTION_INDEX_OFFSET,
+    ] {
+        let mut ix = ed25519_ix(&relayer, &hash);
+        ix.data[index_offset..index_offset + 2].copy_from_slice(&1u16.to_le_bytes());
+        let instructions = [ix, ed25519_ix(&outsider, b"other message"), dex_ix()];
+        assert_eq!(
+            verify(&config, hash, &[relayer.pubkey()], &instructions).unwrap_err(),
+            invalid_signature()
+        );
+    }
+}
+
+#[test]
+fn message_mismatch_is_rejected() {
+    let relayer = Keypair::new();
+    let config = bridge_config(&[&relayer]);
+    let hash = [7u8; 32];
+
+    let instructions = [ed25519_ix(&relayer, &[8u8; 32]), dex_ix()];
+    assert_eq!(
+        verify(&config, hash, &[relayer.pubkey()], &instructions).unwrap_err(),
+        invalid_signature()
+    );
+
+    // 前缀相同但长度不同的消息也不算
+    let instructions = [ed25519_ix(&relayer, &hash[..31]), dex_ix()];
+    assert_eq!(
+        verify(&config, hash, &[relayer.pubkey()], &instructions).unwrap_err(),
+        invalid_signature()
+    );
+}
+
+#[test]
+fn signatures_after_current_instruction_are_ignored() {
+    let relayer = Keypair::new();
+    let config = bridge_config(&[&relayer]);
+    let hash = [7u8; 32];
+
+    let instructions = [dex_ix(), ed25519_ix(&relayer, &hash)];
+    assert_eq!(
+        verify_at(
+            instructions_sysvar::ID,
+            &config,
+            hash,
+            &[relayer.pubkey()],
+            &instructions,
+            0
+        )
+        .unwrap_err(),
+        invalid_signature()
+    );
+}
+
+#[test]
+fn spoofed_instructions_sysvar_is_rejected() {
+    let relayer = Keypair::new();
+    let config = bridge_config(&[&relayer]);
+    let hash = [7u8; 32];
+
+    // 内容与真实sysvar相同，但地址不是指令sysvar
+    let instructions = [ed25519_ix(&relayer, &hash), dex_ix()];
+    let err = verify_at(
+        Pubkey::new_unique(),
+        &config,
+        hash,
+        &[relayer.pubkey()],
+        &instructions,
+        1,
+    )
+    .unwrap_err();
+    assert_eq!(err, ProgramError::UnsupportedSysvar.into());
+}
@@ -0,0 +1,99 @@
+// 风控参数账户的原地迁移：PDA上的旧账户写入版本号和canonical bump，密钥对地址上的旧账户被拒绝
+mod common;
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use dex_core::migration::CURRENT_SCHEMA_VERSION;
+use dex_core::{ErrorCode, MigratableAccount, RiskParameters};
+use solana_program::system_program;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+// 引入schema_version之前的风控参数：保留字段全为0，版本号和bump读出为0
+fn legacy_risk_parameters(market: Pubkey) -> RiskParameters {
+    RiskParameters {
+        market,
+        authority: Pubkey::new_unique(),
+        price_limit_percent: 10,
+        max_open_orders_per_user: 100,
+        max_position_size: u64::MAX,
+        market_open_hour: 0,
+        market_close_hour: 0,
+        is_maintenance_mode: false,
+        circuit_breaker_threshold: 500,
+        circuit_breaker_cooldown_minutes: 10,
+        last_circuit_breaker_time: 0,
+        max_concentration_ratio: 20,
+        min_order_size: 1,
+        max_order_size: u64::MAX,
+        is_active: true,
+        spam_epoch_slots: 0,
+        max_cancel_to_fill_ratio: 0,
+        spam_min_cancels: 0,
+        spam_penalty_mode: 0,
+        spam_placement_fee_lamports: 0,
+        spam_throttle_slots: 0,
+        schema_version: 0,
+        bump: 0,
+        reserved: [0; 33],
+    }
+}
+
+// 写入一个市场的旧风控参数账户，at_pda为false时放在随机的密钥对地址上
+async fn setup(at_pda: bool) -> (ProgramTestContext, Pubkey, u8) {
+    let mut program_test = common::program_test();
+    let market = Pubkey::new_unique();
+    let (pda, bump) =
+        Pubkey::find_program_address(&[b"risk_parameters", market.as_ref()], &dex_core::ID);
+    let address = if at_pda { pda } else { Pubkey::new_unique() };
+    common::add_program_account(
+        &mut program_test,
+        &address,
+        &legacy_risk_parameters(market),
+        std::mem::size_of::<RiskParameters>(),
+    );
+    (program_test.start_with_context().await, address, bump)
+}
+
+fn migrate_ix(context: &ProgramTestContext, account: Pubkey) -> Instruction {
+    Instruction {
+        program_id: dex_core::ID,
+        accounts: dex_core::accounts::MigrateAccount {
+            account,
+            payer: context.payer.pubkey(),
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: dex_core::instruction::MigrateAccount {
+            account_type: MigratableAccount::RiskParameters,
+        }
+        .data(),
+    }
+}
+
+#[tokio::test]
+async fn pda_risk_parameters_get_canonical_bump() {
+    let (mut context, address, bump) = setup(true).await;
+
+    let ix = migrate_ix(&context, address);
+    common::send(&mut context, &[ix], &[]).await.unwrap();
+
+    let migrated: RiskParameters = common::get_program_account(&mut context, &address).await;
+    assert_eq!(migrated.schema_version, CURRENT_SCHEMA_VERSION);
+    assert_eq!(migrated.bump, bump);
+    assert_eq!(migrated.price_limit_percent, 10);
+}
+
+#[tokio::test]
+async fn keypair_risk_parameters_cannot_migrate_in_place() {
+    let (mut context, address, _) = setup(false).await;
+
+    let ix = migrate_ix(&context, address);
+    let result = common::send(&mut context, &[ix], &[]).await;
+    common::assert_program_error(result, ErrorCode::RiskParametersNotPda);
+
+    let legacy: RiskParameters = common::get_program_account(&mut context, &address).await;
+    assert_eq!(legacy.schema_version, 0);
+    assert_eq!(legacy.bump, 0);
+}
@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.19;
+
+/// @title HashedTimelock
+/// @notice EVM side of the dex_core HTLC swaps, driven by relayer/src/chains/evm_htlc.rs.
+/// Locks the native token under a sha256 hashlock and a unix-second timelock, the same
+/// scheme as the dex_core `htlc_lock` / `htlc_claim` / `htlc_refund` instructions.
+contract HashedTimelock {
+    struct LockContract {
+        address payable sender;
+        address payable receiver;
+        uint256 amount;
+        bytes32 hashlock;
+        uint256 timelock;
+        bool withdrawn;
+        bool refunded;
+        bytes32 preimage;
+    }
+
+    event LogHTLCNew(
+        bytes32 indexed contractId,
+        address indexed sender,
+        address indexed receiver,
+        uint256 amount,
+        bytes32 hashlock,
+        uint256 timelock
+    );
+    event LogHTLCWithdraw(bytes32 indexed contractId, bytes32 preimage);
+    event LogHTLCRefund(bytes32 indexed contractId);
+
+    mapping(bytes32 => LockContract) private contracts;
+
+    /// @notice Lock msg.value for `receiver` until `timelock`.
+    /// @dev The id depends only on the call arguments, so a simulated call returns the
+    /// same id the mined transaction will produce.
+    function newContract(address payable receiver, bytes32 hashlock, uint256 timelock)
+        external
+        payable
+        returns (bytes32 contractId)
+    {
+        require(msg.value > 0, "amount must be > 0");
+        require(timelock > block.timestamp, "timelock must be in the future");
+
+        contractId = sha256(abi.encodePacked(msg.sender, receiver, msg.value, hashlock, timelock));
+        require(contracts[contractId].sender == address(0), "contract already exists");
+
+        contracts[contractId] = LockContract(
+            payable(msg.sender), receiver, msg.value, hashlock, timelock, false, false, 0x0
+        );
+        emit LogHTLCNew(contractId, msg.sender, receiver, msg.value, hashlock, timelock);
+    }
+
+    /// @notice Claim the locked amount with the preimage of the hashlock, revealing it on chain.
+    function withdraw(bytes32 contractId, bytes32 preimage) external returns (bool) {
+        LockContract storage c = contracts[contractId];
+        require(c.sender != address(0), "contract does not exist");
+        require(c.receiver == msg.sender, "not the receiver");
+        require(c.hashlock == sha256(abi.encodePacked(preimage)), "hashlock mismatch");
+        require(!c.withdrawn && !c.refunded, "already settled");
+        require(c.timelock > block.timestamp, "timelock expired");
+
+        c.preimage = preimage;
+        c.withdrawn = true;
+        c.receiver.transfer(c.amount);
+        emit LogHTLCWithdraw(contractId, preimage);
+        return true;
+    }
+
+    /// @notice Return the locked amount to the sender once the timelock has passed.
+    function refund(bytes32 contractId) external returns (bool) {
+        LockContract storage c = contracts[contractId];
+        require(c.sender != address(0), "contract does not exist");
+        require(c.sender == msg.sender, "not the sender");
+        require(!c.withdrawn && !c.refunded, "already settled");
+        require(c.timelock <= block.timestamp, "timelock not yet passed");
+
+        c.refunded = true;
+        c.sender.transfer(c.amount);
+        emit LogHTLCRefund(contractId);
+        return true;
+    }
+
+    /// @notice Read a lock; every field is zero when the id does not exist.
+    function getContract(bytes32 contractId)
+        external
+        view
+        returns (
+            address sender,
+            address receiver,
+            uint256 amount,
+            bytes32 hashlock,
+            uint256 timelock,
+            bool withdrawn,
+            bool refunded,
+            bytes32 preimage
+        )
+    {
+        LockContract storage c = contracts[contractId];
+        return (c.sender, c.receiver, c.amount, c.hashlock, c.timelock, c.withdrawn, c.refunded, c.preimage);
+    }
+}
@@ -131,6 +131,7 @@ async fn parse_event(
                 from: format!("{:?}", log.topics[1]),
                 to: "bridge".to_string(),
                 amount: amount.to_string(),
+                target_chain_id: None,
             }
         }
         BscEventType::Withdrawal => {
@@ -142,6 +143,7 @@ async fn parse_event(
                 from: "bridge".to_string(),
                 to: format!("{:?}", log.topics[1]),
                 amount: amount.to_string(),
+                target_chain_id: None,
             }
         }
         BscEventType::PriceUpdate => {
@@ -102,6 +102,7 @@ async fn parse_event(
                 from,
                 to: token_address.clone(), // For deposits, 'to' is the contract
                 amount: amount.to_string(),
+                target_chain_id: None,
             }
         }
         EthEventType::PriceUpdate => {
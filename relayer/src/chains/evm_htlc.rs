@@ -0,0 +1,576 @@
+// Counterpart of the dex_core HTLC instructions on EVM chains.
+//
+// Drives a HashedTimelock contract (sha256 hashlock, unix-second timelock, native
+// token) so a swap can be settled without the relayer multisig: the initiator locks
+// on one chain, the participant locks on the other with the same hashlock and a
+// shorter timelock, and each side claims with the preimage the other one revealed.
+
+use dex_events::DexEvent;
+use ethers::{
+    contract::abigen,
+    core::rand::{thread_rng, RngCore},
+    providers::Middleware,
+    types::{Address, TxHash, U256},
+};
+use sha2::{Digest, Sha256};
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+use thiserror::Error;
+
+abigen!(
+    HashedTimelock,
+    r#"[
+        function newContract(address receiver, bytes32 hashlock, uint256 timelock) external payable returns (bytes32 contractId)
+        function withdraw(bytes32 contractId, bytes32 preimage) external returns (bool)
+        function refund(bytes32 contractId) external returns (bool)
+        function getContract(bytes32 contractId) external view returns (address sender, address receiver, uint256 amount, bytes32 hashlock, uint256 timelock, bool withdrawn, bool refunded, bytes32 preimage)
+    ]"#
+);
+
+/// Minimum gap between the participant's timelock and the initiator's, so the
+/// participant still has time to claim after the initiator reveals the preimage.
+pub const MIN_TIMELOCK_MARGIN_SECS: u64 = 3600;
+
+#[derive(Debug, Error)]
+pub enum HtlcError {
+    #[error("contract call failed: {0}")]
+    Contract(String),
+    #[error("transaction {0:?} dropped before confirmation")]
+    Dropped(TxHash),
+    #[error("HTLC {0} does not exist")]
+    NotFound(String),
+    #[error("counterpart HTLC mismatch: {0}")]
+    Mismatch(&'static str),
+}
+
+impl<M: Middleware> From<ethers::contract::ContractError<M>> for HtlcError {
+    fn from(e: ethers::contract::ContractError<M>) -> Self {
+        HtlcError::Contract(e.to_string())
+    }
+}
+
+/// On-chain state of an EVM HTLC
+#[derive(Debug, Clone)]
+pub struct EvmHtlcState {
+    pub contract_id: [u8; 32],
+    pub sender: Address,
+    pub receiver: Address,
+    pub amount: U256,
+    pub hashlock: [u8; 32],
+    pub timelock: u64,
+    pub withdrawn: bool,
+    pub refunded: bool,
+    pub preimage: Option<[u8; 32]>,
+}
+
+/// Terms the counterpart HTLC must satisfy before we lock or reveal anything
+#[derive(Debug, Clone)]
+pub struct ExpectedHtlc {
+    pub receiver: Address,
+    pub hashlock: [u8; 32],
+    pub min_amount: U256,
+    /// The counterpart must expire at least MIN_TIMELOCK_MARGIN_SECS before this
+    pub expires_before: u64,
+}
+
+/// Generate a random preimage and its sha256 hashlock
+pub fn new_secret() -> ([u8; 32], [u8; 32]) {
+    let mut preimage = [0u8; 32];
+    thread_rng().fill_bytes(&mut preimage);
+    (preimage, hashlock(&preimage))
+}
+
+/// sha256 hashlock, identical to `HtlcEscrow::verify_preimage` on Solana
+pub fn hashlock(preimage: &[u8; 32]) -> [u8; 32] {
+    Sha256::digest(preimage).into()
+}
+
+/// Extract the preimage revealed by a claim of the given Solana HTLC
+pub fn preimage_from_solana_event(event: &DexEvent, htlc: &Pubkey) -> Option<[u8; 32]> {
+    match event {
+        DexEvent::HtlcClaimed(e) if e.htlc == *htlc => Some(e.preimage),
+        _ => None,
+    }
+}
+
+/// Check that the counterpart HTLC locks what was agreed and expires early enough
+pub fn check_counterpart(state: &EvmHtlcState, expected: &ExpectedHtlc) -> Result<(), HtlcError> {
+    if state.withdrawn || state.refunded {
+        return Err(HtlcError::Mismatch("already settled"));
+    }
+    if state.receiver != expected.receiver {
+        return Err(HtlcError::Mismatch("receiver"));
+    }
+    if state.hashlock != expected.hashlock {
+        return Err(HtlcError::Mismatch("hashlock"));
+    }
+    if state.amount < expected.min_amount {
+        return Err(HtlcError::Mismatch("amount"));
+    }
+    if state.timelock.saturating_add(MIN_TIMELOCK_MARGIN_SECS) > expected.expires_before {
+        return Err(HtlcError::Mismatch("timelock"));
+    }
+    Ok(())
+}
+
+pub struct EvmHtlcClient<M> {
+    contract: HashedTimelock<M>,
+}
+
+impl<M: Middleware + 'static> EvmHtlcClient<M> {
+    pub fn new(address: Address, client: Arc<M>) -> Self {
+        Self {
+            contract: HashedTimelock::new(address, client),
+        }
+    }
+
+    /// Lock `amount` of the native token for `receiver`, returning the contract id
+    pub async fn lock(
+        &self,
+        receiver: Address,
+        hashlock: [u8; 32],
+        timelock: u64,
+        amount: U256,
+    ) -> Result<[u8; 32], HtlcError> {
+        let call = self
+            .contract
+            .new_contract(receiver, hashlock, U256::from(timelock))
+            .value(amount);
+
+        // The contract id is derived from the call arguments, so the simulated
+        // return value matches the one the mined transaction produces
+        let contract_id = call.call().await?;
+        let pending = call.send().await?;
+        let tx_hash = pending.tx_hash();
+        pending
+            .await
+            .map_err(|e| HtlcError::Contract(e.to_string()))?
+            .ok_or(HtlcError::Dropped(tx_hash))?;
+
+        Ok(contract_id)
+    }
+
+    /// Claim the HTLC with the preimage revealed on Solana
+    pub async fn claim(
+        &self,
+        contract_id: [u8; 32],
+        preimage: [u8; 32],
+    ) -> Result<TxHash, HtlcError> {
+        let call = self.contract.withdraw(contract_id, preimage);
+        let pending = call.send().await?;
+        let tx_hash = pending.tx_hash();
+        pending
+            .await
+            .map_err(|e| HtlcError::Contract(e.to_string()))?
+            .ok_or(HtlcError::Dropped(tx_hash))?;
+        Ok(tx_hash)
+    }
+
+    /// Refund the HTLC to its sender after the timelock
+    pub async fn refund(&self, contract_id: [u8; 32]) -> Result<TxHash, HtlcError> {
+        let call = self.contract.refund(contract_id);
+        let pending = call.send().await?;
+        let tx_hash = pending.tx_hash();
+        pending
+            .await
+            .map_err(|e| HtlcError::Contract(e.to_string()))?
+            .ok_or(HtlcError::Dropped(tx_hash))?;
+        Ok(tx_hash)
+    }
+
+    /// Read the HTLC state, including the preimage once it has been withdrawn
+    pub async fn get(&self, contract_id: [u8; 32]) -> Result<EvmHtlcState, HtlcError> {
+        let (sender, receiver, amount, hashlock, timelock, withdrawn, refunded, preimage) =
+            self.contract.get_contract(contract_id).call().await?;
+
+        if sender == Address::zero() {
+            return Err(HtlcError::NotFound(hex::encode(contract_id)));
+        }
+
+        Ok(EvmHtlcState {
+            contract_id,
+            sender,
+            receiver,
+            amount,
+            hashlock,
+            timelock: u64::try_from(timelock).map_err(|_| HtlcError::Mismatch("timelock"))?,
+            withdrawn,
+            refunded,
+            preimage: withdrawn.then_some(preimage),
+        })
+    }
+}
+
+// Integration tests against a local anvil (or hardhat) node with relayer/contracts/HashedTimelock.sol
+// deployed. The atomic swap test runs dex_core in solana-program-test against the same node:
+//   anvil
+//   forge create relayer/contracts/HashedTimelock.sol:HashedTimelock --rpc-url http://127.0.0.1:8545 \
+//       --private-key 0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80
+//   EVM_HTLC_ADDRESS=<deployed address> cargo test -p relayer evm_htlc -- --ignored
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::{InstructionData, ToAccountMetas};
+    use anchor_spl::token::spl_token;
+    use ethers::{
+        middleware::SignerMiddleware,
+        providers::{Http, Provider},
+        signers::{LocalWallet, Signer},
+        types::BlockNumber,
+    };
+    use solana_program::{program_option::COption, program_pack::Pack, system_program, sysvar};
+    use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+    use solana_sdk::{
+        account::Account, account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult,
+        instruction::Instruction, rent::Rent, signature::Keypair, signer::Signer as _,
+        transaction::Transaction,
+    };
+
+    // Dev accounts 0 and 1 of both anvil and hardhat
+    const SENDER_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const RECEIVER_KEY: &str = "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+    const TIMELOCK_SECS: u64 = 3600;
+
+    type Client = SignerMiddleware<Provider<Http>, LocalWallet>;
+
+    async fn client(key: &str) -> Arc<Client> {
+        let url = std::env::var("EVM_HTLC_RPC_URL")
+            .unwrap_or_else(|_| "http://127.0.0.1:8545".to_string());
+        let provider = Provider::<Http>::try_from(url).unwrap();
+        let chain_id = provider.get_chainid().await.unwrap().as_u64();
+        let wallet = key.parse::<LocalWallet>().unwrap().with_chain_id(chain_id);
+        Arc::new(SignerMiddleware::new(provider, wallet))
+    }
+
+    fn htlc_address() -> Address {
+        std::env::var("EVM_HTLC_ADDRESS")
+            .expect("EVM_HTLC_ADDRESS must point at a deployed HashedTimelock")
+            .parse()
+            .unwrap()
+    }
+
+    async fn chain_time(client: &Client) -> u64 {
+        client
+            .get_block(BlockNumber::Latest)
+            .await
+            .unwrap()
+            .unwrap()
+            .timestamp
+            .as_u64()
+    }
+
+    // Move the node's clock forward and mine a block so the next call sees it
+    async fn advance_time(client: &Client, secs: u64) {
+        let provider = client.provider();
+        provider
+            .request::<_, serde_json::Value>("evm_increaseTime", [secs])
+            .await
+            .unwrap();
+        provider
+            .request::<_, serde_json::Value>("evm_mine", Vec::<u64>::new())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "requires anvil or hardhat with a HashedTimelock deployed at EVM_HTLC_ADDRESS"]
+    async fn claim_reveals_preimage() {
+        let sender = client(SENDER_KEY).await;
+        let receiver = client(RECEIVER_KEY).await;
+        let sender_htlc = EvmHtlcClient::new(htlc_address(), sender.clone());
+        let receiver_htlc = EvmHtlcClient::new(htlc_address(), receiver.clone());
+
+        let (preimage, hashlock) = new_secret();
+        let timelock = chain_time(&sender).await + TIMELOCK_SECS;
+        let amount = U256::from(1_000_000_000u64);
+        let contract_id = sender_htlc
+            .lock(receiver.address(), hashlock, timelock, amount)
+            .await
+            .unwrap();
+
+        let state = receiver_htlc.get(contract_id).await.unwrap();
+        assert_eq!(state.sender, sender.address());
+        assert_eq!(state.preimage, None);
+        let expected = ExpectedHtlc {
+            receiver: receiver.address(),
+            hashlock,
+            min_amount: amount,
+            expires_before: timelock + MIN_TIMELOCK_MARGIN_SECS,
+        };
+        check_counterpart(&state, &expected).unwrap();
+
+        // A wrong preimage is rejected by the contract
+        assert!(receiver_htlc.claim(contract_id, [0; 32]).await.is_err());
+
+        receiver_htlc.claim(contract_id, preimage).await.unwrap();
+        let state = sender_htlc.get(contract_id).await.unwrap();
+        assert!(state.withdrawn);
+        assert_eq!(state.preimage, Some(preimage));
+        assert!(matches!(
+            check_counterpart(&state, &expected),
+            Err(HtlcError::Mismatch("already settled"))
+        ));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires anvil or hardhat with a HashedTimelock deployed at EVM_HTLC_ADDRESS"]
+    async fn refund_only_after_timelock() {
+        let sender = client(SENDER_KEY).await;
+        let receiver = client(RECEIVER_KEY).await;
+        let sender_htlc = EvmHtlcClient::new(htlc_address(), sender.clone());
+
+        let (_, hashlock) = new_secret();
+        let timelock = chain_time(&sender).await + TIMELOCK_SECS;
+        let contract_id = sender_htlc
+            .lock(
+                receiver.address(),
+                hashlock,
+                timelock,
+                U256::from(1_000_000_000u64),
+            )
+            .await
+            .unwrap();
+
+        assert!(sender_htlc.refund(contract_id).await.is_err());
+
+        advance_time(&sender, TIMELOCK_SECS).await;
+        sender_htlc.refund(contract_id).await.unwrap();
+        let state = sender_htlc.get(contract_id).await.unwrap();
+        assert!(state.refunded);
+        assert!(!state.withdrawn);
+        assert_eq!(state.preimage, None);
+    }
+
+    // Anchor's entry needs the account slice to live as long as the AccountInfos
+    fn process_dex_instruction(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        data: &[u8],
+    ) -> ProgramResult {
+        let accounts = Box::leak(Box::new(accounts.to_vec()));
+        dex_core::entry(program_id, accounts, data)
+    }
+
+    fn add_packed<T: Pack>(program_test: &mut ProgramTest, address: Pubkey, state: T) {
+        let mut data = vec![0u8; T::LEN];
+        state.pack_into_slice(&mut data);
+        program_test.add_account(
+            address,
+            Account {
+                lamports: Rent::default().minimum_balance(data.len()),
+                data,
+                owner: spl_token::ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+    }
+
+    fn add_token_account(
+        program_test: &mut ProgramTest,
+        address: Pubkey,
+        mint: Pubkey,
+        owner: Pubkey,
+        amount: u64,
+    ) {
+        add_packed(
+            program_test,
+            address,
+            spl_token::state::Account {
+                mint,
+                owner,
+                amount,
+                delegate: COption::None,
+                state: spl_token::state::AccountState::Initialized,
+                is_native: COption::None,
+                delegated_amount: 0,
+                close_authority: COption::None,
+            },
+        );
+    }
+
+    // Send one dex_core instruction and decode the events it emitted, like the Solana listener does
+    async fn send_and_decode(
+        context: &mut ProgramTestContext,
+        instruction: Instruction,
+        signers: &[&Keypair],
+    ) -> Vec<DexEvent> {
+        let mut keypairs = vec![&context.payer];
+        keypairs.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&context.payer.pubkey()),
+            &keypairs,
+            context.last_blockhash,
+        );
+        let executed = context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .unwrap();
+        executed.result.unwrap();
+        let logs = executed.metadata.unwrap().log_messages;
+        dex_events::parse_logs(&dex_core::ID, &logs).unwrap()
+    }
+
+    async fn token_balance(context: &mut ProgramTestContext, address: Pubkey) -> u64 {
+        let account = context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .unwrap();
+        spl_token::state::Account::unpack(&account.data)
+            .unwrap()
+            .amount
+    }
+
+    // Alice swaps SPL tokens on Solana for Bob's native token on the EVM node:
+    // Alice locks first with the longer timelock, Bob locks the same hashlock on EVM,
+    // Alice claims on EVM revealing the preimage and Bob uses it to claim on Solana.
+    #[tokio::test]
+    #[ignore = "requires anvil or hardhat with a HashedTimelock deployed at EVM_HTLC_ADDRESS"]
+    async fn atomic_swap_between_solana_and_evm() {
+        const SPL_AMOUNT: u64 = 5_000_000;
+        let eth_amount = U256::from(1_000_000_000u64);
+
+        let mut program_test = ProgramTest::new(
+            "dex_core",
+            dex_core::ID,
+            processor!(process_dex_instruction),
+        );
+        let alice = Keypair::new();
+        let bob = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let alice_tokens = Pubkey::new_unique();
+        let bob_tokens = Pubkey::new_unique();
+        program_test.add_account(
+            alice.pubkey(),
+            Account {
+                lamports: 10_000_000_000,
+                data: vec![],
+                owner: system_program::ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+        add_packed(
+            &mut program_test,
+            mint,
+            spl_token::state::Mint {
+                mint_authority: COption::Some(Pubkey::new_unique()),
+                supply: SPL_AMOUNT,
+                decimals: 6,
+                is_initialized: true,
+                freeze_authority: COption::None,
+            },
+        );
+        add_token_account(
+            &mut program_test,
+            alice_tokens,
+            mint,
+            alice.pubkey(),
+            SPL_AMOUNT,
+        );
+        add_token_account(&mut program_test, bob_tokens, mint, bob.pubkey(), 0);
+        let mut context = program_test.start_with_context().await;
+
+        // On EVM Bob is the sender and Alice the receiver
+        let bob_evm = client(SENDER_KEY).await;
+        let alice_evm = client(RECEIVER_KEY).await;
+        let bob_htlc = EvmHtlcClient::new(htlc_address(), bob_evm.clone());
+        let alice_htlc = EvmHtlcClient::new(htlc_address(), alice_evm.clone());
+
+        // 1. Alice locks on Solana, leaving room for Bob's shorter timelock plus the margin
+        let (preimage, hashlock) = new_secret();
+        let solana_now = context
+            .banks_client
+            .get_sysvar::<Clock>()
+            .await
+            .unwrap()
+            .unix_timestamp as u64;
+        let evm_now = chain_time(&bob_evm).await;
+        let solana_timelock = solana_now.max(evm_now) + 3 * TIMELOCK_SECS;
+        let htlc = Pubkey::find_program_address(
+            &[b"htlc", alice.pubkey().as_ref(), hashlock.as_ref()],
+            &dex_core::ID,
+        )
+        .0;
+        let vault = Pubkey::find_program_address(&[b"htlc_vault", htlc.as_ref()], &dex_core::ID).0;
+        let lock = Instruction {
+            program_id: dex_core::ID,
+            accounts: dex_core::accounts::HtlcLock {
+                htlc,
+                vault,
+                mint,
+                sender_token_account: alice_tokens,
+                sender: alice.pubkey(),
+                system_program: system_program::ID,
+                token_program: spl_token::ID,
+                rent: sysvar::rent::ID,
+            }
+            .to_account_metas(None),
+            data: dex_core::instruction::HtlcLock {
+                hashlock,
+                recipient: bob.pubkey(),
+                amount: SPL_AMOUNT,
+                timelock: solana_timelock as i64,
+            }
+            .data(),
+        };
+        let events = send_and_decode(&mut context, lock, &[&alice]).await;
+        let locked = events
+            .iter()
+            .find_map(|event| match event {
+                DexEvent::HtlcLocked(e) if e.htlc == htlc => Some(e.clone()),
+                _ => None,
+            })
+            .expect("HtlcLocked event");
+
+        // 2. Bob locks the same hashlock on EVM with a shorter timelock
+        let contract_id = bob_htlc
+            .lock(
+                alice_evm.address(),
+                locked.hashlock,
+                evm_now + TIMELOCK_SECS,
+                eth_amount,
+            )
+            .await
+            .unwrap();
+
+        // 3. Alice checks Bob's lock against her own before revealing the preimage
+        let state = alice_htlc.get(contract_id).await.unwrap();
+        let expected = ExpectedHtlc {
+            receiver: alice_evm.address(),
+            hashlock,
+            min_amount: eth_amount,
+            expires_before: locked.timelock as u64,
+        };
+        check_counterpart(&state, &expected).unwrap();
+        alice_htlc.claim(contract_id, preimage).await.unwrap();
+
+        // 4. Bob reads the revealed preimage from EVM; anyone may submit the Solana claim
+        let revealed = bob_htlc.get(contract_id).await.unwrap().preimage.unwrap();
+        let claim = Instruction {
+            program_id: dex_core::ID,
+            accounts: dex_core::accounts::HtlcClaim {
+                htlc,
+                vault,
+                recipient_token_account: bob_tokens,
+                token_program: spl_token::ID,
+            }
+            .to_account_metas(None),
+            data: dex_core::instruction::HtlcClaim { preimage: revealed }.data(),
+        };
+        let events = send_and_decode(&mut context, claim, &[]).await;
+
+        assert_eq!(token_balance(&mut context, bob_tokens).await, SPL_AMOUNT);
+        assert_eq!(token_balance(&mut context, vault).await, 0);
+        assert_eq!(
+            events
+                .iter()
+                .find_map(|event| preimage_from_solana_event(event, &htlc)),
+            Some(preimage)
+        );
+    }
+}
@@ -1,4 +1,5 @@
 pub mod ethereum;
+pub mod evm_htlc;
 pub mod solana;
 
 use crate::events::{Chain, ChainEvent, Message};
@@ -9,10 +9,12 @@ use solana_sdk::{
     transaction::Transaction,
 };
 use tokio::sync::mpsc;
-use std::str::FromStr;
 use std::sync::Arc;
 use crate::events::{ChainEvent, SolanaEvent, SolEventType, EventData};
 use crate::validators::ValidatorSet;
+use dex_events::{DexEvent, SequenceStatus, SequenceTracker};
+use ethers::types::Address;
+use log::warn;
 
 pub async fn start_listener(
     event_sender: mpsc::Sender<ChainEvent>,
@@ -23,15 +25,16 @@ pub async fn start_listener(
         CommitmentConfig::confirmed(),
     );
 
-    let program_id = Pubkey::from_str("YOUR_PROGRAM_ID")?;
+    let program_id = dex_events::PROGRAM_ID;
     let mut slot = client.get_slot()?;
+    let mut tracker = SequenceTracker::new();
 
     loop {
         let new_slot = client.get_slot()?;
         if new_slot > slot {
             // Process new blocks
             for current_slot in slot..=new_slot {
-                process_slot(&client, current_slot, &program_id, &event_sender).await?;
+                process_slot(&client, current_slot, &program_id, &event_sender, &mut tracker).await?;
             }
             slot = new_slot;
         }
@@ -48,6 +51,7 @@ async fn process_slot(
     slot: u64,
     program_id: &Pubkey,
     event_sender: &mpsc::Sender<ChainEvent>,
+    tracker: &mut SequenceTracker,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let block = client.get_block_with_encoding(
         slot,
@@ -56,16 +60,21 @@ async fn process_slot(
 
     for tx in block.transactions {
         if let Some(meta) = tx.meta {
+            // Failed transactions are rolled back, so their events never happened
+            if meta.err.is_some() {
+                continue;
+            }
             if let Some(log_messages) = meta.log_messages {
                 if log_messages.iter().any(|msg| msg.contains(&program_id.to_string())) {
-                    let event = parse_transaction_logs(
+                    let events = parse_transaction_logs(
                         &log_messages,
                         slot,
                         &tx.transaction.signatures[0],
                         program_id,
+                        tracker,
                     )?;
-                    
-                    if let Some(event) = event {
+
+                    for event in events {
                         event_sender.send(ChainEvent::SolanaEvent(event)).await?;
                     }
                 }
@@ -113,43 +122,71 @@ fn parse_transaction_logs(
     slot: u64,
     signature: &str,
     program_id: &Pubkey,
-) -> Result<Option<SolanaEvent>, Box<dyn std::error::Error>> {
-    for log in logs {
-        if let Some(event) = parse_log_message(log, slot, signature, program_id)? {
-            return Ok(Some(event));
+    tracker: &mut SequenceTracker,
+) -> Result<Vec<SolanaEvent>, Box<dyn std::error::Error>> {
+    let mut events = Vec::new();
+
+    for event in dex_events::parse_logs(program_id, logs)? {
+        // A per-market sequence gap means we missed a transaction (dropped block, truncated logs)
+        match tracker.observe(&event) {
+            Some(SequenceStatus::Gap { expected, received }) => {
+                warn!(
+                    "{} event sequence gap in {}: expected {}, received {}",
+                    event.name(),
+                    signature,
+                    expected,
+                    received
+                );
+            }
+            Some(SequenceStatus::Duplicate) => continue,
+            _ => {}
+        }
+
+        if let Some(event) = to_solana_event(event, slot, signature, program_id) {
+            events.push(event);
         }
     }
-    Ok(None)
+
+    Ok(events)
 }
 
-fn parse_log_message(
-    log: &str,
+// Only forward the events the relayer acts on
+fn to_solana_event(
+    event: DexEvent,
     slot: u64,
     signature: &str,
     program_id: &Pubkey,
-) -> Result<Option<SolanaEvent>, Box<dyn std::error::Error>> {
-    // Example log parsing for bridge events
-    if log.contains("bridge_deposit") {
-        let parts: Vec<&str> = log.split(':').collect();
-        if parts.len() >= 2 {
-            return Ok(Some(SolanaEvent {
-                event_type: SolEventType::BridgeDeposit,
-                program_id: *program_id,
-                signature: signature.to_string(),
-                slot,
-                data: EventData::TokenTransfer {
-                    token_address: parts[1].trim().to_string(),
-                    from: parts[2].trim().to_string(),
-                    to: parts[3].trim().to_string(),
-                    amount: parts[4].trim().to_string(),
-                },
-            }));
-        }
-    }
-
-    // Add more event type parsing as needed
+) -> Option<SolanaEvent> {
+    let (event_type, data) = match event {
+        DexEvent::CrossChainOrderCreated(e) => (
+            SolEventType::BridgeDeposit,
+            EventData::TokenTransfer {
+                token_address: e.token_mint.to_string(),
+                from: e.owner.to_string(),
+                // The recipient is left-padded to 32 bytes, EVM addresses are the low 20
+                to: format!("{:?}", Address::from_slice(&e.destination[12..])),
+                amount: e.amount.to_string(),
+                target_chain_id: Some(e.target_chain_id),
+            },
+        ),
+        DexEvent::Trade(e) => (
+            SolEventType::OrderbookUpdate,
+            EventData::PriceUpdate {
+                token_address: e.market.to_string(),
+                price: e.price.to_string(),
+                timestamp: e.timestamp as u64,
+            },
+        ),
+        _ => return None,
+    };
 
-    Ok(None)
+    Some(SolanaEvent {
+        event_type,
+        program_id: *program_id,
+        signature: signature.to_string(),
+        slot,
+        data,
+    })
 }
 
 fn parse_account_data(
@@ -179,4 +216,40 @@ fn parse_account_data(
         })),
         _ => Ok(None),
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dex_events::CrossChainOrderCreatedEvent;
+
+    #[test]
+    fn cross_chain_order_forwards_destination_and_chain_id() {
+        let recipient = Address::repeat_byte(0xab);
+        let mut destination = [0u8; 32];
+        destination[12..].copy_from_slice(recipient.as_bytes());
+        let event = DexEvent::CrossChainOrderCreated(CrossChainOrderCreatedEvent {
+            source_market: Pubkey::new_unique(),
+            seq: 1,
+            target_chain_id: 56,
+            order_id: 7,
+            owner: Pubkey::new_unique(),
+            token_mint: Pubkey::new_unique(),
+            base_amount: 0,
+            quote_amount: 500,
+            amount: 500,
+            destination,
+            timestamp: 0,
+        });
+
+        let event = to_solana_event(event, 10, "sig", &dex_events::PROGRAM_ID).unwrap();
+        match event.data {
+            EventData::TokenTransfer { to, amount, target_chain_id, .. } => {
+                assert_eq!(to, format!("{:?}", recipient));
+                assert_eq!(amount, "500");
+                assert_eq!(target_chain_id, Some(56));
+            }
+            other => panic!("unexpected event data: {:?}", other),
+        }
+    }
+}
@@ -81,6 +81,8 @@ pub enum EventData {
         from: String,
         to: String,
         amount: String,
+        // Destination chain of a bridge deposit, None when the transfer stays on its chain
+        target_chain_id: Option<u64>,
     },
     PriceUpdate {
         token_address: String,